@@ -0,0 +1,376 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::services::job_record_service::now_millis;
+use crate::app::services::job_state_service::{JobFailure, JobState};
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::config::Config;
+use crate::infra::redis::{RedisError, RedisPool};
+use crate::infra::webhook::{WebhookClient, WebhookError};
+
+/// Where to tell a client its job is done.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobCallback {
+    pub url: String,
+    /// Signs each delivery, so the client can tell it came from us.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl JobCallback {
+    /// Rejects URLs `webhooks` may not post to, such as those on private networks.
+    pub async fn new(
+        url: String,
+        secret: Option<String>,
+        webhooks: &WebhookClient,
+    ) -> Result<Self, CallbackError> {
+        webhooks.check(&url).await?;
+        Ok(Self {
+            url,
+            secret: secret.filter(|secret| !secret.is_empty()),
+        })
+    }
+}
+
+/// What a callback tells the client. Links are relative unless `public_url` is configured.
+#[derive(Clone, Debug, Serialize)]
+pub struct CallbackPayload {
+    pub job_id: String,
+    pub status: JobState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobFailure>,
+    pub job_url: String,
+    /// Links to the job's outputs, once it has completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genome_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flame_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+}
+
+impl CallbackPayload {
+    pub fn new(config: &Config, job_id: &str, status: JobState, error: Option<JobFailure>) -> Self {
+        let base = config.public_url.as_deref().unwrap_or_default();
+        let job_url = format!("{}/api/render/{}", base.trim_end_matches('/'), job_id);
+        let output =
+            |path: &str| (status == JobState::Completed).then(|| format!("{}/{}", job_url, path));
+        Self {
+            job_id: job_id.to_string(),
+            status,
+            error,
+            result_url: output("result"),
+            genome_url: output("genome"),
+            flame_url: output("flame"),
+            thumbnail_url: output("thumbnail"),
+            job_url,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Not yet delivered, with attempts left.
+    Pending,
+    Delivered,
+    /// Every attempt failed, or the client refused the request.
+    Failed,
+}
+
+/// How delivering a job's callback went, as of the latest attempt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CallbackDelivery {
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// Status code of the latest response, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_status: Option<u16>,
+    /// Why the latest request got no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the latest attempt was made, in milliseconds since the Unix epoch.
+    pub attempted_at: u64,
+}
+
+/// Delivers job callbacks, retrying with exponential backoff and recording each attempt
+/// alongside the job's other keys.
+#[derive(Clone, Default)]
+pub struct CallbackService;
+
+impl CallbackService {
+    /// Posts `payload` to `callback` until the client accepts it, refuses it with a client
+    /// error, or `callback_max_attempts` run out. Timeouts, 408, 429 and server errors are
+    /// retried.
+    pub async fn deliver(
+        webhooks: &WebhookClient,
+        redis: Option<&RedisPool>,
+        config: &Config,
+        callback: &JobCallback,
+        payload: &CallbackPayload,
+    ) -> CallbackDelivery {
+        let body = serde_json::to_string(payload).unwrap_or_default();
+        let max_attempts = config.callback_max_attempts.max(1);
+        let mut backoff = Duration::from_millis(config.callback_retry_base_ms);
+        let mut delivery = CallbackDelivery {
+            url: callback.url.clone(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            error: None,
+            attempted_at: 0,
+        };
+        loop {
+            delivery.attempts += 1;
+            delivery.attempted_at = now_millis();
+            let sent = webhooks
+                .post(
+                    &callback.url,
+                    body.clone(),
+                    callback.secret.as_deref(),
+                    delivery.attempted_at,
+                )
+                .await;
+            let retryable = match sent {
+                Ok(status) => {
+                    delivery.response_status = Some(status);
+                    delivery.error = None;
+                    if (200..300).contains(&status) {
+                        delivery.status = DeliveryStatus::Delivered;
+                    }
+                    status >= 500 || status == 408 || status == 429
+                }
+                Err(e) => {
+                    delivery.response_status = None;
+                    delivery.error = Some(e.to_string());
+                    true
+                }
+            };
+            if delivery.status == DeliveryStatus::Pending
+                && (!retryable || delivery.attempts >= max_attempts)
+            {
+                delivery.status = DeliveryStatus::Failed;
+            }
+
+            if let Some(redis) = redis
+                && let Err(e) =
+                    Self::record(redis, &payload.job_id, &delivery, config.job_ttl_secs).await
+            {
+                tracing::warn!(job_id = %payload.job_id, error = %e, "Failed to record callback delivery");
+            }
+            if delivery.status != DeliveryStatus::Pending {
+                return delivery;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    async fn record(
+        redis: &RedisPool,
+        job_id: &str,
+        delivery: &CallbackDelivery,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        let body = serde_json::to_string(delivery).unwrap_or_default();
+        redis
+            .set(
+                &RedisKeyService::job_callback(job_id),
+                &body,
+                Some(ttl_secs),
+            )
+            .await
+    }
+
+    pub async fn load(
+        redis: &RedisPool,
+        job_id: &str,
+    ) -> Result<Option<CallbackDelivery>, RedisError> {
+        Ok(redis
+            .get(&RedisKeyService::job_callback(job_id))
+            .await?
+            .and_then(|body| serde_json::from_str(&body).ok()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CallbackError {
+    #[error(transparent)]
+    InvalidUrl(#[from] WebhookError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+
+    use super::*;
+    use crate::app::services::job_state_service::FailureCode;
+    use crate::infra::webhook::{SIGNATURE_HEADER, TIMESTAMP_HEADER, sign};
+
+    /// Records every request and answers with the next of `responses`, then 200.
+    #[derive(Clone)]
+    struct Stub {
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        responses: Arc<Mutex<Vec<StatusCode>>>,
+    }
+
+    async fn receive(State(stub): State<Stub>, headers: HeaderMap, body: String) -> StatusCode {
+        stub.requests.lock().unwrap().push((headers, body));
+        let mut responses = stub.responses.lock().unwrap();
+        if responses.is_empty() {
+            StatusCode::OK
+        } else {
+            responses.remove(0)
+        }
+    }
+
+    async fn serve(responses: Vec<StatusCode>) -> (String, Stub) {
+        let stub = Stub {
+            requests: Arc::default(),
+            responses: Arc::new(Mutex::new(responses)),
+        };
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(stub.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, stub)
+    }
+
+    fn config() -> Config {
+        Config {
+            callback_retry_base_ms: 1,
+            ..Config::default()
+        }
+    }
+
+    /// Allows the stub servers, which listen on loopback.
+    fn loopback_webhooks() -> WebhookClient {
+        WebhookClient::new(Duration::from_secs(5), vec!["127.0.0.1".to_string()]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_signs_each_attempt() {
+        let (url, stub) = serve(vec![StatusCode::SERVICE_UNAVAILABLE]).await;
+        let webhooks = loopback_webhooks();
+        let callback = JobCallback::new(url, Some("s3cret".to_string()), &webhooks)
+            .await
+            .unwrap();
+        let payload = CallbackPayload::new(&config(), "job-1", JobState::Completed, None);
+
+        let delivery =
+            CallbackService::deliver(&webhooks, None, &config(), &callback, &payload).await;
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.response_status, Some(200));
+
+        let requests = stub.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let (headers, body) = &requests[1];
+        let timestamp: u64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign("s3cret", timestamp, body)
+        );
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["job_id"], "job-1");
+        assert_eq!(body["status"], "completed");
+        assert_eq!(body["result_url"], "/api/render/job-1/result");
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_client_errors() {
+        let (url, stub) = serve(vec![StatusCode::GONE]).await;
+        let webhooks = loopback_webhooks();
+        let callback = JobCallback::new(url, None, &webhooks).await.unwrap();
+        let failure = JobFailure::new(FailureCode::RenderFailed, "boom");
+        let payload = CallbackPayload::new(&config(), "job-2", JobState::Failed, Some(failure));
+
+        let delivery =
+            CallbackService::deliver(&webhooks, None, &config(), &callback, &payload).await;
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(410));
+
+        let requests = stub.requests.lock().unwrap();
+        let (headers, body) = &requests[0];
+        assert!(!headers.contains_key(SIGNATURE_HEADER));
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["error"]["code"], "render_failed");
+        assert!(body.get("result_url").is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_non_http_urls() {
+        let webhooks = WebhookClient::new(Duration::from_secs(5), Vec::new()).unwrap();
+        for url in ["ftp://example.com/hook", "not a url"] {
+            assert!(
+                JobCallback::new(url.to_string(), None, &webhooks)
+                    .await
+                    .is_err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_private_addresses_unless_allowed() {
+        let webhooks = WebhookClient::new(Duration::from_secs(5), Vec::new()).unwrap();
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://localhost:8080/hook",
+            "http://10.0.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(
+                matches!(
+                    JobCallback::new(url.to_string(), None, &webhooks).await,
+                    Err(CallbackError::InvalidUrl(WebhookError::Forbidden(_)))
+                ),
+                "{url}"
+            );
+        }
+
+        let webhooks = loopback_webhooks();
+        assert!(
+            JobCallback::new("http://127.0.0.1:9/hook".to_string(), None, &webhooks)
+                .await
+                .is_ok()
+        );
+        assert!(
+            JobCallback::new("https://example.com/hook".to_string(), None, &webhooks)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_follow_redirects() {
+        let app = Router::new().route(
+            "/hook",
+            post(|| async { axum::response::Redirect::temporary("http://127.0.0.1:9/") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let status = loopback_webhooks()
+            .post(&url, "{}".to_string(), None, 0)
+            .await;
+        assert_eq!(status.unwrap(), 307);
+    }
+}
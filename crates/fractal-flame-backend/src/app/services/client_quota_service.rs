@@ -0,0 +1,208 @@
+use crate::app::services::job_record_service::now_millis;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::use_cases::run_render_job_command::Priority;
+use crate::infra::config::Config;
+use crate::infra::redis::RedisPool;
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+/// Suggested wait when a client has too many jobs running; how long theirs take varies too
+/// much to predict.
+const BUSY_RETRY_SECS: u64 = 30;
+
+/// Adds the job to the client's active jobs unless they already have the limit, after dropping
+/// entries past their expiry. Each job is scored by when it expires, so a job that is never
+/// released stops counting once it would have expired anyway.
+const ADMIT_SCRIPT: &str = r#"
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+local limit = tonumber(ARGV[4])
+if limit > 0 and redis.call('ZCARD', KEYS[1]) >= limit then
+    return 0
+end
+redis.call('ZADD', KEYS[1], ARGV[2], ARGV[3])
+local latest = redis.call('ZRANGE', KEYS[1], -1, -1, 'WITHSCORES')
+redis.call('PEXPIREAT', KEYS[1], latest[2])
+return 1
+"#;
+
+/// Per-client limits on jobs in flight and samples per UTC day, counted in Redis so they hold
+/// across instances. A limit of 0 is no limit. When Redis cannot be reached jobs are let
+/// through rather than refused.
+#[derive(Clone, Default)]
+pub struct ClientQuotaService;
+
+impl ClientQuotaService {
+    /// `priority`, unless the job is too large to be interactive, in which case it is
+    /// scheduled as a normal job.
+    pub fn priority(
+        config: &Config,
+        priority: Priority,
+        samples: usize,
+        pixels: usize,
+    ) -> Priority {
+        let within_budget =
+            samples <= config.interactive_max_samples && pixels <= config.interactive_max_pixels;
+        match priority {
+            Priority::Interactive if !within_budget => Priority::Normal,
+            priority => priority,
+        }
+    }
+
+    /// Counts the job, of `samples`, against the client's limits, or refuses it with a hint
+    /// for when to retry.
+    pub async fn admit(
+        redis: &RedisPool,
+        config: &Config,
+        client: &str,
+        job_id: &str,
+        samples: usize,
+    ) -> Result<(), QuotaError> {
+        let active_key = RedisKeyService::client_active_jobs(client);
+        let now = now_millis();
+        let expires_at = now + config.job_ttl_secs * 1000;
+        let limit = config.max_jobs_per_client;
+        let admitted = redis
+            .eval::<i64>(
+                ADMIT_SCRIPT,
+                &[&active_key],
+                &[
+                    now.to_string(),
+                    expires_at.to_string(),
+                    job_id.to_string(),
+                    limit.to_string(),
+                ],
+            )
+            .await;
+        match admitted {
+            Ok(0) => {
+                return Err(QuotaError::TooManyJobs {
+                    limit,
+                    retry_after_secs: BUSY_RETRY_SECS,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(client, error = %e, "Failed to count client jobs");
+                return Ok(());
+            }
+        }
+
+        let limit = config.daily_samples_per_client;
+        if limit == 0 {
+            return Ok(());
+        }
+        let samples_key = RedisKeyService::client_daily_samples(client, now / DAY_MILLIS);
+        let used = redis
+            .incr_by(&samples_key, samples as i64, Some(2 * DAY_MILLIS / 1000))
+            .await;
+        match used {
+            Ok(used) if used > limit as i64 => {
+                let _ = redis.incr_by(&samples_key, -(samples as i64), None).await;
+                let _ = redis.zrem(&active_key, job_id).await;
+                Err(QuotaError::DailySamples {
+                    limit,
+                    retry_after_secs: (DAY_MILLIS - now % DAY_MILLIS).div_ceil(1000),
+                })
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::warn!(client, error = %e, "Failed to count client samples");
+                Ok(())
+            }
+        }
+    }
+
+    /// Frees the client's slot once a job admitted for it is done.
+    pub async fn release(redis: &RedisPool, client: &str, job_id: &str) {
+        let key = RedisKeyService::client_active_jobs(client);
+        if let Err(e) = redis.zrem(&key, job_id).await {
+            tracing::warn!(client, error = %e, "Failed to release client job");
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuotaError {
+    #[error("At most {limit} jobs may be queued or rendering at once")]
+    TooManyJobs { limit: usize, retry_after_secs: u64 },
+    #[error("Daily quota of {limit} samples used up")]
+    DailySamples { limit: usize, retry_after_secs: u64 },
+}
+
+impl QuotaError {
+    pub fn retry_after_secs(&self) -> u64 {
+        match self {
+            Self::TooManyJobs {
+                retry_after_secs, ..
+            }
+            | Self::DailySamples {
+                retry_after_secs, ..
+            } => *retry_after_secs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_interactive_priority_by_budget() {
+        let config = Config {
+            interactive_max_samples: 100,
+            interactive_max_pixels: 64 * 64,
+            ..Config::default()
+        };
+        let priority = |priority, samples, pixels| {
+            ClientQuotaService::priority(&config, priority, samples, pixels)
+        };
+        assert_eq!(
+            priority(Priority::Interactive, 100, 64 * 64),
+            Priority::Interactive
+        );
+        assert_eq!(priority(Priority::Interactive, 101, 16), Priority::Normal);
+        assert_eq!(
+            priority(Priority::Interactive, 10, 64 * 65),
+            Priority::Normal
+        );
+        assert_eq!(priority(Priority::Batch, 1_000, 1 << 20), Priority::Batch);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn limits_active_jobs_until_released_or_expired() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        let redis = RedisPool::from_url(&url).unwrap();
+        let client = "test-quota-client";
+        redis
+            .delete(&RedisKeyService::client_active_jobs(client))
+            .await
+            .unwrap();
+        let config = Config {
+            max_jobs_per_client: 2,
+            ..Config::default()
+        };
+        let admit = |job_id| ClientQuotaService::admit(&redis, &config, client, job_id, 1);
+        admit("a").await.unwrap();
+        admit("b").await.unwrap();
+        assert!(matches!(
+            admit("c").await,
+            Err(QuotaError::TooManyJobs { limit: 2, .. })
+        ));
+        ClientQuotaService::release(&redis, client, "a").await;
+        admit("c").await.unwrap();
+
+        // A job that is never released stops counting once it would have expired, without
+        // taking the client's other jobs with it.
+        let expiring = Config {
+            job_ttl_secs: 0,
+            ..config.clone()
+        };
+        ClientQuotaService::release(&redis, client, "b").await;
+        ClientQuotaService::admit(&redis, &expiring, client, "lost", 1)
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        admit("d").await.unwrap();
+        assert!(admit("e").await.is_err());
+    }
+}
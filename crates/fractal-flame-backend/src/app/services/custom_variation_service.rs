@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use fractal_flame_core::app::transformations::custom::CustomVariationDefinition;
+use fractal_flame_core::app::transformations::registry;
+use fractal_flame_core::domain::CustomVariationSpec;
+
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::minio::{MinioClient, MinioError};
+
+/// Specs already read from MinIO, by id, shared by every request. Variations cannot change
+/// once created, so listing only fetches ids not seen before.
+#[derive(Default)]
+pub struct CustomVariationCache {
+    specs: Mutex<BTreeMap<String, CustomVariationSpec>>,
+}
+
+/// Loads and stores user-defined variations in MinIO as [`CustomVariationSpec`] JSON.
+#[derive(Clone, Default)]
+pub struct CustomVariationService;
+
+impl CustomVariationService {
+    pub fn is_builtin(variation_id: &str) -> bool {
+        registry::is_builtin(variation_id)
+    }
+
+    pub async fn exists(minio: &MinioClient, variation_id: &str) -> bool {
+        minio
+            .get_object(&MinioKeyService::custom_variation_key(variation_id))
+            .await
+            .is_ok()
+    }
+
+    pub async fn save(minio: &MinioClient, record: &CustomVariationSpec) -> Result<(), MinioError> {
+        let body = serde_json::to_vec(record).map_err(|e| MinioError::S3(e.to_string()))?;
+        minio
+            .put_object(
+                &MinioKeyService::custom_variation_key(&record.id),
+                body,
+                "application/json",
+            )
+            .await
+    }
+
+    pub async fn load_spec(minio: &MinioClient, variation_id: &str) -> Option<CustomVariationSpec> {
+        let bytes = minio
+            .get_object(&MinioKeyService::custom_variation_key(variation_id))
+            .await
+            .ok()?;
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| {
+                tracing::warn!(variation_id = %variation_id, error = %e, "Corrupt custom variation record");
+            })
+            .ok()
+    }
+
+    pub async fn load(
+        minio: &MinioClient,
+        variation_id: &str,
+    ) -> Option<Arc<CustomVariationDefinition>> {
+        let record = Self::load_spec(minio, variation_id).await?;
+        record
+            .compile()
+            .inspect_err(|e| {
+                tracing::warn!(variation_id = %variation_id, error = %e, "Stored custom variation no longer compiles");
+            })
+            .ok()
+            .map(Arc::new)
+    }
+
+    /// Specs for every non-built-in id in `ids`; ids that are not found are simply absent.
+    pub async fn load_specs_for_ids(
+        minio: &MinioClient,
+        ids: &[String],
+    ) -> Vec<CustomVariationSpec> {
+        let mut result: Vec<CustomVariationSpec> = Vec::new();
+        for id in ids {
+            if Self::is_builtin(id) || result.iter().any(|s| &s.id == id) {
+                continue;
+            }
+            if let Some(spec) = Self::load_spec(minio, id).await {
+                result.push(spec);
+            }
+        }
+        result
+    }
+
+    /// Stored ids, sorted.
+    async fn list_ids(minio: &MinioClient) -> Vec<String> {
+        let prefix = MinioKeyService::custom_variations_prefix();
+        let keys = match minio.list_keys(prefix).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to list custom variations");
+                return Vec::new();
+            }
+        };
+        let mut ids: Vec<String> = keys
+            .iter()
+            .filter_map(|key| key.strip_prefix(prefix)?.strip_suffix(".json"))
+            .map(str::to_string)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Every stored variation that still compiles, sorted by id.
+    pub async fn list(
+        minio: &MinioClient,
+        cache: &CustomVariationCache,
+    ) -> Vec<Arc<CustomVariationDefinition>> {
+        Self::list_specs(minio, cache)
+            .await
+            .into_iter()
+            .filter_map(|spec| {
+                spec.compile()
+                    .inspect_err(|e| {
+                        tracing::warn!(variation_id = %spec.id, error = %e, "Stored custom variation no longer compiles");
+                    })
+                    .ok()
+                    .map(Arc::new)
+            })
+            .collect()
+    }
+
+    /// Every stored spec, sorted by id: one listing request, plus one fetch per id not yet in
+    /// `cache`.
+    pub async fn list_specs(
+        minio: &MinioClient,
+        cache: &CustomVariationCache,
+    ) -> Vec<CustomVariationSpec> {
+        let ids = Self::list_ids(minio).await;
+        let missing: Vec<String> = {
+            let specs = cache.specs.lock().unwrap_or_else(|e| e.into_inner());
+            ids.iter()
+                .filter(|id| !specs.contains_key(*id))
+                .cloned()
+                .collect()
+        };
+        let mut fetched = Vec::with_capacity(missing.len());
+        for id in &missing {
+            if let Some(spec) = Self::load_spec(minio, id).await {
+                fetched.push(spec);
+            }
+        }
+
+        let mut specs = cache.specs.lock().unwrap_or_else(|e| e.into_inner());
+        specs.extend(fetched.into_iter().map(|spec| (spec.id.clone(), spec)));
+        ids.iter().filter_map(|id| specs.get(id).cloned()).collect()
+    }
+}
@@ -0,0 +1,37 @@
+use fractal_flame_core::domain::FlameGenome;
+
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::minio::{MinioClient, MinioError};
+
+/// Loads and stores the genome each job was rendered from.
+#[derive(Clone, Default)]
+pub struct GenomeService;
+
+impl GenomeService {
+    pub async fn save(
+        minio: &MinioClient,
+        job_id: &str,
+        genome: &FlameGenome,
+    ) -> Result<(), MinioError> {
+        let body = serde_json::to_vec_pretty(genome).map_err(|e| MinioError::S3(e.to_string()))?;
+        minio
+            .put_object(
+                &MinioKeyService::genome_key(job_id),
+                body,
+                "application/json",
+            )
+            .await
+    }
+
+    pub async fn load(minio: &MinioClient, job_id: &str) -> Option<FlameGenome> {
+        let bytes = minio
+            .get_object(&MinioKeyService::genome_key(job_id))
+            .await
+            .ok()?;
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| {
+                tracing::warn!(job_id = %job_id, error = %e, "Corrupt stored genome");
+            })
+            .ok()
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use fractal_flame_core::app::renderer::{PauseExpiry, RenderControl};
+use fractal_flame_core::domain::ToneMapping;
+use tokio::task::JoinHandle;
+
+use crate::app::services::job_event_service::{JobEvent, JobEventService};
+use crate::app::services::job_record_service::now_millis;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::config::Config;
+use crate::infra::redis::{RedisError, RedisPool};
+
+const PAUSED: &str = "paused";
+const PAUSED_AT: &str = "paused_at";
+const CANCELLED: &str = "cancelled";
+const PREVIEW_GAMMA: &str = "preview_gamma";
+const PREVIEW_BRIGHTNESS: &str = "preview_brightness";
+const SNAPSHOT: &str = "snapshot";
+
+/// What clients have asked of a job while it runs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobControls {
+    pub paused: bool,
+    /// When the job was last paused, in milliseconds since the Unix epoch.
+    pub paused_at: Option<u64>,
+    pub cancelled: bool,
+    /// Tone mapping for previews only; the result keeps the genome's own.
+    pub preview_gamma: Option<f64>,
+    pub preview_brightness: Option<f64>,
+    /// When a client last asked for a preview straight away, in milliseconds since the Unix
+    /// epoch.
+    pub snapshot_requested_at: Option<u64>,
+}
+
+impl JobControls {
+    /// Whether the job has been paused for longer than `max_pause_secs` as of `now`.
+    pub fn pause_expired(&self, max_pause_secs: u64, now: u64) -> bool {
+        self.paused
+            && self
+                .paused_at
+                .is_some_and(|at| now.saturating_sub(at) >= max_pause_secs.saturating_mul(1000))
+    }
+
+    /// `base` with the preview overrides applied.
+    pub fn preview_tone_mapping(&self, base: &ToneMapping) -> ToneMapping {
+        ToneMapping {
+            gamma: self.preview_gamma.unwrap_or(base.gamma),
+            brightness: self.preview_brightness.unwrap_or(base.brightness),
+            ..*base
+        }
+    }
+}
+
+/// Pause, resume, cancel and preview requests for a job, kept in a Redis hash so the worker
+/// rendering it on any instance can follow them.
+#[derive(Clone, Default)]
+pub struct JobControlService;
+
+impl JobControlService {
+    pub async fn load(redis: &RedisPool, job_id: &str) -> Result<JobControls, RedisError> {
+        let fields = redis
+            .hgetall(&RedisKeyService::job_controls(job_id))
+            .await?;
+        let number = |field: &str| {
+            fields
+                .get(field)
+                .and_then(|value| value.parse::<f64>().ok())
+        };
+        Ok(JobControls {
+            paused: fields.get(PAUSED).is_some_and(|value| value == "1"),
+            paused_at: fields.get(PAUSED_AT).and_then(|at| at.parse().ok()),
+            cancelled: fields.get(CANCELLED).is_some_and(|value| value == "1"),
+            preview_gamma: number(PREVIEW_GAMMA),
+            preview_brightness: number(PREVIEW_BRIGHTNESS),
+            snapshot_requested_at: fields.get(SNAPSHOT).and_then(|at| at.parse().ok()),
+        })
+    }
+
+    pub async fn set_paused(
+        redis: &RedisPool,
+        job_id: &str,
+        paused: bool,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        if paused {
+            let now = now_millis().to_string();
+            Self::set(redis, job_id, PAUSED_AT, &now, ttl_secs).await?;
+        }
+        let value = if paused { "1" } else { "0" };
+        Self::set(redis, job_id, PAUSED, value, ttl_secs).await?;
+        JobEventService::publish(redis, job_id, &JobEvent::Paused { paused }).await
+    }
+
+    pub async fn cancel(redis: &RedisPool, job_id: &str, ttl_secs: u64) -> Result<(), RedisError> {
+        Self::set(redis, job_id, CANCELLED, "1", ttl_secs).await
+    }
+
+    /// Overrides whichever of the preview's gamma and brightness are given.
+    pub async fn set_preview(
+        redis: &RedisPool,
+        job_id: &str,
+        gamma: Option<f64>,
+        brightness: Option<f64>,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        if let Some(gamma) = gamma {
+            Self::set(redis, job_id, PREVIEW_GAMMA, &gamma.to_string(), ttl_secs).await?;
+        }
+        if let Some(brightness) = brightness {
+            let value = brightness.to_string();
+            Self::set(redis, job_id, PREVIEW_BRIGHTNESS, &value, ttl_secs).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn request_snapshot(
+        redis: &RedisPool,
+        job_id: &str,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        let now = now_millis().to_string();
+        Self::set(redis, job_id, SNAPSHOT, &now, ttl_secs).await
+    }
+
+    async fn set(
+        redis: &RedisPool,
+        job_id: &str,
+        field: &str,
+        value: &str,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        redis
+            .hset(
+                &RedisKeyService::job_controls(job_id),
+                field,
+                value,
+                Some(ttl_secs),
+            )
+            .await
+    }
+
+    /// Applies the job's pause and cancel requests to `control` every
+    /// `progress_sync_interval_ms` until `done` is set. A pause that outlasts
+    /// `max_pause_secs` is ended for everyone following the job, as `pause_expiry` says.
+    /// `control` is also cancelled, for this worker alone, once `lease_lost` is raised.
+    pub fn follow(
+        redis: Arc<RedisPool>,
+        job_id: &str,
+        control: Arc<RenderControl>,
+        done: Arc<AtomicBool>,
+        lease_lost: Option<Arc<AtomicBool>>,
+        config: &Config,
+    ) -> JoinHandle<()> {
+        let job_id = job_id.to_string();
+        let interval = Duration::from_millis(config.progress_sync_interval_ms);
+        let (max_pause_secs, expiry) = (config.max_pause_secs, config.pause_expiry);
+        let ttl_secs = config.job_ttl_secs;
+        tokio::spawn(async move {
+            while !done.load(Ordering::Relaxed) {
+                if lease_lost
+                    .as_ref()
+                    .is_some_and(|lost| lost.load(Ordering::Relaxed))
+                {
+                    control.cancel();
+                }
+                if let Ok(mut controls) = Self::load(&redis, &job_id).await {
+                    if controls.pause_expired(max_pause_secs, now_millis()) {
+                        tracing::info!(job_id = %job_id, ?expiry, "Pause outlasted its limit");
+                        let ended = match expiry {
+                            PauseExpiry::Resume => {
+                                controls.paused = false;
+                                Self::set_paused(&redis, &job_id, false, ttl_secs).await
+                            }
+                            PauseExpiry::Cancel => {
+                                controls.cancelled = true;
+                                Self::cancel(&redis, &job_id, ttl_secs).await
+                            }
+                        };
+                        if let Err(e) = ended {
+                            tracing::warn!(job_id = %job_id, error = %e, "Failed to end pause");
+                        }
+                    }
+                    control.set_paused(controls.paused);
+                    if controls.cancelled {
+                        control.cancel();
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_expire_after_the_limit() {
+        let controls = JobControls {
+            paused: true,
+            paused_at: Some(10_000),
+            ..JobControls::default()
+        };
+        assert!(!controls.pause_expired(60, 69_999));
+        assert!(controls.pause_expired(60, 70_000));
+        // Only pauses expire, and only those with a known start.
+        let resumed = JobControls {
+            paused: false,
+            ..controls.clone()
+        };
+        assert!(!resumed.pause_expired(60, 70_000));
+        let unknown = JobControls {
+            paused_at: None,
+            ..controls
+        };
+        assert!(!unknown.pause_expired(60, 70_000));
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::app::services::job_state_service::{JobFailure, JobState};
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::redis::{RedisError, RedisPool, RedisSubscriber};
+
+/// Sets one shard's count in the hash of shard progress, then the job's progress to their sum.
+const SHARD_PROGRESS_SCRIPT: &str = r#"
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+local total = 0
+for _, samples in ipairs(redis.call('HVALS', KEYS[1])) do
+    total = total + tonumber(samples)
+end
+redis.call('SET', KEYS[2], total, 'EX', ARGV[3])
+return total
+"#;
+
+/// A change to one of a job's stored keys. Events carry the new value rather than a
+/// difference, so a follower that reads the keys after subscribing can apply them in any
+/// overlap without counting anything twice.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    Status {
+        status: JobState,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<JobFailure>,
+    },
+    Progress {
+        progress: u64,
+    },
+    /// Rendering has started, with everything it counts reset.
+    Started {
+        total: u64,
+    },
+    Intermediate {
+        intermediate_version: u64,
+    },
+    Paused {
+        paused: bool,
+    },
+}
+
+/// Stores a job's progress and tells anyone following the job, on its
+/// [`RedisKeyService::job_events`] channel.
+#[derive(Clone, Default)]
+pub struct JobEventService;
+
+impl JobEventService {
+    pub async fn publish(
+        redis: &RedisPool,
+        job_id: &str,
+        event: &JobEvent,
+    ) -> Result<(), RedisError> {
+        let message = serde_json::to_string(event).unwrap_or_default();
+        redis
+            .publish(&RedisKeyService::job_events(job_id), &message)
+            .await
+    }
+
+    /// Resets the job's progress to none of `total` samples.
+    pub async fn start(
+        redis: &RedisPool,
+        job_id: &str,
+        total: u64,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        redis
+            .set(
+                &RedisKeyService::job_total(job_id),
+                &total.to_string(),
+                Some(ttl_secs),
+            )
+            .await?;
+        redis
+            .set(&RedisKeyService::job_progress(job_id), "0", Some(ttl_secs))
+            .await?;
+        redis
+            .set(
+                &RedisKeyService::job_intermediate_version(job_id),
+                "0",
+                Some(ttl_secs),
+            )
+            .await?;
+        Self::publish(redis, job_id, &JobEvent::Started { total }).await
+    }
+
+    pub async fn set_progress(
+        redis: &RedisPool,
+        job_id: &str,
+        progress: u64,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        redis
+            .set(
+                &RedisKeyService::job_progress(job_id),
+                &progress.to_string(),
+                Some(ttl_secs),
+            )
+            .await?;
+        Self::publish(redis, job_id, &JobEvent::Progress { progress }).await
+    }
+
+    /// Copies `progress` into the job's progress every `interval` until `done` is set.
+    pub fn sync_progress(
+        redis: Option<Arc<RedisPool>>,
+        job_id: &str,
+        progress: Arc<AtomicUsize>,
+        done: Arc<AtomicBool>,
+        interval: Duration,
+        ttl_secs: u64,
+    ) -> JoinHandle<()> {
+        let job_id = job_id.to_string();
+        tokio::spawn(async move {
+            let mut reported = 0;
+            while !done.load(Ordering::Relaxed) {
+                tokio::time::sleep(interval).await;
+                let current = progress.load(Ordering::Relaxed);
+                if current != reported
+                    && let Some(ref r) = redis
+                    && Self::set_progress(r, &job_id, current as u64, ttl_secs)
+                        .await
+                        .is_ok()
+                {
+                    reported = current;
+                }
+            }
+        })
+    }
+
+    /// Records `samples` as what shard `index` has rendered and sets the job's progress to the
+    /// total over its shards, for renders split across workers. A shard rendered again after
+    /// its worker stopped replaces its earlier count rather than adding to it.
+    pub async fn set_shard_progress(
+        redis: &RedisPool,
+        job_id: &str,
+        index: usize,
+        samples: u64,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        let shards_key = RedisKeyService::job_shard_progress(job_id);
+        let progress_key = RedisKeyService::job_progress(job_id);
+        let progress = redis
+            .eval::<i64>(
+                SHARD_PROGRESS_SCRIPT,
+                &[&shards_key, &progress_key],
+                &[index.to_string(), samples.to_string(), ttl_secs.to_string()],
+            )
+            .await?;
+        let progress = progress.max(0) as u64;
+        Self::publish(redis, job_id, &JobEvent::Progress { progress }).await
+    }
+
+    pub async fn set_intermediate_version(
+        redis: &RedisPool,
+        job_id: &str,
+        intermediate_version: u64,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        redis
+            .set(
+                &RedisKeyService::job_intermediate_version(job_id),
+                &intermediate_version.to_string(),
+                Some(ttl_secs),
+            )
+            .await?;
+        let event = JobEvent::Intermediate {
+            intermediate_version,
+        };
+        Self::publish(redis, job_id, &event).await
+    }
+
+    /// The job's events from now on, as JSON; see [`JobEventService::parse`].
+    pub fn subscribe(subscriber: &RedisSubscriber, job_id: &str) -> broadcast::Receiver<String> {
+        subscriber.subscribe(&RedisKeyService::job_events(job_id))
+    }
+
+    pub fn parse(message: &str) -> Option<JobEvent> {
+        serde_json::from_str(message).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn counts_a_rerendered_shard_once() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        let redis = RedisPool::from_url(&url).unwrap();
+        let job_id = "test-shard-progress";
+        redis
+            .delete(&RedisKeyService::job_shard_progress(job_id))
+            .await
+            .unwrap();
+        let progress = || async {
+            redis
+                .get(&RedisKeyService::job_progress(job_id))
+                .await
+                .unwrap()
+        };
+
+        JobEventService::set_shard_progress(&redis, job_id, 0, 40, 60)
+            .await
+            .unwrap();
+        JobEventService::set_shard_progress(&redis, job_id, 1, 10, 60)
+            .await
+            .unwrap();
+        assert_eq!(progress().await.as_deref(), Some("50"));
+
+        // Shard 0's worker stopped; another renders it again from the start.
+        JobEventService::set_shard_progress(&redis, job_id, 0, 5, 60)
+            .await
+            .unwrap();
+        assert_eq!(progress().await.as_deref(), Some("15"));
+        JobEventService::set_shard_progress(&redis, job_id, 0, 100, 60)
+            .await
+            .unwrap();
+        assert_eq!(progress().await.as_deref(), Some("110"));
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use fractal_flame_core::app::image_export::ExportFormat;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app::services::job_record_service::Lineage;
+use crate::app::services::job_state_service::{JobState, JobStateService};
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::use_cases::run_render_job_command::Priority;
+use crate::infra::redis::{RedisError, RedisPool};
+
+/// What a job was asked to render.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobParams {
+    pub variation_ids: Vec<String>,
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub iter_per_sample: usize,
+    pub format: ExportFormat,
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Lineage>,
+}
+
+/// A job as listed in the index, for as long as its Redis keys live.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub job_id: String,
+    /// Milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub params: JobParams,
+}
+
+/// Filters and page of a job listing. Time bounds are inclusive, in milliseconds since the
+/// Unix epoch.
+#[derive(Clone, Debug, Default)]
+pub struct JobQuery {
+    pub status: Option<String>,
+    pub created_from: Option<u64>,
+    pub created_to: Option<u64>,
+    pub variation_id: Option<String>,
+    pub oldest_first: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct JobListing {
+    pub summary: JobSummary,
+    pub status: String,
+    /// When the job last entered each state, in milliseconds since the Unix epoch.
+    pub timings: BTreeMap<String, u64>,
+}
+
+pub struct JobPage {
+    pub jobs: Vec<JobListing>,
+    /// Jobs matching the query across all pages.
+    pub total: usize,
+}
+
+/// Stores the job's summary and adds it to the index sets in `KEYS[2..ARGV[5] + 1]`, first
+/// trimming every set in `KEYS[2..]` of jobs created before `ARGV[4]`, which have expired.
+const ADD_SCRIPT: &str = r#"
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+local adds = tonumber(ARGV[5])
+for i = 2, #KEYS do
+    redis.call('ZREMRANGEBYSCORE', KEYS[i], '-inf', '(' .. ARGV[4])
+    if i <= adds + 1 then
+        redis.call('ZADD', KEYS[i], ARGV[6], ARGV[1])
+    end
+end
+return 1
+"#;
+
+/// One page of the index set in `KEYS[1]`, or of its intersection with `KEYS[2]` stored in the
+/// scratch key `KEYS[3]`, between scores `ARGV[1]` and `ARGV[2]`. Returns how many jobs match
+/// and the ids on the page.
+const PAGE_SCRIPT: &str = r#"
+local source = KEYS[1]
+if #KEYS == 3 then
+    redis.call('ZINTERSTORE', KEYS[3], 2, KEYS[1], KEYS[2], 'AGGREGATE', 'MAX')
+    source = KEYS[3]
+end
+local total = redis.call('ZCOUNT', source, ARGV[1], ARGV[2])
+local ids
+if ARGV[5] == '1' then
+    ids = redis.call('ZRANGEBYSCORE', source, ARGV[1], ARGV[2], 'LIMIT', ARGV[3], ARGV[4])
+else
+    ids = redis.call('ZREVRANGEBYSCORE', source, ARGV[2], ARGV[1], 'LIMIT', ARGV[3], ARGV[4])
+end
+if #KEYS == 3 then
+    redis.call('DEL', KEYS[3])
+end
+return {total, ids}
+"#;
+
+/// Status of an indexed job that has not been given one yet.
+const PENDING: &str = "pending";
+
+/// Index of recent jobs in Redis: sorted sets of ids by creation time, one of every job and
+/// one per status and per variation, plus each job's summary and state changes, which expire
+/// with the job's other keys. Entries older than the jobs' lifetime are trimmed as jobs are
+/// added.
+#[derive(Clone, Default)]
+pub struct JobIndexService;
+
+impl JobIndexService {
+    /// The per-status index sets, `pending` first.
+    pub fn status_sets() -> Vec<String> {
+        std::iter::once(PENDING)
+            .chain(JobState::ALL.map(JobState::as_str))
+            .map(RedisKeyService::job_index_by_status)
+            .collect()
+    }
+
+    pub async fn add(
+        redis: &RedisPool,
+        summary: &JobSummary,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        let body = serde_json::to_string(summary).unwrap_or_default();
+        let summary_key = RedisKeyService::job_summary(&summary.job_id);
+        let status_sets = Self::status_sets();
+        let mut sets = vec![RedisKeyService::job_index().to_string()];
+        sets.push(status_sets[0].clone());
+        sets.extend(
+            summary
+                .params
+                .variation_ids
+                .iter()
+                .map(|id| RedisKeyService::job_index_by_variation(id)),
+        );
+        let adds = sets.len();
+        sets.extend(status_sets.into_iter().skip(1));
+
+        let mut keys = vec![summary_key.as_str()];
+        keys.extend(sets.iter().map(String::as_str));
+        let cutoff = summary.created_at.saturating_sub(ttl_secs * 1000);
+        redis
+            .eval::<i64>(
+                ADD_SCRIPT,
+                &keys,
+                &[
+                    summary.job_id.clone(),
+                    body,
+                    ttl_secs.to_string(),
+                    cutoff.to_string(),
+                    adds.to_string(),
+                    summary.created_at.to_string(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load(redis: &RedisPool, job_id: &str) -> Result<Option<JobSummary>, RedisError> {
+        Ok(redis
+            .get(&RedisKeyService::job_summary(job_id))
+            .await?
+            .and_then(|body| serde_json::from_str(&body).ok()))
+    }
+
+    /// Jobs matching `query`, newest first unless asked otherwise. Index entries whose job
+    /// has expired are dropped along the way.
+    pub async fn query(redis: &RedisPool, query: &JobQuery) -> Result<JobPage, RedisError> {
+        let status_set = query
+            .status
+            .as_deref()
+            .map(RedisKeyService::job_index_by_status);
+        let variation_set = query
+            .variation_id
+            .as_deref()
+            .map(RedisKeyService::job_index_by_variation);
+        let scratch = RedisKeyService::job_index_scratch(&Uuid::new_v4().to_string());
+        let keys: Vec<&str> = match (&status_set, &variation_set) {
+            (Some(status), Some(variation)) => vec![status, variation, &scratch],
+            (Some(set), None) | (None, Some(set)) => vec![set],
+            (None, None) => vec![RedisKeyService::job_index()],
+        };
+        let (total, ids) = redis
+            .eval::<(usize, Vec<String>)>(
+                PAGE_SCRIPT,
+                &keys,
+                &[
+                    query
+                        .created_from
+                        .map_or("-inf".to_string(), |from| from.to_string()),
+                    query
+                        .created_to
+                        .map_or("+inf".to_string(), |to| to.to_string()),
+                    query.offset.to_string(),
+                    query.limit.to_string(),
+                    if query.oldest_first { "1" } else { "0" }.to_string(),
+                ],
+            )
+            .await?;
+
+        let mut keys: Vec<String> = ids
+            .iter()
+            .map(|id| RedisKeyService::job_summary(id))
+            .collect();
+        keys.extend(ids.iter().map(|id| RedisKeyService::job_status(id)));
+        let mut values = redis.mget(&keys).await?;
+        let statuses = values.split_off(ids.len());
+        let transition_keys: Vec<String> = ids
+            .iter()
+            .map(|id| RedisKeyService::job_transitions(id))
+            .collect();
+        let transitions = redis.list_many(&transition_keys).await?;
+
+        let mut jobs = Vec::with_capacity(ids.len());
+        for (((job_id, summary), status), transitions) in
+            ids.into_iter().zip(values).zip(statuses).zip(transitions)
+        {
+            let Some(summary) = summary.and_then(|body| serde_json::from_str(&body).ok()) else {
+                Self::remove(redis, &job_id, variation_set.as_deref()).await?;
+                continue;
+            };
+            jobs.push(JobListing {
+                summary,
+                status: status.unwrap_or_else(|| PENDING.to_string()),
+                timings: JobStateService::timings(&JobStateService::parse_transitions(transitions)),
+            });
+        }
+        Ok(JobPage { jobs, total })
+    }
+
+    /// Drops an expired job from the index sets it can be found in.
+    async fn remove(
+        redis: &RedisPool,
+        job_id: &str,
+        variation_set: Option<&str>,
+    ) -> Result<(), RedisError> {
+        redis.zrem(RedisKeyService::job_index(), job_id).await?;
+        for set in Self::status_sets()
+            .iter()
+            .map(String::as_str)
+            .chain(variation_set)
+        {
+            redis.zrem(set, job_id).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn redis() -> Arc<RedisPool> {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        Arc::new(RedisPool::from_url(&url).unwrap())
+    }
+
+    fn summary(job_id: &str, created_at: u64, variation_id: &str) -> JobSummary {
+        JobSummary {
+            job_id: job_id.to_string(),
+            created_at,
+            params: JobParams {
+                variation_ids: vec![variation_id.to_string()],
+                width: 64,
+                height: 64,
+                samples: 1,
+                iter_per_sample: 1,
+                format: ExportFormat::default(),
+                priority: Priority::Normal,
+                lineage: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn pages_by_status_and_variation() {
+        let redis = redis();
+        let mut sets = JobIndexService::status_sets();
+        sets.push(RedisKeyService::job_index().to_string());
+        sets.push(RedisKeyService::job_index_by_variation("test-linear"));
+        sets.push(RedisKeyService::job_index_by_variation("test-swirl"));
+        for key in &sets {
+            redis.delete(key).await.unwrap();
+        }
+        let now = crate::app::services::job_record_service::now_millis();
+        for (i, variation_id) in ["test-linear", "test-swirl", "test-linear"]
+            .into_iter()
+            .enumerate()
+        {
+            let job_id = format!("test-index-{i}");
+            let summary = summary(&job_id, now + i as u64, variation_id);
+            JobIndexService::add(&redis, &summary, 60).await.unwrap();
+        }
+        JobStateService::transition(&redis, "test-index-2", JobState::Queued, 60)
+            .await
+            .unwrap();
+
+        let ids = |page: JobPage| -> (usize, Vec<String>) {
+            let ids = page
+                .jobs
+                .into_iter()
+                .map(|job| job.summary.job_id)
+                .collect();
+            (page.total, ids)
+        };
+        let query = JobQuery {
+            limit: 2,
+            ..JobQuery::default()
+        };
+        let page = JobIndexService::query(&redis, &query).await.unwrap();
+        assert_eq!(
+            ids(page),
+            (3, vec!["test-index-2".into(), "test-index-1".into()])
+        );
+
+        let query = JobQuery {
+            oldest_first: true,
+            offset: 1,
+            limit: 5,
+            variation_id: Some("test-linear".to_string()),
+            ..JobQuery::default()
+        };
+        let page = JobIndexService::query(&redis, &query).await.unwrap();
+        assert_eq!(ids(page), (2, vec!["test-index-2".into()]));
+
+        let query = JobQuery {
+            status: Some("pending".to_string()),
+            variation_id: Some("test-linear".to_string()),
+            limit: 5,
+            ..JobQuery::default()
+        };
+        let page = JobIndexService::query(&redis, &query).await.unwrap();
+        assert_eq!(ids(page), (1, vec!["test-index-0".into()]));
+
+        let query = JobQuery {
+            status: Some("queued".to_string()),
+            limit: 5,
+            ..JobQuery::default()
+        };
+        let page = JobIndexService::query(&redis, &query).await.unwrap();
+        assert_eq!(page.jobs[0].status, "queued");
+        assert_eq!(ids(page), (1, vec!["test-index-2".into()]));
+    }
+}
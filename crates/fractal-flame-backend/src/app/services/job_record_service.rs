@@ -0,0 +1,88 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fractal_flame_core::app::image_export::ExportFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::minio::{MinioClient, MinioError};
+
+/// The jobs a job was derived from and how.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lineage {
+    pub parent_ids: Vec<String>,
+    /// e.g. `remix`, `mutate:swap_variation` or `breed:blend`.
+    pub operation: String,
+}
+
+impl Lineage {
+    pub fn new(parent_ids: Vec<String>, operation: impl Into<String>) -> Self {
+        Self {
+            parent_ids,
+            operation: operation.into(),
+        }
+    }
+}
+
+/// Durable facts about a job that outlive its Redis keys.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub job_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Lineage>,
+    /// Milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// Encoding of the stored result; records from before formats were selectable are PNG.
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+impl JobRecord {
+    pub fn new(job_id: String, lineage: Option<Lineage>) -> Self {
+        Self {
+            job_id,
+            lineage,
+            created_at: now_millis(),
+            format: ExportFormat::default(),
+        }
+    }
+
+    pub fn with_format(mut self, format: ExportFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Default)]
+pub struct JobRecordService;
+
+impl JobRecordService {
+    pub async fn save(minio: &MinioClient, record: &JobRecord) -> Result<(), MinioError> {
+        let body = serde_json::to_vec(record).map_err(|e| MinioError::S3(e.to_string()))?;
+        minio
+            .put_object(
+                &MinioKeyService::job_record_key(&record.job_id),
+                body,
+                "application/json",
+            )
+            .await
+    }
+
+    pub async fn load(minio: &MinioClient, job_id: &str) -> Option<JobRecord> {
+        let bytes = minio
+            .get_object(&MinioKeyService::job_record_key(job_id))
+            .await
+            .ok()?;
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| {
+                tracing::warn!(job_id = %job_id, error = %e, "Corrupt job record");
+            })
+            .ok()
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::services::job_event_service::{JobEvent, JobEventService};
+use crate::app::services::job_index_service::JobIndexService;
+use crate::app::services::job_record_service::now_millis;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::redis::{RedisError, RedisPool};
+
+/// Moves the job to `ARGV[2]` if its current status, `''` for none, is one of `ARGV[6..]`:
+/// sets the status, appends `ARGV[3]` to its transitions, stores the failure `ARGV[5]` if
+/// any, and moves it from whichever of the status index sets in `KEYS[6..]` it is in to
+/// `KEYS[5]`, if it is indexed. Returns the current status if the move is not allowed.
+const RECORD_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1]) or ''
+if current == ARGV[2] then
+    return false
+end
+local allowed = false
+for i = 6, #ARGV do
+    if ARGV[i] == current then
+        allowed = true
+    end
+end
+if not allowed then
+    return current
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[4])
+redis.call('RPUSH', KEYS[2], ARGV[3])
+redis.call('EXPIRE', KEYS[2], ARGV[4])
+if ARGV[5] ~= '' then
+    redis.call('SET', KEYS[3], ARGV[5], 'EX', ARGV[4])
+end
+local created = redis.call('ZSCORE', KEYS[4], ARGV[1])
+if created then
+    for i = 6, #KEYS do
+        redis.call('ZREM', KEYS[i], ARGV[1])
+    end
+    redis.call('ZADD', KEYS[5], created, ARGV[1])
+end
+return false
+"#;
+
+/// Where a render job is in its life:
+/// `queued → rendering → encoding → uploading → completed`, or `failed` or `cancelled` from
+/// any state before `completed`. Jobs may skip ahead, and a job whose worker stopped goes
+/// back to `queued` from wherever it had got to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Rendering,
+    /// Tone mapping and encoding the finished histogram.
+    Encoding,
+    /// Sending the last of the encoded result to MinIO.
+    Uploading,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub const ALL: [JobState; 7] = [
+        Self::Queued,
+        Self::Rendering,
+        Self::Encoding,
+        Self::Uploading,
+        Self::Completed,
+        Self::Failed,
+        Self::Cancelled,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Rendering => "rendering",
+            Self::Encoding => "encoding",
+            Self::Uploading => "uploading",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.as_str() == value)
+    }
+
+    /// Whether the job is done and will not change state again.
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+
+    /// Whether a job in `from`, or in no state yet, may move to `self`.
+    pub fn can_follow(self, from: Option<JobState>) -> bool {
+        let Some(from) = from else {
+            return matches!(
+                self,
+                Self::Queued | Self::Rendering | Self::Failed | Self::Cancelled
+            );
+        };
+        match self {
+            _ if from.is_terminal() => false,
+            Self::Queued => from != Self::Queued,
+            Self::Completed => from != Self::Queued,
+            Self::Failed | Self::Cancelled => true,
+            // Ordered by declaration: rendering, encoding and uploading only move forwards.
+            _ => (from as u8) < (self as u8),
+        }
+    }
+}
+
+/// Why a job failed, for clients to act on without parsing the message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCode {
+    /// The genome could not be generated or compiled.
+    InvalidGenome,
+    RenderFailed,
+    EncodeFailed,
+    UploadFailed,
+    /// A worker could not render one of the job's shards.
+    ShardFailed,
+    /// The job's shards were not all rendered before the job would have expired.
+    TimedOut,
+    /// The job's queued request expired or could not be read.
+    Lost,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JobFailure {
+    pub code: FailureCode,
+    pub message: String,
+}
+
+impl JobFailure {
+    pub fn new(code: FailureCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// How a job ended.
+#[derive(Clone, Debug)]
+pub enum JobOutcome {
+    Completed,
+    Failed(JobFailure),
+    Cancelled,
+}
+
+impl JobOutcome {
+    pub fn state(&self) -> JobState {
+        match self {
+            Self::Completed => JobState::Completed,
+            Self::Failed(_) => JobState::Failed,
+            Self::Cancelled => JobState::Cancelled,
+        }
+    }
+}
+
+/// One state change, in milliseconds since the Unix epoch.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub state: String,
+    pub at: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransitionError {
+    #[error("A job that is {from} cannot become {to}")]
+    Illegal { from: String, to: &'static str },
+    #[error(transparent)]
+    Redis(#[from] RedisError),
+}
+
+/// A job's state, every state change it has been through and why it failed, kept in Redis
+/// alongside the job's other keys. Changes are checked against [`JobState::can_follow`] as
+/// they are made, so a job never leaves a state it has finished in.
+#[derive(Clone, Default)]
+pub struct JobStateService;
+
+impl JobStateService {
+    /// Moves the job to `state`, unless it is already there.
+    pub async fn transition(
+        redis: &RedisPool,
+        job_id: &str,
+        state: JobState,
+        ttl_secs: u64,
+    ) -> Result<(), TransitionError> {
+        Self::record(redis, job_id, state, None, ttl_secs).await?;
+        let event = JobEvent::Status {
+            status: state,
+            error: None,
+        };
+        Ok(JobEventService::publish(redis, job_id, &event).await?)
+    }
+
+    /// Moves the job to `rendering` with its progress reset to none of `total` samples.
+    pub async fn start_rendering(
+        redis: &RedisPool,
+        job_id: &str,
+        total: u64,
+        ttl_secs: u64,
+    ) -> Result<(), TransitionError> {
+        Self::transition(redis, job_id, JobState::Rendering, ttl_secs).await?;
+        Ok(JobEventService::start(redis, job_id, total, ttl_secs).await?)
+    }
+
+    /// Records `failure` as the job moves to `failed`, so anyone who sees the state can read
+    /// the reason.
+    pub async fn fail(
+        redis: &RedisPool,
+        job_id: &str,
+        failure: &JobFailure,
+        ttl_secs: u64,
+    ) -> Result<(), TransitionError> {
+        Self::record(redis, job_id, JobState::Failed, Some(failure), ttl_secs).await?;
+        let event = JobEvent::Status {
+            status: JobState::Failed,
+            error: Some(failure.clone()),
+        };
+        Ok(JobEventService::publish(redis, job_id, &event).await?)
+    }
+
+    async fn record(
+        redis: &RedisPool,
+        job_id: &str,
+        state: JobState,
+        failure: Option<&JobFailure>,
+        ttl_secs: u64,
+    ) -> Result<(), TransitionError> {
+        let status_key = RedisKeyService::job_status(job_id);
+        let transitions_key = RedisKeyService::job_transitions(job_id);
+        let error_key = RedisKeyService::job_error(job_id);
+        let state_set = RedisKeyService::job_index_by_status(state.as_str());
+        let status_sets = JobIndexService::status_sets();
+        let mut keys = vec![
+            status_key.as_str(),
+            transitions_key.as_str(),
+            error_key.as_str(),
+            RedisKeyService::job_index(),
+            state_set.as_str(),
+        ];
+        keys.extend(status_sets.iter().map(String::as_str));
+
+        let transition = Transition {
+            state: state.as_str().to_string(),
+            at: now_millis(),
+        };
+        let mut args = vec![
+            job_id.to_string(),
+            state.as_str().to_string(),
+            serde_json::to_string(&transition).unwrap_or_default(),
+            ttl_secs.to_string(),
+            failure
+                .and_then(|failure| serde_json::to_string(failure).ok())
+                .unwrap_or_default(),
+        ];
+        args.extend(
+            std::iter::once(None)
+                .chain(JobState::ALL.map(Some))
+                .filter(|from| state.can_follow(*from))
+                .map(|from| from.map_or("", JobState::as_str).to_string()),
+        );
+
+        match redis
+            .eval::<Option<String>>(RECORD_SCRIPT, &keys, &args)
+            .await?
+        {
+            None => Ok(()),
+            Some(from) => Err(TransitionError::Illegal {
+                from: if from.is_empty() {
+                    "new".to_string()
+                } else {
+                    from
+                },
+                to: state.as_str(),
+            }),
+        }
+    }
+
+    /// The job's current state, if it is still kept.
+    pub async fn state(redis: &RedisPool, job_id: &str) -> Result<Option<JobState>, RedisError> {
+        Ok(redis
+            .get(&RedisKeyService::job_status(job_id))
+            .await?
+            .as_deref()
+            .and_then(JobState::parse))
+    }
+
+    pub async fn failure(
+        redis: &RedisPool,
+        job_id: &str,
+    ) -> Result<Option<JobFailure>, RedisError> {
+        Ok(redis
+            .get(&RedisKeyService::job_error(job_id))
+            .await?
+            .and_then(|body| serde_json::from_str(&body).ok()))
+    }
+
+    /// Every state change the job has been through, in the order they happened.
+    pub async fn transitions(
+        redis: &RedisPool,
+        job_id: &str,
+    ) -> Result<Vec<Transition>, RedisError> {
+        Ok(Self::parse_transitions(
+            redis
+                .list(&RedisKeyService::job_transitions(job_id))
+                .await?,
+        ))
+    }
+
+    pub fn parse_transitions(entries: Vec<String>) -> Vec<Transition> {
+        entries
+            .iter()
+            .filter_map(|entry| serde_json::from_str(entry).ok())
+            .collect()
+    }
+
+    /// When the job last entered each state it has been in.
+    pub fn timings(transitions: &[Transition]) -> BTreeMap<String, u64> {
+        transitions
+            .iter()
+            .map(|transition| (transition.state.clone(), transition.at))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use JobState::*;
+
+    #[test]
+    fn allows_only_the_state_machines_transitions() {
+        let allowed = |from: Option<JobState>| -> Vec<JobState> {
+            JobState::ALL
+                .into_iter()
+                .filter(|state| state.can_follow(from))
+                .collect()
+        };
+        assert_eq!(allowed(None), [Queued, Rendering, Failed, Cancelled]);
+        assert_eq!(
+            allowed(Some(Queued)),
+            [Rendering, Encoding, Uploading, Failed, Cancelled]
+        );
+        assert_eq!(
+            allowed(Some(Rendering)),
+            [Queued, Encoding, Uploading, Completed, Failed, Cancelled]
+        );
+        assert_eq!(
+            allowed(Some(Encoding)),
+            [Queued, Uploading, Completed, Failed, Cancelled]
+        );
+        assert_eq!(
+            allowed(Some(Uploading)),
+            [Queued, Completed, Failed, Cancelled]
+        );
+        for terminal in [Completed, Failed, Cancelled] {
+            assert!(allowed(Some(terminal)).is_empty());
+        }
+    }
+
+    #[test]
+    fn failure_codes_are_snake_case() {
+        let failure = JobFailure::new(FailureCode::TimedOut, "shards did not finish");
+        let body = serde_json::to_string(&failure).unwrap();
+        assert_eq!(
+            body,
+            r#"{"code":"timed_out","message":"shards did not finish"}"#
+        );
+        assert_eq!(serde_json::from_str::<JobFailure>(&body).unwrap(), failure);
+        for (code, name) in [
+            (FailureCode::InvalidGenome, "\"invalid_genome\""),
+            (FailureCode::RenderFailed, "\"render_failed\""),
+            (FailureCode::EncodeFailed, "\"encode_failed\""),
+            (FailureCode::UploadFailed, "\"upload_failed\""),
+            (FailureCode::ShardFailed, "\"shard_failed\""),
+            (FailureCode::Lost, "\"lost\""),
+        ] {
+            assert_eq!(serde_json::to_string(&code).unwrap(), name);
+        }
+        assert_eq!(JobOutcome::Failed(failure).state(), Failed);
+    }
+
+    #[test]
+    fn timings_keep_the_last_time_each_state_was_entered() {
+        let entries = [
+            r#"{"state":"queued","at":1}"#,
+            r#"{"state":"rendering","at":2}"#,
+            "not json",
+            r#"{"state":"queued","at":3}"#,
+        ];
+        let transitions =
+            JobStateService::parse_transitions(entries.into_iter().map(String::from).collect());
+        assert_eq!(transitions.len(), 3);
+        let timings = JobStateService::timings(&transitions);
+        assert_eq!(timings["queued"], 3);
+        assert_eq!(timings["rendering"], 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn rejects_illegal_transitions_and_keeps_every_change() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        let redis = Arc::new(RedisPool::from_url(&url).unwrap());
+        let job_id = "test-state";
+        for key in [
+            RedisKeyService::job_status(job_id),
+            RedisKeyService::job_transitions(job_id),
+            RedisKeyService::job_error(job_id),
+        ] {
+            redis.delete(&key).await.unwrap();
+        }
+
+        let transition = |state| JobStateService::transition(&redis, job_id, state, 60);
+        transition(Queued).await.unwrap();
+        transition(Rendering).await.unwrap();
+        // Re-queued after its worker stopped, then rendered again.
+        transition(Queued).await.unwrap();
+        transition(Rendering).await.unwrap();
+        transition(Rendering).await.unwrap();
+        let failure = JobFailure::new(FailureCode::EncodeFailed, "out of memory");
+        JobStateService::fail(&redis, job_id, &failure, 60)
+            .await
+            .unwrap();
+        assert!(matches!(
+            transition(Queued).await,
+            Err(TransitionError::Illegal { ref from, to: "queued" }) if from == "failed"
+        ));
+        let other = JobFailure::new(FailureCode::Lost, "gone");
+        assert!(
+            JobStateService::fail(&redis, job_id, &other, 60)
+                .await
+                .is_ok()
+        );
+
+        let states: Vec<String> = JobStateService::transitions(&redis, job_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|transition| transition.state)
+            .collect();
+        assert_eq!(
+            states,
+            ["queued", "rendering", "queued", "rendering", "failed"]
+        );
+        assert_eq!(
+            JobStateService::failure(&redis, job_id).await.unwrap(),
+            Some(failure)
+        );
+    }
+}
@@ -3,9 +3,9 @@
 pub struct MinioKeyService;
 
 impl MinioKeyService {
-    /// Key for render result: `jobs/{job_id}/result.png`
-    pub fn render_result_key(job_id: &str) -> String {
-        format!("jobs/{}/result.png", job_id)
+    /// Key for render result: `jobs/{job_id}/result.{extension}`
+    pub fn render_result_key(job_id: &str, extension: &str) -> String {
+        format!("jobs/{}/result.{}", job_id, extension)
     }
 
     /// Key for intermediate snapshot: `jobs/{job_id}/intermediate.png`
@@ -13,8 +13,53 @@ impl MinioKeyService {
         format!("jobs/{}/intermediate.png", job_id)
     }
 
+    /// Key for the genome a job was rendered from: `jobs/{job_id}/genome.json`
+    pub fn genome_key(job_id: &str) -> String {
+        format!("jobs/{}/genome.json", job_id)
+    }
+
+    /// Key for the keyframes of an animation job: `jobs/{job_id}/animation.json`
+    pub fn animation_key(job_id: &str) -> String {
+        format!("jobs/{}/animation.json", job_id)
+    }
+
+    /// Key for one frame of an animation job: `jobs/{job_id}/frames/{index:04}.png`
+    pub fn frame_key(job_id: &str, index: usize) -> String {
+        format!("jobs/{}/frames/{:04}.png", job_id, index)
+    }
+
+    /// Key for an animation job's assembled file: `jobs/{job_id}/animation.{extension}`
+    pub fn animation_artifact_key(job_id: &str, extension: &str) -> String {
+        format!("jobs/{}/animation.{}", job_id, extension)
+    }
+
+    /// Key for one shard's raw histogram: `jobs/{job_id}/shards/{index:04}.hist`
+    pub fn shard_histogram_key(job_id: &str, index: usize) -> String {
+        format!("jobs/{}/shards/{:04}.hist", job_id, index)
+    }
+
+    /// Key for a job's small preview image: `jobs/{job_id}/thumbnail.png`
+    pub fn thumbnail_key(job_id: &str) -> String {
+        format!("jobs/{}/thumbnail.png", job_id)
+    }
+
+    /// Key for a job's durable record: `jobs/{job_id}/job.json`
+    pub fn job_record_key(job_id: &str) -> String {
+        format!("jobs/{}/job.json", job_id)
+    }
+
     /// Key for variation preview: `previews/{variation_id}_{symmetry}_{gamma}.png`
     pub fn preview_key(variation_id: &str, symmetry: usize, gamma: f64) -> String {
         format!("previews/{}_{}_{:.2}.png", variation_id, symmetry, gamma)
     }
+
+    /// Key for a user-defined variation: `variations/custom/{variation_id}.json`
+    pub fn custom_variation_key(variation_id: &str) -> String {
+        format!("{}{}.json", Self::custom_variations_prefix(), variation_id)
+    }
+
+    /// Prefix under which all user-defined variations are stored.
+    pub fn custom_variations_prefix() -> &'static str {
+        "variations/custom/"
+    }
 }
@@ -1,2 +1,13 @@
+pub mod callback_service;
+pub mod client_quota_service;
+pub mod custom_variation_service;
+pub mod genome_service;
+pub mod job_control_service;
+pub mod job_event_service;
+pub mod job_index_service;
+pub mod job_record_service;
+pub mod job_state_service;
 pub mod minio_key_service;
 pub mod redis_key_service;
+pub mod render_queue_service;
+pub mod shard_queue_service;
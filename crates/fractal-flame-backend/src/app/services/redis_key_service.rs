@@ -17,4 +17,138 @@ impl RedisKeyService {
     pub fn job_intermediate_version(job_id: &str) -> String {
         format!("job:{}:intermediate_version", job_id)
     }
+
+    /// The queued job's command, as JSON.
+    pub fn job_command(job_id: &str) -> String {
+        format!("job:{}:command", job_id)
+    }
+
+    /// The queued animation job's command, as JSON.
+    pub fn job_animation_command(job_id: &str) -> String {
+        format!("job:{}:animation_command", job_id)
+    }
+
+    /// Held by the worker rendering the job for as long as it keeps renewing it.
+    pub fn job_lease(job_id: &str) -> String {
+        format!("job:{}:lease", job_id)
+    }
+
+    /// What the job was asked to render, as JSON for job listings.
+    pub fn job_summary(job_id: &str) -> String {
+        format!("job:{}:summary", job_id)
+    }
+
+    /// Why the job failed, as JSON with a machine-readable code and a message.
+    pub fn job_error(job_id: &str) -> String {
+        format!("job:{}:error", job_id)
+    }
+
+    /// How delivering the job's callback went, as JSON.
+    pub fn job_callback(job_id: &str) -> String {
+        format!("job:{}:callback", job_id)
+    }
+
+    /// Hash of what clients have asked of the job while it runs: pause, cancel and preview
+    /// settings.
+    pub fn job_controls(job_id: &str) -> String {
+        format!("job:{}:controls", job_id)
+    }
+
+    /// Channel the job's progress and state changes are published on, as JSON.
+    pub fn job_events(job_id: &str) -> String {
+        format!("job:{}:events", job_id)
+    }
+
+    /// Matches every job's [`job_events`](Self::job_events) channel.
+    pub fn job_events_pattern() -> &'static str {
+        "job:*:events"
+    }
+
+    /// List of the job's state changes as JSON, oldest first.
+    pub fn job_transitions(job_id: &str) -> String {
+        format!("job:{}:transitions", job_id)
+    }
+
+    /// Set of the indices of the job's shards that have rendered.
+    pub fn job_shards_done(job_id: &str) -> String {
+        format!("job:{}:shards_done", job_id)
+    }
+
+    /// Set of the indices of the job's shards that have failed.
+    pub fn job_shards_failed(job_id: &str) -> String {
+        format!("job:{}:shards_failed", job_id)
+    }
+
+    /// Hash of the samples each of the job's shards has rendered, by shard index.
+    pub fn job_shard_progress(job_id: &str) -> String {
+        format!("job:{}:shard_progress", job_id)
+    }
+
+    /// Sorted set of every job id, scored by creation time.
+    pub fn job_index() -> &'static str {
+        "jobs:index"
+    }
+
+    /// Sorted set of the indexed jobs in `status`, scored like [`RedisKeyService::job_index`].
+    pub fn job_index_by_status(status: &str) -> String {
+        format!("jobs:index:status:{}", status)
+    }
+
+    /// Sorted set of the indexed jobs using the variation, scored like
+    /// [`RedisKeyService::job_index`].
+    pub fn job_index_by_variation(variation_id: &str) -> String {
+        format!("jobs:index:variation:{}", variation_id)
+    }
+
+    /// Scratch key one listing intersects its filters into, deleted before the listing returns.
+    pub fn job_index_scratch(listing_id: &str) -> String {
+        format!("jobs:index:scratch:{}", listing_id)
+    }
+
+    /// Sorted set of job ids waiting for a worker on any instance, lowest virtual finish time
+    /// first.
+    pub fn render_job_queue() -> &'static str {
+        "render:queue"
+    }
+
+    /// Virtual time of the fair scheduler: the finish time of the job taken last.
+    pub fn render_queue_clock() -> &'static str {
+        "render:queue:clock"
+    }
+
+    /// List of job ids taken off the queue by a worker that has not finished them.
+    pub fn render_job_processing() -> &'static str {
+        "render:jobs:processing"
+    }
+
+    /// List of shards waiting for a worker on any instance.
+    pub fn render_shard_queue() -> &'static str {
+        "render:shards"
+    }
+
+    /// List of shards taken off the queue by a worker that has not finished them.
+    pub fn render_shard_processing() -> &'static str {
+        "render:shards:processing"
+    }
+
+    /// Held by the worker rendering the shard for as long as it keeps renewing it.
+    pub fn shard_lease(job_id: &str, index: usize) -> String {
+        format!("job:{}:shard:{}:lease", job_id, index)
+    }
+
+    /// Virtual finish time of the client's most recently queued job.
+    pub fn client_finish_time(client: &str) -> String {
+        format!("client:{}:finish_time", client)
+    }
+
+    /// Sorted set of the jobs the client has queued or rendering, scored by when each stops
+    /// counting, in milliseconds since the Unix epoch.
+    pub fn client_active_jobs(client: &str) -> String {
+        format!("client:{}:active_jobs", client)
+    }
+
+    /// Samples the client has requested on `day`, counted in days since the Unix epoch.
+    pub fn client_daily_samples(client: &str, day: u64) -> String {
+        format!("client:{}:samples:{}", client, day)
+    }
 }
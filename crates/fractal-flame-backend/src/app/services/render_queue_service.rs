@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::app::services::job_state_service::{JobState, JobStateService, TransitionError};
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::redis::{RedisError, RedisPool};
+
+/// Stamps a job with its virtual finish time: it starts once the scheduler's clock or the
+/// client's previous job is done, whichever is later, and lasts its weighted cost.
+const ENQUEUE_SCRIPT: &str = r#"
+local clock = tonumber(redis.call('GET', KEYS[2]) or '0')
+local previous = tonumber(redis.call('GET', KEYS[3]) or '0')
+local finish = math.max(clock, previous) + tonumber(ARGV[2])
+redis.call('SET', KEYS[3], tostring(finish), 'EX', ARGV[3])
+redis.call('ZADD', KEYS[1], tostring(finish), ARGV[1])
+return 1
+"#;
+
+/// Takes the job that finishes first in virtual time, advances the clock to it and moves it
+/// onto the processing list, all at once so no job is lost between the two.
+const TAKE_SCRIPT: &str = r#"
+local popped = redis.call('ZPOPMIN', KEYS[1])
+if #popped == 0 then
+    return false
+end
+local clock = tonumber(redis.call('GET', KEYS[2]) or '0')
+if tonumber(popped[2]) > clock then
+    redis.call('SET', KEYS[2], popped[2])
+end
+redis.call('LPUSH', KEYS[3], popped[1])
+return popped[1]
+"#;
+
+/// Extends the lease if `token` still holds it.
+const RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('EXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// Takes the work off the processing list and drops its lease, unless another worker has
+/// leased it since.
+const RELEASE_SCRIPT: &str = r#"
+local holder = redis.call('GET', KEYS[2])
+if holder and holder ~= ARGV[2] then
+    return 0
+end
+redis.call('LREM', KEYS[1], 1, ARGV[1])
+redis.call('DEL', KEYS[2])
+return 1
+"#;
+
+/// Renews a lease key in the background until dropped, so however the worker holding it
+/// stops, the lease lapses and the work is picked up again.
+///
+/// Each take of the work stores a token of its own under the key, and only that token's
+/// holder renews or releases it. Once the lease has lapsed or passed to another worker,
+/// renewal stops and [`Lease::lost`] is raised so this worker can give the work up.
+pub struct Lease {
+    renewal: JoinHandle<()>,
+    lost: Arc<AtomicBool>,
+    key: String,
+    token: String,
+}
+
+impl Lease {
+    /// Leases `key` to a new holder and returns its token.
+    pub async fn acquire(
+        redis: &RedisPool,
+        key: &str,
+        lease_secs: u64,
+    ) -> Result<String, RedisError> {
+        let token = Uuid::new_v4().to_string();
+        redis.set(key, &token, Some(lease_secs)).await?;
+        Ok(token)
+    }
+
+    pub fn hold(redis: Arc<RedisPool>, key: String, token: String, lease_secs: u64) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+        let renewal = {
+            let (key, token, lost) = (key.clone(), token.clone(), lost.clone());
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs((lease_secs / 3).max(1))).await;
+                    let renewed = redis
+                        .eval::<i64>(
+                            RENEW_SCRIPT,
+                            &[&key],
+                            &[token.clone(), lease_secs.to_string()],
+                        )
+                        .await;
+                    match renewed {
+                        Ok(1) => {}
+                        Ok(_) => {
+                            tracing::warn!(key, "Lease lapsed or was taken over by another worker");
+                            lost.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        Err(e) => tracing::warn!(key, error = %e, "Failed to renew lease"),
+                    }
+                }
+            })
+        };
+        Self {
+            renewal,
+            lost,
+            key,
+            token,
+        }
+    }
+
+    /// Raised once this worker no longer holds the lease.
+    pub fn lost(&self) -> Arc<AtomicBool> {
+        self.lost.clone()
+    }
+
+    /// Takes `member` off the `processing` list and drops the lease, unless another worker
+    /// has leased it since. Returns whether it did.
+    pub async fn release(
+        &self,
+        redis: &RedisPool,
+        processing: &str,
+        member: &str,
+    ) -> Result<bool, RedisError> {
+        let released = redis
+            .eval::<i64>(
+                RELEASE_SCRIPT,
+                &[processing, &self.key],
+                &[member.to_string(), self.token.clone()],
+            )
+            .await?;
+        Ok(released == 1)
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+/// The durable queue of render jobs shared by every instance, scheduled by weighted fair
+/// queueing: each client's jobs are spaced out in virtual time by their cost over their
+/// weight, so one client's long renders do not hold back other clients' short ones, and
+/// interactive jobs overtake normal and batch ones.
+///
+/// A worker moves the job it takes onto a processing list and holds a lease on it while it
+/// renders; jobs whose lease lapses are put back at the front of the queue, so each job is
+/// picked up at least once.
+#[derive(Clone, Default)]
+pub struct RenderQueueService;
+
+impl RenderQueueService {
+    /// `cost` is the job's work over its weight, in whatever unit every job shares. The
+    /// caller marks the job `queued`.
+    pub async fn enqueue(
+        redis: &RedisPool,
+        job_id: &str,
+        client: &str,
+        cost: f64,
+        ttl_secs: u64,
+    ) -> Result<(), RedisError> {
+        redis
+            .eval::<i64>(
+                ENQUEUE_SCRIPT,
+                &[
+                    RedisKeyService::render_job_queue(),
+                    RedisKeyService::render_queue_clock(),
+                    &RedisKeyService::client_finish_time(client),
+                ],
+                &[job_id.to_string(), cost.to_string(), ttl_secs.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Takes the next job, if any is queued, and leases it. Returns the job id and the
+    /// lease's token, which [`RenderQueueService::hold`] needs.
+    pub async fn take(
+        redis: &RedisPool,
+        lease_secs: u64,
+    ) -> Result<Option<(String, String)>, RedisError> {
+        let Some(job_id) = redis
+            .eval::<Option<String>>(
+                TAKE_SCRIPT,
+                &[
+                    RedisKeyService::render_job_queue(),
+                    RedisKeyService::render_queue_clock(),
+                    RedisKeyService::render_job_processing(),
+                ],
+                &[],
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        let token = Lease::acquire(redis, &RedisKeyService::job_lease(&job_id), lease_secs).await?;
+        Ok(Some((job_id, token)))
+    }
+
+    /// Keeps `job_id`'s lease for as long as `token` holds it, until the returned [`Lease`] is
+    /// dropped.
+    pub fn hold(redis: Arc<RedisPool>, job_id: &str, token: &str, lease_secs: u64) -> Lease {
+        Lease::hold(
+            redis,
+            RedisKeyService::job_lease(job_id),
+            token.to_string(),
+            lease_secs,
+        )
+    }
+
+    /// Releases a job the worker is done with, whether it succeeded or not, unless another
+    /// worker has taken it over since.
+    pub async fn complete(
+        redis: &RedisPool,
+        job_id: &str,
+        lease: &Lease,
+    ) -> Result<(), RedisError> {
+        lease
+            .release(redis, RedisKeyService::render_job_processing(), job_id)
+            .await?;
+        Ok(())
+    }
+
+    /// How many jobs will be taken before `job_id`, if it is still queued.
+    pub async fn position(redis: &RedisPool, job_id: &str) -> Result<Option<usize>, RedisError> {
+        redis
+            .zrank(RedisKeyService::render_job_queue(), job_id)
+            .await
+    }
+
+    /// Re-queues processing jobs without a lease. A worker leases a job just after taking it,
+    /// so a job is only treated as orphaned once it has gone unleased on two calls in a row;
+    /// `suspects` carries the first sightings between calls. Returns how many were re-queued.
+    pub async fn requeue_orphans(
+        redis: &RedisPool,
+        suspects: &mut HashSet<String>,
+        ttl_secs: u64,
+    ) -> Result<usize, RedisError> {
+        let mut unleased = HashSet::new();
+        for job_id in redis.list(RedisKeyService::render_job_processing()).await? {
+            if redis
+                .get(&RedisKeyService::job_lease(&job_id))
+                .await?
+                .is_none()
+            {
+                unleased.insert(job_id);
+            }
+        }
+
+        let orphans: Vec<String> = unleased.intersection(suspects).cloned().collect();
+        let mut requeued = 0;
+        for job_id in orphans {
+            unleased.remove(&job_id);
+            // Another instance may have got here first; only whoever removes it re-queues it.
+            if redis
+                .remove(RedisKeyService::render_job_processing(), &job_id)
+                .await?
+            {
+                // A job that finished before its worker stopped stays finished.
+                match JobStateService::transition(redis, &job_id, JobState::Queued, ttl_secs).await
+                {
+                    Ok(()) => {}
+                    Err(TransitionError::Illegal { .. }) => continue,
+                    Err(TransitionError::Redis(e)) => return Err(e),
+                }
+                // Scheduled at the current virtual time, so it goes before every job that has
+                // not started yet.
+                let clock = redis
+                    .get(RedisKeyService::render_queue_clock())
+                    .await?
+                    .and_then(|clock| clock.parse().ok())
+                    .unwrap_or(0.0);
+                redis
+                    .zadd(RedisKeyService::render_job_queue(), &job_id, clock)
+                    .await?;
+                requeued += 1;
+            }
+        }
+        *suspects = unleased;
+        Ok(requeued)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redis() -> Arc<RedisPool> {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        Arc::new(RedisPool::from_url(&url).unwrap())
+    }
+
+    #[tokio::test]
+    async fn dropping_a_lease_stops_renewing_it() {
+        let lease = Lease::hold(redis(), "test:lease".to_string(), "token".to_string(), 3);
+        let renewal = lease.renewal.abort_handle();
+        assert!(!renewal.is_finished());
+        drop(lease);
+        tokio::task::yield_now().await;
+        assert!(renewal.is_finished());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn takes_leases_and_requeues_jobs() {
+        let redis = redis();
+        for key in [
+            RedisKeyService::render_job_queue(),
+            RedisKeyService::render_queue_clock(),
+            RedisKeyService::render_job_processing(),
+            &RedisKeyService::client_finish_time("test-a"),
+            &RedisKeyService::client_finish_time("test-b"),
+        ] {
+            redis.delete(key).await.unwrap();
+        }
+        // Finish times: slow at 10, quick at 1, then after_slow at 11 behind its client's job.
+        RenderQueueService::enqueue(&redis, "slow", "test-a", 10.0, 60)
+            .await
+            .unwrap();
+        RenderQueueService::enqueue(&redis, "quick", "test-b", 1.0, 60)
+            .await
+            .unwrap();
+        RenderQueueService::enqueue(&redis, "after_slow", "test-a", 1.0, 60)
+            .await
+            .unwrap();
+        assert_eq!(
+            RenderQueueService::position(&redis, "slow").await.unwrap(),
+            Some(1)
+        );
+
+        let take = || async {
+            let (job_id, token) = RenderQueueService::take(&redis, 60).await.unwrap()?;
+            let lease = RenderQueueService::hold(redis.clone(), &job_id, &token, 3);
+            Some((job_id, lease))
+        };
+        let (quick, lease) = take().await.unwrap();
+        assert_eq!(quick, "quick");
+        RenderQueueService::complete(&redis, &quick, &lease)
+            .await
+            .unwrap();
+        let (slow, stale) = take().await.unwrap();
+        assert_eq!(slow, "slow");
+        let processing = RedisKeyService::render_job_processing();
+        assert_eq!(redis.list(processing).await.unwrap(), ["slow"]);
+
+        // A leased job is left alone; one whose worker stopped renewing it is re-queued on the
+        // second check that finds it unleased, ahead of every job not yet started.
+        let mut suspects = HashSet::new();
+        let requeue = RenderQueueService::requeue_orphans;
+        assert_eq!(requeue(&redis, &mut suspects, 60).await.unwrap(), 0);
+        redis
+            .delete(&RedisKeyService::job_lease("slow"))
+            .await
+            .unwrap();
+        assert_eq!(requeue(&redis, &mut suspects, 60).await.unwrap(), 0);
+        assert_eq!(requeue(&redis, &mut suspects, 60).await.unwrap(), 1);
+        assert!(redis.list(processing).await.unwrap().is_empty());
+        assert_eq!(
+            RenderQueueService::position(&redis, "slow").await.unwrap(),
+            Some(0)
+        );
+        let (slow, lease) = take().await.unwrap();
+        assert_eq!(slow, "slow");
+
+        // The worker that lost the job notices on its next renewal, and neither renews nor
+        // releases the new worker's lease.
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(stale.lost().load(Ordering::Relaxed));
+        assert!(!lease.lost().load(Ordering::Relaxed));
+        RenderQueueService::complete(&redis, &slow, &stale)
+            .await
+            .unwrap();
+        assert_eq!(redis.list(processing).await.unwrap(), ["slow"]);
+        assert!(
+            redis
+                .get(&RedisKeyService::job_lease("slow"))
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        RenderQueueService::complete(&redis, &slow, &lease)
+            .await
+            .unwrap();
+        let (after_slow, lease) = take().await.unwrap();
+        assert_eq!(after_slow, "after_slow");
+        RenderQueueService::complete(&redis, &after_slow, &lease)
+            .await
+            .unwrap();
+        assert!(take().await.is_none());
+    }
+}
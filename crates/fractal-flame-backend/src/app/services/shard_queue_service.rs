@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::services::render_queue_service::Lease;
+use crate::app::use_cases::render_shard_command::RenderShardCommand;
+use crate::infra::redis::{RedisError, RedisPool};
+
+/// The queue of shards shared by every instance's shard workers. Like
+/// [`RenderQueueService`](crate::app::services::render_queue_service::RenderQueueService), a
+/// worker moves the shard it takes onto a processing list and holds a lease on it while it
+/// renders; shards whose lease lapses are put back at the front of the queue, so each shard is
+/// rendered at least once.
+#[derive(Clone, Default)]
+pub struct ShardQueueService;
+
+impl ShardQueueService {
+    pub async fn enqueue(redis: &RedisPool, shard: &RenderShardCommand) -> Result<(), RedisError> {
+        let payload = serde_json::to_string(shard).unwrap_or_default();
+        redis
+            .push(RedisKeyService::render_shard_queue(), &payload)
+            .await
+    }
+
+    /// Takes the oldest shard, waiting up to `wait_secs` for one. Returns the payload as
+    /// stored, which [`ShardQueueService::complete`] needs, and the shard if it parses.
+    pub async fn take(
+        redis: &RedisPool,
+        wait_secs: u64,
+    ) -> Result<Option<(String, Option<RenderShardCommand>)>, RedisError> {
+        let payload = redis
+            .move_blocking(
+                RedisKeyService::render_shard_queue(),
+                RedisKeyService::render_shard_processing(),
+                wait_secs,
+            )
+            .await?;
+        Ok(payload.map(|payload| {
+            let shard = serde_json::from_str(&payload).ok();
+            (payload, shard)
+        }))
+    }
+
+    /// Leases `shard` and keeps the lease until the returned [`Lease`] is dropped.
+    pub async fn lease(
+        redis: Arc<RedisPool>,
+        shard: &RenderShardCommand,
+        lease_secs: u64,
+    ) -> Result<Lease, RedisError> {
+        let key = RedisKeyService::shard_lease(&shard.job_id, shard.index);
+        let token = Lease::acquire(&redis, &key, lease_secs).await?;
+        Ok(Lease::hold(redis, key, token, lease_secs))
+    }
+
+    /// Releases a shard the worker is done with, whether it succeeded or not. A leased shard
+    /// is left alone once another worker has taken it over.
+    pub async fn complete(
+        redis: &RedisPool,
+        payload: &str,
+        lease: Option<&Lease>,
+    ) -> Result<(), RedisError> {
+        let processing = RedisKeyService::render_shard_processing();
+        match lease {
+            Some(lease) => lease.release(redis, processing, payload).await.map(|_| ()),
+            None => redis.remove(processing, payload).await.map(|_| ()),
+        }
+    }
+
+    /// Re-queues processing shards without a lease, once they have gone unleased on two calls
+    /// in a row, as [`RenderQueueService::requeue_orphans`] does for jobs. Returns how many were
+    /// re-queued.
+    ///
+    /// [`RenderQueueService::requeue_orphans`]:
+    /// crate::app::services::render_queue_service::RenderQueueService::requeue_orphans
+    pub async fn requeue_orphans(
+        redis: &RedisPool,
+        suspects: &mut HashSet<String>,
+    ) -> Result<usize, RedisError> {
+        let processing = RedisKeyService::render_shard_processing();
+        let mut unleased = HashSet::new();
+        for payload in redis.list(processing).await? {
+            let Ok(shard) = serde_json::from_str::<RenderShardCommand>(&payload) else {
+                // Nothing can render it, so there is nothing to put back.
+                redis.remove(processing, &payload).await?;
+                continue;
+            };
+            let key = RedisKeyService::shard_lease(&shard.job_id, shard.index);
+            if redis.get(&key).await?.is_none() {
+                unleased.insert(payload);
+            }
+        }
+
+        let orphans: Vec<String> = unleased.intersection(suspects).cloned().collect();
+        let mut requeued = 0;
+        for payload in orphans {
+            unleased.remove(&payload);
+            // Another instance may have got here first; only whoever removes it re-queues it.
+            if redis.remove(processing, &payload).await? {
+                redis
+                    .push_front(RedisKeyService::render_shard_queue(), &payload)
+                    .await?;
+                requeued += 1;
+            }
+        }
+        *suspects = unleased;
+        Ok(requeued)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn requeues_shards_whose_lease_lapsed() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+        let redis = Arc::new(RedisPool::from_url(&url).unwrap());
+        let queue = RedisKeyService::render_shard_queue();
+        let processing = RedisKeyService::render_shard_processing();
+        redis.delete(queue).await.unwrap();
+        redis.delete(processing).await.unwrap();
+        let shard = |index| RenderShardCommand {
+            job_id: "test-job".to_string(),
+            index,
+            samples: 10,
+            seed: index as u64,
+        };
+        ShardQueueService::enqueue(&redis, &shard(0)).await.unwrap();
+        ShardQueueService::enqueue(&redis, &shard(1)).await.unwrap();
+
+        let (payload, taken) = ShardQueueService::take(&redis, 1).await.unwrap().unwrap();
+        let taken = taken.unwrap();
+        assert_eq!(taken.index, 0);
+        let lease = ShardQueueService::lease(redis.clone(), &taken, 60)
+            .await
+            .unwrap();
+        let mut suspects = HashSet::new();
+        let requeue = ShardQueueService::requeue_orphans;
+        assert_eq!(requeue(&redis, &mut suspects).await.unwrap(), 0);
+        assert_eq!(requeue(&redis, &mut suspects).await.unwrap(), 0);
+
+        // The worker stops: its lease goes and the shard is re-queued ahead of shard 1.
+        drop(lease);
+        let key = RedisKeyService::shard_lease(&taken.job_id, taken.index);
+        redis.delete(&key).await.unwrap();
+        assert_eq!(requeue(&redis, &mut suspects).await.unwrap(), 0);
+        assert_eq!(requeue(&redis, &mut suspects).await.unwrap(), 1);
+        assert!(redis.list(processing).await.unwrap().is_empty());
+
+        let (again, retaken) = ShardQueueService::take(&redis, 1).await.unwrap().unwrap();
+        assert_eq!(again, payload);
+        let lease = ShardQueueService::lease(redis.clone(), &retaken.unwrap(), 60)
+            .await
+            .unwrap();
+        ShardQueueService::complete(&redis, &again, Some(&lease))
+            .await
+            .unwrap();
+        let (_, next) = ShardQueueService::take(&redis, 1).await.unwrap().unwrap();
+        assert_eq!(next.unwrap().index, 1);
+    }
+}
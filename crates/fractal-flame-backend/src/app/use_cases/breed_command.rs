@@ -0,0 +1,12 @@
+use fractal_flame_core::app::evolution::Crossover;
+
+pub struct BreedCommand {
+    /// The two parent jobs; camera, palette and quality come from the first.
+    pub parent_ids: [String; 2],
+    /// Number of children to render.
+    pub count: usize,
+    /// Random per child when `None`.
+    pub method: Option<Crossover>,
+    /// API key of the client the children are rendered for.
+    pub client: String,
+}
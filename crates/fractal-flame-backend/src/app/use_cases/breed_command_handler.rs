@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::evolution::{Crossover, EvolutionError, crossover};
+use fractal_flame_core::app::generation::generation_rng;
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::infra::random;
+
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_record_service::Lineage;
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+
+use super::breed_command::BreedCommand;
+use super::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+
+pub struct BredChild {
+    pub job_id: String,
+    pub method: Crossover,
+}
+
+pub struct BreedCommandHandler {
+    config: Config,
+    minio: Arc<MinioClient>,
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl BreedCommandHandler {
+    pub fn new(
+        config: Config,
+        minio: Arc<MinioClient>,
+        run_handler: RunRenderJobCommandHandler,
+    ) -> Self {
+        Self {
+            config,
+            minio,
+            run_handler,
+        }
+    }
+
+    /// Starts `count` jobs, each rendering a crossover of the two parents' genomes.
+    pub async fn handle(&self, command: BreedCommand) -> Result<Vec<BredChild>, BreedError> {
+        if command.count == 0 || command.count > self.config.max_batch_size {
+            return Err(BreedError::BatchSize {
+                count: command.count,
+                max: self.config.max_batch_size,
+            });
+        }
+        let [first_id, second_id] = &command.parent_ids;
+        let first = GenomeService::load(&self.minio, first_id)
+            .await
+            .ok_or_else(|| BreedError::NotFound(first_id.clone()))?;
+        let second = GenomeService::load(&self.minio, second_id)
+            .await
+            .ok_or_else(|| BreedError::NotFound(second_id.clone()))?;
+
+        let mut rng = generation_rng(random::generate_seed());
+        let children = (0..command.count)
+            .map(|_| match command.method {
+                Some(method) => method
+                    .apply(&first, &second, &mut rng)
+                    .map(|child| (method, child)),
+                None => crossover(&first, &second, &mut rng),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (_, genome) in &children {
+            self.run_handler
+                .validate_genome(genome)
+                .map_err(EvolutionError::Genome)?;
+        }
+
+        let mut bred = Vec::with_capacity(children.len());
+        for (method, genome) in children {
+            let job_id = self
+                .run_handler
+                .submit(RunRenderJobCommand {
+                    source: RenderSource::Genome(Box::new(genome)),
+                    lineage: Some(Lineage::new(
+                        command.parent_ids.to_vec(),
+                        format!("breed:{}", method.name()),
+                    )),
+                    format: ExportFormat::default(),
+                    client: Some(command.client.clone()),
+                    priority: Priority::default(),
+                    callback: None,
+                })
+                .await?;
+            bred.push(BredChild { job_id, method });
+        }
+        Ok(bred)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BreedError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error("count must be between 1 and {max}, got {count}")]
+    BatchSize { count: usize, max: usize },
+    #[error(transparent)]
+    Evolution(#[from] EvolutionError),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
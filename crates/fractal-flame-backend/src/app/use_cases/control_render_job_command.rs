@@ -0,0 +1,30 @@
+/// What a client asks of a job while it runs.
+pub enum JobControlAction {
+    Pause,
+    Resume,
+    Cancel,
+    /// Tone maps previews with whichever of these are given instead of the genome's own.
+    Preview {
+        gamma: Option<f64>,
+        brightness: Option<f64>,
+    },
+    /// Publishes a preview straight away.
+    Snapshot,
+}
+
+impl JobControlAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Cancel => "cancel",
+            Self::Preview { .. } => "preview",
+            Self::Snapshot => "snapshot",
+        }
+    }
+}
+
+pub struct ControlRenderJobCommand {
+    pub job_id: String,
+    pub action: JobControlAction,
+}
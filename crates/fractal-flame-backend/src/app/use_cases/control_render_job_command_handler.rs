@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use crate::app::services::job_control_service::JobControlService;
+use crate::app::services::job_index_service::JobIndexService;
+use crate::app::services::job_state_service::JobState;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::config::Config;
+use crate::infra::redis::{RedisError, RedisPool};
+
+use super::control_render_job_command::{ControlRenderJobCommand, JobControlAction};
+
+/// How a job renders, which decides the control requests it follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JobKind {
+    /// In one piece, with live previews.
+    Render,
+    /// Tile by tile or in shards, without previews.
+    Split,
+    /// Frame by frame, without following any requests.
+    Animation,
+}
+
+impl JobKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Render => "render",
+            Self::Split => "tiled and sharded",
+            Self::Animation => "animation",
+        }
+    }
+
+    fn follows(self, action: &JobControlAction) -> bool {
+        match self {
+            Self::Render => true,
+            Self::Split => matches!(
+                action,
+                JobControlAction::Pause | JobControlAction::Resume | JobControlAction::Cancel
+            ),
+            Self::Animation => false,
+        }
+    }
+}
+
+pub struct ControlRenderJobCommandHandler {
+    config: Config,
+    redis: Arc<RedisPool>,
+}
+
+impl ControlRenderJobCommandHandler {
+    pub fn new(config: Config, redis: Arc<RedisPool>) -> Self {
+        Self { config, redis }
+    }
+
+    /// Passes the request on to whichever worker renders the job. A job cancelled before a
+    /// worker takes it is dropped when one does.
+    pub async fn handle(
+        &self,
+        command: ControlRenderJobCommand,
+    ) -> Result<(), ControlRenderJobError> {
+        let job_id = &command.job_id;
+        let state = self
+            .redis
+            .get(&RedisKeyService::job_status(job_id))
+            .await?
+            .as_deref()
+            .and_then(JobState::parse)
+            .ok_or_else(|| ControlRenderJobError::NotFound(job_id.clone()))?;
+        if state.is_terminal() {
+            return Err(ControlRenderJobError::Finished(state.as_str()));
+        }
+        let kind = self.kind(job_id).await?;
+        if !kind.follows(&command.action) {
+            return Err(ControlRenderJobError::Unsupported {
+                kind: kind.name(),
+                action: command.action.name(),
+            });
+        }
+
+        let redis = &self.redis;
+        let ttl = self.config.job_ttl_secs;
+        match command.action {
+            JobControlAction::Pause => {
+                JobControlService::set_paused(redis, job_id, true, ttl).await?
+            }
+            JobControlAction::Resume => {
+                JobControlService::set_paused(redis, job_id, false, ttl).await?
+            }
+            JobControlAction::Cancel => JobControlService::cancel(redis, job_id, ttl).await?,
+            JobControlAction::Preview { gamma, brightness } => {
+                if gamma.is_some_and(|gamma| !(gamma.is_finite() && gamma > 0.0)) {
+                    return Err(ControlRenderJobError::InvalidPreview(
+                        "gamma must be positive",
+                    ));
+                }
+                if brightness
+                    .is_some_and(|brightness| !(brightness.is_finite() && brightness >= 0.0))
+                {
+                    return Err(ControlRenderJobError::InvalidPreview(
+                        "brightness must not be negative",
+                    ));
+                }
+                JobControlService::set_preview(redis, job_id, gamma, brightness, ttl).await?
+            }
+            JobControlAction::Snapshot => {
+                JobControlService::request_snapshot(redis, job_id, ttl).await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Render jobs are told apart by the size and budget they were indexed with; animations
+    /// are not indexed, but keep their command while they may still run.
+    async fn kind(&self, job_id: &str) -> Result<JobKind, RedisError> {
+        if let Some(summary) = JobIndexService::load(&self.redis, job_id).await? {
+            let params = summary.params;
+            let sharded =
+                self.config.shard_samples > 0 && params.samples > self.config.shard_samples;
+            return Ok(
+                if sharded || self.config.renders_tiled(params.width, params.height) {
+                    JobKind::Split
+                } else {
+                    JobKind::Render
+                },
+            );
+        }
+        let animation = RedisKeyService::job_animation_command(job_id);
+        Ok(match self.redis.get(&animation).await? {
+            Some(_) => JobKind::Animation,
+            None => JobKind::Render,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ControlRenderJobError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error("Job is already {0}")]
+    Finished(&'static str),
+    #[error("Invalid preview settings: {0}")]
+    InvalidPreview(&'static str),
+    #[error("{kind} jobs do not support {action}")]
+    Unsupported {
+        kind: &'static str,
+        action: &'static str,
+    },
+    #[error(transparent)]
+    Redis(#[from] RedisError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_only_accept_the_controls_they_follow() {
+        let actions = [
+            JobControlAction::Pause,
+            JobControlAction::Resume,
+            JobControlAction::Cancel,
+            JobControlAction::Preview {
+                gamma: Some(2.2),
+                brightness: None,
+            },
+            JobControlAction::Snapshot,
+        ];
+        let followed = |kind: JobKind| -> Vec<&str> {
+            actions
+                .iter()
+                .filter(|action| kind.follows(action))
+                .map(JobControlAction::name)
+                .collect()
+        };
+        assert_eq!(
+            followed(JobKind::Render),
+            ["pause", "resume", "cancel", "preview", "snapshot"]
+        );
+        assert_eq!(followed(JobKind::Split), ["pause", "resume", "cancel"]);
+        assert!(followed(JobKind::Animation).is_empty());
+    }
+}
@@ -0,0 +1,9 @@
+use std::collections::BTreeMap;
+
+pub struct CreateCustomVariationCommand {
+    pub id: String,
+    pub name: String,
+    pub x: String,
+    pub y: String,
+    pub parameters: BTreeMap<String, f64>,
+}
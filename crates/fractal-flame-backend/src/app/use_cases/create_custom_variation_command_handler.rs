@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::expression::ExpressionError;
+
+use fractal_flame_core::domain::CustomVariationSpec;
+
+use crate::app::services::custom_variation_service::CustomVariationService;
+use crate::infra::minio::{MinioClient, MinioError};
+
+use super::create_custom_variation_command::CreateCustomVariationCommand;
+use super::get_all_variations_command::VariationDto;
+
+pub struct CreateCustomVariationCommandHandler {
+    minio: Arc<MinioClient>,
+}
+
+impl CreateCustomVariationCommandHandler {
+    pub fn new(minio: Arc<MinioClient>) -> Self {
+        Self { minio }
+    }
+
+    pub async fn handle(
+        &self,
+        command: CreateCustomVariationCommand,
+    ) -> Result<VariationDto, CreateCustomVariationError> {
+        let CreateCustomVariationCommand {
+            id,
+            name,
+            x,
+            y,
+            parameters,
+        } = command;
+
+        let valid_id = !id.is_empty()
+            && id.len() <= 64
+            && id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid_id {
+            return Err(CreateCustomVariationError::InvalidId(id));
+        }
+        if CustomVariationService::is_builtin(&id)
+            || CustomVariationService::exists(&self.minio, &id).await
+        {
+            return Err(CreateCustomVariationError::AlreadyExists(id));
+        }
+
+        let record = CustomVariationSpec {
+            name: if name.trim().is_empty() {
+                id.clone()
+            } else {
+                name
+            },
+            id,
+            x,
+            y,
+            parameters,
+        };
+        let definition = record.compile()?;
+
+        CustomVariationService::save(&self.minio, &record).await?;
+
+        Ok(VariationDto {
+            id: definition.id.clone(),
+            name: definition.name.clone(),
+            formula_latex: definition.formula().to_string(),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CreateCustomVariationError {
+    #[error("Invalid variation id '{0}': use 1-64 lowercase letters, digits or '_'")]
+    InvalidId(String),
+    #[error("Variation '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("Invalid expression: {0}")]
+    Expression(#[from] ExpressionError),
+    #[error("Failed to store variation: {0}")]
+    Storage(#[from] MinioError),
+}
@@ -2,21 +2,36 @@ use std::sync::Arc;
 
 use fractal_flame_core::domain::transformation::Transformation;
 
+use crate::app::services::custom_variation_service::{
+    CustomVariationCache, CustomVariationService,
+};
+use crate::infra::minio::MinioClient;
+
 use super::get_all_variations_command::{
     GetAllVariationsCommand, GetAllVariationsCommandResult, VariationDto,
 };
 
 pub struct GetAllVariationsCommandHandler {
     pub transformations: Arc<Vec<Box<dyn Transformation + Send + Sync>>>,
+    pub minio: Option<Arc<MinioClient>>,
+    pub custom_variations: Arc<CustomVariationCache>,
 }
 
 impl GetAllVariationsCommandHandler {
-    pub fn new(transformations: Arc<Vec<Box<dyn Transformation + Send + Sync>>>) -> Self {
-        Self { transformations }
+    pub fn new(
+        transformations: Arc<Vec<Box<dyn Transformation + Send + Sync>>>,
+        minio: Option<Arc<MinioClient>>,
+        custom_variations: Arc<CustomVariationCache>,
+    ) -> Self {
+        Self {
+            transformations,
+            minio,
+            custom_variations,
+        }
     }
 
-    pub fn handle(&self, _command: GetAllVariationsCommand) -> GetAllVariationsCommandResult {
-        let variations = self
+    pub async fn handle(&self, _command: GetAllVariationsCommand) -> GetAllVariationsCommandResult {
+        let mut variations: Vec<VariationDto> = self
             .transformations
             .iter()
             .map(|t| VariationDto {
@@ -26,6 +41,19 @@ impl GetAllVariationsCommandHandler {
             })
             .collect();
 
+        if let Some(ref minio) = self.minio {
+            variations.extend(
+                CustomVariationService::list(minio, &self.custom_variations)
+                    .await
+                    .iter()
+                    .map(|d| VariationDto {
+                        id: d.id.clone(),
+                        name: d.name.clone(),
+                        formula_latex: d.formula().to_string(),
+                    }),
+            );
+        }
+
         GetAllVariationsCommandResult { variations }
     }
 }
@@ -0,0 +1,6 @@
+use fractal_flame_core::app::image_export::AnimationFormat;
+
+pub struct GetRenderAnimationCommand {
+    pub job_id: String,
+    pub format: AnimationFormat,
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::minio::MinioClient;
+
+use super::get_render_animation_command::GetRenderAnimationCommand;
+
+#[derive(Debug)]
+pub enum GetRenderAnimationOutcome {
+    Ready(Vec<u8>),
+    NotFound,
+}
+
+pub struct GetRenderAnimationCommandHandler {
+    minio: Arc<MinioClient>,
+}
+
+impl GetRenderAnimationCommandHandler {
+    pub fn new(minio: Arc<MinioClient>) -> Self {
+        Self { minio }
+    }
+
+    pub async fn handle(&self, command: GetRenderAnimationCommand) -> GetRenderAnimationOutcome {
+        let key =
+            MinioKeyService::animation_artifact_key(&command.job_id, command.format.extension());
+
+        match self.minio.get_object(&key).await {
+            Ok(bytes) => GetRenderAnimationOutcome::Ready(bytes),
+            Err(_) => GetRenderAnimationOutcome::NotFound,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub struct GetRenderFlameCommand {
+    pub job_id: String,
+}
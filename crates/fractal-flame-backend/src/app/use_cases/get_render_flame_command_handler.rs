@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::flam3;
+
+use crate::app::services::genome_service::GenomeService;
+use crate::infra::minio::MinioClient;
+
+use super::get_render_flame_command::GetRenderFlameCommand;
+
+#[derive(Debug)]
+pub enum GetRenderFlameOutcome {
+    Ready(String),
+    NotFound,
+}
+
+pub struct GetRenderFlameCommandHandler {
+    minio: Arc<MinioClient>,
+}
+
+impl GetRenderFlameCommandHandler {
+    pub fn new(minio: Arc<MinioClient>) -> Self {
+        Self { minio }
+    }
+
+    pub async fn handle(&self, command: GetRenderFlameCommand) -> GetRenderFlameOutcome {
+        match GenomeService::load(&self.minio, &command.job_id).await {
+            Some(genome) => GetRenderFlameOutcome::Ready(flam3::to_flame_xml(&genome)),
+            None => GetRenderFlameOutcome::NotFound,
+        }
+    }
+}
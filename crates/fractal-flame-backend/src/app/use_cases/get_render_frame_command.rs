@@ -0,0 +1,4 @@
+pub struct GetRenderFrameCommand {
+    pub job_id: String,
+    pub index: usize,
+}
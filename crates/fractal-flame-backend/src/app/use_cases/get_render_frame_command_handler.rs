@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::minio::MinioClient;
+
+use super::get_render_frame_command::GetRenderFrameCommand;
+
+#[derive(Debug)]
+pub enum GetRenderFrameOutcome {
+    Ready(Vec<u8>),
+    NotFound,
+}
+
+pub struct GetRenderFrameCommandHandler {
+    minio: Arc<MinioClient>,
+}
+
+impl GetRenderFrameCommandHandler {
+    pub fn new(minio: Arc<MinioClient>) -> Self {
+        Self { minio }
+    }
+
+    pub async fn handle(&self, command: GetRenderFrameCommand) -> GetRenderFrameOutcome {
+        let key = MinioKeyService::frame_key(&command.job_id, command.index);
+
+        match self.minio.get_object(&key).await {
+            Ok(png_bytes) => GetRenderFrameOutcome::Ready(png_bytes),
+            Err(_) => GetRenderFrameOutcome::NotFound,
+        }
+    }
+}
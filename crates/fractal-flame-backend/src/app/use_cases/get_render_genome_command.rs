@@ -0,0 +1,3 @@
+pub struct GetRenderGenomeCommand {
+    pub job_id: String,
+}
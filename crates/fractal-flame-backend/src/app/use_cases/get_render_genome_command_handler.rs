@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use fractal_flame_core::domain::FlameGenome;
+
+use crate::app::services::genome_service::GenomeService;
+use crate::infra::minio::MinioClient;
+
+use super::get_render_genome_command::GetRenderGenomeCommand;
+
+#[derive(Debug)]
+pub enum GetRenderGenomeOutcome {
+    Ready(Box<FlameGenome>),
+    NotFound,
+}
+
+pub struct GetRenderGenomeCommandHandler {
+    minio: Arc<MinioClient>,
+}
+
+impl GetRenderGenomeCommandHandler {
+    pub fn new(minio: Arc<MinioClient>) -> Self {
+        Self { minio }
+    }
+
+    pub async fn handle(&self, command: GetRenderGenomeCommand) -> GetRenderGenomeOutcome {
+        match GenomeService::load(&self.minio, &command.job_id).await {
+            Some(genome) => GetRenderGenomeOutcome::Ready(Box::new(genome)),
+            None => GetRenderGenomeOutcome::NotFound,
+        }
+    }
+}
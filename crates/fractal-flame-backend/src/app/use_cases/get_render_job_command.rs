@@ -0,0 +1,3 @@
+pub struct GetRenderJobCommand {
+    pub job_id: String,
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::app::services::callback_service::{CallbackDelivery, CallbackService};
+use crate::app::services::job_index_service::{JobIndexService, JobSummary};
+use crate::app::services::job_state_service::{JobFailure, JobStateService, Transition};
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::infra::redis::{RedisError, RedisPool};
+
+use super::get_render_job_command::GetRenderJobCommand;
+
+#[derive(Clone, Debug)]
+pub struct JobDetails {
+    pub status: String,
+    /// What the job was asked to render. Jobs that are not indexed, such as animations, have
+    /// only their state.
+    pub summary: Option<JobSummary>,
+    pub transitions: Vec<Transition>,
+    pub progress: u64,
+    pub total: u64,
+    /// Why the job failed, once it has.
+    pub error: Option<JobFailure>,
+    /// How delivering the job's callback went, if it asked for one.
+    pub callback: Option<CallbackDelivery>,
+}
+
+pub struct GetRenderJobCommandHandler {
+    redis: Arc<RedisPool>,
+}
+
+impl GetRenderJobCommandHandler {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// The job's parameters and state history, or `None` if its keys have expired or it never
+    /// existed.
+    pub async fn handle(
+        &self,
+        command: GetRenderJobCommand,
+    ) -> Result<Option<JobDetails>, RedisError> {
+        let job_id = &command.job_id;
+        let summary = JobIndexService::load(&self.redis, job_id).await?;
+        let status = self.redis.get(&RedisKeyService::job_status(job_id)).await?;
+        let status = match (status, &summary) {
+            (Some(status), _) => status,
+            (None, Some(_)) => "pending".to_string(),
+            (None, None) => return Ok(None),
+        };
+
+        let counter = |key: String| async move {
+            Ok::<_, RedisError>(
+                self.redis
+                    .get(&key)
+                    .await?
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0),
+            )
+        };
+        let progress = counter(RedisKeyService::job_progress(job_id)).await?;
+        let total = counter(RedisKeyService::job_total(job_id)).await?;
+        let error = JobStateService::failure(&self.redis, job_id).await?;
+        let callback = CallbackService::load(&self.redis, job_id).await?;
+
+        Ok(Some(JobDetails {
+            status,
+            summary,
+            transitions: JobStateService::transitions(&self.redis, job_id).await?,
+            progress,
+            total,
+            error,
+            callback,
+        }))
+    }
+}
@@ -1,5 +1,8 @@
 use std::sync::Arc;
 
+use fractal_flame_core::app::image_export::ExportFormat;
+
+use crate::app::services::job_record_service::JobRecordService;
 use crate::app::services::minio_key_service::MinioKeyService;
 use crate::infra::minio::MinioClient;
 
@@ -7,7 +10,7 @@ use super::get_render_result_command::GetRenderResultCommand;
 
 #[derive(Debug)]
 pub enum GetRenderResultOutcome {
-    Ready(Vec<u8>),
+    Ready(Vec<u8>, ExportFormat),
     Pending,
 }
 
@@ -21,10 +24,15 @@ impl GetRenderResultCommandHandler {
     }
 
     pub async fn handle(&self, command: GetRenderResultCommand) -> GetRenderResultOutcome {
-        let key = MinioKeyService::render_result_key(&command.job_id);
+        // A job whose record is missing is assumed to have the default PNG result.
+        let format = JobRecordService::load(&self.minio, &command.job_id)
+            .await
+            .map(|record| record.format)
+            .unwrap_or_default();
+        let key = MinioKeyService::render_result_key(&command.job_id, format.extension());
 
         match self.minio.get_object(&key).await {
-            Ok(png_bytes) => GetRenderResultOutcome::Ready(png_bytes),
+            Ok(bytes) => GetRenderResultOutcome::Ready(bytes, format),
             Err(_) => GetRenderResultOutcome::Pending,
         }
     }
@@ -0,0 +1,3 @@
+pub struct GetRenderThumbnailCommand {
+    pub job_id: String,
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use fractal_flame_core::app::image_export::{ExportFormat, export_image};
+use fractal_flame_core::app::renderer::Renderer;
+use tokio::sync::Semaphore;
+
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+
+use super::get_render_thumbnail_command::GetRenderThumbnailCommand;
+
+#[derive(Debug)]
+pub enum GetRenderThumbnailOutcome {
+    Ready(Vec<u8>),
+    NotFound,
+}
+
+/// Shared by every thumbnail request: at most so many thumbnails render at once, and
+/// requests for a job whose thumbnail is already rendering wait for it instead of rendering
+/// it again.
+pub struct ThumbnailRenders {
+    permits: Semaphore,
+    in_flight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl ThumbnailRenders {
+    pub fn new(max_renders: usize) -> Self {
+        Self {
+            permits: Semaphore::new(max_renders.max(1)),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The lock held while `job_id`'s thumbnail renders.
+    fn job_lock(&self, job_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.entry(job_id.to_string()).or_default().clone()
+    }
+
+    /// Forgets `job_id`'s lock once no other request holds it.
+    fn release(&self, job_id: &str, lock: Arc<tokio::sync::Mutex<()>>) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        drop(lock);
+        if in_flight
+            .get(job_id)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            in_flight.remove(job_id);
+        }
+    }
+}
+
+pub struct GetRenderThumbnailCommandHandler {
+    config: Config,
+    minio: Arc<MinioClient>,
+    renders: Arc<ThumbnailRenders>,
+}
+
+impl GetRenderThumbnailCommandHandler {
+    pub fn new(config: Config, minio: Arc<MinioClient>, renders: Arc<ThumbnailRenders>) -> Self {
+        Self {
+            config,
+            minio,
+            renders,
+        }
+    }
+
+    /// A PNG at most `preview_size` on a side, rendered from the job's genome at preview
+    /// quality the first time it is asked for, so it is available before or without the full
+    /// result.
+    pub async fn handle(
+        &self,
+        command: GetRenderThumbnailCommand,
+    ) -> Result<GetRenderThumbnailOutcome, Box<dyn std::error::Error + Send + Sync>> {
+        let key = MinioKeyService::thumbnail_key(&command.job_id);
+        if let Ok(cached) = self.minio.get_object(&key).await {
+            return Ok(GetRenderThumbnailOutcome::Ready(cached));
+        }
+
+        let lock = self.renders.job_lock(&command.job_id);
+        let outcome = {
+            let _rendering = lock.lock().await;
+            // Whoever held the lock before may have just rendered it.
+            match self.minio.get_object(&key).await {
+                Ok(cached) => Ok(GetRenderThumbnailOutcome::Ready(cached)),
+                Err(_) => self.render(&command.job_id, &key).await,
+            }
+        };
+        self.renders.release(&command.job_id, lock);
+        outcome
+    }
+
+    async fn render(
+        &self,
+        job_id: &str,
+        key: &str,
+    ) -> Result<GetRenderThumbnailOutcome, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(mut genome) = GenomeService::load(&self.minio, job_id).await else {
+            return Ok(GetRenderThumbnailOutcome::NotFound);
+        };
+        let size = self.config.preview_size as f64;
+        let scale = size / genome.camera.width.max(genome.camera.height) as f64;
+        genome.resize(
+            ((genome.camera.width as f64 * scale).round() as usize).max(1),
+            ((genome.camera.height as f64 * scale).round() as usize).max(1),
+        );
+        genome.quality.samples = genome.quality.samples.min(self.config.preview_samples);
+        genome.quality.iter_per_sample =
+            genome.quality.iter_per_sample.min(self.config.preview_iter);
+
+        let _permit = self.renders.permits.acquire().await?;
+        let max_threads = self.config.max_threads;
+        let png = tokio::task::spawn_blocking(
+            move || -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                let renderer = Renderer::from_genome(&genome, max_threads)?;
+                renderer.render()?;
+                Ok(export_image(
+                    renderer.canvas.as_ref(),
+                    &genome.tone_mapping,
+                    ExportFormat::Png,
+                    None,
+                )?)
+            },
+        )
+        .await??;
+
+        if let Err(e) = self.minio.put_object(key, png.clone(), "image/png").await {
+            tracing::warn!(key = %key, error = %e, "Failed to cache thumbnail in MinIO");
+        }
+        Ok(GetRenderThumbnailOutcome::Ready(png))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shares_a_job_lock_until_the_last_request_releases_it() {
+        let renders = ThumbnailRenders::new(1);
+        let first = renders.job_lock("job");
+        let second = renders.job_lock("job");
+        assert!(Arc::ptr_eq(&first, &second));
+        let rendering = first.lock().await;
+        assert!(second.try_lock().is_err());
+
+        renders.release("job", second);
+        assert!(renders.in_flight.lock().unwrap().contains_key("job"));
+        drop(rendering);
+        renders.release("job", first);
+        assert!(renders.in_flight.lock().unwrap().is_empty());
+    }
+}
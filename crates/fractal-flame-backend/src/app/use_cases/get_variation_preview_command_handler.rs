@@ -3,14 +3,14 @@ use std::sync::Arc;
 use fractal_flame_core::app::image_export::fractal_image_to_png;
 use fractal_flame_core::app::renderer::Renderer;
 use fractal_flame_core::app::transformations::{
-    base_affine_transformation::BaseAffineTransformation, diamond::Diamond, disc::Disc, ex::Ex,
-    handkerchief::Handkerchief, heart::Heart, horseshoe::Horseshoe, hyperbolic::Hyperbolic,
-    linear::Linear, polar::Polar, sinusoidal::Sinusoidal, spherical::Spherical, spiral::Spiral,
-    swirl::Swirl,
+    base_affine_transformation::BaseAffineTransformation,
+    custom::{CustomVariation, CustomVariationDefinition},
+    registry,
 };
 use fractal_flame_core::domain::transformation::Transformation;
 use fractal_flame_core::domain::{FractalImage, Rect};
 
+use crate::app::services::custom_variation_service::CustomVariationService;
 use crate::app::services::minio_key_service::MinioKeyService;
 use crate::infra::config::Config;
 use crate::infra::minio::MinioClient;
@@ -32,26 +32,21 @@ fn preview_base_affines() -> Vec<BaseAffineTransformation> {
 
 fn create_preview_transformations(
     id: &str,
+    custom: Option<Arc<CustomVariationDefinition>>,
 ) -> Result<Vec<Box<dyn Transformation + Send + Sync>>, Box<dyn std::error::Error + Send + Sync>> {
     let bases = preview_base_affines();
     let mut transformations = Vec::with_capacity(bases.len());
     for base in bases {
-        let t: Box<dyn Transformation + Send + Sync> = match id {
-            "diamond" => Box::new(Diamond { base }),
-            "disc" => Box::new(Disc::new(base)),
-            "ex" => Box::new(Ex::new(base)),
-            "heart" => Box::new(Heart::new(base)),
-            "horseshoe" => Box::new(Horseshoe::new(base)),
-            "spherical" => Box::new(Spherical::new(base)),
-            "swirl" => Box::new(Swirl::new(base)),
-            "linear" => Box::new(Linear::new(base)),
-            "polar" => Box::new(Polar::new(base)),
-            "spiral" => Box::new(Spiral::new(base)),
-            "handkerchief" => Box::new(Handkerchief::new(base)),
-            "hyperbolic" => Box::new(Hyperbolic::new(base)),
-            "sinusoidal" => Box::new(Sinusoidal::new(base)),
-            _ => return Err(format!("Unknown variation id: {}", id).into()),
-        };
+        let t: Box<dyn Transformation + Send + Sync> =
+            match registry::create_variation(id, base.clone()) {
+                Some(t) => t,
+                None => match custom {
+                    Some(ref definition) => {
+                        Box::new(CustomVariation::new(base, definition.clone()))
+                    }
+                    None => return Err(format!("Unknown variation id: {}", id).into()),
+                },
+            };
         transformations.push(t);
     }
     Ok(transformations)
@@ -78,12 +73,16 @@ impl GetVariationPreviewCommandHandler {
             return Ok(cached);
         }
 
-        let transformations = create_preview_transformations(&command.variation_id)?;
+        let custom = if CustomVariationService::is_builtin(&command.variation_id) {
+            None
+        } else {
+            CustomVariationService::load(&self.minio, &command.variation_id).await
+        };
+        let transformations = create_preview_transformations(&command.variation_id, custom)?;
 
         let size = self.config.preview_size;
         let canvas = FractalImage::new(size, size);
-        let aspect = size as f64 / size as f64;
-        let world = Rect::new(-aspect, -1.0, 2.0 * aspect, 2.0);
+        let world = Rect::new(-1.0, -1.0, 2.0, 2.0);
 
         let renderer = Renderer::new(
             canvas,
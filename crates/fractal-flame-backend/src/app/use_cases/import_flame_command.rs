@@ -0,0 +1,8 @@
+pub struct ImportFlameCommand {
+    /// Contents of a flam3/Apophysis `.flame` document.
+    pub xml: String,
+    /// Which `<flame>` of the document to render.
+    pub index: usize,
+    /// API key of the client starting the render.
+    pub client: String,
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::flam3::{self, FlameImportError};
+use fractal_flame_core::app::image_export::ExportFormat;
+
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::services::custom_variation_service::{
+    CustomVariationCache, CustomVariationService,
+};
+use crate::infra::minio::MinioClient;
+
+use super::import_flame_command::ImportFlameCommand;
+use super::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+
+pub struct ImportFlameResult {
+    pub job_id: String,
+    pub name: Option<String>,
+}
+
+pub struct ImportFlameCommandHandler {
+    minio: Arc<MinioClient>,
+    custom_variations: Arc<CustomVariationCache>,
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl ImportFlameCommandHandler {
+    pub fn new(
+        minio: Arc<MinioClient>,
+        custom_variations: Arc<CustomVariationCache>,
+        run_handler: RunRenderJobCommandHandler,
+    ) -> Self {
+        Self {
+            minio,
+            custom_variations,
+            run_handler,
+        }
+    }
+
+    pub async fn handle(
+        &self,
+        command: ImportFlameCommand,
+    ) -> Result<ImportFlameResult, ImportFlameError> {
+        let custom = CustomVariationService::list_specs(&self.minio, &self.custom_variations).await;
+        let genome = flam3::parse_flame_at(&command.xml, command.index, &custom)?;
+        self.run_handler
+            .validate_genome(&genome)
+            .map_err(FlameImportError::from)?;
+        let name = genome.name.clone();
+
+        let job_id = self
+            .run_handler
+            .submit(RunRenderJobCommand {
+                source: RenderSource::Genome(Box::new(genome)),
+                lineage: None,
+                format: ExportFormat::default(),
+                client: Some(command.client),
+                priority: Priority::default(),
+                callback: None,
+            })
+            .await?;
+        Ok(ImportFlameResult { job_id, name })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportFlameError {
+    #[error(transparent)]
+    Import(#[from] FlameImportError),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
@@ -0,0 +1,10 @@
+pub struct ListJobsCommand {
+    pub status: Option<String>,
+    /// Inclusive bounds on creation time, in milliseconds since the Unix epoch.
+    pub created_from: Option<u64>,
+    pub created_to: Option<u64>,
+    pub variation_id: Option<String>,
+    pub oldest_first: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use crate::app::services::job_index_service::{JobIndexService, JobPage, JobQuery};
+use crate::infra::redis::{RedisError, RedisPool};
+
+use super::list_jobs_command::ListJobsCommand;
+
+/// Most jobs returned on one page.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+pub struct ListJobsCommandHandler {
+    redis: Arc<RedisPool>,
+}
+
+impl ListJobsCommandHandler {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// Jobs started within the last `job_ttl_secs`, which is as long as the index keeps them.
+    pub async fn handle(&self, command: ListJobsCommand) -> Result<JobPage, RedisError> {
+        JobIndexService::query(
+            &self.redis,
+            &JobQuery {
+                status: command.status,
+                created_from: command.created_from,
+                created_to: command.created_to,
+                variation_id: command.variation_id,
+                oldest_first: command.oldest_first,
+                offset: command.offset,
+                limit: command.limit.min(MAX_PAGE_SIZE),
+            },
+        )
+        .await
+    }
+}
@@ -1,12 +1,46 @@
+pub mod breed_command;
+pub mod breed_command_handler;
+pub mod control_render_job_command;
+pub mod control_render_job_command_handler;
+pub mod create_custom_variation_command;
+pub mod create_custom_variation_command_handler;
 pub mod get_all_variations_command;
 pub mod get_all_variations_command_handler;
 pub mod get_intermediate_result_command;
 pub mod get_intermediate_result_command_handler;
+pub mod get_render_animation_command;
+pub mod get_render_animation_command_handler;
+pub mod get_render_flame_command;
+pub mod get_render_flame_command_handler;
+pub mod get_render_frame_command;
+pub mod get_render_frame_command_handler;
+pub mod get_render_genome_command;
+pub mod get_render_genome_command_handler;
+pub mod get_render_job_command;
+pub mod get_render_job_command_handler;
 pub mod get_render_result_command;
 pub mod get_render_result_command_handler;
+pub mod get_render_thumbnail_command;
+pub mod get_render_thumbnail_command_handler;
 pub mod get_variation_preview_command;
 pub mod get_variation_preview_command_handler;
+pub mod import_flame_command;
+pub mod import_flame_command_handler;
+pub mod list_jobs_command;
+pub mod list_jobs_command_handler;
+pub mod mutate_render_command;
+pub mod mutate_render_command_handler;
+pub mod remix_render_command;
+pub mod remix_render_command_handler;
+pub mod render_animation_command;
+pub mod render_animation_command_handler;
+pub mod render_from_image_command;
+pub mod render_from_image_command_handler;
 pub mod render_progress_command;
 pub mod render_progress_command_handler;
+pub mod render_shard_command;
+pub mod render_shard_command_handler;
 pub mod run_render_job_command;
 pub mod run_render_job_command_handler;
+pub mod start_render_v2_command;
+pub mod start_render_v2_command_handler;
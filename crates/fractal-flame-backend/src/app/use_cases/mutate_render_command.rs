@@ -0,0 +1,7 @@
+pub struct MutateRenderCommand {
+    pub job_id: String,
+    /// Number of mutated children to render.
+    pub count: usize,
+    /// API key of the client the children are rendered for.
+    pub client: String,
+}
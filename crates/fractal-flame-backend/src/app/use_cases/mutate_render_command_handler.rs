@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::evolution::{EvolutionError, Mutation, mutate};
+use fractal_flame_core::app::generation::generation_rng;
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::app::transformations::registry;
+use fractal_flame_core::infra::random;
+
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_record_service::Lineage;
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+
+use super::mutate_render_command::MutateRenderCommand;
+use super::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+
+pub struct MutatedChild {
+    pub job_id: String,
+    pub mutation: Mutation,
+}
+
+pub struct MutateRenderCommandHandler {
+    config: Config,
+    minio: Arc<MinioClient>,
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl MutateRenderCommandHandler {
+    pub fn new(
+        config: Config,
+        minio: Arc<MinioClient>,
+        run_handler: RunRenderJobCommandHandler,
+    ) -> Self {
+        Self {
+            config,
+            minio,
+            run_handler,
+        }
+    }
+
+    /// Starts `count` jobs, each rendering the parent's genome with one random mutation.
+    /// Swapped and added variations are drawn from the built-ins and the parent's own custom
+    /// variations, so every child stays self-contained.
+    pub async fn handle(
+        &self,
+        command: MutateRenderCommand,
+    ) -> Result<Vec<MutatedChild>, MutateRenderError> {
+        if command.count == 0 || command.count > self.config.max_batch_size {
+            return Err(MutateRenderError::BatchSize {
+                count: command.count,
+                max: self.config.max_batch_size,
+            });
+        }
+        let parent = GenomeService::load(&self.minio, &command.job_id)
+            .await
+            .ok_or_else(|| MutateRenderError::NotFound(command.job_id.clone()))?;
+
+        let pool: Vec<String> = registry::VARIATION_IDS
+            .iter()
+            .map(|id| id.to_string())
+            .chain(parent.custom_variations.iter().map(|c| c.id.clone()))
+            .collect();
+        let mut rng = generation_rng(random::generate_seed());
+        let children = (0..command.count)
+            .map(|_| mutate(&parent, &pool, &mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (_, genome) in &children {
+            self.run_handler
+                .validate_genome(genome)
+                .map_err(EvolutionError::Genome)?;
+        }
+
+        let mut mutated = Vec::with_capacity(children.len());
+        for (mutation, genome) in children {
+            let job_id = self
+                .run_handler
+                .submit(RunRenderJobCommand {
+                    source: RenderSource::Genome(Box::new(genome)),
+                    lineage: Some(Lineage::new(
+                        vec![command.job_id.clone()],
+                        format!("mutate:{}", mutation.name()),
+                    )),
+                    format: ExportFormat::default(),
+                    client: Some(command.client.clone()),
+                    priority: Priority::default(),
+                    callback: None,
+                })
+                .await?;
+            mutated.push(MutatedChild { job_id, mutation });
+        }
+        Ok(mutated)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MutateRenderError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error("count must be between 1 and {max}, got {count}")]
+    BatchSize { count: usize, max: usize },
+    #[error(transparent)]
+    Evolution(#[from] EvolutionError),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
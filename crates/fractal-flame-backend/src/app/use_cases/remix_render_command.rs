@@ -0,0 +1,28 @@
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::domain::Background;
+
+/// Fields left as `None` keep the parent job's value.
+#[derive(Clone, Debug, Default)]
+pub struct RemixOverrides {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+    pub iter_per_sample: Option<usize>,
+    pub gamma: Option<f64>,
+    pub brightness: Option<f64>,
+    pub background: Option<Background>,
+    pub symmetry: Option<usize>,
+    pub center_x: Option<f64>,
+    pub center_y: Option<f64>,
+    pub zoom: Option<f64>,
+    pub rotate: Option<f64>,
+    pub seed: Option<u64>,
+    pub format: Option<ExportFormat>,
+}
+
+pub struct RemixRenderCommand {
+    pub job_id: String,
+    pub overrides: RemixOverrides,
+    /// API key of the client starting the remix.
+    pub client: String,
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::genome::{GenomeError, GenomeLimits};
+use fractal_flame_core::app::image_export::{ImageExportError, validate_streamable};
+use fractal_flame_core::domain::{Camera, FlameGenome};
+
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_record_service::{JobRecordService, Lineage};
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+
+use super::remix_render_command::{RemixOverrides, RemixRenderCommand};
+use super::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+
+pub struct RemixRenderCommandHandler {
+    config: Config,
+    minio: Arc<MinioClient>,
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl RemixRenderCommandHandler {
+    pub fn new(
+        config: Config,
+        minio: Arc<MinioClient>,
+        run_handler: RunRenderJobCommandHandler,
+    ) -> Self {
+        Self {
+            config,
+            minio,
+            run_handler,
+        }
+    }
+
+    /// Starts a new job from the parent's exact genome with `overrides` applied.
+    pub async fn handle(&self, command: RemixRenderCommand) -> Result<String, RemixRenderError> {
+        let mut genome = GenomeService::load(&self.minio, &command.job_id)
+            .await
+            .ok_or_else(|| RemixRenderError::NotFound(command.job_id.clone()))?;
+        apply_overrides(
+            &mut genome,
+            &command.overrides,
+            &self.config.genome_limits(),
+        )?;
+        self.run_handler.validate_genome(&genome)?;
+        let format = match command.overrides.format {
+            Some(format) => format,
+            None => JobRecordService::load(&self.minio, &command.job_id)
+                .await
+                .map(|record| record.format)
+                .unwrap_or_default(),
+        };
+        format.validate()?;
+        if self
+            .config
+            .renders_tiled(genome.camera.width, genome.camera.height)
+        {
+            validate_streamable(format)?;
+        }
+
+        Ok(self
+            .run_handler
+            .submit(RunRenderJobCommand {
+                source: RenderSource::Genome(Box::new(genome)),
+                lineage: Some(Lineage::new(vec![command.job_id], "remix")),
+                format,
+                client: Some(command.client),
+                priority: Priority::default(),
+                callback: None,
+            })
+            .await?)
+    }
+}
+
+/// A new size given on one axis only keeps the aspect ratio; unless `samples` is overridden the
+/// sample count follows the pixel count so density is preserved.
+fn apply_overrides(
+    genome: &mut FlameGenome,
+    overrides: &RemixOverrides,
+    limits: &GenomeLimits,
+) -> Result<(), GenomeError> {
+    let out_of_range = || {
+        GenomeError::Invalid(format!(
+            "width and height must be between 1 and {}",
+            Camera::MAX_DIMENSION
+        ))
+    };
+    let sides = 1..=Camera::MAX_DIMENSION;
+    if [overrides.width, overrides.height]
+        .into_iter()
+        .flatten()
+        .any(|side| !sides.contains(&side))
+    {
+        return Err(out_of_range());
+    }
+    for (name, value, max) in [
+        ("samples", overrides.samples, limits.max_samples),
+        (
+            "iter_per_sample",
+            overrides.iter_per_sample,
+            limits.max_iter_per_sample,
+        ),
+    ] {
+        if value.is_some_and(|value| !(1..=max).contains(&value)) {
+            return Err(GenomeError::Invalid(format!(
+                "{} must be between 1 and {}",
+                name, max
+            )));
+        }
+    }
+    // The side that follows the aspect ratio may still come out too large; `validate` catches
+    // that once the genome is resized.
+    let scale = |side: usize, numerator: usize, denominator: usize| {
+        side.checked_mul(numerator)
+            .map(|area| area.div_ceil(denominator.max(1)))
+            .ok_or_else(out_of_range)
+    };
+    let (width, height) = (genome.camera.width, genome.camera.height);
+    let (new_width, new_height) = match (overrides.width, overrides.height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, scale(w, height, width)?),
+        (None, Some(h)) => (scale(h, width, height)?, h),
+        (None, None) => (width, height),
+    };
+    genome.resize(new_width, new_height);
+
+    if let Some(samples) = overrides.samples {
+        genome.quality.samples = samples;
+    }
+    if let Some(iter_per_sample) = overrides.iter_per_sample {
+        genome.quality.iter_per_sample = iter_per_sample;
+    }
+    if let Some(gamma) = overrides.gamma {
+        genome.tone_mapping.gamma = gamma;
+    }
+    if let Some(brightness) = overrides.brightness {
+        genome.tone_mapping.brightness = brightness;
+    }
+    if let Some(background) = overrides.background {
+        genome.tone_mapping.background = background;
+    }
+    if let Some(symmetry) = overrides.symmetry {
+        genome.symmetry = symmetry;
+    }
+    if let Some(center_x) = overrides.center_x {
+        genome.camera.center_x = center_x;
+    }
+    if let Some(center_y) = overrides.center_y {
+        genome.camera.center_y = center_y;
+    }
+    if let Some(zoom) = overrides.zoom {
+        genome.camera.zoom = zoom;
+    }
+    if let Some(rotate) = overrides.rotate {
+        genome.camera.rotate = rotate;
+    }
+    if overrides.seed.is_some() {
+        genome.seed = overrides.seed;
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RemixRenderError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error(transparent)]
+    Invalid(#[from] GenomeError),
+    #[error(transparent)]
+    Format(#[from] ImageExportError),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genome() -> FlameGenome {
+        FlameGenome::from_json(
+            r#"{"transforms": [], "camera": {"width": 100, "height": 100, "center_x": 0, "center_y": 0, "zoom": 1, "rotate": 0}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_quality_overrides_outside_the_limits() {
+        let limits = GenomeLimits::default();
+        for (samples, iter_per_sample) in [
+            (Some(0), None),
+            (Some(limits.max_samples + 1), None),
+            (None, Some(0)),
+            (None, Some(usize::MAX)),
+        ] {
+            let overrides = RemixOverrides {
+                samples,
+                iter_per_sample,
+                ..RemixOverrides::default()
+            };
+            assert!(
+                apply_overrides(&mut genome(), &overrides, &limits).is_err(),
+                "accepted samples {:?}, iter_per_sample {:?}",
+                samples,
+                iter_per_sample
+            );
+        }
+
+        let mut genome = genome();
+        let overrides = RemixOverrides {
+            samples: Some(limits.max_samples),
+            iter_per_sample: Some(1),
+            ..RemixOverrides::default()
+        };
+        apply_overrides(&mut genome, &overrides, &limits).unwrap();
+        assert_eq!(genome.quality.samples, limits.max_samples);
+        assert_eq!(genome.quality.iter_per_sample, 1);
+    }
+}
@@ -0,0 +1,34 @@
+use fractal_flame_core::app::image_export::{AnimationFormat, AnimationSettings};
+use fractal_flame_core::domain::{Keyframe, MotionBlur};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnimationSource {
+    /// Frames rendered evenly from the first keyframe to the last, both included.
+    Keyframes {
+        keyframes: Vec<Keyframe>,
+        frames: usize,
+    },
+    /// One full turn of every transform of a finished job's genome.
+    RotationLoop { job_id: String, frames: usize },
+}
+
+/// Animated files assembled from the frames once they are all rendered.
+#[derive(Serialize, Deserialize)]
+pub struct AnimationOutput {
+    pub formats: Vec<AnimationFormat>,
+    pub settings: AnimationSettings,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RenderAnimationCommand {
+    pub source: AnimationSource,
+    /// Accumulates sub-frames over each frame's shutter interval; `None` renders single instants.
+    pub motion_blur: Option<MotionBlur>,
+    pub output: AnimationOutput,
+    /// API key of the client that asked for the animation, which quotas and fair scheduling
+    /// are counted against.
+    #[serde(default)]
+    pub client: Option<String>,
+}
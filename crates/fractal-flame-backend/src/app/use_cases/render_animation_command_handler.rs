@@ -0,0 +1,623 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use fractal_flame_core::app::animation::{
+    AnimationError, blurred_frames, blurred_rotation_loop, frame_genomes, rotation_loop,
+};
+use fractal_flame_core::app::genome::GenomeError;
+use fractal_flame_core::app::image_export::{
+    AnimationFormat, AnimationSettings, ExportFormat, ImageExportError, encode_animation,
+    export_image, rgba_from_png,
+};
+use fractal_flame_core::app::renderer::Renderer;
+use fractal_flame_core::domain::{FlameGenome, Keyframe, MotionBlur, TemporalSample};
+use fractal_flame_core::infra::random;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::app::services::client_quota_service::{ClientQuotaService, QuotaError};
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_event_service::JobEventService;
+use crate::app::services::job_record_service::{JobRecord, JobRecordService, Lineage};
+use crate::app::services::job_state_service::{JobState, JobStateService};
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::services::render_queue_service::RenderQueueService;
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+use crate::infra::redis::RedisPool;
+
+use super::render_animation_command::{AnimationOutput, AnimationSource, RenderAnimationCommand};
+use super::run_render_job_command::Priority;
+
+/// Stored as `animation.json` so the job can be reproduced.
+#[derive(Serialize)]
+struct AnimationRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyframes: Option<&'a [Keyframe]>,
+    /// Job whose genome a rotation loop spins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation_of: Option<&'a str>,
+    frames: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    motion_blur: Option<&'a MotionBlur>,
+    formats: &'a [AnimationFormat],
+    settings: &'a AnimationSettings,
+}
+
+/// What a job renders, resolved from its [`AnimationSource`].
+struct ResolvedAnimation {
+    genomes: Vec<FlameGenome>,
+    /// Sub-frames of each frame when motion blur is on.
+    subframes: Option<Vec<Vec<TemporalSample>>>,
+    motion_blur: Option<MotionBlur>,
+    keyframes: Option<Vec<Keyframe>>,
+    rotation_of: Option<String>,
+    /// Samples across every frame, which progress counts up to.
+    total_samples: u64,
+}
+
+/// Samples across all of `genomes`, or an error if they cannot be counted.
+fn total_samples(genomes: &[FlameGenome]) -> Result<u64, RenderAnimationError> {
+    genomes
+        .iter()
+        .try_fold(0u64, |total, g| total.checked_add(g.quality.samples as u64))
+        .ok_or(RenderAnimationError::TooManySamples)
+}
+
+/// Renders every frame of an animation as one job. Progress counts samples across all frames,
+/// and each finished frame becomes the job's intermediate image. Once every frame is stored they
+/// are assembled into the requested animated files.
+#[derive(Clone)]
+pub struct RenderAnimationCommandHandler {
+    config: Config,
+    redis: Option<Arc<RedisPool>>,
+    minio: Arc<MinioClient>,
+    /// Raised once another worker has taken over the queued job this copy is rendering,
+    /// which it then gives up between frames without recording anything.
+    lease_lost: Option<Arc<AtomicBool>>,
+}
+
+impl RenderAnimationCommandHandler {
+    pub fn new(config: Config, redis: Option<Arc<RedisPool>>, minio: Arc<MinioClient>) -> Self {
+        Self {
+            config,
+            redis,
+            minio,
+            lease_lost: None,
+        }
+    }
+
+    /// Validates the source and counts the job against its client's quotas, then queues it for
+    /// the next free worker and returns its id. Without Redis, or if queueing fails, the job
+    /// starts rendering here straight away.
+    pub async fn handle(
+        &self,
+        mut command: RenderAnimationCommand,
+    ) -> Result<String, RenderAnimationError> {
+        let frames = match &command.source {
+            AnimationSource::Keyframes { frames, .. } => *frames,
+            AnimationSource::RotationLoop { frames, .. } => *frames,
+        };
+        if frames > self.config.max_animation_frames {
+            return Err(RenderAnimationError::TooManyFrames {
+                frames,
+                max: self.config.max_animation_frames,
+            });
+        }
+        if let Some(ref blur) = command.motion_blur
+            && blur.temporal_samples > self.config.max_temporal_samples
+        {
+            return Err(RenderAnimationError::TooManyTemporalSamples {
+                temporal_samples: blur.temporal_samples,
+                max: self.config.max_temporal_samples,
+            });
+        }
+        // Every frame shares the first keyframe's seed, fixed here so a queued job renders
+        // the same frames wherever it is picked up.
+        if let AnimationSource::Keyframes { keyframes, .. } = &mut command.source
+            && let Some(first) = keyframes.first_mut()
+        {
+            first.genome.seed.get_or_insert_with(random::generate_seed);
+        }
+        let mut formats = Vec::with_capacity(command.output.formats.len());
+        for &format in &command.output.formats {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        command.output.formats = formats;
+        let resolved = self.resolve(&command).await?;
+
+        let job_id = Uuid::new_v4().to_string();
+        if let (Some(redis), Some(client)) = (&self.redis, &command.client) {
+            let samples = usize::try_from(resolved.total_samples).unwrap_or(usize::MAX);
+            ClientQuotaService::admit(redis, &self.config, client, &job_id, samples).await?;
+        }
+        if let Some(ref redis) = self.redis {
+            self.set_status(&job_id, JobState::Queued).await;
+            match self.enqueue(redis, &job_id, &command, &resolved).await {
+                Ok(()) => return Ok(job_id),
+                Err(e) => {
+                    tracing::warn!(job_id = %job_id, error = %e, "Failed to queue animation, rendering it here");
+                }
+            }
+        }
+        let handler = self.clone();
+        let job_id_clone = job_id.clone();
+        tokio::spawn(async move {
+            handler
+                .handle_owned(job_id_clone, resolved, command.output, command.client)
+                .await;
+        });
+        Ok(job_id)
+    }
+
+    /// Renders an animation job a worker took off the queue, for as long as its lease holds.
+    pub async fn handle_queued(
+        &self,
+        job_id: String,
+        command: RenderAnimationCommand,
+        lease_lost: Arc<AtomicBool>,
+    ) {
+        let handler = Self {
+            lease_lost: Some(lease_lost),
+            ..self.clone()
+        };
+        match handler.resolve(&command).await {
+            Ok(resolved) => {
+                handler
+                    .handle_owned(job_id, resolved, command.output, command.client)
+                    .await
+            }
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "Queued animation cannot be rendered");
+                self.fail(&job_id).await;
+                self.release(&job_id, command.client.as_deref()).await;
+            }
+        }
+    }
+
+    async fn resolve(
+        &self,
+        command: &RenderAnimationCommand,
+    ) -> Result<ResolvedAnimation, RenderAnimationError> {
+        let motion_blur = command.motion_blur;
+        match &command.source {
+            AnimationSource::Keyframes { keyframes, frames } => {
+                let genomes = frame_genomes(keyframes, *frames)?;
+                let subframes = motion_blur
+                    .as_ref()
+                    .map(|blur| blurred_frames(keyframes, *frames, blur))
+                    .transpose()?;
+                // Frames only use variations found in some keyframe, so these catch every bad id.
+                for (index, keyframe) in keyframes.iter().enumerate() {
+                    keyframe
+                        .genome
+                        .build_transformations()
+                        .map_err(|source| RenderAnimationError::Keyframe { index, source })?;
+                }
+                Ok(ResolvedAnimation {
+                    total_samples: total_samples(&genomes)?,
+                    genomes,
+                    subframes,
+                    motion_blur,
+                    keyframes: Some(keyframes.clone()),
+                    rotation_of: None,
+                })
+            }
+            AnimationSource::RotationLoop { job_id, frames } => {
+                let mut genome = GenomeService::load(&self.minio, job_id)
+                    .await
+                    .ok_or_else(|| RenderAnimationError::NotFound(job_id.clone()))?;
+                genome.seed.get_or_insert_with(random::generate_seed);
+                genome
+                    .build_transformations()
+                    .map_err(RenderAnimationError::Genome)?;
+                let subframes = motion_blur
+                    .as_ref()
+                    .map(|blur| blurred_rotation_loop(&genome, *frames, blur))
+                    .transpose()?;
+                let genomes = rotation_loop(&genome, *frames)?;
+                Ok(ResolvedAnimation {
+                    total_samples: total_samples(&genomes)?,
+                    genomes,
+                    subframes,
+                    motion_blur,
+                    keyframes: None,
+                    rotation_of: Some(job_id.clone()),
+                })
+            }
+        }
+    }
+
+    async fn enqueue(
+        &self,
+        redis: &RedisPool,
+        job_id: &str,
+        command: &RenderAnimationCommand,
+        resolved: &ResolvedAnimation,
+    ) -> Result<(), String> {
+        let payload = serde_json::to_string(command).map_err(|e| e.to_string())?;
+        let job_ttl = self.config.job_ttl_secs;
+        redis
+            .set(
+                &RedisKeyService::job_animation_command(job_id),
+                &payload,
+                Some(job_ttl),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let iter_per_sample = resolved.genomes[0].quality.iter_per_sample;
+        let client = command.client.as_deref().unwrap_or("internal");
+        let client_weight = self
+            .config
+            .client_weights
+            .get(client)
+            .copied()
+            .unwrap_or(1.0);
+        let weight = (Priority::Normal.weight() * client_weight).max(f64::EPSILON);
+        let cost = resolved.total_samples as f64 * iter_per_sample as f64 / weight;
+        RenderQueueService::enqueue(redis, job_id, client, cost, job_ttl)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Renders in a task of its own, so a panic fails this job rather than the worker, then
+    /// frees its client's slot.
+    async fn handle_owned(
+        &self,
+        job_id: String,
+        resolved: ResolvedAnimation,
+        output: AnimationOutput,
+        client: Option<String>,
+    ) {
+        let handler = self.clone();
+        let render_job_id = job_id.clone();
+        let render = tokio::spawn(async move {
+            handler.handle_inner(render_job_id, resolved, output).await;
+        });
+        match render.await {
+            _ if self.lease_lost() => {
+                tracing::warn!(job_id = %job_id, "Another worker took the job over, giving it up");
+                return;
+            }
+            Ok(()) => {}
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "Animation job panicked");
+                self.fail(&job_id).await;
+            }
+        }
+        self.release(&job_id, client.as_deref()).await;
+    }
+
+    async fn release(&self, job_id: &str, client: Option<&str>) {
+        if let (Some(redis), Some(client)) = (&self.redis, client) {
+            ClientQuotaService::release(redis, client, job_id).await;
+        }
+    }
+
+    fn lease_lost(&self) -> bool {
+        self.lease_lost
+            .as_ref()
+            .is_some_and(|lost| lost.load(Ordering::Relaxed))
+    }
+
+    async fn set_status(&self, job_id: &str, state: JobState) {
+        if let Some(ref r) = self.redis
+            && let Err(e) =
+                JobStateService::transition(r, job_id, state, self.config.job_ttl_secs).await
+        {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to record job state");
+        }
+    }
+
+    async fn fail(&self, job_id: &str) {
+        self.set_status(job_id, JobState::Failed).await;
+    }
+
+    /// Encodes the stored frames as `format`, fetching and decoding one frame at a time.
+    async fn assemble(
+        &self,
+        job_id: &str,
+        frame_count: usize,
+        (width, height): (u32, u32),
+        format: AnimationFormat,
+        settings: AnimationSettings,
+    ) -> Result<(), String> {
+        let runtime = tokio::runtime::Handle::current();
+        let minio = self.minio.clone();
+        let frames_job_id = job_id.to_string();
+        let bytes = tokio::task::spawn_blocking(move || {
+            let frames = (0..frame_count).map(|index| {
+                let key = MinioKeyService::frame_key(&frames_job_id, index);
+                let png_bytes = runtime.block_on(minio.get_object(&key)).map_err(|e| {
+                    ImageExportError::Animation(format!("frame {} unavailable: {}", index, e))
+                })?;
+                rgba_from_png(&png_bytes)
+            });
+            encode_animation(format, width, height, frames, &settings)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+        self.minio
+            .put_object(
+                &MinioKeyService::animation_artifact_key(job_id, format.extension()),
+                bytes,
+                format.content_type(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn handle_inner(
+        &self,
+        job_id: String,
+        resolved: ResolvedAnimation,
+        output: AnimationOutput,
+    ) {
+        let ResolvedAnimation {
+            genomes,
+            subframes,
+            motion_blur,
+            keyframes,
+            rotation_of,
+            total_samples,
+        } = resolved;
+        let lineage = rotation_of
+            .as_ref()
+            .map(|parent_id| Lineage::new(vec![parent_id.clone()], "rotation"));
+        let record = JobRecord::new(job_id.clone(), lineage);
+        if let Err(e) = JobRecordService::save(&self.minio, &record).await {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to store job record");
+        }
+        let animation = AnimationRecord {
+            keyframes: keyframes.as_deref(),
+            rotation_of: rotation_of.as_deref(),
+            frames: genomes.len(),
+            motion_blur: motion_blur.as_ref(),
+            formats: &output.formats,
+            settings: &output.settings,
+        };
+        if let Ok(body) = serde_json::to_vec(&animation)
+            && let Err(e) = self
+                .minio
+                .put_object(
+                    &MinioKeyService::animation_key(&job_id),
+                    body,
+                    "application/json",
+                )
+                .await
+        {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to store animation");
+        }
+        if let Err(e) = GenomeService::save(&self.minio, &job_id, &genomes[0]).await {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to store genome");
+        }
+
+        if let Some(ref r) = self.redis {
+            let _ = JobStateService::start_rendering(
+                r,
+                &job_id,
+                total_samples,
+                self.config.job_ttl_secs,
+            )
+            .await;
+        }
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let render_done = Arc::new(AtomicBool::new(false));
+        let progress_sync_handle = JobEventService::sync_progress(
+            self.redis.clone(),
+            &job_id,
+            progress.clone(),
+            render_done.clone(),
+            Duration::from_millis(self.config.progress_sync_interval_ms),
+            self.config.job_ttl_secs,
+        );
+
+        let frame_count = genomes.len();
+        let size = (
+            genomes[0].camera.width as u32,
+            genomes[0].camera.height as u32,
+        );
+        let mut poster = None;
+        let mut failed = false;
+        let mut subframes = subframes.map(Vec::into_iter);
+        for (index, genome) in genomes.into_iter().enumerate() {
+            if self.lease_lost() {
+                break;
+            }
+            let progress = progress.clone();
+            let max_threads = self.config.max_threads;
+            let frame_subframes = subframes.as_mut().and_then(Iterator::next);
+            let result = tokio::task::spawn_blocking(move || {
+                let mut renderer =
+                    Renderer::from_genome(&genome, max_threads).map_err(|e| e.to_string())?;
+                renderer.progress = Some(progress);
+                match frame_subframes {
+                    Some(subframes) => renderer.render_temporal(&subframes),
+                    None => renderer.render(),
+                }
+                .map_err(|e| e.to_string())?;
+                export_image(
+                    renderer.canvas.as_ref(),
+                    &genome.tone_mapping,
+                    ExportFormat::Png,
+                    Some(&genome),
+                )
+                .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+
+            let png_bytes = match result {
+                Ok(png_bytes) => png_bytes,
+                Err(e) => {
+                    tracing::error!(job_id = %job_id, frame = index, error = %e, "Animation frame failed");
+                    failed = true;
+                    break;
+                }
+            };
+            let key = MinioKeyService::frame_key(&job_id, index);
+            if let Err(e) = self
+                .minio
+                .put_object(&key, png_bytes.clone(), "image/png")
+                .await
+            {
+                tracing::error!(job_id = %job_id, frame = index, error = %e, "Failed to upload frame to MinIO");
+                failed = true;
+                break;
+            }
+            let intermediate = MinioKeyService::intermediate_key(&job_id);
+            if self
+                .minio
+                .put_object(&intermediate, png_bytes.clone(), "image/png")
+                .await
+                .is_ok()
+                && let Some(ref r) = self.redis
+            {
+                let version = index as u64 + 1;
+                let _ = JobEventService::set_intermediate_version(
+                    r,
+                    &job_id,
+                    version,
+                    self.config.job_ttl_secs,
+                )
+                .await;
+            }
+            if index == 0 {
+                poster = Some(png_bytes);
+            }
+        }
+
+        render_done.store(true, Ordering::Relaxed);
+        let _ = progress_sync_handle.await;
+
+        // The worker that took the job over renders and records the rest.
+        if self.lease_lost() {
+            return;
+        }
+        if failed {
+            self.fail(&job_id).await;
+            return;
+        }
+        // The first frame doubles as the job's result image.
+        if let Some(png_bytes) = poster
+            && let Err(e) = self
+                .minio
+                .put_object(
+                    &MinioKeyService::render_result_key(&job_id, "png"),
+                    png_bytes,
+                    "image/png",
+                )
+                .await
+        {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to upload result to MinIO");
+            self.fail(&job_id).await;
+            return;
+        }
+        for &format in &output.formats {
+            if let Err(e) = self
+                .assemble(&job_id, frame_count, size, format, output.settings)
+                .await
+            {
+                tracing::error!(job_id = %job_id, format = format.extension(), error = %e, "Failed to assemble animation");
+                self.fail(&job_id).await;
+                return;
+            }
+        }
+        tracing::info!(job_id = %job_id, frames = frame_count, "Animation job completed");
+        if let Some(ref r) = self.redis {
+            let _ =
+                JobEventService::set_progress(r, &job_id, total_samples, self.config.job_ttl_secs)
+                    .await;
+        }
+        self.set_status(&job_id, JobState::Completed).await;
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderAnimationError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error("At most {max} frames may be rendered, got {frames}")]
+    TooManyFrames { frames: usize, max: usize },
+    #[error("At most {max} temporal samples may be used per frame, got {temporal_samples}")]
+    TooManyTemporalSamples { temporal_samples: usize, max: usize },
+    #[error("The frames' samples add up to more than can be counted")]
+    TooManySamples,
+    #[error(transparent)]
+    Animation(#[from] AnimationError),
+    #[error("Keyframe {index} cannot be rendered: {source}")]
+    Keyframe { index: usize, source: GenomeError },
+    #[error("Genome cannot be rendered: {0}")]
+    Genome(GenomeError),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
+
+#[cfg(test)]
+mod tests {
+    use fractal_flame_core::domain::TemporalFilter;
+
+    use super::*;
+    use crate::infra::minio::MinioConfig;
+
+    fn handler() -> RenderAnimationCommandHandler {
+        let minio = MinioClient::new(MinioConfig {
+            endpoint: "http://127.0.0.1:9000".to_string(),
+            access_key: "minio".to_string(),
+            secret_key: "minio".to_string(),
+            bucket: "renders".to_string(),
+            region: "us-east-1".to_string(),
+        })
+        .unwrap();
+        let config = Config {
+            max_temporal_samples: 16,
+            ..Config::default()
+        };
+        RenderAnimationCommandHandler::new(config, None, Arc::new(minio))
+    }
+
+    fn command(temporal_samples: usize) -> RenderAnimationCommand {
+        RenderAnimationCommand {
+            source: AnimationSource::Keyframes {
+                keyframes: Vec::new(),
+                frames: 2,
+            },
+            motion_blur: Some(MotionBlur {
+                temporal_samples,
+                shutter: 0.5,
+                filter: TemporalFilter::Box,
+            }),
+            output: AnimationOutput {
+                formats: Vec::new(),
+                settings: AnimationSettings::default(),
+            },
+            client: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_more_temporal_samples_than_configured() {
+        let handler = handler();
+        assert!(matches!(
+            handler.handle(command(17)).await,
+            Err(RenderAnimationError::TooManyTemporalSamples {
+                temporal_samples: 17,
+                max: 16
+            })
+        ));
+        // Within the limit the request goes on to keyframe validation.
+        assert!(matches!(
+            handler.handle(command(16)).await,
+            Err(RenderAnimationError::Animation(AnimationError::NoKeyframes))
+        ));
+    }
+}
@@ -0,0 +1,6 @@
+pub struct RenderFromImageCommand {
+    /// A PNG previously produced by a render job.
+    pub png: Vec<u8>,
+    /// API key of the client starting the render.
+    pub client: String,
+}
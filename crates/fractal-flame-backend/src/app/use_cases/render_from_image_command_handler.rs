@@ -0,0 +1,50 @@
+use fractal_flame_core::app::image_export::{ExportFormat, PngMetadataError, genome_from_png};
+
+use crate::app::services::client_quota_service::QuotaError;
+
+use super::render_from_image_command::RenderFromImageCommand;
+use super::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+
+pub struct RenderFromImageCommandHandler {
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl RenderFromImageCommandHandler {
+    pub fn new(run_handler: RunRenderJobCommandHandler) -> Self {
+        Self { run_handler }
+    }
+
+    /// Re-renders the genome embedded in the image and returns the new job id.
+    pub async fn handle(
+        &self,
+        command: RenderFromImageCommand,
+    ) -> Result<String, RenderFromImageError> {
+        let genome = genome_from_png(&command.png)?;
+        self.run_handler
+            .validate_genome(&genome)
+            .map_err(|e| RenderFromImageError::InvalidGenome(e.to_string()))?;
+
+        Ok(self
+            .run_handler
+            .submit(RunRenderJobCommand {
+                source: RenderSource::Genome(Box::new(genome)),
+                lineage: None,
+                format: ExportFormat::default(),
+                client: Some(command.client),
+                priority: Priority::default(),
+                callback: None,
+            })
+            .await?)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderFromImageError {
+    #[error(transparent)]
+    Metadata(#[from] PngMetadataError),
+    #[error("Embedded genome cannot be rendered: {0}")]
+    InvalidGenome(String),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
@@ -1,25 +1,143 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use tokio::sync::{broadcast, mpsc};
+
+use crate::app::services::job_control_service::JobControlService;
+use crate::app::services::job_event_service::{JobEvent, JobEventService};
+use crate::app::services::job_state_service::{JobFailure, JobState, JobStateService};
 use crate::app::services::redis_key_service::RedisKeyService;
-use crate::infra::redis::RedisPool;
+use crate::app::services::render_queue_service::RenderQueueService;
+use crate::infra::redis::{RedisPool, RedisSubscriber};
 
 use super::render_progress_command::RenderProgressCommand;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct JobProgress {
     pub status: String,
     pub progress: u64,
     pub total: u64,
     pub intermediate_version: u64,
+    /// Jobs ahead of this one, while it is queued.
+    pub queue_position: Option<u64>,
+    /// Why the job failed, once it has.
+    pub error: Option<JobFailure>,
+    pub paused: bool,
+}
+
+/// How a follower catches up after missing job events.
+#[derive(Debug, PartialEq)]
+enum Resync {
+    /// Events were dropped: read the stored keys again.
+    Reload,
+    /// The subscription ended: subscribe again, then read the stored keys.
+    Resubscribe,
 }
 
+impl JobProgress {
+    fn is_finished(&self) -> bool {
+        JobState::parse(&self.status).is_some_and(JobState::is_terminal)
+    }
+
+    fn apply(&mut self, event: JobEvent) {
+        match event {
+            JobEvent::Status { status, error } => {
+                self.status = status.as_str().to_string();
+                self.error = error;
+                if status != JobState::Queued {
+                    self.queue_position = None;
+                }
+            }
+            JobEvent::Progress { progress } => self.progress = progress,
+            JobEvent::Started { total } => {
+                self.progress = 0;
+                self.total = total;
+                self.intermediate_version = 0;
+            }
+            JobEvent::Intermediate {
+                intermediate_version,
+            } => self.intermediate_version = intermediate_version,
+            JobEvent::Paused { paused } => self.paused = paused,
+        }
+    }
+
+    /// Applies a received job event, or says how to catch up if events were missed.
+    fn receive(&mut self, received: Result<String, broadcast::error::RecvError>) -> Option<Resync> {
+        match received {
+            Ok(message) => {
+                if let Some(event) = JobEventService::parse(&message) {
+                    self.apply(event);
+                }
+                None
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => Some(Resync::Reload),
+            Err(broadcast::error::RecvError::Closed) => Some(Resync::Resubscribe),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct RenderProgressCommandHandler {
     redis: Arc<RedisPool>,
+    events: Arc<RedisSubscriber>,
+    /// How often a queued job's place in the queue is read, since the jobs ahead of it
+    /// publish nothing to it.
+    queue_poll_interval: Duration,
 }
 
 impl RenderProgressCommandHandler {
-    pub fn new(redis: Arc<RedisPool>) -> Self {
-        Self { redis }
+    pub fn new(
+        redis: Arc<RedisPool>,
+        events: Arc<RedisSubscriber>,
+        queue_poll_interval: Duration,
+    ) -> Self {
+        Self {
+            redis,
+            events,
+            queue_poll_interval,
+        }
+    }
+
+    /// Sends the job's progress, then again each time its job events change it, until the
+    /// job has ended or the receiver is dropped. The stored keys are read only to start
+    /// with, and again whenever events may have been missed.
+    pub fn watch(&self, command: RenderProgressCommand) -> mpsc::Receiver<JobProgress> {
+        let (tx, rx) = mpsc::channel(32);
+        let handler = self.clone();
+        tokio::spawn(async move {
+            // Subscribe before reading, so no change falls between the two.
+            let mut events = JobEventService::subscribe(&handler.events, &command.job_id);
+            let mut info = handler.get_progress(&command).await;
+            let mut queue_ticker = tokio::time::interval(handler.queue_poll_interval);
+            queue_ticker.tick().await;
+            loop {
+                if tx.send(info.clone()).await.is_err() || info.is_finished() {
+                    return;
+                }
+                let sent = info.clone();
+                while info == sent {
+                    tokio::select! {
+                        received = events.recv() => match info.receive(received) {
+                            None => {}
+                            Some(Resync::Reload) => {
+                                info = handler.get_progress(&command).await;
+                            }
+                            Some(Resync::Resubscribe) => {
+                                tokio::time::sleep(handler.queue_poll_interval).await;
+                                events =
+                                    JobEventService::subscribe(&handler.events, &command.job_id);
+                                info = handler.get_progress(&command).await;
+                            }
+                        },
+                        _ = queue_ticker.tick(), if info.status == JobState::Queued.as_str() => {
+                            info.queue_position = handler.queue_position(&command.job_id).await;
+                        }
+                        _ = tx.closed() => return,
+                    }
+                }
+            }
+        });
+        rx
     }
 
     pub async fn get_progress(&self, command: &RenderProgressCommand) -> JobProgress {
@@ -60,11 +178,131 @@ impl RenderProgressCommandHandler {
             .and_then(|v| v.parse().ok())
             .unwrap_or(0);
 
+        let queue_position = if status == JobState::Queued.as_str() {
+            self.queue_position(job_id).await
+        } else {
+            None
+        };
+
+        let error = if status == JobState::Failed.as_str() {
+            JobStateService::failure(&self.redis, job_id)
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+
+        let paused = JobControlService::load(&self.redis, job_id)
+            .await
+            .is_ok_and(|controls| controls.paused);
+
         JobProgress {
             status,
             progress,
             total,
             intermediate_version,
+            queue_position,
+            error,
+            paused,
+        }
+    }
+
+    async fn queue_position(&self, job_id: &str) -> Option<u64> {
+        RenderQueueService::position(&self.redis, job_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|position| position as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::services::job_state_service::FailureCode;
+
+    fn queued() -> JobProgress {
+        JobProgress {
+            status: "queued".to_string(),
+            progress: 0,
+            total: 0,
+            intermediate_version: 0,
+            queue_position: Some(3),
+            error: None,
+            paused: false,
         }
     }
+
+    #[test]
+    fn applies_job_events() {
+        let mut info = queued();
+        info.apply(JobEvent::Status {
+            status: JobState::Queued,
+            error: None,
+        });
+        assert_eq!(info.queue_position, Some(3));
+
+        info.apply(JobEvent::Status {
+            status: JobState::Rendering,
+            error: None,
+        });
+        assert_eq!(info.status, "rendering");
+        assert_eq!(info.queue_position, None);
+
+        info.apply(JobEvent::Started { total: 100 });
+        info.apply(JobEvent::Progress { progress: 40 });
+        info.apply(JobEvent::Intermediate {
+            intermediate_version: 2,
+        });
+        info.apply(JobEvent::Paused { paused: true });
+        assert_eq!((info.progress, info.total), (40, 100));
+        assert_eq!(info.intermediate_version, 2);
+        assert!(info.paused);
+
+        // Starting again, e.g. after being re-queued, resets what was counted.
+        info.apply(JobEvent::Started { total: 100 });
+        assert_eq!((info.progress, info.intermediate_version), (0, 0));
+        assert!(!info.is_finished());
+
+        let failure = JobFailure::new(FailureCode::RenderFailed, "boom");
+        info.apply(JobEvent::Status {
+            status: JobState::Failed,
+            error: Some(failure.clone()),
+        });
+        assert_eq!(info.error, Some(failure));
+        assert!(info.is_finished());
+    }
+
+    #[tokio::test]
+    async fn catches_up_after_missed_events() {
+        let (sender, mut receiver) = broadcast::channel(1);
+        let mut info = queued();
+
+        let event = serde_json::to_string(&JobEvent::Progress { progress: 7 }).unwrap();
+        sender.send(event).unwrap();
+        assert_eq!(info.receive(receiver.recv().await), None);
+        assert_eq!(info.progress, 7);
+
+        sender.send("not an event".to_string()).unwrap();
+        assert_eq!(info.receive(receiver.recv().await), None);
+        assert_eq!(
+            info,
+            JobProgress {
+                progress: 7,
+                ..queued()
+            }
+        );
+
+        sender.send("first".to_string()).unwrap();
+        sender.send("second".to_string()).unwrap();
+        assert_eq!(info.receive(receiver.recv().await), Some(Resync::Reload));
+
+        drop(sender);
+        let _ = receiver.recv().await;
+        assert_eq!(
+            info.receive(receiver.recv().await),
+            Some(Resync::Resubscribe)
+        );
+    }
 }
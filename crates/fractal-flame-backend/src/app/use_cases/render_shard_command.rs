@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// A slice of a job's sample budget, rendered from the job's stored genome by whichever
+/// instance takes it off the queue.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RenderShardCommand {
+    pub job_id: String,
+    pub index: usize,
+    pub samples: usize,
+    /// Distinct per shard, so shards do not retrace each other's orbits.
+    pub seed: u64,
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use fractal_flame_core::app::histogram::histogram_to_bytes;
+use fractal_flame_core::app::renderer::Renderer;
+
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_control_service::JobControlService;
+use crate::app::services::job_event_service::JobEventService;
+use crate::app::services::job_state_service::JobStateService;
+use crate::app::services::minio_key_service::MinioKeyService;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::services::render_queue_service::Lease;
+use crate::app::services::shard_queue_service::ShardQueueService;
+use crate::infra::config::Config;
+use crate::infra::minio::{MinioClient, MinioError};
+use crate::infra::redis::RedisPool;
+
+use super::render_shard_command::RenderShardCommand;
+
+/// How long one wait on the shard queue lasts before polling again.
+const QUEUE_POLL_SECS: u64 = 5;
+
+#[derive(Clone)]
+pub struct RenderShardCommandHandler {
+    config: Config,
+    redis: Arc<RedisPool>,
+    minio: Arc<MinioClient>,
+    /// Raised once another worker has taken over the shard this copy is rendering, which it
+    /// then gives up without uploading or reporting anything.
+    lease_lost: Option<Arc<AtomicBool>>,
+}
+
+impl RenderShardCommandHandler {
+    pub fn new(config: Config, redis: Arc<RedisPool>, minio: Arc<MinioClient>) -> Self {
+        Self {
+            config,
+            redis,
+            minio,
+            lease_lost: None,
+        }
+    }
+
+    /// Takes shards off the queue and renders them one at a time, for as long as the server
+    /// runs.
+    pub async fn run(self) {
+        loop {
+            match ShardQueueService::take(&self.redis, QUEUE_POLL_SECS).await {
+                Ok(Some((payload, Some(command)))) => self.handle_queued(&payload, command).await,
+                Ok(Some((payload, None))) => {
+                    tracing::warn!(payload, "Dropping malformed shard");
+                    if let Err(e) = ShardQueueService::complete(&self.redis, &payload, None).await {
+                        tracing::warn!(error = %e, "Failed to release shard");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to poll the shard queue");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    /// Renders a shard taken off the queue, renewing its lease until it is done.
+    async fn handle_queued(&self, payload: &str, command: RenderShardCommand) {
+        let lease_secs = self.config.job_lease_secs;
+        let lease = ShardQueueService::lease(self.redis.clone(), &command, lease_secs)
+            .await
+            .inspect_err(|e| {
+                tracing::warn!(job_id = %command.job_id, error = %e, "Failed to lease shard");
+            })
+            .ok();
+        // Rendered in a task of its own, so a panic fails this shard rather than the worker.
+        let handler = Self {
+            lease_lost: lease.as_ref().map(Lease::lost),
+            ..self.clone()
+        };
+        let shard = command.clone();
+        let render = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.handle(shard).await }
+        });
+        if let Err(e) = render.await
+            && !handler.lease_lost()
+        {
+            tracing::error!(job_id = %command.job_id, error = %e, "Shard panicked");
+            self.report(&command.job_id, command.index, false).await;
+        }
+        if let Err(e) = ShardQueueService::complete(&self.redis, payload, lease.as_ref()).await {
+            tracing::warn!(job_id = %command.job_id, error = %e, "Failed to release shard");
+        }
+    }
+
+    fn lease_lost(&self) -> bool {
+        self.lease_lost
+            .as_ref()
+            .is_some_and(|lost| lost.load(Ordering::Relaxed))
+    }
+
+    /// Puts shards whose worker stopped renewing their lease back on the queue, checking once
+    /// per lease period for as long as the server runs.
+    pub async fn requeue_orphans(self) {
+        let lease = Duration::from_secs(self.config.job_lease_secs.max(1));
+        let mut suspects = HashSet::new();
+        loop {
+            tokio::time::sleep(lease).await;
+            match ShardQueueService::requeue_orphans(&self.redis, &mut suspects).await {
+                Ok(0) => {}
+                Ok(requeued) => tracing::warn!(requeued, "Re-queued shards orphaned by a worker"),
+                Err(e) => tracing::warn!(error = %e, "Failed to check for orphaned shards"),
+            }
+        }
+    }
+
+    /// Renders one shard and records it as done or failed for the job's coordinator. Shards
+    /// of a job that has already finished are dropped unrendered.
+    pub async fn handle(&self, command: RenderShardCommand) {
+        let job_id = command.job_id.clone();
+        let index = command.index;
+        if self.job_finished(&job_id).await {
+            tracing::info!(job_id = %job_id, shard = index, "Skipping shard of a finished job");
+            return;
+        }
+        let rendered = match self.render(command).await {
+            Ok(()) => true,
+            Err(RenderShardError::LeaseLost) => {
+                tracing::warn!(job_id = %job_id, shard = index, "Another worker took the shard over, giving it up");
+                return;
+            }
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "Shard failed");
+                false
+            }
+        };
+        self.report(&job_id, index, rendered).await;
+    }
+
+    /// Shards are recorded by index, so one rendered again after its worker stopped counts once.
+    async fn report(&self, job_id: &str, index: usize, rendered: bool) {
+        let shards = if rendered {
+            RedisKeyService::job_shards_done(job_id)
+        } else {
+            RedisKeyService::job_shards_failed(job_id)
+        };
+        if let Err(e) = self
+            .redis
+            .sadd(&shards, &index.to_string(), Some(self.config.job_ttl_secs))
+            .await
+        {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to report shard");
+        }
+    }
+
+    /// Whether the job has reached a final state, or expired, so nothing will read its shards.
+    /// If Redis cannot be reached the job is assumed to still be running.
+    async fn job_finished(&self, job_id: &str) -> bool {
+        match JobStateService::state(&self.redis, job_id).await {
+            Ok(state) => state.is_none_or(|state| state.is_terminal()),
+            Err(e) => {
+                tracing::warn!(job_id = %job_id, error = %e, "Failed to read job state");
+                false
+            }
+        }
+    }
+
+    async fn render(&self, command: RenderShardCommand) -> Result<(), RenderShardError> {
+        let mut genome = GenomeService::load(&self.minio, &command.job_id)
+            .await
+            .ok_or_else(|| RenderShardError::NotFound(command.job_id.clone()))?;
+        genome.quality.samples = command.samples;
+        genome.seed = Some(command.seed);
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let render_done = Arc::new(AtomicBool::new(false));
+        let progress_sync_handle = self.spawn_progress_sync(
+            &command.job_id,
+            command.index,
+            progress.clone(),
+            render_done.clone(),
+        );
+        // Pausing or cancelling a job pauses or cancels its shards wherever they render.
+        let control = Arc::new(self.config.render_control());
+        let control_sync_handle = JobControlService::follow(
+            self.redis.clone(),
+            &command.job_id,
+            control.clone(),
+            render_done.clone(),
+            self.lease_lost.clone(),
+            &self.config,
+        );
+
+        let max_threads = self.config.max_threads;
+        let result = tokio::task::spawn_blocking(move || {
+            let mut renderer =
+                Renderer::from_genome(&genome, max_threads).map_err(|e| e.to_string())?;
+            renderer.progress = Some(progress);
+            renderer.control = Some(control);
+            renderer.render().map_err(|e| e.to_string())?;
+            histogram_to_bytes(&renderer.canvas).map_err(|e| e.to_string())
+        })
+        .await;
+
+        render_done.store(true, Ordering::Relaxed);
+        let _ = progress_sync_handle.await;
+        let _ = control_sync_handle.await;
+
+        if self.lease_lost() {
+            return Err(RenderShardError::LeaseLost);
+        }
+        let histogram = result
+            .map_err(|e| RenderShardError::Render(e.to_string()))?
+            .map_err(RenderShardError::Render)?;
+        let key = MinioKeyService::shard_histogram_key(&command.job_id, command.index);
+        self.minio
+            .put_object(&key, histogram, "application/octet-stream")
+            .await?;
+        // The coordinator deletes the job's histograms once it has finished, so one uploaded
+        // after that would never be read or deleted.
+        if self.job_finished(&command.job_id).await {
+            let _ = self.minio.delete_object(&key).await;
+        }
+        Ok(())
+    }
+
+    /// Records this shard's samples in the job's progress, which every shard shares, until
+    /// `render_done` is set.
+    fn spawn_progress_sync(
+        &self,
+        job_id: &str,
+        index: usize,
+        progress: Arc<AtomicUsize>,
+        render_done: Arc<AtomicBool>,
+    ) -> tokio::task::JoinHandle<()> {
+        let progress_sync_interval = Duration::from_millis(self.config.progress_sync_interval_ms);
+        let job_ttl = self.config.job_ttl_secs;
+        let redis = self.redis.clone();
+        let job_id = job_id.to_string();
+
+        tokio::spawn(async move {
+            let mut reported = 0;
+            loop {
+                let finished = render_done.load(Ordering::Relaxed);
+                let current = progress.load(Ordering::Relaxed);
+                if current != reported
+                    && JobEventService::set_shard_progress(
+                        &redis,
+                        &job_id,
+                        index,
+                        current as u64,
+                        job_ttl,
+                    )
+                    .await
+                    .is_ok()
+                {
+                    reported = current;
+                }
+                if finished {
+                    break;
+                }
+                tokio::time::sleep(progress_sync_interval).await;
+            }
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderShardError {
+    #[error("Job '{0}' not found")]
+    NotFound(String),
+    #[error("Failed to render shard: {0}")]
+    Render(String),
+    #[error("Another worker took the shard over")]
+    LeaseLost,
+    #[error(transparent)]
+    Storage(#[from] MinioError),
+}
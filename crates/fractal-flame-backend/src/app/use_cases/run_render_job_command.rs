@@ -1,7 +1,65 @@
+use fractal_flame_core::app::generation::StrategyConfig;
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::domain::FlameGenome;
+use serde::{Deserialize, Serialize};
+
+use crate::app::services::callback_service::JobCallback;
+use crate::app::services::job_record_service::Lineage;
+
+#[derive(Serialize, Deserialize)]
+pub enum RenderSource {
+    /// Transforms generated for the selected variations.
+    Variations {
+        variation_ids: Vec<String>,
+        /// Defaults to contractive random affines with the configured weights.
+        strategy: Option<StrategyConfig>,
+        /// Job whose transforms seed a `template` strategy that has none of its own.
+        template_job_id: Option<String>,
+        symmetry: usize,
+        gamma: f64,
+        width: usize,
+        height: usize,
+    },
+    /// A complete genome, e.g. imported from a `.flame` file.
+    Genome(Box<FlameGenome>),
+}
+
+/// Scheduling class of a job; higher classes get a larger share of the workers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Previews and other renders someone is waiting to see.
+    Interactive,
+    #[default]
+    Normal,
+    /// Large or bulk renders nobody is watching.
+    Batch,
+}
+
+impl Priority {
+    pub fn weight(self) -> f64 {
+        match self {
+            Self::Interactive => 16.0,
+            Self::Normal => 4.0,
+            Self::Batch => 1.0,
+        }
+    }
+}
+
+/// Serializable so it can wait in the job queue for a worker on any instance.
+#[derive(Serialize, Deserialize)]
 pub struct RunRenderJobCommand {
-    pub variation_ids: Vec<String>,
-    pub symmetry: usize,
-    pub gamma: f64,
-    pub width: usize,
-    pub height: usize,
+    pub source: RenderSource,
+    /// Jobs this render was derived from.
+    pub lineage: Option<Lineage>,
+    pub format: ExportFormat,
+    /// API key of the client that asked for the job, which quotas and fair scheduling are
+    /// counted against. Commands queued before clients were recorded have none.
+    #[serde(default)]
+    pub client: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Told when the job completes or fails.
+    #[serde(default)]
+    pub callback: Option<JobCallback>,
 }
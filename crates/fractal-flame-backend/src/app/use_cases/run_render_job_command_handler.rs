@@ -1,136 +1,549 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::Duration;
 
+use fractal_flame_core::app::generation::{StrategyConfig, generation_rng};
+use fractal_flame_core::app::genome::GenomeError;
+use fractal_flame_core::app::histogram::merge_histogram;
 use fractal_flame_core::app::image_export::{
-    fractal_image_to_intermediate_png, fractal_image_to_png,
+    ExportFormat, fractal_image_to_intermediate_png, stream_image, validate_streamable,
 };
-use fractal_flame_core::app::renderer::Renderer;
-use fractal_flame_core::domain::{FractalImage, Rect};
+use fractal_flame_core::app::renderer::{RenderControl, Renderer};
+use fractal_flame_core::app::tiled::TiledRenderer;
+use fractal_flame_core::domain::{Camera, FlameGenome, FractalImage, Quality, ToneMapping};
+use fractal_flame_core::infra::random;
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use crate::app::services::callback_service::{CallbackPayload, CallbackService, JobCallback};
+use crate::app::services::client_quota_service::{ClientQuotaService, QuotaError};
+use crate::app::services::custom_variation_service::CustomVariationService;
+use crate::app::services::genome_service::GenomeService;
+use crate::app::services::job_control_service::{JobControlService, JobControls};
+use crate::app::services::job_event_service::JobEventService;
+use crate::app::services::job_index_service::{JobIndexService, JobParams, JobSummary};
+use crate::app::services::job_record_service::{JobRecord, JobRecordService, now_millis};
+use crate::app::services::job_state_service::{
+    FailureCode, JobFailure, JobOutcome, JobState, JobStateService,
+};
 use crate::app::services::minio_key_service::MinioKeyService;
 use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::services::render_queue_service::RenderQueueService;
+use crate::app::services::shard_queue_service::ShardQueueService;
 use crate::infra::config::Config;
-use crate::infra::dependency::generate_transformations_for_ids;
-use crate::infra::minio::MinioClient;
+use crate::infra::dependency::default_strategy;
+use crate::infra::minio::{MinioClient, MinioError};
 use crate::infra::redis::RedisPool;
+use crate::infra::webhook::WebhookClient;
+
+use super::render_animation_command::RenderAnimationCommand;
+use super::render_animation_command_handler::RenderAnimationCommandHandler;
+use super::render_shard_command::RenderShardCommand;
+use super::run_render_job_command::{RenderSource, RunRenderJobCommand};
+
+/// How long an idle worker waits before checking the job queue again.
+const QUEUE_IDLE_POLL_MS: u64 = 250;
 
-use super::run_render_job_command::RunRenderJobCommand;
+/// What a worker took off the queue.
+enum QueuedJob {
+    Render(RunRenderJobCommand),
+    Animation(RenderAnimationCommand),
+}
 
 #[derive(Clone)]
 pub struct RunRenderJobCommandHandler {
     pub config: Config,
     pub redis: Option<Arc<RedisPool>>,
     pub minio: Arc<MinioClient>,
+    pub webhooks: WebhookClient,
+    /// Raised once another worker has taken over the queued job this copy is rendering,
+    /// which it then gives up without recording anything.
+    lease_lost: Option<Arc<AtomicBool>>,
 }
 
 impl RunRenderJobCommandHandler {
-    pub fn new(config: Config, redis: Option<Arc<RedisPool>>, minio: Arc<MinioClient>) -> Self {
+    pub fn new(
+        config: Config,
+        redis: Option<Arc<RedisPool>>,
+        minio: Arc<MinioClient>,
+        webhooks: WebhookClient,
+    ) -> Self {
         Self {
             config,
             redis,
             minio,
+            webhooks,
+            lease_lost: None,
         }
     }
 
-    pub fn start(&self, command: RunRenderJobCommand) -> String {
+    /// Checks that `genome` can be rendered and stays within the configured limits.
+    pub fn validate_genome(&self, genome: &FlameGenome) -> Result<(), GenomeError> {
+        genome.validate_within(&self.config.genome_limits())
+    }
+
+    /// Counts the job against its client's quotas, which are only kept with Redis, then queues
+    /// it for the next free worker on any instance and returns its id. Without Redis, or if
+    /// queueing fails, the job starts rendering here straight away.
+    /// Jobs too large for the interactive budget are scheduled as normal ones.
+    pub async fn submit(&self, mut command: RunRenderJobCommand) -> Result<String, QuotaError> {
         let job_id = Uuid::new_v4().to_string();
+        let (samples, _) = self.budget(&command.source);
+        let (width, height) = Self::size(&command.source);
+        command.priority = ClientQuotaService::priority(
+            &self.config,
+            command.priority,
+            samples,
+            width.saturating_mul(height),
+        );
+        if let (Some(redis), Some(client)) = (&self.redis, &command.client) {
+            ClientQuotaService::admit(redis, &self.config, client, &job_id, samples).await?;
+        }
+        Ok(self.start_as(job_id, command).await)
+    }
+
+    async fn start_as(&self, job_id: String, command: RunRenderJobCommand) -> String {
+        if let Some(ref redis) = self.redis {
+            let summary = self.summary(&job_id, &command);
+            if let Err(e) = JobIndexService::add(redis, &summary, self.config.job_ttl_secs).await {
+                tracing::warn!(job_id = %job_id, error = %e, "Failed to index job");
+            }
+            self.set_status(&job_id, JobState::Queued).await;
+            match self.enqueue(redis, &job_id, &command).await {
+                Ok(()) => return job_id,
+                Err(e) => {
+                    tracing::warn!(job_id = %job_id, error = %e, "Failed to queue job, rendering it here");
+                }
+            }
+        }
         let handler = self.clone();
         let job_id_clone = job_id.clone();
         tokio::spawn(async move {
-            handler.handle_inner(job_id_clone, command).await;
+            handler.handle_owned(job_id_clone, command).await;
         });
         job_id
     }
 
-    async fn set_redis(&self, key: &str, value: &str) {
-        if let Some(ref r) = self.redis {
-            let _ = r.set(key, value, Some(self.config.job_ttl_secs)).await;
+    /// Samples and iterations per sample the job asks for.
+    fn budget(&self, source: &RenderSource) -> (usize, usize) {
+        match source {
+            RenderSource::Variations { .. } => (self.config.samples, self.config.iter_per_sample),
+            RenderSource::Genome(genome) => {
+                (genome.quality.samples, genome.quality.iter_per_sample)
+            }
         }
     }
 
-    async fn handle_inner(&self, job_id: String, command: RunRenderJobCommand) {
-        let RunRenderJobCommand {
-            variation_ids,
-            symmetry,
-            gamma,
-            width,
-            height,
-        } = command;
-
-        let transformations = match generate_transformations_for_ids(&self.config, &variation_ids) {
-            Ok(t) => t,
-            Err(e) => {
-                tracing::error!(job_id = %job_id, error = %e, "Failed to generate transformations");
-                self.set_redis(&RedisKeyService::job_status(&job_id), "failed")
-                    .await;
+    /// Width and height of the job's result.
+    fn size(source: &RenderSource) -> (usize, usize) {
+        match source {
+            RenderSource::Variations { width, height, .. } => (*width, *height),
+            RenderSource::Genome(genome) => (genome.camera.width, genome.camera.height),
+        }
+    }
+
+    fn summary(&self, job_id: &str, command: &RunRenderJobCommand) -> JobSummary {
+        let (samples, iter_per_sample) = self.budget(&command.source);
+        let (width, height) = Self::size(&command.source);
+        let variation_ids = match command.source {
+            RenderSource::Variations {
+                ref variation_ids, ..
+            } => variation_ids.clone(),
+            RenderSource::Genome(ref genome) => {
+                let mut ids: Vec<String> = genome
+                    .transforms
+                    .iter()
+                    .chain(genome.final_transform.iter())
+                    .flat_map(|t| t.variations.iter().map(|v| v.id.clone()))
+                    .collect();
+                ids.sort();
+                ids.dedup();
+                ids
+            }
+        };
+        JobSummary {
+            job_id: job_id.to_string(),
+            created_at: now_millis(),
+            params: JobParams {
+                variation_ids,
+                width,
+                height,
+                samples,
+                iter_per_sample,
+                format: command.format,
+                priority: command.priority,
+                lineage: command.lineage.clone(),
+            },
+        }
+    }
+
+    async fn enqueue(
+        &self,
+        redis: &RedisPool,
+        job_id: &str,
+        command: &RunRenderJobCommand,
+    ) -> Result<(), String> {
+        let payload = serde_json::to_string(command).map_err(|e| e.to_string())?;
+        let job_ttl = self.config.job_ttl_secs;
+        redis
+            .set(
+                &RedisKeyService::job_command(job_id),
+                &payload,
+                Some(job_ttl),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let (samples, iter_per_sample) = self.budget(&command.source);
+        let client = command.client.as_deref().unwrap_or("internal");
+        let client_weight = self
+            .config
+            .client_weights
+            .get(client)
+            .copied()
+            .unwrap_or(1.0);
+        let weight = (command.priority.weight() * client_weight).max(f64::EPSILON);
+        let cost = samples as f64 * iter_per_sample as f64 / weight;
+        RenderQueueService::enqueue(redis, job_id, client, cost, job_ttl)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Renders queued jobs one at a time, for as long as the server runs. Each instance runs
+    /// `render_workers` of these.
+    pub async fn run(self) {
+        let Some(redis) = self.redis.clone() else {
+            return;
+        };
+        let lease_secs = self.config.job_lease_secs;
+        loop {
+            match RenderQueueService::take(&redis, lease_secs).await {
+                Ok(Some((job_id, token))) => self.handle_queued(&redis, job_id, &token).await,
+                Ok(None) => tokio::time::sleep(Duration::from_millis(QUEUE_IDLE_POLL_MS)).await,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to poll the job queue");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    /// Reads a queued job's command, which render and animation jobs store under different
+    /// keys.
+    async fn load_queued(&self, redis: &RedisPool, job_id: &str) -> Option<QueuedJob> {
+        fn parse<T: DeserializeOwned>(job_id: &str, payload: String) -> Option<T> {
+            serde_json::from_str(&payload)
+                .inspect_err(|e| {
+                    tracing::warn!(job_id = %job_id, error = %e, "Corrupt queued job");
+                })
+                .ok()
+        }
+        if let Ok(Some(payload)) = redis.get(&RedisKeyService::job_command(job_id)).await {
+            return parse(job_id, payload).map(QueuedJob::Render);
+        }
+        let key = RedisKeyService::job_animation_command(job_id);
+        let payload = redis.get(&key).await.ok().flatten()?;
+        parse(job_id, payload).map(QueuedJob::Animation)
+    }
+
+    /// Renders a job taken off the queue, renewing its lease until it is done.
+    async fn handle_queued(&self, redis: &Arc<RedisPool>, job_id: String, token: &str) {
+        let lease =
+            RenderQueueService::hold(redis.clone(), &job_id, token, self.config.job_lease_secs);
+        let handler = Self {
+            lease_lost: Some(lease.lost()),
+            ..self.clone()
+        };
+        match handler.load_queued(redis, &job_id).await {
+            Some(QueuedJob::Render(command)) => handler.handle_owned(job_id.clone(), command).await,
+            Some(QueuedJob::Animation(command)) => {
+                RenderAnimationCommandHandler::new(
+                    self.config.clone(),
+                    self.redis.clone(),
+                    self.minio.clone(),
+                )
+                .handle_queued(job_id.clone(), command, lease.lost())
+                .await
+            }
+            None => {
+                tracing::error!(job_id = %job_id, "Queued job has no command");
+                let failure = JobFailure::new(
+                    FailureCode::Lost,
+                    "the queued request expired or could not be read",
+                );
+                self.fail(&job_id, &failure).await;
+            }
+        }
+        if let Err(e) = RenderQueueService::complete(redis, &job_id, &lease).await {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to release job");
+        }
+    }
+
+    fn lease_lost(&self) -> bool {
+        self.lease_lost
+            .as_ref()
+            .is_some_and(|lost| lost.load(Ordering::Relaxed))
+    }
+
+    /// Puts jobs whose worker stopped renewing their lease back on the queue, checking once per
+    /// lease period for as long as the server runs.
+    pub async fn requeue_orphans(self) {
+        let Some(redis) = self.redis.clone() else {
+            return;
+        };
+        let lease = Duration::from_secs(self.config.job_lease_secs.max(1));
+        let mut suspects = HashSet::new();
+        loop {
+            tokio::time::sleep(lease).await;
+            match RenderQueueService::requeue_orphans(
+                &redis,
+                &mut suspects,
+                self.config.job_ttl_secs,
+            )
+            .await
+            {
+                Ok(0) => {}
+                Ok(requeued) => tracing::warn!(requeued, "Re-queued jobs orphaned by a worker"),
+                Err(e) => tracing::warn!(error = %e, "Failed to check for orphaned jobs"),
+            }
+        }
+    }
+
+    async fn set_status(&self, job_id: &str, state: JobState) {
+        if let Some(ref r) = self.redis
+            && let Err(e) =
+                JobStateService::transition(r, job_id, state, self.config.job_ttl_secs).await
+        {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to record job state");
+        }
+    }
+
+    async fn fail(&self, job_id: &str, failure: &JobFailure) {
+        if let Some(ref r) = self.redis
+            && let Err(e) =
+                JobStateService::fail(r, job_id, failure, self.config.job_ttl_secs).await
+        {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to record job failure");
+        }
+    }
+
+    async fn resolve_genome(&self, source: RenderSource) -> Result<FlameGenome, String> {
+        match source {
+            RenderSource::Variations {
+                variation_ids,
+                strategy,
+                template_job_id,
+                symmetry,
+                gamma,
+                width,
+                height,
+            } => {
+                let mut strategy = strategy.unwrap_or_else(|| default_strategy(&self.config));
+                let mut custom_variations = Vec::new();
+                if let StrategyConfig::Template(ref mut template) = strategy
+                    && template.transforms.is_empty()
+                    && let Some(ref template_job_id) = template_job_id
+                {
+                    let parent = GenomeService::load(&self.minio, template_job_id)
+                        .await
+                        .ok_or_else(|| format!("Template job '{}' not found", template_job_id))?;
+                    template.transforms = parent.transforms;
+                    custom_variations = parent.custom_variations;
+                }
+                if strategy.uses_variation_ids() && variation_ids.is_empty() {
+                    return Err("No variations selected".to_string());
+                }
+
+                let seed = random::generate_seed();
+                let transforms = strategy
+                    .strategy()
+                    .generate(&variation_ids, &mut generation_rng(seed))
+                    .map_err(|e| e.to_string())?;
+
+                let used_ids: Vec<String> = transforms
+                    .iter()
+                    .flat_map(|t| t.variations.iter().map(|v| v.id.clone()))
+                    .filter(|id| !custom_variations.iter().any(|c| &c.id == id))
+                    .collect();
+                custom_variations.extend(
+                    CustomVariationService::load_specs_for_ids(&self.minio, &used_ids).await,
+                );
+
+                Ok(FlameGenome {
+                    name: None,
+                    transforms,
+                    final_transform: None,
+                    custom_variations,
+                    symmetry,
+                    camera: Camera {
+                        width,
+                        height,
+                        center_x: 0.0,
+                        center_y: 0.0,
+                        zoom: 1.0,
+                        rotate: 0.0,
+                    },
+                    palette: None,
+                    tone_mapping: ToneMapping {
+                        gamma,
+                        ..ToneMapping::default()
+                    },
+                    quality: Quality {
+                        samples: self.config.samples,
+                        iter_per_sample: self.config.iter_per_sample,
+                    },
+                    seed: Some(seed),
+                })
+            }
+            RenderSource::Genome(genome) => Ok(*genome),
+        }
+    }
+
+    /// Renders the job, then frees its client's slot and tells the client how it went.
+    async fn handle_owned(&self, job_id: String, command: RunRenderJobCommand) {
+        let client = command.client.clone();
+        let callback = command.callback.clone();
+        // Rendered in a task of its own, so a panic fails this job rather than the worker.
+        let handler = self.clone();
+        let render_job_id = job_id.clone();
+        let render =
+            tokio::spawn(async move { handler.handle_inner(render_job_id, command).await });
+        let outcome = match render.await {
+            _ if self.lease_lost() => {
+                tracing::warn!(job_id = %job_id, "Another worker took the job over, giving it up");
                 return;
             }
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "Render job panicked");
+                let failure = JobFailure::new(FailureCode::RenderFailed, e.to_string());
+                self.fail(&job_id, &failure).await;
+                JobOutcome::Failed(failure)
+            }
         };
+        if let (Some(redis), Some(client)) = (&self.redis, client) {
+            ClientQuotaService::release(redis, &client, &job_id).await;
+        }
+        if let Some(callback) = callback {
+            let state = outcome.state();
+            let error = match outcome {
+                JobOutcome::Failed(failure) => Some(failure),
+                _ => None,
+            };
+            self.notify(&job_id, callback, state, error);
+        }
+    }
 
-        let total_samples = self.config.samples;
-        self.set_redis(&RedisKeyService::job_status(&job_id), "rendering")
-            .await;
-        self.set_redis(
-            &RedisKeyService::job_total(&job_id),
-            &total_samples.to_string(),
-        )
-        .await;
-        self.set_redis(&RedisKeyService::job_progress(&job_id), "0")
-            .await;
-        self.set_redis(&RedisKeyService::job_intermediate_version(&job_id), "0")
+    /// Delivers the job's callback in the background, so retries do not hold up the worker.
+    fn notify(
+        &self,
+        job_id: &str,
+        callback: JobCallback,
+        state: JobState,
+        error: Option<JobFailure>,
+    ) {
+        let payload = CallbackPayload::new(&self.config, job_id, state, error);
+        let handler = self.clone();
+        tokio::spawn(async move {
+            let delivery = CallbackService::deliver(
+                &handler.webhooks,
+                handler.redis.as_deref(),
+                &handler.config,
+                &callback,
+                &payload,
+            )
             .await;
+            tracing::info!(
+                job_id = %payload.job_id,
+                status = ?delivery.status,
+                attempts = delivery.attempts,
+                "Job callback finished"
+            );
+        });
+    }
 
-        let canvas = FractalImage::new(width, height);
-        let aspect = width as f64 / height as f64;
-        let world = Rect::new(-aspect, -1.0, 2.0 * aspect, 2.0);
-
-        let mut renderer = Renderer::new(
-            canvas,
-            world,
-            transformations,
-            self.config.samples,
-            self.config.iter_per_sample,
-            symmetry,
-            gamma,
-            self.config.max_threads,
-        );
+    async fn handle_inner(&self, job_id: String, command: RunRenderJobCommand) -> JobOutcome {
+        if self.cancel_requested(&job_id).await {
+            tracing::info!(job_id = %job_id, "Job cancelled before it started");
+            self.set_status(&job_id, JobState::Cancelled).await;
+            return JobOutcome::Cancelled;
+        }
+        let format = command.format;
+        let record = JobRecord::new(job_id.clone(), command.lineage).with_format(format);
+        if let Err(e) = JobRecordService::save(&self.minio, &record).await {
+            tracing::warn!(job_id = %job_id, error = %e, "Failed to store job record");
+        }
+
+        let prepared = match self.resolve_genome(command.source).await {
+            Ok(mut genome) => {
+                // Fix the seed up front so the stored genome reproduces this render.
+                genome.seed.get_or_insert_with(random::generate_seed);
+                let (width, height) = (genome.camera.width, genome.camera.height);
+                let renderer = if self.config.renders_tiled(width, height) {
+                    // Tiles build their own renderers; this only checks that the genome builds.
+                    genome.build_transformations().map(|_| None)
+                } else {
+                    Renderer::from_genome(&genome, self.config.max_threads).map(Some)
+                };
+                renderer
+                    .map(|renderer| (genome, renderer))
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e),
+        };
+        let (genome, renderer) = match prepared {
+            Ok((genome, renderer)) => {
+                if let Err(e) = GenomeService::save(&self.minio, &job_id, &genome).await {
+                    tracing::warn!(job_id = %job_id, error = %e, "Failed to store genome");
+                }
+                (genome, renderer)
+            }
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "Failed to generate transformations");
+                let failure = JobFailure::new(FailureCode::InvalidGenome, e);
+                self.fail(&job_id, &failure).await;
+                return JobOutcome::Failed(failure);
+            }
+        };
+        let Some(mut renderer) = renderer else {
+            return self.handle_tiled(job_id, genome, format).await;
+        };
+        if let Some(redis) = self.redis.clone()
+            && self.config.shard_samples > 0
+            && renderer.samples > self.config.shard_samples
+        {
+            let canvas = renderer.canvas.clone();
+            return self
+                .handle_distributed(job_id, genome, canvas, format, redis)
+                .await;
+        }
+        let tone_mapping = genome.tone_mapping;
+
+        let total_samples = renderer.samples;
+        self.mark_rendering(&job_id, total_samples).await;
 
         let progress = Arc::new(AtomicUsize::new(0));
         renderer.progress = Some(progress.clone());
+        let control = Arc::new(self.config.render_control());
+        renderer.control = Some(control.clone());
 
         let canvas_shared = renderer.canvas.clone();
         let render_done = Arc::new(AtomicBool::new(false));
 
-        let progress_sync_interval = Duration::from_millis(self.config.progress_sync_interval_ms);
         let intermediate_image_interval =
             Duration::from_millis(self.config.intermediate_image_interval_ms);
         let job_ttl = self.config.job_ttl_secs;
 
-        let progress_sync_handle = {
-            let progress = progress.clone();
-            let render_done = render_done.clone();
-            let redis = self.redis.clone();
-            let job_id = job_id.clone();
-
-            tokio::spawn(async move {
-                while !render_done.load(Ordering::Relaxed) {
-                    tokio::time::sleep(progress_sync_interval).await;
-                    let current = progress.load(Ordering::Relaxed);
-                    if let Some(ref r) = redis {
-                        let _ = r
-                            .set(
-                                &RedisKeyService::job_progress(&job_id),
-                                &current.to_string(),
-                                Some(job_ttl),
-                            )
-                            .await;
-                    }
-                }
-            })
-        };
+        let progress_sync_handle =
+            self.spawn_progress_sync(&job_id, progress.clone(), render_done.clone());
+        let control_sync_handle =
+            self.spawn_control_sync(&job_id, control.clone(), render_done.clone());
 
+        // Publishes a preview whenever there is something new to see: more samples, other
+        // preview settings, or a client asking for one.
         let image_monitor_handle = {
             let progress = progress.clone();
             let render_done = render_done.clone();
@@ -138,11 +551,11 @@ impl RunRenderJobCommandHandler {
             let redis = self.redis.clone();
             let minio = self.minio.clone();
             let job_id = job_id.clone();
-            let gamma_for_monitor = gamma;
 
             tokio::spawn(async move {
                 let mut intermediate_version: u64 = 0;
-                let mut last_progress: usize = 0;
+                let mut shown = (0, tone_mapping);
+                let mut last_snapshot = None;
 
                 while !render_done.load(Ordering::Relaxed) {
                     tokio::time::sleep(intermediate_image_interval).await;
@@ -151,16 +564,27 @@ impl RunRenderJobCommandHandler {
                         break;
                     }
 
-                    let current = progress.load(Ordering::Relaxed);
-                    if current == last_progress {
+                    let controls = match redis {
+                        Some(ref r) => JobControlService::load(r, &job_id)
+                            .await
+                            .unwrap_or_default(),
+                        None => JobControls::default(),
+                    };
+                    let snapshot_requested = controls.snapshot_requested_at != last_snapshot;
+                    last_snapshot = controls.snapshot_requested_at;
+                    let current = (
+                        progress.load(Ordering::Relaxed),
+                        controls.preview_tone_mapping(&tone_mapping),
+                    );
+                    if current == shown && !snapshot_requested {
                         continue;
                     }
-                    last_progress = current;
+                    shown = current;
 
                     let canvas_snap = canvas_for_monitor.clone();
-                    let snap_gamma = gamma_for_monitor;
+                    let snap_tone_mapping = current.1;
                     let png_result = tokio::task::spawn_blocking(move || {
-                        fractal_image_to_intermediate_png(&canvas_snap, snap_gamma)
+                        fractal_image_to_intermediate_png(&canvas_snap, &snap_tone_mapping)
                     })
                     .await;
 
@@ -169,13 +593,13 @@ impl RunRenderJobCommandHandler {
                         if minio.put_object(&key, png_bytes, "image/png").await.is_ok() {
                             intermediate_version += 1;
                             if let Some(ref r) = redis {
-                                let _ = r
-                                    .set(
-                                        &RedisKeyService::job_intermediate_version(&job_id),
-                                        &intermediate_version.to_string(),
-                                        Some(job_ttl),
-                                    )
-                                    .await;
+                                let _ = JobEventService::set_intermediate_version(
+                                    r,
+                                    &job_id,
+                                    intermediate_version,
+                                    job_ttl,
+                                )
+                                .await;
                             }
                         }
                     }
@@ -183,78 +607,393 @@ impl RunRenderJobCommandHandler {
             })
         };
 
-        let minio = self.minio.clone();
-        let redis = self.redis.clone();
-
-        let result = tokio::task::spawn_blocking(move || {
-            renderer.render().map_err(|e| e.to_string())?;
-            renderer.apply_gamma_correction();
-            fractal_image_to_png(renderer.canvas.as_ref()).map_err(|e| e.to_string())
-        })
-        .await;
+        let (phase, phase_sync_handle) = self.spawn_phase_sync(&job_id, JobState::Rendering);
+        let key = MinioKeyService::render_result_key(&job_id, format.extension());
+        let result = self
+            .minio
+            .put_object_streamed(&key, format.content_type(), move |out| {
+                renderer.render().map_err(|e| e.to_string())?;
+                let _ = phase.send(JobState::Encoding);
+                stream_image(
+                    renderer.canvas.as_ref(),
+                    &genome.tone_mapping,
+                    format,
+                    Some(&genome),
+                    out,
+                )
+                .map_err(|e| e.to_string())?;
+                let _ = phase.send(JobState::Uploading);
+                Ok(())
+            })
+            .await;
 
         render_done.store(true, Ordering::Relaxed);
         let _ = progress_sync_handle.await;
+        if let Some(handle) = control_sync_handle {
+            let _ = handle.await;
+        }
         let _ = image_monitor_handle.await;
+        let last_phase = phase_sync_handle.await.unwrap_or(JobState::Rendering);
 
-        match result {
-            Ok(Ok(png_bytes)) => {
-                let key = MinioKeyService::render_result_key(&job_id);
-                if let Err(e) = minio.put_object(&key, png_bytes, "image/png").await {
-                    tracing::error!(job_id = %job_id, error = %e, "Failed to upload result to MinIO");
-                    if let Some(ref r) = redis {
-                        let _ = r
-                            .set(
-                                &RedisKeyService::job_status(&job_id),
-                                "failed",
-                                Some(job_ttl),
-                            )
-                            .await;
-                    }
-                    return;
-                }
+        let result = result.map_err(|e| failure_in(last_phase, e));
+        self.finish(&job_id, total_samples, result, &control).await
+    }
+
+    /// Renders a result too large for one canvas tile by tile. There is no canvas to preview,
+    /// so no intermediate images are published.
+    async fn handle_tiled(
+        &self,
+        job_id: String,
+        genome: FlameGenome,
+        format: ExportFormat,
+    ) -> JobOutcome {
+        // Fail before rendering anything if the format cannot be streamed.
+        if let Err(e) = validate_streamable(format) {
+            tracing::error!(job_id = %job_id, error = %e, "Cannot stream a tiled render");
+            let failure = JobFailure::new(FailureCode::EncodeFailed, e.to_string());
+            self.fail(&job_id, &failure).await;
+            return JobOutcome::Failed(failure);
+        }
+        let mut tiled = TiledRenderer::new(genome, self.config.tile_size, self.config.max_threads);
+        let total_samples = tiled.total_samples();
+        self.mark_rendering(&job_id, total_samples).await;
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        tiled.progress = Some(progress.clone());
+        let control = Arc::new(self.config.render_control());
+        tiled.control = Some(control.clone());
+        let render_done = Arc::new(AtomicBool::new(false));
+        let progress_sync_handle = self.spawn_progress_sync(&job_id, progress, render_done.clone());
+        let control_sync_handle =
+            self.spawn_control_sync(&job_id, control.clone(), render_done.clone());
+
+        tracing::info!(
+            job_id = %job_id,
+            tiles = tiled.tiles().len(),
+            "Rendering job tile by tile"
+        );
+        let (phase, phase_sync_handle) = self.spawn_phase_sync(&job_id, JobState::Rendering);
+        let key = MinioKeyService::render_result_key(&job_id, format.extension());
+        let result = self
+            .minio
+            .put_object_streamed(&key, format.content_type(), move |out| {
+                let histogram = tiled.render_tiles().map_err(|e| e.to_string())?;
+                let _ = phase.send(JobState::Encoding);
+                tiled
+                    .encode(histogram, format, out)
+                    .map_err(|e| e.to_string())?;
+                let _ = phase.send(JobState::Uploading);
+                Ok(())
+            })
+            .await;
+
+        render_done.store(true, Ordering::Relaxed);
+        let _ = progress_sync_handle.await;
+        if let Some(handle) = control_sync_handle {
+            let _ = handle.await;
+        }
+        let last_phase = phase_sync_handle.await.unwrap_or(JobState::Rendering);
+
+        let result = result.map_err(|e| failure_in(last_phase, e));
+        self.finish(&job_id, total_samples, result, &control).await
+    }
+
+    /// Splits the sample budget into shards for render workers on any instance, then merges
+    /// their histograms into `canvas` and tone maps the result once. Shards are seeded apart, so
+    /// together they trace as many distinct orbits as one render of the whole budget.
+    async fn handle_distributed(
+        &self,
+        job_id: String,
+        genome: FlameGenome,
+        canvas: Arc<FractalImage>,
+        format: ExportFormat,
+        redis: Arc<RedisPool>,
+    ) -> JobOutcome {
+        let total_samples = genome.quality.samples;
+        let shard_samples = self.config.shard_samples;
+        let seed = genome.seed.unwrap_or_default();
+        let shards: Vec<RenderShardCommand> = (0..total_samples.div_ceil(shard_samples))
+            .map(|index| RenderShardCommand {
+                job_id: job_id.clone(),
+                index,
+                samples: shard_samples.min(total_samples - index * shard_samples),
+                seed: random::derive_seed(seed, index as u64),
+            })
+            .collect();
+
+        for key in [
+            RedisKeyService::job_shards_done(&job_id),
+            RedisKeyService::job_shards_failed(&job_id),
+            RedisKeyService::job_shard_progress(&job_id),
+        ] {
+            let _ = redis.delete(&key).await;
+        }
+        self.mark_rendering(&job_id, total_samples).await;
+
+        tracing::info!(
+            job_id = %job_id,
+            shards = shards.len(),
+            "Distributing job across render workers"
+        );
+        // Shard workers follow pause requests themselves; this only needs to notice a cancel.
+        let control = Arc::new(self.config.render_control());
+        let done = Arc::new(AtomicBool::new(false));
+        let control_sync_handle = self.spawn_control_sync(&job_id, control.clone(), done.clone());
+
+        let mut result = Ok(());
+        for shard in &shards {
+            if let Err(e) = ShardQueueService::enqueue(&redis, shard).await {
+                result = Err(JobFailure::new(
+                    FailureCode::ShardFailed,
+                    format!("failed to queue shard: {}", e),
+                ));
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self
+                .await_shards(&job_id, shards.len(), &redis, &control)
+                .await;
+        }
+        if result.is_ok() {
+            result = self.merge_shards(&job_id, &shards, canvas.clone()).await;
+        }
+        if result.is_ok() {
+            self.set_status(&job_id, JobState::Encoding).await;
+            let (phase, phase_sync_handle) = self.spawn_phase_sync(&job_id, JobState::Encoding);
+            let key = MinioKeyService::render_result_key(&job_id, format.extension());
+            let streamed = self
+                .minio
+                .put_object_streamed(&key, format.content_type(), move |out| {
+                    stream_image(
+                        canvas.as_ref(),
+                        &genome.tone_mapping,
+                        format,
+                        Some(&genome),
+                        out,
+                    )
+                    .map_err(|e| e.to_string())?;
+                    let _ = phase.send(JobState::Uploading);
+                    Ok(())
+                })
+                .await;
+            let last_phase = phase_sync_handle.await.unwrap_or(JobState::Encoding);
+            result = streamed.map_err(|e| failure_in(last_phase, e));
+        }
+
+        done.store(true, Ordering::Relaxed);
+        if let Some(handle) = control_sync_handle {
+            let _ = handle.await;
+        }
+        let outcome = self.finish(&job_id, total_samples, result, &control).await;
+        if self.lease_lost() {
+            // The shards' histograms now belong to the worker that took the job over.
+            return outcome;
+        }
+        // Only once the job has finished, so a shard still rendering either uploads before
+        // this or sees the job finished and deletes its own histogram.
+        for shard in &shards {
+            let key = MinioKeyService::shard_histogram_key(&job_id, shard.index);
+            let _ = self.minio.delete_object(&key).await;
+        }
+        outcome
+    }
+
+    /// Waits until workers have rendered all `count` shards, any of them has failed, the job
+    /// is cancelled, or it would have expired.
+    async fn await_shards(
+        &self,
+        job_id: &str,
+        count: usize,
+        redis: &RedisPool,
+        control: &RenderControl,
+    ) -> Result<(), JobFailure> {
+        let poll_interval = Duration::from_millis(self.config.progress_sync_interval_ms);
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.job_ttl_secs);
+        let counter = |key: String| async move { redis.scard(&key).await.unwrap_or(0) };
+        loop {
+            if control.is_cancelled() {
+                // Recorded as a cancellation rather than this failure; see `finish`.
+                return Err(JobFailure::new(FailureCode::RenderFailed, "cancelled"));
+            }
+            if counter(RedisKeyService::job_shards_failed(job_id)).await > 0 {
+                return Err(JobFailure::new(
+                    FailureCode::ShardFailed,
+                    "a shard failed to render",
+                ));
+            }
+            if counter(RedisKeyService::job_shards_done(job_id)).await >= count {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(JobFailure::new(
+                    FailureCode::TimedOut,
+                    "timed out waiting for shards",
+                ));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn merge_shards(
+        &self,
+        job_id: &str,
+        shards: &[RenderShardCommand],
+        canvas: Arc<FractalImage>,
+    ) -> Result<(), JobFailure> {
+        let shard_failed = |index: usize, e: String| {
+            JobFailure::new(FailureCode::ShardFailed, format!("shard {}: {}", index, e))
+        };
+        for shard in shards {
+            let histogram = self
+                .minio
+                .get_object(&MinioKeyService::shard_histogram_key(job_id, shard.index))
+                .await
+                .map_err(|e| shard_failed(shard.index, e.to_string()))?;
+            let canvas = canvas.clone();
+            tokio::task::spawn_blocking(move || merge_histogram(&canvas, &histogram))
+                .await
+                .map_err(|e| shard_failed(shard.index, e.to_string()))?
+                .map_err(|e| shard_failed(shard.index, e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn mark_rendering(&self, job_id: &str, total_samples: usize) {
+        if let Some(ref r) = self.redis {
+            let total = total_samples as u64;
+            let _ =
+                JobStateService::start_rendering(r, job_id, total, self.config.job_ttl_secs).await;
+        }
+    }
+
+    /// Copies `progress` into Redis until `render_done` is set.
+    fn spawn_progress_sync(
+        &self,
+        job_id: &str,
+        progress: Arc<AtomicUsize>,
+        render_done: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        JobEventService::sync_progress(
+            self.redis.clone(),
+            job_id,
+            progress,
+            render_done,
+            Duration::from_millis(self.config.progress_sync_interval_ms),
+            self.config.job_ttl_secs,
+        )
+    }
+
+    /// Records each phase the blocking half of a render reports after `from`, and returns the
+    /// last one once the sender is dropped.
+    fn spawn_phase_sync(
+        &self,
+        job_id: &str,
+        from: JobState,
+    ) -> (mpsc::UnboundedSender<JobState>, JoinHandle<JobState>) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handler = self.clone();
+        let job_id = job_id.to_string();
+        let handle = tokio::spawn(async move {
+            let mut last = from;
+            while let Some(state) = rx.recv().await {
+                handler.set_status(&job_id, state).await;
+                last = state;
+            }
+            last
+        });
+        (tx, handle)
+    }
+
+    /// Follows the job's pause and cancel requests until `render_done` is set. Controls are
+    /// only kept with Redis.
+    fn spawn_control_sync(
+        &self,
+        job_id: &str,
+        control: Arc<RenderControl>,
+        render_done: Arc<AtomicBool>,
+    ) -> Option<JoinHandle<()>> {
+        let redis = self.redis.clone()?;
+        Some(JobControlService::follow(
+            redis,
+            job_id,
+            control,
+            render_done,
+            self.lease_lost.clone(),
+            &self.config,
+        ))
+    }
+
+    async fn cancel_requested(&self, job_id: &str) -> bool {
+        match self.redis {
+            Some(ref r) => JobControlService::load(r, job_id)
+                .await
+                .is_ok_and(|controls| controls.cancelled),
+            None => false,
+        }
+    }
+
+    /// Records how the job ended once its result is uploaded, or has failed to be. A job that
+    /// failed after it was asked to stop was cancelled, whatever the failure; shards may stop
+    /// before `control` hears of it.
+    async fn finish(
+        &self,
+        job_id: &str,
+        total_samples: usize,
+        result: Result<(), JobFailure>,
+        control: &RenderControl,
+    ) -> JobOutcome {
+        // The worker that took the job over records how it ends.
+        if self.lease_lost() {
+            return JobOutcome::Cancelled;
+        }
+        let outcome = match result {
+            Err(_) if control.is_cancelled() || self.cancel_requested(job_id).await => {
+                JobOutcome::Cancelled
+            }
+            Ok(()) => JobOutcome::Completed,
+            Err(failure) => JobOutcome::Failed(failure),
+        };
+        let job_ttl = self.config.job_ttl_secs;
+        match &outcome {
+            JobOutcome::Completed => {
                 tracing::info!(job_id = %job_id, "Render job completed, result uploaded to MinIO");
-                if let Some(ref r) = redis {
-                    let _ = r
-                        .set(
-                            &RedisKeyService::job_progress(&job_id),
-                            &total_samples.to_string(),
-                            Some(job_ttl),
-                        )
-                        .await;
-                    let _ = r
-                        .set(
-                            &RedisKeyService::job_status(&job_id),
-                            "completed",
-                            Some(job_ttl),
-                        )
+                if let Some(ref r) = self.redis {
+                    let _ = JobEventService::set_progress(r, job_id, total_samples as u64, job_ttl)
                         .await;
                 }
+                self.set_status(job_id, JobState::Completed).await;
             }
-            Ok(Err(e)) => {
-                tracing::error!(job_id = %job_id, error = %e, "Render job failed");
-                if let Some(ref r) = redis {
-                    let _ = r
-                        .set(
-                            &RedisKeyService::job_status(&job_id),
-                            "failed",
-                            Some(job_ttl),
-                        )
-                        .await;
-                }
+            JobOutcome::Cancelled => {
+                tracing::info!(job_id = %job_id, "Render job cancelled");
+                self.set_status(job_id, JobState::Cancelled).await;
             }
-            Err(e) => {
-                tracing::error!(job_id = %job_id, error = %e, "Render job task panicked");
-                if let Some(ref r) = redis {
-                    let _ = r
-                        .set(
-                            &RedisKeyService::job_status(&job_id),
-                            "failed",
-                            Some(job_ttl),
-                        )
-                        .await;
-                }
+            JobOutcome::Failed(failure) => {
+                tracing::error!(
+                    job_id = %job_id,
+                    code = ?failure.code,
+                    error = %failure.message,
+                    "Render job failed"
+                );
+                self.fail(job_id, failure).await;
             }
         }
+        outcome
+    }
+}
+
+/// Why a streamed render failed, given the last phase its writer reported. Errors from the
+/// upload itself are upload failures whatever the phase.
+fn failure_in(phase: JobState, error: MinioError) -> JobFailure {
+    match error {
+        MinioError::Source(message) => {
+            let code = match phase {
+                JobState::Rendering => FailureCode::RenderFailed,
+                JobState::Encoding => FailureCode::EncodeFailed,
+                _ => FailureCode::UploadFailed,
+            };
+            JobFailure::new(code, message)
+        }
+        e => JobFailure::new(FailureCode::UploadFailed, e.to_string()),
     }
 }
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::domain::{Affine, Background, Color, GenomeVariation, Palette};
+use serde::Deserialize;
+
+use super::run_render_job_command::Priority;
+
+fn one() -> f64 {
+    1.0
+}
+
+/// A fully specified transform: `x' = ax + by + c`, `y' = dx + ey + f`, then the variations.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransformSpec {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+    #[serde(default)]
+    pub post: Option<Affine>,
+    #[serde(default = "one")]
+    pub weight: f64,
+    /// Required unless `color_index` selects a color from the request palette.
+    #[serde(default)]
+    pub color: Option<Color>,
+    #[serde(default)]
+    pub color_index: Option<f64>,
+    pub variations: Vec<GenomeVariation>,
+    /// Custom variation parameter overrides keyed as `{variation_id}_{parameter}`.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+pub struct StartRenderV2Command {
+    pub name: Option<String>,
+    pub transforms: Vec<TransformSpec>,
+    pub final_transform: Option<TransformSpec>,
+    pub symmetry: usize,
+    pub gamma: f64,
+    pub brightness: f64,
+    pub background: Background,
+    pub width: usize,
+    pub height: usize,
+    pub samples: Option<usize>,
+    pub iter_per_sample: Option<usize>,
+    pub center_x: f64,
+    pub center_y: f64,
+    pub zoom: f64,
+    pub rotate: f64,
+    pub palette: Option<Palette>,
+    pub seed: Option<u64>,
+    pub format: ExportFormat,
+    /// API key of the client starting the render.
+    pub client: String,
+    pub priority: Priority,
+    /// Resolve and validate the genome without starting a job.
+    pub dry_run: bool,
+}
@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+use fractal_flame_core::app::image_export::validate_streamable;
+use fractal_flame_core::domain::{
+    Affine, Camera, CustomVariationSpec, FlameGenome, GenomeTransform, Quality, ToneMapping,
+};
+use serde::Serialize;
+
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::services::custom_variation_service::CustomVariationService;
+use crate::infra::config::Config;
+use crate::infra::minio::MinioClient;
+
+use super::run_render_job_command::{RenderSource, RunRenderJobCommand};
+use super::run_render_job_command_handler::RunRenderJobCommandHandler;
+use super::start_render_v2_command::{StartRenderV2Command, TransformSpec};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldError {
+    /// Path of the offending field in the request body, e.g. `transforms[1].variations[0].id`.
+    pub field: String,
+    pub message: String,
+}
+
+pub enum StartRenderV2Outcome {
+    Started(String),
+    DryRun(Box<FlameGenome>),
+}
+
+pub struct StartRenderV2CommandHandler {
+    config: Config,
+    minio: Arc<MinioClient>,
+    run_handler: RunRenderJobCommandHandler,
+}
+
+impl StartRenderV2CommandHandler {
+    pub fn new(
+        config: Config,
+        minio: Arc<MinioClient>,
+        run_handler: RunRenderJobCommandHandler,
+    ) -> Self {
+        Self {
+            config,
+            minio,
+            run_handler,
+        }
+    }
+
+    pub async fn handle(
+        &self,
+        command: StartRenderV2Command,
+    ) -> Result<StartRenderV2Outcome, StartRenderV2Error> {
+        let ids: Vec<String> = command
+            .transforms
+            .iter()
+            .chain(command.final_transform.iter())
+            .flat_map(|t| t.variations.iter().map(|v| v.id.clone()))
+            .collect();
+        let custom = CustomVariationService::load_specs_for_ids(&self.minio, &ids).await;
+
+        let errors = validate(&command, &custom, &self.config);
+        if !errors.is_empty() {
+            return Err(StartRenderV2Error::Validation(errors));
+        }
+
+        let dry_run = command.dry_run;
+        let format = command.format;
+        let client = command.client.clone();
+        let priority = command.priority;
+        let genome = self.resolve_genome(command, custom);
+        // Catches what only compilation can, such as a parameter override breaking an expression.
+        if let Err(e) = genome
+            .build_transformations()
+            .and_then(|_| genome.build_final_transformation())
+        {
+            return Err(StartRenderV2Error::Validation(vec![FieldError {
+                field: "transforms".to_string(),
+                message: e.to_string(),
+            }]));
+        }
+
+        if dry_run {
+            return Ok(StartRenderV2Outcome::DryRun(Box::new(genome)));
+        }
+        let job_id = self
+            .run_handler
+            .submit(RunRenderJobCommand {
+                source: RenderSource::Genome(Box::new(genome)),
+                lineage: None,
+                format,
+                client: Some(client),
+                priority,
+                callback: None,
+            })
+            .await?;
+        Ok(StartRenderV2Outcome::Started(job_id))
+    }
+
+    fn resolve_genome(
+        &self,
+        command: StartRenderV2Command,
+        custom: Vec<CustomVariationSpec>,
+    ) -> FlameGenome {
+        let used = |id: &str| {
+            command
+                .transforms
+                .iter()
+                .chain(command.final_transform.iter())
+                .any(|t| t.variations.iter().any(|v| v.id == id))
+        };
+        let custom_variations = custom.into_iter().filter(|c| used(&c.id)).collect();
+
+        FlameGenome {
+            name: command.name,
+            transforms: command.transforms.into_iter().map(to_genome).collect(),
+            final_transform: command.final_transform.map(to_genome),
+            custom_variations,
+            symmetry: command.symmetry,
+            camera: Camera {
+                width: command.width,
+                height: command.height,
+                center_x: command.center_x,
+                center_y: command.center_y,
+                zoom: command.zoom,
+                rotate: command.rotate,
+            },
+            palette: command.palette,
+            tone_mapping: ToneMapping {
+                gamma: command.gamma,
+                brightness: command.brightness,
+                background: command.background,
+            },
+            quality: Quality {
+                samples: command.samples.unwrap_or(self.config.samples),
+                iter_per_sample: command
+                    .iter_per_sample
+                    .unwrap_or(self.config.iter_per_sample),
+            },
+            seed: command.seed,
+        }
+    }
+}
+
+fn to_genome(spec: TransformSpec) -> GenomeTransform {
+    GenomeTransform {
+        affine: Affine::new(spec.a, spec.b, spec.c, spec.d, spec.e, spec.f),
+        post: spec.post,
+        weight: spec.weight,
+        color: spec.color.unwrap_or_default(),
+        color_index: spec.color_index,
+        variations: spec.variations,
+        parameters: spec.parameters,
+    }
+}
+
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    fn finite(&mut self, field: impl Into<String>, value: f64) {
+        if !value.is_finite() {
+            self.push(field, "must be a finite number");
+        }
+    }
+
+    fn positive(&mut self, field: impl Into<String>, value: f64) {
+        if !(value.is_finite() && value > 0.0) {
+            self.push(field, "must be a positive number");
+        }
+    }
+}
+
+fn validate_transform(
+    errors: &mut Errors,
+    path: &str,
+    spec: &TransformSpec,
+    has_palette: bool,
+    custom: &[CustomVariationSpec],
+) {
+    for (name, value) in [
+        ("a", spec.a),
+        ("b", spec.b),
+        ("c", spec.c),
+        ("d", spec.d),
+        ("e", spec.e),
+        ("f", spec.f),
+    ] {
+        errors.finite(format!("{}.{}", path, name), value);
+    }
+    if let Some(ref post) = spec.post {
+        for (name, value) in [
+            ("a", post.a),
+            ("b", post.b),
+            ("c", post.c),
+            ("d", post.d),
+            ("e", post.e),
+            ("f", post.f),
+        ] {
+            errors.finite(format!("{}.post.{}", path, name), value);
+        }
+    }
+    if !(spec.weight.is_finite() && spec.weight >= 0.0) {
+        errors.push(format!("{}.weight", path), "must be zero or positive");
+    }
+    match spec.color_index {
+        Some(index) if !(0.0..=1.0).contains(&index) => {
+            errors.push(format!("{}.color_index", path), "must be between 0 and 1");
+        }
+        Some(_) if !has_palette && spec.color.is_none() => {
+            errors.push(
+                format!("{}.color", path),
+                "is required because the request has no palette for color_index",
+            );
+        }
+        None if spec.color.is_none() => {
+            errors.push(
+                format!("{}.color", path),
+                "is required unless color_index selects a palette color",
+            );
+        }
+        _ => {}
+    }
+
+    if spec.variations.is_empty() {
+        errors.push(
+            format!("{}.variations", path),
+            "must contain at least one variation",
+        );
+    }
+    for (j, variation) in spec.variations.iter().enumerate() {
+        let field = format!("{}.variations[{}]", path, j);
+        if !CustomVariationService::is_builtin(&variation.id)
+            && !custom.iter().any(|c| c.id == variation.id)
+        {
+            errors.push(
+                format!("{}.id", field),
+                format!("unknown variation '{}'", variation.id),
+            );
+        }
+        errors.finite(format!("{}.weight", field), variation.weight);
+    }
+
+    for (key, value) in &spec.parameters {
+        let field = format!("{}.parameters.{}", path, key);
+        let known = spec.variations.iter().any(|v| {
+            custom.iter().any(|c| {
+                c.id == v.id
+                    && key
+                        .strip_prefix(&format!("{}_", c.id))
+                        .is_some_and(|name| c.parameters.contains_key(name))
+            })
+        });
+        if !known {
+            errors.push(
+                field.clone(),
+                "must name a parameter of a custom variation in this transform, as {id}_{parameter}",
+            );
+        }
+        errors.finite(field, *value);
+    }
+}
+
+fn validate(
+    command: &StartRenderV2Command,
+    custom: &[CustomVariationSpec],
+    config: &Config,
+) -> Vec<FieldError> {
+    let mut errors = Errors(Vec::new());
+    let has_palette = command
+        .palette
+        .as_ref()
+        .is_some_and(|p| !p.colors.is_empty());
+
+    if command.transforms.is_empty() {
+        errors.push("transforms", "must contain at least one transform");
+    }
+    for (i, spec) in command.transforms.iter().enumerate() {
+        validate_transform(
+            &mut errors,
+            &format!("transforms[{}]", i),
+            spec,
+            has_palette,
+            custom,
+        );
+    }
+    if !command.transforms.is_empty() && command.transforms.iter().all(|t| t.weight == 0.0) {
+        errors.push("transforms", "weights must not all be zero");
+    }
+    if let Some(ref spec) = command.final_transform {
+        validate_transform(&mut errors, "final_transform", spec, has_palette, custom);
+    }
+
+    for (field, value) in [("width", command.width), ("height", command.height)] {
+        if !(1..=Camera::MAX_DIMENSION).contains(&value) {
+            errors.push(
+                field,
+                format!("must be between 1 and {}", Camera::MAX_DIMENSION),
+            );
+        }
+    }
+    let limits = config.genome_limits();
+    for (field, value, max) in [
+        ("symmetry", Some(command.symmetry), limits.max_symmetry),
+        ("samples", command.samples, limits.max_samples),
+        (
+            "iter_per_sample",
+            command.iter_per_sample,
+            limits.max_iter_per_sample,
+        ),
+    ] {
+        if value.is_some_and(|value| !(1..=max).contains(&value)) {
+            errors.push(field, format!("must be between 1 and {}", max));
+        }
+    }
+    errors.positive("gamma", command.gamma);
+    errors.positive("brightness", command.brightness);
+    errors.positive("zoom", command.zoom);
+    errors.finite("center_x", command.center_x);
+    errors.finite("center_y", command.center_y);
+    errors.finite("rotate", command.rotate);
+    if command
+        .palette
+        .as_ref()
+        .is_some_and(|p| p.colors.is_empty())
+    {
+        errors.push("palette.colors", "must contain at least one color");
+    }
+    if let Err(e) = command.format.validate() {
+        errors.push("format", e.to_string());
+    } else if config.renders_tiled(command.width, command.height)
+        && let Err(e) = validate_streamable(command.format)
+    {
+        errors.push("format", e.to_string());
+    }
+
+    errors.0
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StartRenderV2Error {
+    #[error("{} invalid field(s)", .0.len())]
+    Validation(Vec<FieldError>),
+    #[error(transparent)]
+    Quota(#[from] QuotaError),
+}
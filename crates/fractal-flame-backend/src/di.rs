@@ -1,10 +1,29 @@
+use std::time::Duration;
+
 use crate::app::use_cases::{
+    breed_command_handler::BreedCommandHandler,
+    control_render_job_command_handler::ControlRenderJobCommandHandler,
+    create_custom_variation_command_handler::CreateCustomVariationCommandHandler,
     get_all_variations_command_handler::GetAllVariationsCommandHandler,
     get_intermediate_result_command_handler::GetIntermediateResultCommandHandler,
+    get_render_animation_command_handler::GetRenderAnimationCommandHandler,
+    get_render_flame_command_handler::GetRenderFlameCommandHandler,
+    get_render_frame_command_handler::GetRenderFrameCommandHandler,
+    get_render_genome_command_handler::GetRenderGenomeCommandHandler,
+    get_render_job_command_handler::GetRenderJobCommandHandler,
     get_render_result_command_handler::GetRenderResultCommandHandler,
+    get_render_thumbnail_command_handler::GetRenderThumbnailCommandHandler,
     get_variation_preview_command_handler::GetVariationPreviewCommandHandler,
+    import_flame_command_handler::ImportFlameCommandHandler,
+    list_jobs_command_handler::ListJobsCommandHandler,
+    mutate_render_command_handler::MutateRenderCommandHandler,
+    remix_render_command_handler::RemixRenderCommandHandler,
+    render_animation_command_handler::RenderAnimationCommandHandler,
+    render_from_image_command_handler::RenderFromImageCommandHandler,
     render_progress_command_handler::RenderProgressCommandHandler,
+    render_shard_command_handler::RenderShardCommandHandler,
     run_render_job_command_handler::RunRenderJobCommandHandler,
+    start_render_v2_command_handler::StartRenderV2CommandHandler,
 };
 use crate::infra::Dependencies;
 
@@ -16,6 +35,7 @@ pub fn get_run_render_job_command_handler(
         deps.config.clone(),
         deps.redis.clone(),
         minio.clone(),
+        deps.webhooks.clone(),
     ))
 }
 
@@ -26,6 +46,45 @@ pub fn get_get_render_result_command_handler(
     Some(GetRenderResultCommandHandler::new(minio.clone()))
 }
 
+pub fn get_get_render_flame_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderFlameCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(GetRenderFlameCommandHandler::new(minio.clone()))
+}
+
+pub fn get_get_render_frame_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderFrameCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(GetRenderFrameCommandHandler::new(minio.clone()))
+}
+
+pub fn get_get_render_animation_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderAnimationCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(GetRenderAnimationCommandHandler::new(minio.clone()))
+}
+
+pub fn get_get_render_genome_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderGenomeCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(GetRenderGenomeCommandHandler::new(minio.clone()))
+}
+
+pub fn get_get_render_thumbnail_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderThumbnailCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(GetRenderThumbnailCommandHandler::new(
+        deps.config.clone(),
+        minio.clone(),
+        deps.thumbnail_renders.clone(),
+    ))
+}
+
 pub fn get_get_intermediate_result_command_handler(
     deps: &Dependencies,
 ) -> Option<GetIntermediateResultCommandHandler> {
@@ -33,17 +92,55 @@ pub fn get_get_intermediate_result_command_handler(
     Some(GetIntermediateResultCommandHandler::new(minio.clone()))
 }
 
+pub fn get_control_render_job_command_handler(
+    deps: &Dependencies,
+) -> Option<ControlRenderJobCommandHandler> {
+    let redis = deps.redis.as_ref()?;
+    Some(ControlRenderJobCommandHandler::new(
+        deps.config.clone(),
+        redis.clone(),
+    ))
+}
+
 pub fn get_render_progress_command_handler(
     deps: &Dependencies,
 ) -> Option<RenderProgressCommandHandler> {
     let redis = deps.redis.as_ref()?;
-    Some(RenderProgressCommandHandler::new(redis.clone()))
+    let events = deps.events.as_ref()?;
+    Some(RenderProgressCommandHandler::new(
+        redis.clone(),
+        events.clone(),
+        Duration::from_millis(deps.config.sse_poll_interval_ms),
+    ))
+}
+
+pub fn get_get_render_job_command_handler(
+    deps: &Dependencies,
+) -> Option<GetRenderJobCommandHandler> {
+    let redis = deps.redis.as_ref()?;
+    Some(GetRenderJobCommandHandler::new(redis.clone()))
+}
+
+pub fn get_list_jobs_command_handler(deps: &Dependencies) -> Option<ListJobsCommandHandler> {
+    let redis = deps.redis.as_ref()?;
+    Some(ListJobsCommandHandler::new(redis.clone()))
 }
 
 pub fn get_get_all_variations_command_handler(
     deps: &Dependencies,
 ) -> GetAllVariationsCommandHandler {
-    GetAllVariationsCommandHandler::new(deps.transformations.clone())
+    GetAllVariationsCommandHandler::new(
+        deps.transformations.clone(),
+        deps.minio.clone(),
+        deps.custom_variations.clone(),
+    )
+}
+
+pub fn get_create_custom_variation_command_handler(
+    deps: &Dependencies,
+) -> Option<CreateCustomVariationCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(CreateCustomVariationCommandHandler::new(minio.clone()))
 }
 
 pub fn get_get_variation_preview_command_handler(
@@ -55,3 +152,85 @@ pub fn get_get_variation_preview_command_handler(
         deps.config.clone(),
     ))
 }
+
+pub fn get_import_flame_command_handler(deps: &Dependencies) -> Option<ImportFlameCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(ImportFlameCommandHandler::new(
+        minio.clone(),
+        deps.custom_variations.clone(),
+        run_handler,
+    ))
+}
+
+pub fn get_render_from_image_command_handler(
+    deps: &Dependencies,
+) -> Option<RenderFromImageCommandHandler> {
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(RenderFromImageCommandHandler::new(run_handler))
+}
+
+pub fn get_remix_render_command_handler(deps: &Dependencies) -> Option<RemixRenderCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(RemixRenderCommandHandler::new(
+        deps.config.clone(),
+        minio.clone(),
+        run_handler,
+    ))
+}
+
+pub fn get_mutate_render_command_handler(
+    deps: &Dependencies,
+) -> Option<MutateRenderCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(MutateRenderCommandHandler::new(
+        deps.config.clone(),
+        minio.clone(),
+        run_handler,
+    ))
+}
+
+pub fn get_breed_command_handler(deps: &Dependencies) -> Option<BreedCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(BreedCommandHandler::new(
+        deps.config.clone(),
+        minio.clone(),
+        run_handler,
+    ))
+}
+
+pub fn get_start_render_v2_command_handler(
+    deps: &Dependencies,
+) -> Option<StartRenderV2CommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    let run_handler = get_run_render_job_command_handler(deps)?;
+    Some(StartRenderV2CommandHandler::new(
+        deps.config.clone(),
+        minio.clone(),
+        run_handler,
+    ))
+}
+
+pub fn get_render_animation_command_handler(
+    deps: &Dependencies,
+) -> Option<RenderAnimationCommandHandler> {
+    let minio = deps.minio.as_ref()?;
+    Some(RenderAnimationCommandHandler::new(
+        deps.config.clone(),
+        deps.redis.clone(),
+        minio.clone(),
+    ))
+}
+
+pub fn get_render_shard_command_handler(deps: &Dependencies) -> Option<RenderShardCommandHandler> {
+    let redis = deps.redis.as_ref()?;
+    let minio = deps.minio.as_ref()?;
+    Some(RenderShardCommandHandler::new(
+        deps.config.clone(),
+        redis.clone(),
+        minio.clone(),
+    ))
+}
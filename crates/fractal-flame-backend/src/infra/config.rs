@@ -1,4 +1,7 @@
+use fractal_flame_core::app::genome::GenomeLimits;
+use fractal_flame_core::app::renderer::{PauseExpiry, RenderControl};
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 fn default_samples() -> usize {
@@ -39,6 +42,63 @@ fn default_preview_samples() -> usize {
 fn default_preview_iter() -> usize {
     150
 }
+fn default_max_samples() -> usize {
+    GenomeLimits::default().max_samples
+}
+fn default_max_iter_per_sample() -> usize {
+    GenomeLimits::default().max_iter_per_sample
+}
+fn default_max_symmetry() -> usize {
+    GenomeLimits::default().max_symmetry
+}
+fn default_max_thumbnail_renders() -> usize {
+    2
+}
+fn default_max_upload_bytes() -> usize {
+    64 * 1024 * 1024
+}
+fn default_max_batch_size() -> usize {
+    32
+}
+fn default_max_animation_frames() -> usize {
+    1000
+}
+fn default_max_temporal_samples() -> usize {
+    64
+}
+fn default_interactive_max_samples() -> usize {
+    1_000_000
+}
+fn default_interactive_max_pixels() -> usize {
+    1920 * 1080
+}
+fn default_tiled_render_min_pixels() -> usize {
+    4096 * 4096
+}
+fn default_tile_size() -> usize {
+    1024
+}
+fn default_shard_workers() -> usize {
+    1
+}
+fn default_render_workers() -> usize {
+    1
+}
+fn default_job_lease_secs() -> u64 {
+    30
+}
+fn default_max_pause_secs() -> u64 {
+    600
+}
+fn default_callback_max_attempts() -> u32 {
+    5
+}
+fn default_callback_retry_base_ms() -> u64 {
+    1000
+}
+fn default_callback_timeout_secs() -> u64 {
+    10
+}
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -67,6 +127,93 @@ pub struct Config {
     pub preview_samples: usize,
     #[serde(default = "default_preview_iter")]
     pub preview_iter: usize,
+    /// Most samples any one job may ask for.
+    #[serde(default = "default_max_samples")]
+    pub max_samples: usize,
+    /// Most iterations per sample any one job may ask for.
+    #[serde(default = "default_max_iter_per_sample")]
+    pub max_iter_per_sample: usize,
+    /// Most rotational copies any one job may ask for; each one is drawn on every iteration.
+    #[serde(default = "default_max_symmetry")]
+    pub max_symmetry: usize,
+    /// Most thumbnails rendered at once on cache misses; further requests wait their turn.
+    #[serde(default = "default_max_thumbnail_renders")]
+    pub max_thumbnail_renders: usize,
+    /// Body size limit for uploaded images and `.flame` files.
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+    /// Most child jobs one mutate or breed request may start.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Most frames one animation job may render.
+    #[serde(default = "default_max_animation_frames")]
+    pub max_animation_frames: usize,
+    /// Most motion-blur sub-frames each animation frame may be rendered from.
+    #[serde(default = "default_max_temporal_samples")]
+    pub max_temporal_samples: usize,
+    /// Renders larger than this many pixels are rendered tile by tile, without live previews.
+    #[serde(default = "default_tiled_render_min_pixels")]
+    pub tiled_render_min_pixels: usize,
+    /// Largest tile side, in pixels, for tiled renders.
+    #[serde(default = "default_tile_size")]
+    pub tile_size: usize,
+    /// Jobs with more samples are split into shards of this many, rendered by workers on every
+    /// instance. 0 renders each job on the instance that received it.
+    #[serde(default)]
+    pub shard_samples: usize,
+    /// Shards this instance renders at once.
+    #[serde(default = "default_shard_workers")]
+    pub shard_workers: usize,
+    /// Queued jobs this instance renders at once.
+    #[serde(default = "default_render_workers")]
+    pub render_workers: usize,
+    /// How long a job stays leased to a worker that stops renewing it before it is re-queued.
+    #[serde(default = "default_job_lease_secs")]
+    pub job_lease_secs: u64,
+    /// Longest a client may keep a job paused, counted from the pause request, before
+    /// `pause_expiry` applies. Paused renders hold their worker.
+    #[serde(default = "default_max_pause_secs")]
+    pub max_pause_secs: u64,
+    /// Whether a job paused for `max_pause_secs` resumes or is cancelled.
+    #[serde(default)]
+    pub pause_expiry: PauseExpiry,
+    /// API keys clients may name themselves by in `X-Api-Key`. Requests with any other key
+    /// count as anonymous.
+    #[serde(default)]
+    pub api_keys: HashSet<String>,
+    /// Most samples a job may ask for and still be scheduled as `interactive`; larger jobs
+    /// are scheduled as `normal`.
+    #[serde(default = "default_interactive_max_samples")]
+    pub interactive_max_samples: usize,
+    /// Most pixels a job may render and still be scheduled as `interactive`.
+    #[serde(default = "default_interactive_max_pixels")]
+    pub interactive_max_pixels: usize,
+    /// Jobs one client may have queued or rendering at once; 0 is unlimited.
+    #[serde(default)]
+    pub max_jobs_per_client: usize,
+    /// Samples one client may request per UTC day; 0 is unlimited.
+    #[serde(default)]
+    pub daily_samples_per_client: usize,
+    /// Share of the workers each client gets relative to others, keyed by API key. Clients
+    /// not listed weigh 1.
+    #[serde(default)]
+    pub client_weights: HashMap<String, f64>,
+    /// Base URL clients reach this server at, for links in job callbacks. Links are relative
+    /// without it.
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// Attempts to deliver a job callback before giving up.
+    #[serde(default = "default_callback_max_attempts")]
+    pub callback_max_attempts: u32,
+    /// Wait before the first retry of a callback, doubling after each one.
+    #[serde(default = "default_callback_retry_base_ms")]
+    pub callback_retry_base_ms: u64,
+    #[serde(default = "default_callback_timeout_secs")]
+    pub callback_timeout_secs: u64,
+    /// Hosts job callbacks may be posted to, wherever they resolve. Empty allows any host
+    /// that resolves only to public addresses.
+    #[serde(default)]
+    pub callback_allowed_hosts: Vec<String>,
 }
 
 impl Default for Config {
@@ -84,11 +231,61 @@ impl Default for Config {
             preview_size: default_preview_size(),
             preview_samples: default_preview_samples(),
             preview_iter: default_preview_iter(),
+            max_samples: default_max_samples(),
+            max_iter_per_sample: default_max_iter_per_sample(),
+            max_symmetry: default_max_symmetry(),
+            max_thumbnail_renders: default_max_thumbnail_renders(),
+            max_upload_bytes: default_max_upload_bytes(),
+            max_batch_size: default_max_batch_size(),
+            max_animation_frames: default_max_animation_frames(),
+            max_temporal_samples: default_max_temporal_samples(),
+            tiled_render_min_pixels: default_tiled_render_min_pixels(),
+            tile_size: default_tile_size(),
+            shard_samples: 0,
+            shard_workers: default_shard_workers(),
+            render_workers: default_render_workers(),
+            job_lease_secs: default_job_lease_secs(),
+            max_pause_secs: default_max_pause_secs(),
+            pause_expiry: PauseExpiry::default(),
+            api_keys: HashSet::new(),
+            interactive_max_samples: default_interactive_max_samples(),
+            interactive_max_pixels: default_interactive_max_pixels(),
+            max_jobs_per_client: 0,
+            daily_samples_per_client: 0,
+            client_weights: HashMap::new(),
+            public_url: None,
+            callback_max_attempts: default_callback_max_attempts(),
+            callback_retry_base_ms: default_callback_retry_base_ms(),
+            callback_timeout_secs: default_callback_timeout_secs(),
+            callback_allowed_hosts: Vec::new(),
         }
     }
 }
 
 impl Config {
+    /// Whether a `width` × `height` result is rendered tile by tile, which only formats
+    /// written row by row support.
+    pub fn renders_tiled(&self, width: usize, height: usize) -> bool {
+        width.saturating_mul(height) > self.tiled_render_min_pixels
+    }
+
+    /// The most work a job's genome may ask for.
+    pub fn genome_limits(&self) -> GenomeLimits {
+        GenomeLimits {
+            max_samples: self.max_samples,
+            max_iter_per_sample: self.max_iter_per_sample,
+            max_symmetry: self.max_symmetry,
+        }
+    }
+
+    /// A control for one render, whose pauses end after `max_pause_secs`.
+    pub fn render_control(&self) -> RenderControl {
+        RenderControl::with_max_pause(
+            std::time::Duration::from_secs(self.max_pause_secs),
+            self.pause_expiry,
+        )
+    }
+
     pub fn from_file(path: Option<impl AsRef<Path>>) -> Result<Self, ConfigError> {
         let path = path
             .map(|p| p.as_ref().to_path_buf())
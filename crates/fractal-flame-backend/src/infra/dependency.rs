@@ -1,74 +1,47 @@
 use std::sync::Arc;
 
-use fractal_flame_core::app::renderer::get_random_color;
-use fractal_flame_core::app::transformations::{
-    base_affine_transformation::BaseAffineTransformation, diamond::Diamond, disc::Disc, ex::Ex,
-    handkerchief::Handkerchief, heart::Heart, horseshoe::Horseshoe, hyperbolic::Hyperbolic,
-    linear::Linear, polar::Polar, sinusoidal::Sinusoidal, spherical::Spherical, spiral::Spiral,
-    swirl::Swirl,
+use fractal_flame_core::app::generation::{
+    ContractiveRandom, StrategyConfig, ValueRange, generation_rng,
 };
+use fractal_flame_core::app::genome::{CustomVariations, build_transform};
+use fractal_flame_core::app::transformations::registry;
 use fractal_flame_core::domain::transformation::Transformation;
 use fractal_flame_core::infra::random;
 
 use super::config::Config;
 use super::minio::{MinioClient, MinioConfig};
-use super::redis::RedisPool;
-
-fn search_affine_transformation(
-    config: &Config,
-) -> Result<BaseAffineTransformation, Box<dyn std::error::Error + Send + Sync>> {
-    loop {
-        let a = random::generate_f64(-1.5, 1.5, true)?;
-        let b = random::generate_f64(-1.5, 1.5, true)?;
-        let c = random::generate_f64(-2.0, 2.0, true)?;
-        let d = random::generate_f64(-1.5, 1.5, true)?;
-        let e = random::generate_f64(-1.5, 1.5, true)?;
-        let f = random::generate_f64(-2.0, 2.0, true)?;
-        let color = get_random_color()?;
-
-        let det = a * e - b * d;
-        if (a * a + d * d) < 1.0
-            && (b * b + e * e) < 1.0
-            && (a * a + b * b + d * d + e * e) < 1.0 + det * det
-        {
-            let weight = random::generate_f64(
-                config.transformation_min_weight,
-                config.transformation_max_weight,
-                true,
-            )?;
-            return Ok(BaseAffineTransformation::new(
-                weight, color, a, b, c, d, e, f,
-            ));
-        }
-    }
+use super::redis::{RedisPool, RedisSubscriber};
+use super::webhook::WebhookClient;
+use crate::app::services::custom_variation_service::CustomVariationCache;
+use crate::app::services::redis_key_service::RedisKeyService;
+use crate::app::use_cases::get_render_thumbnail_command_handler::ThumbnailRenders;
+
+/// Contractive random affines with the configured weight range.
+pub fn default_strategy(config: &Config) -> StrategyConfig {
+    StrategyConfig::ContractiveRandom(ContractiveRandom {
+        weight: ValueRange::new(
+            config.transformation_min_weight,
+            config.transformation_max_weight,
+        ),
+        ..ContractiveRandom::default()
+    })
 }
 
 fn initialize_transformations(
     config: &Config,
 ) -> Result<Vec<Box<dyn Transformation + Send + Sync>>, Box<dyn std::error::Error + Send + Sync>> {
-    let mut transformations: Vec<Box<dyn Transformation + Send + Sync>> = vec![];
-
-    let types: Vec<fn(BaseAffineTransformation) -> Box<dyn Transformation + Send + Sync>> = vec![
-        |base| Box::new(Diamond { base }),
-        |base| Box::new(Disc::new(base)),
-        |base| Box::new(Ex::new(base)),
-        |base| Box::new(Heart::new(base)),
-        |base| Box::new(Horseshoe::new(base)),
-        |base| Box::new(Spherical::new(base)),
-        |base| Box::new(Swirl::new(base)),
-        |base| Box::new(Linear::new(base)),
-        |base| Box::new(Polar::new(base)),
-        |base| Box::new(Spiral::new(base)),
-        |base| Box::new(Handkerchief::new(base)),
-        |base| Box::new(Hyperbolic::new(base)),
-        |base| Box::new(Sinusoidal::new(base)),
-    ];
-
-    for create in types {
-        let base = search_affine_transformation(config)?;
-        transformations.push(create(base));
+    let ids: Vec<String> = registry::VARIATION_IDS
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    let transforms = default_strategy(config)
+        .strategy()
+        .generate(&ids, &mut generation_rng(random::generate_seed()))?;
+
+    let mut transformations = Vec::with_capacity(transforms.len());
+    for transform in &transforms {
+        transformations.push(build_transform(transform, None, &CustomVariations::new())?);
     }
-
     Ok(transformations)
 }
 
@@ -77,48 +50,31 @@ pub struct Dependencies {
     pub config: Config,
     pub transformations: Arc<Vec<Box<dyn Transformation + Send + Sync>>>,
     pub redis: Option<Arc<RedisPool>>,
+    /// Job events published by any instance, over one connection shared by every follower.
+    pub events: Option<Arc<RedisSubscriber>>,
     pub minio: Option<Arc<MinioClient>>,
-}
-
-pub fn generate_transformations_for_ids(
-    config: &Config,
-    ids: &[String],
-) -> Result<Vec<Box<dyn Transformation + Send + Sync>>, Box<dyn std::error::Error + Send + Sync>> {
-    if ids.is_empty() {
-        return Err("No variations selected".into());
-    }
-    let mut result = Vec::with_capacity(ids.len());
-    for id in ids {
-        let base = search_affine_transformation(config)?;
-        let t: Box<dyn Transformation + Send + Sync> = match id.as_str() {
-            "diamond" => Box::new(Diamond { base }),
-            "disc" => Box::new(Disc::new(base)),
-            "ex" => Box::new(Ex::new(base)),
-            "heart" => Box::new(Heart::new(base)),
-            "horseshoe" => Box::new(Horseshoe::new(base)),
-            "spherical" => Box::new(Spherical::new(base)),
-            "swirl" => Box::new(Swirl::new(base)),
-            "linear" => Box::new(Linear::new(base)),
-            "polar" => Box::new(Polar::new(base)),
-            "spiral" => Box::new(Spiral::new(base)),
-            "handkerchief" => Box::new(Handkerchief::new(base)),
-            "hyperbolic" => Box::new(Hyperbolic::new(base)),
-            "sinusoidal" => Box::new(Sinusoidal::new(base)),
-            other => return Err(format!("Unknown variation id: {}", other).into()),
-        };
-        result.push(t);
-    }
-    Ok(result)
+    pub webhooks: WebhookClient,
+    /// Bounds and coalesces thumbnail renders across requests.
+    pub thumbnail_renders: Arc<ThumbnailRenders>,
+    pub custom_variations: Arc<CustomVariationCache>,
 }
 
 impl Dependencies {
     pub fn new(config: Config) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let transformations = initialize_transformations(&config)?;
 
-        let redis = std::env::var("REDIS_URL")
-            .ok()
-            .filter(|s| !s.is_empty())
-            .map(|url| RedisPool::from_url(&url))
+        let redis_url = std::env::var("REDIS_URL").ok().filter(|s| !s.is_empty());
+        let redis = redis_url
+            .as_deref()
+            .map(RedisPool::from_url)
+            .transpose()
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
+                Box::new(std::io::Error::other(e.to_string()))
+            })?
+            .map(Arc::new);
+        let events = redis_url
+            .as_deref()
+            .map(|url| RedisSubscriber::from_url(url, RedisKeyService::job_events_pattern()))
             .transpose()
             .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
                 Box::new(std::io::Error::new(
@@ -156,11 +112,22 @@ impl Dependencies {
             Some(Arc::new(client))
         })();
 
+        let webhooks = WebhookClient::new(
+            std::time::Duration::from_secs(config.callback_timeout_secs),
+            config.callback_allowed_hosts.clone(),
+        )?;
+
+        let thumbnail_renders = Arc::new(ThumbnailRenders::new(config.max_thumbnail_renders));
+
         Ok(Self {
             config,
             transformations: Arc::new(transformations),
             redis,
+            events,
             minio,
+            webhooks,
+            thumbnail_renders,
+            custom_variations: Arc::new(CustomVariationCache::default()),
         })
     }
 }
@@ -1,6 +1,15 @@
+use std::io::{self, Write};
+
 use s3::bucket::Bucket;
 use s3::creds::Credentials;
 use s3::region::Region;
+use s3::serde_types::Part;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Size of each part of a streamed upload. S3 requires every part but the last to be at least
+/// 5 MiB.
+const UPLOAD_PART_BYTES: usize = 8 * 1024 * 1024;
 
 #[derive(Clone)]
 pub struct MinioClient {
@@ -63,6 +72,102 @@ impl MinioClient {
         Ok(())
     }
 
+    /// Runs `write` on a blocking thread and uploads what it writes to `key` as it goes, so the
+    /// object is never held in memory whole. Objects smaller than one part are uploaded in a
+    /// single request; larger ones use a multipart upload, which is aborted if `write` fails.
+    pub async fn put_object_streamed<F>(
+        &self,
+        key: &str,
+        content_type: &str,
+        write: F,
+    ) -> Result<(), MinioError>
+    where
+        F: FnOnce(PartWriter) -> Result<(), String> + Send + 'static,
+    {
+        // One part in flight while the next is written keeps memory at about two parts.
+        let (tx, mut rx) = mpsc::channel(1);
+        let producer = tokio::task::spawn_blocking(move || {
+            write(PartWriter {
+                buf: Vec::with_capacity(UPLOAD_PART_BYTES),
+                tx,
+            })
+        });
+
+        let Some(first) = rx.recv().await else {
+            produced(producer).await?;
+            return self.put_object(key, Vec::new(), content_type).await;
+        };
+        let Some(second) = rx.recv().await else {
+            produced(producer).await?;
+            return self.put_object(key, first, content_type).await;
+        };
+
+        let upload = self
+            .bucket
+            .initiate_multipart_upload(key, content_type)
+            .await
+            .map_err(|e| MinioError::S3(e.to_string()))?;
+        let result = self
+            .upload_parts(
+                key,
+                &upload.upload_id,
+                content_type,
+                [first, second],
+                rx,
+                producer,
+            )
+            .await;
+        if result.is_err()
+            && let Err(e) = self.bucket.abort_upload(key, &upload.upload_id).await
+        {
+            tracing::warn!(key, error = %e, "Failed to abort multipart upload");
+        }
+        result
+    }
+
+    async fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        content_type: &str,
+        received: [Vec<u8>; 2],
+        mut rx: mpsc::Receiver<Vec<u8>>,
+        producer: JoinHandle<Result<(), String>>,
+    ) -> Result<(), MinioError> {
+        let mut parts = Vec::new();
+        let mut pending = received.into_iter();
+        loop {
+            let chunk = match pending.next() {
+                Some(chunk) => chunk,
+                None => match rx.recv().await {
+                    Some(chunk) => chunk,
+                    None => break,
+                },
+            };
+            let part_number = parts.len() as u32 + 1;
+            let part: Part = self
+                .bucket
+                .put_multipart_chunk(chunk, key, part_number, upload_id, content_type)
+                .await
+                .map_err(|e| MinioError::S3(e.to_string()))?;
+            parts.push(part);
+        }
+        produced(producer).await?;
+
+        let response = self
+            .bucket
+            .complete_multipart_upload(key, upload_id, parts)
+            .await
+            .map_err(|e| MinioError::S3(e.to_string()))?;
+        if response.status_code() != 200 {
+            return Err(MinioError::S3(format!(
+                "multipart upload completion returned status {}",
+                response.status_code()
+            )));
+        }
+        Ok(())
+    }
+
     pub async fn get_object(&self, key: &str) -> Result<Vec<u8>, MinioError> {
         let response = self
             .bucket
@@ -72,6 +177,26 @@ impl MinioClient {
         Ok(response.bytes().to_vec())
     }
 
+    pub async fn delete_object(&self, key: &str) -> Result<(), MinioError> {
+        self.bucket
+            .delete_object(key)
+            .await
+            .map_err(|e| MinioError::S3(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, MinioError> {
+        let results = self
+            .bucket
+            .list(prefix.to_string(), None)
+            .await
+            .map_err(|e| MinioError::S3(e.to_string()))?;
+        Ok(results
+            .into_iter()
+            .flat_map(|r| r.contents.into_iter().map(|o| o.key))
+            .collect())
+    }
+
     pub async fn ping(&self) -> Result<(), MinioError> {
         self.bucket
             .list("__health__".to_string(), Some("/".to_string()))
@@ -81,10 +206,53 @@ impl MinioClient {
     }
 }
 
+/// Waits for the writer of a streamed upload, which has closed its end of the channel.
+async fn produced(producer: JoinHandle<Result<(), String>>) -> Result<(), MinioError> {
+    producer
+        .await
+        .map_err(|e| MinioError::Source(format!("writer panicked: {}", e)))?
+        .map_err(MinioError::Source)
+}
+
+/// Blocking [`Write`] end of [`MinioClient::put_object_streamed`]. Each full part is handed to
+/// the upload; the rest goes when the writer is dropped.
+pub struct PartWriter {
+    buf: Vec<u8>,
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+impl Write for PartWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= UPLOAD_PART_BYTES {
+            let part = std::mem::replace(&mut self.buf, Vec::with_capacity(UPLOAD_PART_BYTES));
+            self.tx
+                .blocking_send(part)
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "upload stopped"))?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for PartWriter {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            let _ = self.tx.blocking_send(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum MinioError {
     #[error("S3 error: {0}")]
     S3(String),
     #[error("Credentials error: {0}")]
     Creds(String),
+    /// The writer of a streamed upload failed, so nothing was stored.
+    #[error("Failed to produce object: {0}")]
+    Source(String),
 }
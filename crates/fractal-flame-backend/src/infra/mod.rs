@@ -2,6 +2,7 @@ pub mod config;
 pub mod dependency;
 pub mod minio;
 pub mod redis;
+pub mod webhook;
 
 pub use config::Config;
 pub use dependency::Dependencies;
@@ -1,5 +1,15 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+
 use deadpool_redis::redis::AsyncCommands;
 use deadpool_redis::{Config, Pool, Runtime};
+use futures::StreamExt;
+use tokio::sync::broadcast;
+
+/// Messages a slow subscriber may fall behind by before it misses some.
+const SUBSCRIBER_CAPACITY: usize = 64;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Clone)]
 pub struct RedisPool {
@@ -47,6 +57,331 @@ impl RedisPool {
         Ok(())
     }
 
+    /// Adds `value` to the list at `key`; lists are popped oldest first.
+    pub async fn push(&self, key: &str, value: &str) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("LPUSH")
+            .arg(key)
+            .arg(value)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(())
+    }
+
+    /// Moves the oldest value of the list at `source` onto the front of the list at
+    /// `destination` and returns it, waiting up to `timeout_secs` for one.
+    pub async fn move_blocking(
+        &self,
+        source: &str,
+        destination: &str,
+        timeout_secs: u64,
+    ) -> Result<Option<String>, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("BLMOVE")
+            .arg(source)
+            .arg(destination)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .arg(timeout_secs)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    /// Adds `value` to the list at `key` as its oldest, so it is popped next.
+    pub async fn push_front(&self, key: &str, value: &str) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("RPUSH")
+            .arg(key)
+            .arg(value)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(())
+    }
+
+    /// Adds `delta` to the integer at `key`, refreshing its expiry, and returns the new value.
+    pub async fn incr_by(
+        &self,
+        key: &str,
+        delta: i64,
+        ttl_secs: Option<u64>,
+    ) -> Result<i64, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let value: i64 = deadpool_redis::redis::cmd("INCRBY")
+            .arg(key)
+            .arg(delta)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        if let Some(ttl) = ttl_secs {
+            let _: i64 = deadpool_redis::redis::cmd("EXPIRE")
+                .arg(key)
+                .arg(ttl)
+                .query_async(&mut conn)
+                .await
+                .map_err(RedisError::Redis)?;
+        }
+        Ok(value)
+    }
+
+    /// Adds `member` to the set at `key`, refreshing the set's expiry.
+    pub async fn sadd(
+        &self,
+        key: &str,
+        member: &str,
+        ttl_secs: Option<u64>,
+    ) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("SADD")
+            .arg(key)
+            .arg(member)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        if let Some(ttl) = ttl_secs {
+            let _: i64 = deadpool_redis::redis::cmd("EXPIRE")
+                .arg(key)
+                .arg(ttl)
+                .query_async(&mut conn)
+                .await
+                .map_err(RedisError::Redis)?;
+        }
+        Ok(())
+    }
+
+    /// Number of members of the set at `key`; none if it does not exist.
+    pub async fn scard(&self, key: &str) -> Result<usize, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("SCARD")
+            .arg(key)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    /// Removes one occurrence of `value` from the list at `key`, returning whether there was
+    /// one.
+    pub async fn remove(&self, key: &str, value: &str) -> Result<bool, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let removed: i64 = deadpool_redis::redis::cmd("LREM")
+            .arg(key)
+            .arg(1)
+            .arg(value)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(removed > 0)
+    }
+
+    /// Every value in the list at `key`, newest first.
+    pub async fn list(&self, key: &str) -> Result<Vec<String>, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("LRANGE")
+            .arg(key)
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    /// Adds `member` to the sorted set at `key`, or moves it to `score` if already there.
+    pub async fn zadd(&self, key: &str, member: &str, score: f64) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("ZADD")
+            .arg(key)
+            .arg(score)
+            .arg(member)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(())
+    }
+
+    /// How many members of the sorted set at `key` score lower than `member`, if it is there.
+    pub async fn zrank(&self, key: &str, member: &str) -> Result<Option<usize>, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("ZRANK")
+            .arg(key)
+            .arg(member)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    /// The values at `keys`, in one round trip.
+    pub async fn mget(&self, keys: &[String]) -> Result<Vec<Option<String>>, RedisError> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    pub async fn zrem(&self, key: &str, member: &str) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("ZREM")
+            .arg(key)
+            .arg(member)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(())
+    }
+
+    /// Sets `field` of the hash at `key`, refreshing the hash's expiry.
+    pub async fn hset(
+        &self,
+        key: &str,
+        field: &str,
+        value: &str,
+        ttl_secs: Option<u64>,
+    ) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        conn.hset::<_, _, _, ()>(key, field, value)
+            .await
+            .map_err(RedisError::Redis)?;
+        if let Some(ttl) = ttl_secs {
+            let _: i64 = deadpool_redis::redis::cmd("EXPIRE")
+                .arg(key)
+                .arg(ttl)
+                .query_async(&mut conn)
+                .await
+                .map_err(RedisError::Redis)?;
+        }
+        Ok(())
+    }
+
+    pub async fn hgetall(&self, key: &str) -> Result<HashMap<String, String>, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        conn.hgetall(key).await.map_err(RedisError::Redis)
+    }
+
+    /// [`RedisPool::list`] of each of `keys`, pipelined into one round trip.
+    pub async fn list_many(&self, keys: &[String]) -> Result<Vec<Vec<String>>, RedisError> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let mut pipe = deadpool_redis::redis::pipe();
+        for key in keys {
+            pipe.cmd("LRANGE").arg(key).arg(0).arg(-1);
+        }
+        pipe.query_async(&mut conn).await.map_err(RedisError::Redis)
+    }
+
+    /// Runs a Lua `script`, which Redis executes atomically.
+    pub async fn eval<T: deadpool_redis::redis::FromRedisValue>(
+        &self,
+        script: &str,
+        keys: &[&str],
+        args: &[String],
+    ) -> Result<T, RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        deadpool_redis::redis::cmd("EVAL")
+            .arg(script)
+            .arg(keys.len())
+            .arg(keys)
+            .arg(args)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)
+    }
+
+    pub async fn publish(&self, channel: &str, message: &str) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        let _: i64 = deadpool_redis::redis::cmd("PUBLISH")
+            .arg(channel)
+            .arg(message)
+            .query_async(&mut conn)
+            .await
+            .map_err(RedisError::Redis)?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), RedisError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| RedisError::Pool(e.to_string()))?;
+        conn.del::<_, ()>(key).await.map_err(RedisError::Redis)
+    }
+
     pub async fn ping(&self) -> Result<(), RedisError> {
         let mut conn = self
             .pool
@@ -61,6 +396,95 @@ impl RedisPool {
     }
 }
 
+type Subscribers = Arc<Mutex<HashMap<String, broadcast::Sender<String>>>>;
+
+/// Listens on every channel matching `pattern` over a single connection, and hands each
+/// message to whoever here subscribed to its channel. Reconnects when the connection drops.
+pub struct RedisSubscriber {
+    client: deadpool_redis::redis::Client,
+    pattern: String,
+    subscribers: Subscribers,
+    listening: Once,
+}
+
+impl RedisSubscriber {
+    pub fn from_url(url: &str, pattern: &str) -> Result<Self, RedisError> {
+        Ok(Self {
+            client: deadpool_redis::redis::Client::open(url)?,
+            pattern: pattern.to_string(),
+            subscribers: Arc::default(),
+            listening: Once::new(),
+        })
+    }
+
+    /// Messages published on `channel` from now on. The receiver is closed whenever the
+    /// connection is re-established, since messages may have been missed in between.
+    pub fn subscribe(&self, channel: &str) -> broadcast::Receiver<String> {
+        self.listening.call_once(|| {
+            tokio::spawn(Self::listen(
+                self.client.clone(),
+                self.pattern.clone(),
+                self.subscribers.clone(),
+            ));
+        });
+        Self::register(&self.subscribers, channel)
+    }
+
+    /// A receiver for `channel`, sharing one sender with everyone else listening to it.
+    fn register(subscribers: &Subscribers, channel: &str) -> broadcast::Receiver<String> {
+        let mut subscribers = subscribers.lock().unwrap();
+        subscribers.retain(|_, sender| sender.receiver_count() > 0);
+        subscribers
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(SUBSCRIBER_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Hands `payload` to `channel`'s receivers, forgetting the channel once none are left.
+    fn dispatch(subscribers: &Subscribers, channel: &str, payload: String) {
+        let mut subscribers = subscribers.lock().unwrap();
+        if let Some(sender) = subscribers.get(channel)
+            && sender.send(payload).is_err()
+        {
+            subscribers.remove(channel);
+        }
+    }
+
+    async fn listen(
+        client: deadpool_redis::redis::Client,
+        pattern: String,
+        subscribers: Subscribers,
+    ) {
+        loop {
+            if let Err(e) = Self::forward(&client, &pattern, &subscribers).await {
+                tracing::warn!(error = %e, "Redis subscription failed");
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Forwards messages until the connection drops.
+    async fn forward(
+        client: &deadpool_redis::redis::Client,
+        pattern: &str,
+        subscribers: &Subscribers,
+    ) -> Result<(), RedisError> {
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.psubscribe(pattern).await?;
+        // Anyone who subscribed before now may have missed messages.
+        subscribers.lock().unwrap().clear();
+
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let Ok(payload) = message.get_payload::<String>() else {
+                continue;
+            };
+            Self::dispatch(subscribers, message.get_channel_name(), payload);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RedisError {
     #[error("Redis pool error: {0}")]
@@ -68,3 +492,44 @@ pub enum RedisError {
     #[error("Redis error: {0}")]
     Redis(#[from] deadpool_redis::redis::RedisError),
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast::error::RecvError;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn hands_messages_to_each_channels_receivers() {
+        let subscribers = Subscribers::default();
+        let mut first = RedisSubscriber::register(&subscribers, "job:a:events");
+        let mut second = RedisSubscriber::register(&subscribers, "job:a:events");
+        let mut other = RedisSubscriber::register(&subscribers, "job:b:events");
+
+        RedisSubscriber::dispatch(&subscribers, "job:a:events", "hello".to_string());
+        assert_eq!(first.recv().await.unwrap(), "hello");
+        assert_eq!(second.recv().await.unwrap(), "hello");
+        assert!(other.try_recv().is_err());
+
+        // A channel nobody listens to any more is forgotten on its next message.
+        drop((first, second));
+        RedisSubscriber::dispatch(&subscribers, "job:a:events", "gone".to_string());
+        assert!(!subscribers.lock().unwrap().contains_key("job:a:events"));
+    }
+
+    #[tokio::test]
+    async fn slow_receivers_lag_and_reconnects_close_them() {
+        let subscribers = Subscribers::default();
+        let mut receiver = RedisSubscriber::register(&subscribers, "job:a:events");
+        for i in 0..=SUBSCRIBER_CAPACITY {
+            RedisSubscriber::dispatch(&subscribers, "job:a:events", i.to_string());
+        }
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(1))));
+        assert_eq!(receiver.recv().await.unwrap(), "1");
+
+        // What `forward` does once it has subscribed again.
+        subscribers.lock().unwrap().clear();
+        while receiver.try_recv().is_ok() {}
+        assert!(matches!(receiver.recv().await, Err(RecvError::Closed)));
+    }
+}
@@ -0,0 +1,184 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+
+/// Milliseconds since the Unix epoch at which the request was signed.
+pub const TIMESTAMP_HEADER: &str = "x-fractal-timestamp";
+/// `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}` under the callback's secret.
+pub const SIGNATURE_HEADER: &str = "x-fractal-signature";
+
+/// Whether `ip` is reachable from the internet at large, as opposed to this host, its
+/// private network or the link it is on, which client-given URLs must not reach.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.is_multicast())
+            }
+        },
+    }
+}
+
+/// Resolves hosts as usual but only to their public addresses, so a name that resolves
+/// somewhere else by the time we connect is refused rather than followed.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(WebhookError::Forbidden(host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Posts JSON to URLs clients gave us, signed with their secret if they gave one. Only
+/// `allowed_hosts` are reached if any are configured; otherwise only public addresses are,
+/// both when a URL is checked and when it is posted to, and redirects are never followed.
+#[derive(Clone)]
+pub struct WebhookClient {
+    http: reqwest::Client,
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl WebhookClient {
+    pub fn new(timeout: Duration, allowed_hosts: Vec<String>) -> Result<Self, WebhookError> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if allowed_hosts.is_empty() {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        let http = builder
+            .build()
+            .map_err(|e| WebhookError::Request(e.to_string()))?;
+        let allowed_hosts = allowed_hosts
+            .into_iter()
+            .map(|host| host.to_ascii_lowercase())
+            .collect();
+        Ok(Self {
+            http,
+            allowed_hosts: Arc::new(allowed_hosts),
+        })
+    }
+
+    /// Checks that `url` is an `http` or `https` URL we may post to, resolving its host.
+    pub async fn check(&self, url: &str) -> Result<(), WebhookError> {
+        let url = reqwest::Url::parse(url).map_err(|e| WebhookError::InvalidUrl(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(WebhookError::InvalidUrl(format!(
+                "unsupported scheme '{}'",
+                url.scheme()
+            )));
+        }
+        let Some(host) = url.host_str() else {
+            return Err(WebhookError::InvalidUrl("missing host".to_string()));
+        };
+        // IPv6 hosts come in brackets.
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        if !self.allowed_hosts.is_empty() {
+            return match self.allowed_hosts.contains(&host.to_ascii_lowercase()) {
+                true => Ok(()),
+                false => Err(WebhookError::Forbidden(host)),
+            };
+        }
+
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<IpAddr> = match host.parse() {
+            Ok(ip) => vec![ip],
+            Err(_) => tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| WebhookError::Request(e.to_string()))?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+        if addrs.is_empty() || !addrs.into_iter().all(is_public) {
+            return Err(WebhookError::Forbidden(host));
+        }
+        Ok(())
+    }
+
+    /// Posts `body` to `url` and returns the response status.
+    pub async fn post(
+        &self,
+        url: &str,
+        body: String,
+        secret: Option<&str>,
+        timestamp: u64,
+    ) -> Result<u16, WebhookError> {
+        // Addresses in the URL itself never reach the resolver.
+        self.check(url).await?;
+        let mut request = self
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string());
+        if let Some(secret) = secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, &body));
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| WebhookError::Request(e.to_string()))?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// The [`SIGNATURE_HEADER`] value for `body` sent at `timestamp`.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", digest)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook URL: {0}")]
+    InvalidUrl(String),
+    #[error("Webhooks may not be sent to {0}")]
+    Forbidden(String),
+    #[error("Webhook request failed: {0}")]
+    Request(String),
+}
@@ -4,6 +4,7 @@ mod infra;
 mod views;
 
 use axum::Router;
+use axum::extract::DefaultBodyLimit;
 use axum::routing::{get, post};
 use std::net::SocketAddr;
 use tower_http::LatencyUnit;
@@ -23,8 +24,27 @@ async fn main() {
         tracing::warn!("Failed to load config from file ({}), using defaults", e);
         infra::Config::default()
     });
+    let upload_limit = DefaultBodyLimit::max(config.max_upload_bytes);
     let deps = infra::Dependencies::new(config).expect("Failed to initialize dependencies");
 
+    if deps.redis.is_some()
+        && let Some(run_handler) = di::get_run_render_job_command_handler(&deps)
+    {
+        for _ in 0..deps.config.render_workers {
+            tokio::spawn(run_handler.clone().run());
+        }
+        tokio::spawn(run_handler.requeue_orphans());
+    }
+    // Shards are only queued when `shard_samples` is set.
+    if deps.config.shard_samples > 0
+        && let Some(shard_handler) = di::get_render_shard_command_handler(&deps)
+    {
+        for _ in 0..deps.config.shard_workers {
+            tokio::spawn(shard_handler.clone().run());
+        }
+        tokio::spawn(shard_handler.requeue_orphans());
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
@@ -37,19 +57,89 @@ async fn main() {
             "/api/variations",
             get(views::get_variations::get_variations),
         )
+        .route(
+            "/api/variations/custom",
+            post(views::create_custom_variation::create_custom_variation),
+        )
         .route(
             "/api/variations/{id}/preview",
             get(views::get_variation_preview::get_variation_preview),
         )
+        .route("/api/render", get(views::list_jobs::list_jobs))
         .route("/api/render/start", post(views::start_render::start_render))
+        .route(
+            "/api/v2/render/start",
+            post(views::start_render_v2::start_render_v2),
+        )
+        .route(
+            "/api/render/flame",
+            post(views::import_flame::import_flame).layer(upload_limit),
+        )
+        .route(
+            "/api/render/from-image",
+            post(views::render_from_image::render_from_image).layer(upload_limit),
+        )
+        .route(
+            "/api/render/animation",
+            post(views::render_animation::render_animation),
+        )
+        .route(
+            "/api/render/{job_id}",
+            get(views::get_render_job::get_render_job),
+        )
         .route(
             "/api/render/{job_id}/result",
             get(views::get_render_result::get_render_result),
         )
+        .route(
+            "/api/render/{job_id}/thumbnail",
+            get(views::get_render_thumbnail::get_render_thumbnail),
+        )
+        .route(
+            "/api/render/{job_id}/flame",
+            get(views::get_render_flame::get_render_flame),
+        )
+        .route(
+            "/api/render/{job_id}/frames/{index}",
+            get(views::get_render_frame::get_render_frame),
+        )
+        .route(
+            "/api/render/{job_id}/animation.gif",
+            get(views::get_render_animation::get_render_animation_gif),
+        )
+        .route(
+            "/api/render/{job_id}/animation.png",
+            get(views::get_render_animation::get_render_animation_apng),
+        )
+        .route(
+            "/api/render/{job_id}/animation.webp",
+            get(views::get_render_animation::get_render_animation_webp),
+        )
+        .route(
+            "/api/render/{job_id}/rotation",
+            post(views::render_animation::render_rotation),
+        )
+        .route(
+            "/api/render/{job_id}/genome",
+            get(views::get_render_genome::get_render_genome),
+        )
+        .route(
+            "/api/render/{job_id}/remix",
+            post(views::remix_render::remix_render),
+        )
+        .route(
+            "/api/render/{job_id}/mutate",
+            post(views::mutate_render::mutate_render),
+        )
+        .route("/api/breed", post(views::breed::breed))
         .route(
             "/api/render/{job_id}/progress",
             get(views::render_progress::render_progress),
         )
+        .route(
+            "/api/render/{job_id}/ws",
+            get(views::render_socket::render_socket),
+        )
         .route(
             "/api/render/{job_id}/intermediate",
             get(views::get_intermediate_result::get_intermediate_result),
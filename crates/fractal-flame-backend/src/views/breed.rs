@@ -0,0 +1,80 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use fractal_flame_core::app::evolution::Crossover;
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::breed_command::BreedCommand;
+use crate::app::use_cases::breed_command_handler::BreedError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{client_id, quota_exceeded};
+
+fn default_count() -> usize {
+    8
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreedRequest {
+    pub parents: [String; 2],
+    #[serde(default = "default_count")]
+    pub count: usize,
+    pub method: Option<Crossover>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BredChildResponse {
+    pub job_id: String,
+    pub method: Crossover,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreedResponse {
+    pub parent_ids: [String; 2],
+    pub children: Vec<BredChildResponse>,
+}
+
+pub async fn breed(
+    State(deps): State<Dependencies>,
+    headers: HeaderMap,
+    Json(body): Json<BreedRequest>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_breed_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let command = BreedCommand {
+        parent_ids: body.parents.clone(),
+        count: body.count,
+        method: body.method,
+        client: client_id(&headers, &deps.config),
+    };
+
+    match handler.handle(command).await {
+        Ok(children) => (
+            StatusCode::ACCEPTED,
+            Json(BreedResponse {
+                parent_ids: body.parents,
+                children: children
+                    .into_iter()
+                    .map(|c| BredChildResponse {
+                        job_id: c.job_id,
+                        method: c.method,
+                    })
+                    .collect(),
+            }),
+        )
+            .into_response(),
+        Err(e @ BreedError::NotFound(_)) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+        Err(BreedError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
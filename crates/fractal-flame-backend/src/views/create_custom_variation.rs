@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde::Deserialize;
+
+use crate::app::use_cases::create_custom_variation_command::CreateCustomVariationCommand;
+use crate::app::use_cases::create_custom_variation_command_handler::CreateCustomVariationError;
+use crate::di;
+use crate::infra::Dependencies;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCustomVariationRequest {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub x: String,
+    pub y: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+pub async fn create_custom_variation(
+    State(deps): State<Dependencies>,
+    Json(body): Json<CreateCustomVariationRequest>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_create_custom_variation_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let command = CreateCustomVariationCommand {
+        id: body.id,
+        name: body.name,
+        x: body.x,
+        y: body.y,
+        parameters: body.parameters,
+    };
+
+    match handler.handle(command).await {
+        Ok(variation) => (StatusCode::CREATED, Json(variation)).into_response(),
+        Err(e @ CreateCustomVariationError::AlreadyExists(_)) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e @ CreateCustomVariationError::Storage(_)) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,60 @@
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Response},
+};
+use fractal_flame_core::app::image_export::AnimationFormat;
+
+use crate::app::use_cases::get_render_animation_command::GetRenderAnimationCommand;
+use crate::app::use_cases::get_render_animation_command_handler::GetRenderAnimationOutcome;
+use crate::di;
+use crate::infra::Dependencies;
+
+async fn get_render_animation(
+    deps: &Dependencies,
+    job_id: String,
+    format: AnimationFormat,
+) -> Response {
+    let Some(handler) = di::get_get_render_animation_command_handler(deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler
+        .handle(GetRenderAnimationCommand { job_id, format })
+        .await
+    {
+        GetRenderAnimationOutcome::Ready(bytes) => (
+            AppendHeaders([(header::CONTENT_TYPE, format.content_type())]),
+            bytes,
+        )
+            .into_response(),
+        GetRenderAnimationOutcome::NotFound => {
+            (StatusCode::NOT_FOUND, "Animation not found".to_string()).into_response()
+        }
+    }
+}
+
+pub async fn get_render_animation_gif(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    get_render_animation(&deps, job_id, AnimationFormat::Gif).await
+}
+
+pub async fn get_render_animation_apng(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    get_render_animation(&deps, job_id, AnimationFormat::Apng).await
+}
+
+pub async fn get_render_animation_webp(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    get_render_animation(&deps, job_id, AnimationFormat::Webp).await
+}
@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{AppendHeaders, IntoResponse},
+};
+
+use crate::app::use_cases::get_render_flame_command::GetRenderFlameCommand;
+use crate::app::use_cases::get_render_flame_command_handler::GetRenderFlameOutcome;
+use crate::di;
+use crate::infra::Dependencies;
+
+/// The job's parameters as a flam3 `.flame` file for Apophysis or JWildfire.
+pub async fn get_render_flame(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_get_render_flame_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let outcome = handler
+        .handle(GetRenderFlameCommand {
+            job_id: job_id.clone(),
+        })
+        .await;
+
+    match outcome {
+        GetRenderFlameOutcome::Ready(xml) => (
+            AppendHeaders([
+                (header::CONTENT_TYPE, "application/xml".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.flame\"", job_id),
+                ),
+            ]),
+            xml,
+        )
+            .into_response(),
+        GetRenderFlameOutcome::NotFound => {
+            (StatusCode::NOT_FOUND, "Job not found".to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{AppendHeaders, IntoResponse},
+};
+
+use crate::app::use_cases::get_render_frame_command::GetRenderFrameCommand;
+use crate::app::use_cases::get_render_frame_command_handler::GetRenderFrameOutcome;
+use crate::di;
+use crate::infra::Dependencies;
+
+pub async fn get_render_frame(
+    State(deps): State<Dependencies>,
+    Path((job_id, index)): Path<(String, usize)>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_get_render_frame_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler
+        .handle(GetRenderFrameCommand { job_id, index })
+        .await
+    {
+        GetRenderFrameOutcome::Ready(png_bytes) => (
+            AppendHeaders([(header::CONTENT_TYPE, "image/png")]),
+            png_bytes,
+        )
+            .into_response(),
+        GetRenderFrameOutcome::NotFound => {
+            (StatusCode::NOT_FOUND, "Frame not found".to_string()).into_response()
+        }
+    }
+}
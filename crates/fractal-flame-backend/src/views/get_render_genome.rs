@@ -0,0 +1,32 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::app::use_cases::get_render_genome_command::GetRenderGenomeCommand;
+use crate::app::use_cases::get_render_genome_command_handler::GetRenderGenomeOutcome;
+use crate::di;
+use crate::infra::Dependencies;
+
+/// The resolved genome (transforms, colors, seed and settings) a job was rendered from.
+pub async fn get_render_genome(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_get_render_genome_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler.handle(GetRenderGenomeCommand { job_id }).await {
+        GetRenderGenomeOutcome::Ready(genome) => Json(*genome).into_response(),
+        GetRenderGenomeOutcome::NotFound => {
+            (StatusCode::NOT_FOUND, "Job not found".to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Serialize;
+
+use crate::app::services::callback_service::CallbackDelivery;
+use crate::app::services::job_index_service::JobParams;
+use crate::app::services::job_state_service::{JobFailure, Transition};
+use crate::app::use_cases::get_render_job_command::GetRenderJobCommand;
+use crate::di;
+use crate::infra::Dependencies;
+
+#[derive(Debug, Serialize)]
+pub struct RenderJobResponse {
+    pub job_id: String,
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<JobParams>,
+    /// Every state the job has been in, oldest first, with when it got there.
+    pub transitions: Vec<Transition>,
+    pub progress: u64,
+    pub total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobFailure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback: Option<CallbackDelivery>,
+}
+
+/// A job's parameters, state history and, if it failed, why.
+pub async fn get_render_job(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_get_render_job_command_handler(&deps) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Redis not configured").into_response();
+    };
+
+    match handler
+        .handle(GetRenderJobCommand {
+            job_id: job_id.clone(),
+        })
+        .await
+    {
+        Ok(Some(job)) => Json(RenderJobResponse {
+            job_id,
+            state: job.status,
+            created_at: job.summary.as_ref().map(|summary| summary.created_at),
+            params: job.summary.map(|summary| summary.params),
+            transitions: job.transitions,
+            progress: job.progress,
+            total: job.total,
+            error: job.error,
+            callback: job.callback,
+        })
+        .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Job not found").into_response(),
+        Err(e) => {
+            tracing::error!(job_id = %job_id, error = %e, "Failed to load job");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load job").into_response()
+        }
+    }
+}
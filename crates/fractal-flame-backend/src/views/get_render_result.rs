@@ -28,9 +28,9 @@ pub async fn get_render_result(
         .await;
 
     match outcome {
-        GetRenderResultOutcome::Ready(png_bytes) => (
-            AppendHeaders([(header::CONTENT_TYPE, "image/png")]),
-            png_bytes,
+        GetRenderResultOutcome::Ready(bytes, format) => (
+            AppendHeaders([(header::CONTENT_TYPE, format.content_type())]),
+            bytes,
         )
             .into_response(),
         GetRenderResultOutcome::Pending => {
@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{AppendHeaders, IntoResponse},
+};
+
+use crate::app::use_cases::get_render_thumbnail_command::GetRenderThumbnailCommand;
+use crate::app::use_cases::get_render_thumbnail_command_handler::GetRenderThumbnailOutcome;
+use crate::di;
+use crate::infra::Dependencies;
+
+pub async fn get_render_thumbnail(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_get_render_thumbnail_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler.handle(GetRenderThumbnailCommand { job_id }).await {
+        Ok(GetRenderThumbnailOutcome::Ready(png)) => {
+            (AppendHeaders([(header::CONTENT_TYPE, "image/png")]), png).into_response()
+        }
+        Ok(GetRenderThumbnailOutcome::NotFound) => {
+            (StatusCode::NOT_FOUND, "Job not found".to_string()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
@@ -11,6 +11,6 @@ pub async fn get_variations(
     State(deps): State<Dependencies>,
 ) -> Json<GetAllVariationsCommandResult> {
     let handler: GetAllVariationsCommandHandler = di::get_get_all_variations_command_handler(&deps);
-    let result = handler.handle(GetAllVariationsCommand {});
+    let result = handler.handle(GetAllVariationsCommand {}).await;
     Json(result)
 }
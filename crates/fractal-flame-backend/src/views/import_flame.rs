@@ -0,0 +1,60 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::import_flame_command::ImportFlameCommand;
+use crate::app::use_cases::import_flame_command_handler::ImportFlameError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{client_id, quota_exceeded};
+
+#[derive(Debug, Deserialize)]
+pub struct ImportFlameQuery {
+    #[serde(default)]
+    pub index: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFlameResponse {
+    pub job_id: String,
+    pub name: Option<String>,
+}
+
+/// Renders a flame from an uploaded flam3/Apophysis `.flame` document (the raw XML body).
+pub async fn import_flame(
+    State(deps): State<Dependencies>,
+    Query(query): Query<ImportFlameQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_import_flame_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let command = ImportFlameCommand {
+        xml: body,
+        index: query.index,
+        client: client_id(&headers, &deps.config),
+    };
+
+    match handler.handle(command).await {
+        Ok(result) => (
+            StatusCode::ACCEPTED,
+            Json(ImportFlameResponse {
+                job_id: result.job_id,
+                name: result.name,
+            }),
+        )
+            .into_response(),
+        Err(ImportFlameError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::services::job_index_service::JobParams;
+use crate::app::use_cases::list_jobs_command::ListJobsCommand;
+use crate::app::use_cases::list_jobs_command_handler::MAX_PAGE_SIZE;
+use crate::di;
+use crate::infra::Dependencies;
+
+fn default_limit() -> usize {
+    20
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListJobsQuery {
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Earliest creation time, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub from: Option<u64>,
+    /// Latest creation time, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub to: Option<u64>,
+    #[serde(default)]
+    pub variation_id: Option<String>,
+    /// By creation time; newest first by default.
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobEntry {
+    pub job_id: String,
+    pub created_at: u64,
+    pub status: String,
+    pub params: JobParams,
+    pub timings: BTreeMap<String, u64>,
+    pub thumbnail_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListJobsResponse {
+    pub jobs: Vec<JobEntry>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Recent jobs, filtered and a page at a time.
+pub async fn list_jobs(
+    State(deps): State<Dependencies>,
+    Query(query): Query<ListJobsQuery>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_list_jobs_command_handler(&deps) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Redis not configured").into_response();
+    };
+
+    let command = ListJobsCommand {
+        status: query.status,
+        created_from: query.from,
+        created_to: query.to,
+        variation_id: query.variation_id,
+        oldest_first: matches!(query.order, SortOrder::Asc),
+        offset: query.offset,
+        limit: query.limit,
+    };
+    // What the handler pages by, not what was asked for.
+    let limit = command.limit.min(MAX_PAGE_SIZE);
+
+    match handler.handle(command).await {
+        Ok(page) => Json(ListJobsResponse {
+            jobs: page
+                .jobs
+                .into_iter()
+                .map(|job| JobEntry {
+                    thumbnail_url: format!("/api/render/{}/thumbnail", job.summary.job_id),
+                    job_id: job.summary.job_id,
+                    created_at: job.summary.created_at,
+                    status: job.status,
+                    params: job.summary.params,
+                    timings: job.timings,
+                })
+                .collect(),
+            total: page.total,
+            offset: query.offset,
+            limit,
+        })
+        .into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list jobs");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list jobs").into_response()
+        }
+    }
+}
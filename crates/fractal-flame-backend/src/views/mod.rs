@@ -1,7 +1,23 @@
+pub mod breed;
+pub mod create_custom_variation;
 pub mod get_intermediate_result;
+pub mod get_render_animation;
+pub mod get_render_flame;
+pub mod get_render_frame;
+pub mod get_render_genome;
+pub mod get_render_job;
 pub mod get_render_result;
+pub mod get_render_thumbnail;
 pub mod get_variation_preview;
 pub mod get_variations;
 pub mod health;
+pub mod import_flame;
+pub mod list_jobs;
+pub mod mutate_render;
+pub mod remix_render;
+pub mod render_animation;
+pub mod render_from_image;
 pub mod render_progress;
+pub mod render_socket;
 pub mod start_render;
+pub mod start_render_v2;
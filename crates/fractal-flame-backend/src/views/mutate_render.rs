@@ -0,0 +1,79 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use fractal_flame_core::app::evolution::Mutation;
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::mutate_render_command::MutateRenderCommand;
+use crate::app::use_cases::mutate_render_command_handler::MutateRenderError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{client_id, quota_exceeded};
+
+fn default_count() -> usize {
+    8
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MutateQuery {
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MutatedChildResponse {
+    pub job_id: String,
+    pub mutation: Mutation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MutateResponse {
+    pub parent_id: String,
+    pub children: Vec<MutatedChildResponse>,
+}
+
+pub async fn mutate_render(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+    Query(query): Query<MutateQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_mutate_render_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let command = MutateRenderCommand {
+        job_id: job_id.clone(),
+        count: query.count,
+        client: client_id(&headers, &deps.config),
+    };
+
+    match handler.handle(command).await {
+        Ok(children) => (
+            StatusCode::ACCEPTED,
+            Json(MutateResponse {
+                parent_id: job_id,
+                children: children
+                    .into_iter()
+                    .map(|c| MutatedChildResponse {
+                        job_id: c.job_id,
+                        mutation: c.mutation,
+                    })
+                    .collect(),
+            }),
+        )
+            .into_response(),
+        Err(e @ MutateRenderError::NotFound(_)) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(MutateRenderError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
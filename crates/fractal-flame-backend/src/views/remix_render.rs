@@ -0,0 +1,92 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::domain::Background;
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::remix_render_command::{RemixOverrides, RemixRenderCommand};
+use crate::app::use_cases::remix_render_command_handler::RemixRenderError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{client_id, quota_exceeded};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemixRequest {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+    pub iter_per_sample: Option<usize>,
+    pub gamma: Option<f64>,
+    pub brightness: Option<f64>,
+    pub background: Option<Background>,
+    pub symmetry: Option<usize>,
+    pub center_x: Option<f64>,
+    pub center_y: Option<f64>,
+    pub zoom: Option<f64>,
+    pub rotate: Option<f64>,
+    pub seed: Option<u64>,
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemixResponse {
+    pub job_id: String,
+    pub parent_id: String,
+}
+
+pub async fn remix_render(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<RemixRequest>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_remix_render_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let command = RemixRenderCommand {
+        job_id: job_id.clone(),
+        overrides: RemixOverrides {
+            width: body.width,
+            height: body.height,
+            samples: body.samples,
+            iter_per_sample: body.iter_per_sample,
+            gamma: body.gamma,
+            brightness: body.brightness,
+            background: body.background,
+            symmetry: body.symmetry,
+            center_x: body.center_x,
+            center_y: body.center_y,
+            zoom: body.zoom,
+            rotate: body.rotate,
+            seed: body.seed,
+            format: body.format,
+        },
+        client: client_id(&headers, &deps.config),
+    };
+
+    match handler.handle(command).await {
+        Ok(new_job_id) => (
+            StatusCode::ACCEPTED,
+            Json(RemixResponse {
+                job_id: new_job_id,
+                parent_id: job_id,
+            }),
+        )
+            .into_response(),
+        Err(e @ RemixRenderError::NotFound(_)) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(RemixRenderError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
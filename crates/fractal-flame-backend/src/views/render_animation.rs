@@ -0,0 +1,133 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use fractal_flame_core::app::image_export::{AnimationFormat, AnimationSettings};
+use fractal_flame_core::domain::{Keyframe, MotionBlur};
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::render_animation_command::{
+    AnimationOutput, AnimationSource, RenderAnimationCommand,
+};
+use crate::app::use_cases::render_animation_command_handler::RenderAnimationError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{client_id, quota_exceeded};
+
+fn default_formats() -> Vec<AnimationFormat> {
+    vec![AnimationFormat::Gif]
+}
+
+fn default_rotation_frames() -> usize {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderAnimationRequest {
+    pub keyframes: Vec<Keyframe>,
+    pub frames: usize,
+    #[serde(default)]
+    pub motion_blur: Option<MotionBlur>,
+    /// Animated files assembled once every frame is rendered; empty keeps only the frames.
+    #[serde(default = "default_formats")]
+    pub formats: Vec<AnimationFormat>,
+    #[serde(default)]
+    pub settings: AnimationSettings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderRotationRequest {
+    #[serde(default = "default_rotation_frames")]
+    pub frames: usize,
+    #[serde(default)]
+    pub motion_blur: Option<MotionBlur>,
+    #[serde(default = "default_formats")]
+    pub formats: Vec<AnimationFormat>,
+    #[serde(default)]
+    pub settings: AnimationSettings,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderAnimationResponse {
+    pub job_id: String,
+    pub frames: usize,
+}
+
+async fn start(
+    deps: &Dependencies,
+    headers: &HeaderMap,
+    source: AnimationSource,
+    frames: usize,
+    motion_blur: Option<MotionBlur>,
+    output: AnimationOutput,
+) -> Response {
+    let Some(handler) = di::get_render_animation_command_handler(deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler
+        .handle(RenderAnimationCommand {
+            source,
+            motion_blur,
+            output,
+            client: Some(client_id(headers, &deps.config)),
+        })
+        .await
+    {
+        Ok(job_id) => (
+            StatusCode::ACCEPTED,
+            Json(RenderAnimationResponse { job_id, frames }),
+        )
+            .into_response(),
+        Err(e @ RenderAnimationError::NotFound(_)) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(RenderAnimationError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// Renders `frames` frames interpolated between the keyframes as one job; frames are served
+/// from `/api/render/{job_id}/frames/{index}` and animated files from
+/// `/api/render/{job_id}/animation.{gif,png,webp}`.
+pub async fn render_animation(
+    State(deps): State<Dependencies>,
+    headers: HeaderMap,
+    Json(body): Json<RenderAnimationRequest>,
+) -> impl IntoResponse {
+    let frames = body.frames;
+    let source = AnimationSource::Keyframes {
+        keyframes: body.keyframes,
+        frames,
+    };
+    let output = AnimationOutput {
+        formats: body.formats,
+        settings: body.settings,
+    };
+    start(&deps, &headers, source, frames, body.motion_blur, output).await
+}
+
+/// Renders a loop of a finished job's genome with each transform's linear part turning once
+/// around that transform's origin, as in flam3's rotation loops.
+pub async fn render_rotation(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<RenderRotationRequest>,
+) -> impl IntoResponse {
+    let frames = body.frames;
+    let source = AnimationSource::RotationLoop { job_id, frames };
+    let output = AnimationOutput {
+        formats: body.formats,
+        settings: body.settings,
+    };
+    start(&deps, &headers, source, frames, body.motion_blur, output).await
+}
@@ -0,0 +1,40 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::app::use_cases::render_from_image_command::RenderFromImageCommand;
+use crate::app::use_cases::render_from_image_command_handler::RenderFromImageError;
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{StartRenderResponse, client_id, quota_exceeded};
+
+/// Starts a new job from the genome embedded in an uploaded PNG (the raw request body).
+pub async fn render_from_image(
+    State(deps): State<Dependencies>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_render_from_image_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    match handler
+        .handle(RenderFromImageCommand {
+            png: body.to_vec(),
+            client: client_id(&headers, &deps.config),
+        })
+        .await
+    {
+        Ok(job_id) => (StatusCode::ACCEPTED, Json(StartRenderResponse { job_id })).into_response(),
+        Err(RenderFromImageError::Quota(e)) => quota_exceeded(e),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
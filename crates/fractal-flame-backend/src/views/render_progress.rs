@@ -10,10 +10,12 @@ use axum::{
     },
 };
 use serde::Serialize;
-use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
 use tokio_stream::wrappers::ReceiverStream;
 
+use crate::app::services::job_state_service::{JobFailure, JobState};
 use crate::app::use_cases::render_progress_command::RenderProgressCommand;
+use crate::app::use_cases::render_progress_command_handler::JobProgress;
 use crate::di;
 use crate::infra::Dependencies;
 
@@ -23,6 +25,11 @@ struct ProgressPayload {
     progress: u64,
     total: u64,
     intermediate_version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<JobFailure>,
+    paused: bool,
 }
 
 pub async fn render_progress(
@@ -33,52 +40,27 @@ pub async fn render_progress(
         return (StatusCode::SERVICE_UNAVAILABLE, "Redis not configured").into_response();
     };
 
-    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(32);
-    let command = RenderProgressCommand { job_id };
-    let poll_interval = Duration::from_millis(deps.config.sse_poll_interval_ms);
-
-    tokio::spawn(async move {
-        loop {
-            let info = handler.get_progress(&command).await;
-
-            let payload = ProgressPayload {
-                status: info.status.clone(),
-                progress: info.progress,
-                total: info.total,
-                intermediate_version: info.intermediate_version,
-            };
-            let data = serde_json::to_string(&payload).unwrap_or_default();
-
-            match info.status.as_str() {
-                "completed" => {
-                    let _ = tx
-                        .send(Ok(Event::default().event("completed").data(data)))
-                        .await;
-                    break;
-                }
-                "failed" => {
-                    let _ = tx
-                        .send(Ok(Event::default().event("failed").data(data)))
-                        .await;
-                    break;
-                }
-                _ => {
-                    if tx
-                        .send(Ok(Event::default().event("progress").data(data)))
-                        .await
-                        .is_err()
-                    {
-                        break;
-                    }
-                }
-            }
-
-            tokio::time::sleep(poll_interval).await;
-        }
-    });
-
-    let stream: ReceiverStream<Result<Event, Infallible>> = ReceiverStream::new(rx);
+    let updates = handler.watch(RenderProgressCommand { job_id });
+    let stream = ReceiverStream::new(updates).map(|info| Ok::<_, Infallible>(event(info)));
     Sse::new(stream)
         .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
         .into_response()
 }
+
+/// A `progress` event, or one named after the state the job ended in.
+fn event(info: JobProgress) -> Event {
+    let name = JobState::parse(&info.status)
+        .filter(|state| state.is_terminal())
+        .map_or("progress", JobState::as_str);
+    let payload = ProgressPayload {
+        status: info.status,
+        progress: info.progress,
+        total: info.total,
+        intermediate_version: info.intermediate_version,
+        queue_position: info.queue_position,
+        error: info.error,
+        paused: info.paused,
+    };
+    let data = serde_json::to_string(&payload).unwrap_or_default();
+    Event::default().event(name).data(data)
+}
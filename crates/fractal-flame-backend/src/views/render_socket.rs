@@ -0,0 +1,177 @@
+use axum::{
+    extract::{
+        Path, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::app::services::job_state_service::{JobFailure, JobState};
+use crate::app::use_cases::control_render_job_command::{
+    ControlRenderJobCommand, JobControlAction,
+};
+use crate::app::use_cases::control_render_job_command_handler::ControlRenderJobCommandHandler;
+use crate::app::use_cases::get_intermediate_result_command::GetIntermediateResultCommand;
+use crate::app::use_cases::get_intermediate_result_command_handler::{
+    GetIntermediateResultCommandHandler, GetIntermediateResultOutcome,
+};
+use crate::app::use_cases::render_progress_command::RenderProgressCommand;
+use crate::app::use_cases::render_progress_command_handler::JobProgress;
+use crate::di;
+use crate::infra::Dependencies;
+
+/// Control messages, e.g. `{"type": "preview", "gamma": 3.0}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Pause,
+    Resume,
+    Cancel,
+    Preview {
+        #[serde(default)]
+        gamma: Option<f64>,
+        #[serde(default)]
+        brightness: Option<f64>,
+    },
+    Snapshot,
+}
+
+impl From<ClientMessage> for JobControlAction {
+    fn from(message: ClientMessage) -> Self {
+        match message {
+            ClientMessage::Pause => Self::Pause,
+            ClientMessage::Resume => Self::Resume,
+            ClientMessage::Cancel => Self::Cancel,
+            ClientMessage::Preview { gamma, brightness } => Self::Preview { gamma, brightness },
+            ClientMessage::Snapshot => Self::Snapshot,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StatusMessage {
+    /// `progress` while the job runs, then the state it ended in.
+    #[serde(rename = "type")]
+    kind: &'static str,
+    status: String,
+    progress: u64,
+    total: u64,
+    intermediate_version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<JobFailure>,
+    paused: bool,
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    message: String,
+}
+
+/// Streams a job's progress as JSON text messages and each new preview as a binary PNG
+/// message, and takes control messages to pause, resume or cancel it, change the preview's
+/// tone mapping, or ask for a preview straight away. Closes once the job has ended.
+pub async fn render_socket(
+    State(deps): State<Dependencies>,
+    Path(job_id): Path<String>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let (Some(progress), Some(control)) = (
+        di::get_render_progress_command_handler(&deps),
+        di::get_control_render_job_command_handler(&deps),
+    ) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Redis not configured").into_response();
+    };
+    let frames = di::get_get_intermediate_result_command_handler(&deps);
+    let updates = progress.watch(RenderProgressCommand {
+        job_id: job_id.clone(),
+    });
+
+    ws.on_upgrade(move |socket| stream_job(socket, job_id, updates, control, frames))
+        .into_response()
+}
+
+async fn stream_job(
+    mut socket: WebSocket,
+    job_id: String,
+    mut updates: mpsc::Receiver<JobProgress>,
+    control: ControlRenderJobCommandHandler,
+    frames: Option<GetIntermediateResultCommandHandler>,
+) {
+    let mut frame_version = 0;
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let rejected = match serde_json::from_str::<ClientMessage>(text.as_str()) {
+                        Ok(message) => control
+                            .handle(ControlRenderJobCommand {
+                                job_id: job_id.clone(),
+                                action: message.into(),
+                            })
+                            .await
+                            .err()
+                            .map(|e| e.to_string()),
+                        Err(e) => Some(format!("Invalid control message: {}", e)),
+                    };
+                    if let Some(message) = rejected {
+                        let reply = ErrorMessage { kind: "error", message };
+                        let text = serde_json::to_string(&reply).unwrap_or_default();
+                        if socket.send(Message::Text(text.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            update = updates.recv() => {
+                let Some(info) = update else {
+                    break;
+                };
+                let ended = JobState::parse(&info.status).filter(|state| state.is_terminal());
+                let message = StatusMessage {
+                    kind: ended.map_or("progress", JobState::as_str),
+                    status: info.status,
+                    progress: info.progress,
+                    total: info.total,
+                    intermediate_version: info.intermediate_version,
+                    queue_position: info.queue_position,
+                    error: info.error,
+                    paused: info.paused,
+                };
+                let text = serde_json::to_string(&message).unwrap_or_default();
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+                if ended.is_some() {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+
+                if let Some(ref frames) = frames
+                    && info.intermediate_version > frame_version
+                {
+                    frame_version = info.intermediate_version;
+                    let frame = frames
+                        .handle(GetIntermediateResultCommand {
+                            job_id: job_id.clone(),
+                        })
+                        .await;
+                    if let GetIntermediateResultOutcome::Ready(png) = frame
+                        && socket.send(Message::Binary(png.into())).await.is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
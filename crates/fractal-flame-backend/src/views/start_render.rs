@@ -1,17 +1,50 @@
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Response},
+};
+use fractal_flame_core::app::generation::StrategyConfig;
+use fractal_flame_core::app::image_export::{ExportFormat, validate_streamable};
 use serde::{Deserialize, Serialize};
 
-use crate::app::use_cases::run_render_job_command::RunRenderJobCommand;
+use crate::app::services::callback_service::JobCallback;
+use crate::app::services::client_quota_service::QuotaError;
+use crate::app::use_cases::run_render_job_command::{Priority, RenderSource, RunRenderJobCommand};
 use crate::di;
-use crate::infra::Dependencies;
+use crate::infra::{Config, Dependencies};
+
+/// Names the client a render is started for; quotas and fair scheduling are per key.
+const API_KEY_HEADER: &str = "x-api-key";
+/// Shared by every request without a configured API key.
+const ANONYMOUS_CLIENT: &str = "anonymous";
 
 #[derive(Debug, Deserialize)]
 pub struct StartRenderRequest {
+    #[serde(default)]
     pub variation_ids: Vec<String>,
+    /// How transforms are generated, e.g. `{"kind": "similarity"}`.
+    #[serde(default)]
+    pub strategy: Option<StrategyConfig>,
+    /// Template genome for `{"kind": "template"}` without inline transforms.
+    #[serde(default)]
+    pub template_job_id: Option<String>,
     pub symmetry: usize,
     pub gamma: f64,
     pub width: usize,
     pub height: usize,
+    /// Encoding of the result, e.g. `{"type": "jpeg", "quality": 90}`; PNG by default.
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// `interactive`, `normal` or `batch`; `normal` by default.
+    #[serde(default)]
+    pub priority: Priority,
+    /// Posted a JSON summary of the job once it completes or fails.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    /// Signs callbacks with HMAC-SHA256 in `X-Fractal-Signature`.
+    #[serde(default)]
+    pub callback_secret: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -19,8 +52,39 @@ pub struct StartRenderResponse {
     pub job_id: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct QuotaExceededResponse {
+    pub error: String,
+    pub retry_after_secs: u64,
+}
+
+/// The request's API key if it is one of `config.api_keys`, otherwise the anonymous client.
+pub fn client_id(headers: &HeaderMap, config: &Config) -> String {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .filter(|key| config.api_keys.contains(*key))
+        .unwrap_or(ANONYMOUS_CLIENT)
+        .to_string()
+}
+
+/// `429 Too Many Requests`, with the retry hint both in `Retry-After` and the body.
+pub fn quota_exceeded(e: QuotaError) -> Response {
+    let retry_after_secs = e.retry_after_secs();
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        AppendHeaders([(header::RETRY_AFTER, retry_after_secs.to_string())]),
+        Json(QuotaExceededResponse {
+            error: e.to_string(),
+            retry_after_secs,
+        }),
+    )
+        .into_response()
+}
+
 pub async fn start_render(
     State(deps): State<Dependencies>,
+    headers: HeaderMap,
     Json(body): Json<StartRenderRequest>,
 ) -> impl IntoResponse {
     let Some(handler) = di::get_run_render_job_command_handler(&deps) else {
@@ -31,7 +95,11 @@ pub async fn start_render(
             .into_response();
     };
 
-    if body.variation_ids.is_empty() {
+    let uses_variation_ids = body
+        .strategy
+        .as_ref()
+        .is_none_or(StrategyConfig::uses_variation_ids);
+    if uses_variation_ids && body.variation_ids.is_empty() {
         return (
             StatusCode::BAD_REQUEST,
             "Select at least one variation".to_string(),
@@ -39,15 +107,71 @@ pub async fn start_render(
             .into_response();
     }
 
+    if let Err(e) = body.format.validate() {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    if deps.config.renders_tiled(body.width, body.height)
+        && let Err(e) = validate_streamable(body.format)
+    {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    if let Some(Err(e)) = body.strategy.as_ref().map(StrategyConfig::validate) {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+
+    let callback = match body.callback_url {
+        Some(url) => match JobCallback::new(url, body.callback_secret, &deps.webhooks).await {
+            Ok(callback) => Some(callback),
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        },
+        None => None,
+    };
+
     let command = RunRenderJobCommand {
-        variation_ids: body.variation_ids.clone(),
-        symmetry: body.symmetry,
-        gamma: body.gamma,
-        width: body.width,
-        height: body.height,
+        source: RenderSource::Variations {
+            variation_ids: body.variation_ids,
+            strategy: body.strategy,
+            template_job_id: body.template_job_id,
+            symmetry: body.symmetry,
+            gamma: body.gamma,
+            width: body.width,
+            height: body.height,
+        },
+        lineage: None,
+        format: body.format,
+        client: Some(client_id(&headers, &deps.config)),
+        priority: body.priority,
+        callback,
     };
 
-    let job_id = handler.start(command);
+    match handler.submit(command).await {
+        Ok(job_id) => (StatusCode::ACCEPTED, Json(StartRenderResponse { job_id })).into_response(),
+        Err(e) => quota_exceeded(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
 
-    (StatusCode::ACCEPTED, Json(StartRenderResponse { job_id })).into_response()
+    use super::*;
+
+    #[test]
+    fn unknown_api_keys_are_anonymous() {
+        let config = Config {
+            api_keys: ["known".to_string()].into(),
+            ..Config::default()
+        };
+        let client = |key: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(key) = key {
+                headers.insert(API_KEY_HEADER, HeaderValue::from_static(key));
+            }
+            client_id(&headers, &config)
+        };
+        assert_eq!(client(Some("known")), "known");
+        assert_eq!(client(Some("made-up")), ANONYMOUS_CLIENT);
+        assert_eq!(client(Some("")), ANONYMOUS_CLIENT);
+        assert_eq!(client(None), ANONYMOUS_CLIENT);
+    }
 }
@@ -0,0 +1,150 @@
+use axum::{
+    Json,
+    extract::{State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use fractal_flame_core::app::image_export::ExportFormat;
+use fractal_flame_core::domain::{Background, FlameGenome, Palette};
+use serde::{Deserialize, Serialize};
+
+use crate::app::use_cases::run_render_job_command::Priority;
+use crate::app::use_cases::start_render_v2_command::{StartRenderV2Command, TransformSpec};
+use crate::app::use_cases::start_render_v2_command_handler::{
+    FieldError, StartRenderV2Error, StartRenderV2Outcome,
+};
+use crate::di;
+use crate::infra::Dependencies;
+use crate::views::start_render::{StartRenderResponse, client_id, quota_exceeded};
+
+fn one() -> f64 {
+    1.0
+}
+fn default_symmetry() -> usize {
+    1
+}
+fn default_gamma() -> f64 {
+    2.2
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StartRenderV2Request {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub transforms: Vec<TransformSpec>,
+    #[serde(default)]
+    pub final_transform: Option<TransformSpec>,
+    #[serde(default = "default_symmetry")]
+    pub symmetry: usize,
+    #[serde(default = "default_gamma")]
+    pub gamma: f64,
+    #[serde(default = "one")]
+    pub brightness: f64,
+    #[serde(default)]
+    pub background: Background,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub samples: Option<usize>,
+    #[serde(default)]
+    pub iter_per_sample: Option<usize>,
+    #[serde(default)]
+    pub center_x: f64,
+    #[serde(default)]
+    pub center_y: f64,
+    #[serde(default = "one")]
+    pub zoom: f64,
+    #[serde(default)]
+    pub rotate: f64,
+    #[serde(default)]
+    pub palette: Option<Palette>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Encoding of the result, e.g. `{"type": "jpeg", "quality": 90}`; PNG by default.
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// `interactive`, `normal` or `batch`; `normal` by default.
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationErrorResponse {
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DryRunResponse {
+    pub genome: FlameGenome,
+}
+
+fn validation_error(errors: Vec<FieldError>) -> axum::response::Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ValidationErrorResponse { errors }),
+    )
+        .into_response()
+}
+
+/// Starts a render from fully specified transforms, or with `dry_run` returns the resolved genome.
+pub async fn start_render_v2(
+    State(deps): State<Dependencies>,
+    headers: HeaderMap,
+    body: Result<Json<StartRenderV2Request>, JsonRejection>,
+) -> impl IntoResponse {
+    let Some(handler) = di::get_start_render_v2_command_handler(&deps) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "MinIO not configured".to_string(),
+        )
+            .into_response();
+    };
+
+    let body = match body {
+        Ok(Json(body)) => body,
+        Err(rejection) => {
+            return validation_error(vec![FieldError {
+                field: "body".to_string(),
+                message: rejection.body_text(),
+            }]);
+        }
+    };
+
+    let command = StartRenderV2Command {
+        name: body.name,
+        transforms: body.transforms,
+        final_transform: body.final_transform,
+        symmetry: body.symmetry,
+        gamma: body.gamma,
+        brightness: body.brightness,
+        background: body.background,
+        width: body.width,
+        height: body.height,
+        samples: body.samples,
+        iter_per_sample: body.iter_per_sample,
+        center_x: body.center_x,
+        center_y: body.center_y,
+        zoom: body.zoom,
+        rotate: body.rotate,
+        palette: body.palette,
+        seed: body.seed,
+        format: body.format,
+        client: client_id(&headers, &deps.config),
+        priority: body.priority,
+        dry_run: body.dry_run,
+    };
+
+    match handler.handle(command).await {
+        Ok(StartRenderV2Outcome::Started(job_id)) => {
+            (StatusCode::ACCEPTED, Json(StartRenderResponse { job_id })).into_response()
+        }
+        Ok(StartRenderV2Outcome::DryRun(genome)) => {
+            (StatusCode::OK, Json(DryRunResponse { genome: *genome })).into_response()
+        }
+        Err(StartRenderV2Error::Validation(errors)) => validation_error(errors),
+        Err(StartRenderV2Error::Quota(e)) => quota_exceeded(e),
+    }
+}
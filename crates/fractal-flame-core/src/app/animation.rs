@@ -0,0 +1,535 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::app::evolution::crossover::{blend_color, blend_parameters, blend_variations, lerp};
+use crate::app::genome::GenomeError;
+use crate::domain::{
+    Affine, Camera, FlameGenome, GenomeTransform, Keyframe, MotionBlur, Palette, Quality,
+    TemporalFilter, TemporalSample, ToneMapping,
+};
+
+/// The linear part of an affine as a rotation applied after scale and shear:
+/// `[[a, b], [d, e]] = R(angle) * [[scale_x, shear], [0, scale_y]]`.
+struct Decomposed {
+    angle: f64,
+    scale_x: f64,
+    scale_y: f64,
+    shear: f64,
+}
+
+fn decompose(m: &Affine) -> Decomposed {
+    let angle = m.d.atan2(m.a);
+    let (sin, cos) = angle.sin_cos();
+    Decomposed {
+        angle,
+        scale_x: m.a.hypot(m.d),
+        scale_y: cos * m.e - sin * m.b,
+        shear: cos * m.b + sin * m.e,
+    }
+}
+
+/// Interpolates rotation along the shorter arc and scale, shear and translation linearly, so a
+/// rotating transform keeps its size instead of shrinking through the midpoint.
+fn interpolate_affine(a: &Affine, b: &Affine, t: f64) -> Affine {
+    let (da, db) = (decompose(a), decompose(b));
+    let mut turn = (db.angle - da.angle) % TAU;
+    if turn > PI {
+        turn -= TAU;
+    } else if turn < -PI {
+        turn += TAU;
+    }
+    let angle = da.angle + turn * t;
+    let scale_x = lerp(da.scale_x, db.scale_x, t);
+    let scale_y = lerp(da.scale_y, db.scale_y, t);
+    let shear = lerp(da.shear, db.shear, t);
+    let (sin, cos) = angle.sin_cos();
+    Affine::new(
+        cos * scale_x,
+        cos * shear - sin * scale_y,
+        lerp(a.c, b.c, t),
+        sin * scale_x,
+        sin * shear + cos * scale_y,
+        lerp(a.f, b.f, t),
+    )
+}
+
+fn interpolate_transform(a: &GenomeTransform, b: &GenomeTransform, t: f64) -> GenomeTransform {
+    let post = match (a.post, b.post) {
+        (None, None) => None,
+        (pa, pb) => Some(interpolate_affine(
+            &pa.unwrap_or(Affine::IDENTITY),
+            &pb.unwrap_or(Affine::IDENTITY),
+            t,
+        )),
+    };
+    let color_index = match (a.color_index, b.color_index) {
+        (Some(x), Some(y)) => Some(lerp(x, y, t)),
+        (x, y) => x.or(y),
+    };
+    GenomeTransform {
+        affine: interpolate_affine(&a.affine, &b.affine, t),
+        post,
+        weight: lerp(a.weight, b.weight, t),
+        color: blend_color(a.color, b.color, t),
+        color_index,
+        variations: blend_variations(&a.variations, &b.variations, t),
+        parameters: blend_parameters(&a.parameters, &b.parameters, t),
+    }
+}
+
+/// The same transform with zero weight, so transforms missing from one keyframe fade in or out.
+fn faded(transform: &GenomeTransform) -> GenomeTransform {
+    GenomeTransform {
+        weight: 0.0,
+        ..transform.clone()
+    }
+}
+
+fn interpolate_palette(a: Option<&Palette>, b: Option<&Palette>, t: f64) -> Option<Palette> {
+    match (a, b) {
+        (Some(a), Some(b)) if a.colors.len() == b.colors.len() => Some(Palette {
+            colors: a
+                .colors
+                .iter()
+                .zip(&b.colors)
+                .map(|(x, y)| blend_color(*x, *y, t))
+                .collect(),
+        }),
+        _ if t < 0.5 => a.cloned(),
+        _ => b.cloned(),
+    }
+}
+
+/// Genome `t` of the way from `a` to `b`. Discrete settings switch at the midpoint.
+fn interpolate_genomes(a: &FlameGenome, b: &FlameGenome, t: f64) -> FlameGenome {
+    let transforms = (0..a.transforms.len().max(b.transforms.len()))
+        .map(|i| match (a.transforms.get(i), b.transforms.get(i)) {
+            (Some(x), Some(y)) => interpolate_transform(x, y, t),
+            (Some(x), None) => interpolate_transform(x, &faded(x), t),
+            (None, Some(y)) => interpolate_transform(&faded(y), y, t),
+            (None, None) => unreachable!("index is below the longer length"),
+        })
+        .collect();
+    let final_transform = match (&a.final_transform, &b.final_transform) {
+        (Some(x), Some(y)) => Some(interpolate_transform(x, y, t)),
+        (x, y) => if t < 0.5 { x } else { y }.clone(),
+    };
+
+    let mut custom_variations = a.custom_variations.clone();
+    for spec in &b.custom_variations {
+        if !custom_variations.iter().any(|c| c.id == spec.id) {
+            custom_variations.push(spec.clone());
+        }
+    }
+
+    FlameGenome {
+        name: a.name.clone(),
+        transforms,
+        final_transform,
+        custom_variations,
+        symmetry: if t < 0.5 { a.symmetry } else { b.symmetry },
+        camera: Camera {
+            width: a.camera.width,
+            height: a.camera.height,
+            center_x: lerp(a.camera.center_x, b.camera.center_x, t),
+            center_y: lerp(a.camera.center_y, b.camera.center_y, t),
+            // Geometric, so zooming feels uniform.
+            zoom: a.camera.zoom * (b.camera.zoom / a.camera.zoom).powf(t),
+            rotate: lerp(a.camera.rotate, b.camera.rotate, t),
+        },
+        palette: interpolate_palette(a.palette.as_ref(), b.palette.as_ref(), t),
+        tone_mapping: ToneMapping {
+            gamma: lerp(a.tone_mapping.gamma, b.tone_mapping.gamma, t),
+            brightness: lerp(a.tone_mapping.brightness, b.tone_mapping.brightness, t),
+            background: if t < 0.5 {
+                a.tone_mapping.background
+            } else {
+                b.tone_mapping.background
+            },
+        },
+        quality: Quality {
+            samples: lerp(a.quality.samples as f64, b.quality.samples as f64, t).round() as usize,
+            iter_per_sample: a.quality.iter_per_sample,
+        },
+        seed: a.seed,
+    }
+}
+
+/// Checks that there is at least one keyframe, times strictly increase and every genome is
+/// valid.
+pub fn validate_keyframes(keyframes: &[Keyframe]) -> Result<(), AnimationError> {
+    if keyframes.is_empty() {
+        return Err(AnimationError::NoKeyframes);
+    }
+    for (i, keyframe) in keyframes.iter().enumerate() {
+        if !keyframe.time.is_finite() || i > 0 && keyframe.time <= keyframes[i - 1].time {
+            return Err(AnimationError::UnorderedTimes { index: i });
+        }
+        keyframe
+            .genome
+            .validate()
+            .map_err(|source| AnimationError::Genome { index: i, source })?;
+    }
+    Ok(())
+}
+
+/// The genome at `time`, clamped to the first and last keyframes. Every frame has the first
+/// keyframe's size and seed, so frames line up and noise does not flicker between them.
+pub fn interpolate(keyframes: &[Keyframe], time: f64) -> Result<FlameGenome, AnimationError> {
+    validate_keyframes(keyframes)?;
+    Ok(interpolate_valid(keyframes, time))
+}
+
+/// [`interpolate`] for keyframes that have already passed [`validate_keyframes`].
+fn interpolate_valid(keyframes: &[Keyframe], time: f64) -> FlameGenome {
+    let first = &keyframes[0];
+    let segment = keyframes
+        .windows(2)
+        .find(|w| time < w[1].time)
+        .map(|w| (&w[0], &w[1]));
+    let mut genome = match segment {
+        Some((a, _)) if time <= a.time => a.genome.clone(),
+        Some((a, b)) => {
+            interpolate_genomes(&a.genome, &b.genome, (time - a.time) / (b.time - a.time))
+        }
+        None => keyframes[keyframes.len() - 1].genome.clone(),
+    };
+    genome.camera.width = first.genome.camera.width;
+    genome.camera.height = first.genome.camera.height;
+    genome.seed = first.genome.seed;
+    genome
+}
+
+/// `frames` genomes evenly spaced from the first keyframe to the last, both included.
+pub fn frame_genomes(
+    keyframes: &[Keyframe],
+    frames: usize,
+) -> Result<Vec<FlameGenome>, AnimationError> {
+    if frames == 0 {
+        return Err(AnimationError::NoFrames);
+    }
+    validate_keyframes(keyframes)?;
+    let start = keyframes[0].time;
+    let end = keyframes[keyframes.len() - 1].time;
+    Ok((0..frames)
+        .map(|i| {
+            let t = if frames == 1 {
+                0.0
+            } else {
+                i as f64 / (frames - 1) as f64
+            };
+            interpolate_valid(keyframes, lerp(start, end, t))
+        })
+        .collect())
+}
+
+fn rotate_linear(m: &Affine, angle: f64) -> Affine {
+    let (sin, cos) = angle.sin_cos();
+    Affine::new(
+        cos * m.a - sin * m.d,
+        cos * m.b - sin * m.e,
+        m.c,
+        sin * m.a + cos * m.d,
+        sin * m.b + cos * m.e,
+        m.f,
+    )
+}
+
+fn rotated(genome: &FlameGenome, angle: f64) -> FlameGenome {
+    let mut frame = genome.clone();
+    for t in frame.transforms.iter_mut() {
+        t.affine = rotate_linear(&t.affine, angle);
+    }
+    frame
+}
+
+fn validate_rotation(genome: &FlameGenome, frames: usize) -> Result<(), AnimationError> {
+    if frames == 0 {
+        return Err(AnimationError::NoFrames);
+    }
+    genome
+        .validate()
+        .map_err(|source| AnimationError::Genome { index: 0, source })
+}
+
+/// `frames` genomes spinning each transform's linear part once around its own origin, as
+/// flam3's rotation loops do. The 360° frame is left out so the sequence repeats seamlessly.
+pub fn rotation_loop(
+    genome: &FlameGenome,
+    frames: usize,
+) -> Result<Vec<FlameGenome>, AnimationError> {
+    validate_rotation(genome, frames)?;
+    Ok((0..frames)
+        .map(|i| rotated(genome, TAU * i as f64 / frames as f64))
+        .collect())
+}
+
+/// Where each sub-frame sits relative to its frame, in frames, and its weight. Sub-frames are
+/// centered in equal slices of the shutter and the weights sum to one.
+pub fn temporal_offsets(blur: &MotionBlur) -> Result<Vec<(f64, f64)>, AnimationError> {
+    if blur.temporal_samples == 0 {
+        return Err(AnimationError::MotionBlur(
+            "temporal_samples must be at least 1".to_string(),
+        ));
+    }
+    if !(blur.shutter.is_finite() && blur.shutter >= 0.0) {
+        return Err(AnimationError::MotionBlur(
+            "shutter must be a non-negative number".to_string(),
+        ));
+    }
+    if let TemporalFilter::Exponential { exponent } = blur.filter
+        && !exponent.is_finite()
+    {
+        return Err(AnimationError::MotionBlur(
+            "exponent must be finite".to_string(),
+        ));
+    }
+
+    let n = blur.temporal_samples;
+    // Exponential weights are taken relative to the heaviest sub-frame, which weighs exactly
+    // one, so large exponents cannot underflow every weight to zero.
+    let last = (n as f64 - 0.5) / n as f64;
+    let offsets: Vec<(f64, f64)> = (0..n)
+        .map(|j| {
+            // Position within the open shutter, strictly between 0 and 1.
+            let u = (j as f64 + 0.5) / n as f64;
+            let weight = match blur.filter {
+                TemporalFilter::Box => 1.0,
+                // Falls to about 14% at the edges of the shutter.
+                TemporalFilter::Gaussian => (-2.0 * (2.0 * u - 1.0).powi(2)).exp(),
+                TemporalFilter::Exponential { exponent } if exponent >= 0.0 => {
+                    (u / last).powf(exponent)
+                }
+                TemporalFilter::Exponential { exponent } => ((1.0 - u) / last).powf(-exponent),
+            };
+            (blur.shutter * (u - 0.5), weight)
+        })
+        .collect();
+    let total: f64 = offsets.iter().map(|(_, w)| w).sum();
+    if !(total.is_finite() && total > 0.0) {
+        return Err(AnimationError::MotionBlur(
+            "filter weights cannot be normalized".to_string(),
+        ));
+    }
+    Ok(offsets
+        .into_iter()
+        .map(|(offset, weight)| (offset, weight / total))
+        .collect())
+}
+
+/// Like [`frame_genomes`], but each frame is the sub-frame genomes its shutter interval sees.
+/// Sub-frames before the first keyframe or after the last are clamped to it.
+pub fn blurred_frames(
+    keyframes: &[Keyframe],
+    frames: usize,
+    blur: &MotionBlur,
+) -> Result<Vec<Vec<TemporalSample>>, AnimationError> {
+    if frames == 0 {
+        return Err(AnimationError::NoFrames);
+    }
+    validate_keyframes(keyframes)?;
+    let offsets = temporal_offsets(blur)?;
+    let start = keyframes[0].time;
+    let end = keyframes[keyframes.len() - 1].time;
+    let step = if frames == 1 {
+        0.0
+    } else {
+        (end - start) / (frames - 1) as f64
+    };
+    Ok((0..frames)
+        .map(|i| {
+            offsets
+                .iter()
+                .map(|&(offset, weight)| TemporalSample {
+                    genome: interpolate_valid(keyframes, start + step * (i as f64 + offset)),
+                    weight,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Like [`rotation_loop`], but each frame is the sub-frame genomes its shutter interval sees.
+pub fn blurred_rotation_loop(
+    genome: &FlameGenome,
+    frames: usize,
+    blur: &MotionBlur,
+) -> Result<Vec<Vec<TemporalSample>>, AnimationError> {
+    validate_rotation(genome, frames)?;
+    let offsets = temporal_offsets(blur)?;
+    Ok((0..frames)
+        .map(|i| {
+            offsets
+                .iter()
+                .map(|&(offset, weight)| TemporalSample {
+                    genome: rotated(genome, TAU * (i as f64 + offset) / frames as f64),
+                    weight,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnimationError {
+    #[error("Animation has no keyframes")]
+    NoKeyframes,
+    #[error("Animation must have at least one frame")]
+    NoFrames,
+    #[error("Keyframe {index} does not come strictly after the previous one")]
+    UnorderedTimes { index: usize },
+    #[error("Keyframe {index} is invalid: {source}")]
+    Genome { index: usize, source: GenomeError },
+    #[error("Invalid motion blur: {0}")]
+    MotionBlur(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::renderer::Renderer;
+    use crate::domain::{Color, GenomeVariation};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn keyframe(time: f64, affine: Affine, variation: &str, color: Color) -> Keyframe {
+        Keyframe {
+            time,
+            genome: FlameGenome {
+                name: None,
+                transforms: vec![GenomeTransform {
+                    affine,
+                    post: None,
+                    weight: 1.0,
+                    color,
+                    color_index: None,
+                    variations: vec![GenomeVariation {
+                        id: variation.to_string(),
+                        weight: 1.0,
+                    }],
+                    parameters: BTreeMap::new(),
+                }],
+                final_transform: None,
+                custom_variations: Vec::new(),
+                symmetry: 1,
+                camera: Camera {
+                    width: 16,
+                    height: 16,
+                    center_x: 0.0,
+                    center_y: 0.0,
+                    zoom: 1.0,
+                    rotate: 0.0,
+                },
+                palette: None,
+                tone_mapping: ToneMapping::default(),
+                quality: Quality::default(),
+                seed: Some(time as u64),
+            },
+        }
+    }
+
+    #[test]
+    fn interpolates_rotation_without_shrinking() {
+        let keyframes = [
+            keyframe(
+                0.0,
+                Affine::new(0.5, 0.0, 0.0, 0.0, 0.5, 0.0),
+                "linear",
+                Color { r: 0, g: 0, b: 0 },
+            ),
+            // A quarter turn of the same scale.
+            keyframe(
+                2.0,
+                Affine::new(0.0, -0.5, 1.0, 0.5, 0.0, 0.0),
+                "swirl",
+                Color {
+                    r: 200,
+                    g: 100,
+                    b: 0,
+                },
+            ),
+        ];
+        let mid = interpolate(&keyframes, 1.0).unwrap();
+        let t = &mid.transforms[0];
+        let angle = t.affine.d.atan2(t.affine.a);
+        assert!((angle - PI / 4.0).abs() < 1e-9);
+        assert!((t.affine.a.hypot(t.affine.d) - 0.5).abs() < 1e-9);
+        assert!((t.affine.c - 0.5).abs() < 1e-9);
+        assert_eq!(
+            t.color,
+            Color {
+                r: 100,
+                g: 50,
+                b: 0
+            }
+        );
+        let weights: Vec<f64> = t.variations.iter().map(|v| v.weight).collect();
+        assert_eq!(weights, [0.5, 0.5]);
+        assert_eq!(mid.seed, Some(0));
+
+        let frames = frame_genomes(&keyframes, 3).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].transforms, keyframes[0].genome.transforms);
+        assert_eq!(frames[2].transforms, keyframes[1].genome.transforms);
+
+        let spin = rotation_loop(&keyframes[0].genome, 4).unwrap();
+        assert_eq!(spin.len(), 4);
+        assert_eq!(spin[0], keyframes[0].genome);
+        let half = spin[2].transforms[0].affine;
+        assert!((half.a + 0.5).abs() < 1e-9 && (half.e + 0.5).abs() < 1e-9);
+
+        let blurred = blurred_frames(&keyframes, 3, &MotionBlur::default()).unwrap();
+        assert_eq!(blurred.len(), 3);
+        assert_eq!(blurred[1].len(), 8);
+        let total: f64 = blurred[1].iter().map(|s| s.weight).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        // The middle frame's shutter spans half a second either side of t = 1.
+        let first = &blurred[1][0].genome.transforms[0].affine;
+        let first_angle = first.d.atan2(first.a);
+        assert!((first_angle - PI / 4.0 * (1.0 - 0.5 + 1.0 / 16.0)).abs() < 1e-9);
+
+        let mut center = blurred[1][4].genome.clone();
+        center.quality = Quality {
+            samples: 50,
+            iter_per_sample: 10,
+        };
+        let mut renderer = Renderer::from_genome(&center, 2).unwrap();
+        let progress = Arc::new(AtomicUsize::new(0));
+        renderer.progress = Some(progress.clone());
+        renderer.render_temporal(&blurred[1]).unwrap();
+        assert_eq!(progress.load(Ordering::Relaxed), 50);
+        assert_eq!(renderer.samples, 50);
+
+        let trailing = MotionBlur {
+            temporal_samples: 4,
+            shutter: 0.5,
+            filter: TemporalFilter::Exponential { exponent: 2.0 },
+        };
+        let offsets = temporal_offsets(&trailing).unwrap();
+        assert!(offsets.windows(2).all(|w| w[0].1 < w[1].1));
+        assert!((offsets[3].0 - 0.1875).abs() < 1e-9);
+        for exponent in [1e6, -1e6, f64::MAX, -f64::MAX] {
+            let offsets = temporal_offsets(&MotionBlur {
+                filter: TemporalFilter::Exponential { exponent },
+                ..trailing
+            })
+            .unwrap();
+            let total: f64 = offsets.iter().map(|(_, w)| w).sum();
+            assert!(offsets.iter().all(|(_, w)| w.is_finite()));
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+        assert!(matches!(
+            temporal_offsets(&MotionBlur {
+                temporal_samples: 0,
+                ..MotionBlur::default()
+            }),
+            Err(AnimationError::MotionBlur(_))
+        ));
+
+        let unordered = [keyframes[1].clone(), keyframes[0].clone()];
+        assert!(matches!(
+            frame_genomes(&unordered, 2),
+            Err(AnimationError::UnorderedTimes { index: 1 })
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use rand::seq::IndexedRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::EvolutionError;
+use crate::domain::{Affine, Color, FlameGenome, GenomeTransform, GenomeVariation};
+
+/// How two parent genomes are combined into a child.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossover {
+    /// Each transform slot is taken whole from one parent or the other.
+    Interleave,
+    /// Matching transforms are linearly blended by a random factor.
+    Blend,
+}
+
+impl Crossover {
+    pub const ALL: [Crossover; 2] = [Self::Interleave, Self::Blend];
+
+    /// The snake_case name used in JSON.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Interleave => "interleave",
+            Self::Blend => "blend",
+        }
+    }
+
+    /// A child of `first` and `second`. Camera, palette, tone mapping, quality and symmetry
+    /// come from `first`; custom variations from both. The child has no seed.
+    pub fn apply(
+        &self,
+        first: &FlameGenome,
+        second: &FlameGenome,
+        rng: &mut dyn RngCore,
+    ) -> Result<FlameGenome, EvolutionError> {
+        let transforms = match self {
+            Self::Interleave => interleave(&first.transforms, &second.transforms, rng),
+            Self::Blend => blend_all(
+                &first.transforms,
+                &second.transforms,
+                rng.random_range(0.25..=0.75),
+            ),
+        };
+        let final_transform = if rng.random_bool(0.5) {
+            first.final_transform.clone()
+        } else {
+            second.final_transform.clone()
+        };
+
+        let mut custom_variations = first.custom_variations.clone();
+        for spec in &second.custom_variations {
+            if !custom_variations.iter().any(|c| c.id == spec.id) {
+                custom_variations.push(spec.clone());
+            }
+        }
+
+        let child = FlameGenome {
+            name: None,
+            transforms,
+            final_transform,
+            custom_variations,
+            seed: None,
+            ..first.clone()
+        };
+        child.validate()?;
+        Ok(child)
+    }
+}
+
+/// Combines two parents with a crossover chosen uniformly at random.
+pub fn crossover(
+    first: &FlameGenome,
+    second: &FlameGenome,
+    rng: &mut dyn RngCore,
+) -> Result<(Crossover, FlameGenome), EvolutionError> {
+    let method = *Crossover::ALL.choose(rng).expect("ALL is not empty");
+    method
+        .apply(first, second, rng)
+        .map(|child| (method, child))
+}
+
+/// Slot by slot: one parent's transform when both have one, otherwise the longer parent's
+/// transform with even odds. The first slot is always filled.
+fn interleave(
+    first: &[GenomeTransform],
+    second: &[GenomeTransform],
+    rng: &mut dyn RngCore,
+) -> Vec<GenomeTransform> {
+    (0..first.len().max(second.len()))
+        .filter_map(|i| match (first.get(i), second.get(i)) {
+            (Some(a), Some(b)) => Some(if rng.random_bool(0.5) { a } else { b }),
+            (Some(t), None) | (None, Some(t)) => rng.random_bool(0.5).then_some(t),
+            (None, None) => None,
+        })
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn blend_affine(a: &Affine, b: &Affine, t: f64) -> Affine {
+    Affine::new(
+        lerp(a.a, b.a, t),
+        lerp(a.b, b.b, t),
+        lerp(a.c, b.c, t),
+        lerp(a.d, b.d, t),
+        lerp(a.e, b.e, t),
+        lerp(a.f, b.f, t),
+    )
+}
+
+pub(crate) fn blend_color(a: Color, b: Color, t: f64) -> Color {
+    let channel = |x: u8, y: u8| lerp(x as f64, y as f64, t).round() as u8;
+    Color {
+        r: channel(a.r, b.r),
+        g: channel(a.g, b.g),
+        b: channel(a.b, b.b),
+    }
+}
+
+/// Variations present in either transform, weighted as if missing ones had weight 0.
+pub(crate) fn blend_variations(
+    a: &[GenomeVariation],
+    b: &[GenomeVariation],
+    t: f64,
+) -> Vec<GenomeVariation> {
+    let weight_in = |vs: &[GenomeVariation], id: &str| {
+        vs.iter()
+            .filter(|v| v.id == id)
+            .map(|v| v.weight)
+            .sum::<f64>()
+    };
+    let mut result: Vec<GenomeVariation> = Vec::new();
+    for v in a.iter().chain(b) {
+        if result.iter().any(|r| r.id == v.id) {
+            continue;
+        }
+        result.push(GenomeVariation {
+            id: v.id.clone(),
+            weight: lerp(weight_in(a, &v.id), weight_in(b, &v.id), t),
+        });
+    }
+    result
+}
+
+/// Parameters of either map; ones present in both are interpolated.
+pub(crate) fn blend_parameters(
+    a: &BTreeMap<String, f64>,
+    b: &BTreeMap<String, f64>,
+    t: f64,
+) -> BTreeMap<String, f64> {
+    let mut parameters = b.clone();
+    for (k, v) in a {
+        let blended = b.get(k).map_or(*v, |w| lerp(*v, *w, t));
+        parameters.insert(k.clone(), blended);
+    }
+    parameters
+}
+
+fn blend(a: &GenomeTransform, b: &GenomeTransform, t: f64) -> GenomeTransform {
+    let post = match (a.post, b.post) {
+        (None, None) => None,
+        (pa, pb) => Some(blend_affine(
+            &pa.unwrap_or(Affine::IDENTITY),
+            &pb.unwrap_or(Affine::IDENTITY),
+            t,
+        )),
+    };
+    let color_index = match (a.color_index, b.color_index) {
+        (Some(x), Some(y)) => Some(lerp(x, y, t)),
+        (x, y) => x.or(y),
+    };
+    GenomeTransform {
+        affine: blend_affine(&a.affine, &b.affine, t),
+        post,
+        weight: lerp(a.weight, b.weight, t),
+        color: blend_color(a.color, b.color, t),
+        color_index,
+        variations: blend_variations(&a.variations, &b.variations, t),
+        parameters: blend_parameters(&a.parameters, &b.parameters, t),
+    }
+}
+
+/// Blends transforms pairwise by index; the longer parent's extra transforms are kept as-is.
+fn blend_all(
+    first: &[GenomeTransform],
+    second: &[GenomeTransform],
+    t: f64,
+) -> Vec<GenomeTransform> {
+    let longer = if first.len() >= second.len() {
+        first
+    } else {
+        second
+    };
+    first
+        .iter()
+        .zip(second)
+        .map(|(a, b)| blend(a, b, t))
+        .chain(longer.iter().skip(first.len().min(second.len())).cloned())
+        .collect()
+}
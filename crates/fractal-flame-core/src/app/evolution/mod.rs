@@ -0,0 +1,112 @@
+pub mod crossover;
+pub mod mutation;
+
+pub use crossover::{Crossover, crossover};
+pub use mutation::{Mutation, mutate};
+
+use crate::app::generation::GenerationError;
+use crate::app::genome::GenomeError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvolutionError {
+    #[error("Mutation {0:?} does not apply to this genome")]
+    NotApplicable(Mutation),
+    #[error(transparent)]
+    Generation(#[from] GenerationError),
+    #[error("Child genome is invalid: {0}")]
+    Genome(#[from] GenomeError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::generation::{StrategyConfig, generation_rng};
+    use crate::domain::{Camera, FlameGenome, Quality, ToneMapping};
+
+    fn genome(ids: &[&str], seed: u64) -> FlameGenome {
+        let ids: Vec<String> = ids.iter().map(|s| s.to_string()).collect();
+        FlameGenome {
+            name: None,
+            transforms: StrategyConfig::default()
+                .strategy()
+                .generate(&ids, &mut generation_rng(seed))
+                .unwrap(),
+            final_transform: None,
+            custom_variations: Vec::new(),
+            symmetry: 1,
+            camera: Camera {
+                width: 16,
+                height: 16,
+                center_x: 0.0,
+                center_y: 0.0,
+                zoom: 1.0,
+                rotate: 0.0,
+            },
+            palette: None,
+            tone_mapping: ToneMapping::default(),
+            quality: Quality::default(),
+            seed: Some(seed),
+        }
+    }
+
+    #[test]
+    fn mutations_change_the_genome() {
+        let parent = genome(&["linear", "swirl"], 1);
+        let pool = vec!["linear".to_string(), "polar".to_string()];
+        let mut rng = generation_rng(3);
+        for mutation in Mutation::ALL {
+            let child = mutation.apply(&parent, &pool, &mut rng).unwrap();
+            assert_ne!(child.transforms, parent.transforms, "{:?}", mutation);
+            assert_eq!(child.seed, None);
+        }
+        assert_eq!(
+            Mutation::AddTransform
+                .apply(&parent, &pool, &mut rng)
+                .unwrap()
+                .transforms
+                .len(),
+            3
+        );
+
+        let single = genome(&["linear"], 2);
+        assert!(matches!(
+            Mutation::RemoveTransform.apply(&single, &pool, &mut rng),
+            Err(EvolutionError::NotApplicable(Mutation::RemoveTransform))
+        ));
+        for _ in 0..20 {
+            let (mutation, _) = mutate(&single, &[], &mut rng).unwrap();
+            assert!(matches!(
+                mutation,
+                Mutation::PerturbAffines | Mutation::RandomizeColors
+            ));
+        }
+    }
+
+    #[test]
+    fn crossover_combines_parents() {
+        let first = genome(&["linear", "swirl", "polar"], 1);
+        let second = genome(&["spherical"], 2);
+        let mut rng = generation_rng(5);
+
+        let child = Crossover::Interleave
+            .apply(&first, &second, &mut rng)
+            .unwrap();
+        assert!(!child.transforms.is_empty() && child.transforms.len() <= 3);
+        for (i, t) in child.transforms.iter().enumerate().skip(1) {
+            assert!(first.transforms[1..].contains(t), "slot {}", i);
+        }
+
+        let blended = Crossover::Blend.apply(&first, &second, &mut rng).unwrap();
+        assert_eq!(blended.transforms.len(), 3);
+        let ids: Vec<&str> = blended.transforms[0]
+            .variations
+            .iter()
+            .map(|v| v.id.as_str())
+            .collect();
+        assert_eq!(ids, ["linear", "spherical"]);
+        assert_eq!(blended.transforms[1..], first.transforms[1..]);
+
+        let same = Crossover::Blend.apply(&first, &first, &mut rng).unwrap();
+        assert_eq!(same.transforms, first.transforms);
+    }
+}
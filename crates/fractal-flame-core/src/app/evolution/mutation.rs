@@ -0,0 +1,139 @@
+use rand::seq::IndexedRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::EvolutionError;
+use crate::app::generation::template::jitter_affine;
+use crate::app::generation::{ContractiveRandom, GenerationStrategy, random_color};
+use crate::domain::FlameGenome;
+
+/// Maximum absolute change of each affine coefficient in [`Mutation::PerturbAffines`].
+const PERTURBATION: f64 = 0.1;
+
+/// A single random edit of a genome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutation {
+    /// Nudges every coefficient of every affine.
+    PerturbAffines,
+    /// Replaces one variation of one transform with another from the pool.
+    SwapVariation,
+    /// Gives every transform a new color or palette index.
+    RandomizeColors,
+    /// Appends a random contractive transform using a variation from the pool.
+    AddTransform,
+    /// Drops one transform.
+    RemoveTransform,
+}
+
+impl Mutation {
+    pub const ALL: [Mutation; 5] = [
+        Self::PerturbAffines,
+        Self::SwapVariation,
+        Self::RandomizeColors,
+        Self::AddTransform,
+        Self::RemoveTransform,
+    ];
+
+    /// The snake_case name used in JSON.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PerturbAffines => "perturb_affines",
+            Self::SwapVariation => "swap_variation",
+            Self::RandomizeColors => "randomize_colors",
+            Self::AddTransform => "add_transform",
+            Self::RemoveTransform => "remove_transform",
+        }
+    }
+
+    /// Whether this mutation can change `genome` given the variation ids it may draw from.
+    pub fn is_applicable(&self, genome: &FlameGenome, variation_pool: &[String]) -> bool {
+        match self {
+            Self::PerturbAffines | Self::RandomizeColors => true,
+            Self::SwapVariation => variation_pool.len() > 1,
+            Self::AddTransform => !variation_pool.is_empty(),
+            Self::RemoveTransform => genome.transforms.len() > 1,
+        }
+    }
+
+    /// A mutated copy of `genome`. The child has no seed, so it gets a fresh one when rendered.
+    pub fn apply(
+        &self,
+        genome: &FlameGenome,
+        variation_pool: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<FlameGenome, EvolutionError> {
+        if !self.is_applicable(genome, variation_pool) {
+            return Err(EvolutionError::NotApplicable(*self));
+        }
+        let mut child = genome.clone();
+        child.seed = None;
+        match self {
+            Self::PerturbAffines => {
+                for t in child.transforms.iter_mut() {
+                    t.affine = jitter_affine(&t.affine, PERTURBATION, rng);
+                    t.post = t.post.map(|p| jitter_affine(&p, PERTURBATION, rng));
+                }
+            }
+            Self::SwapVariation => {
+                let t = rng.random_range(0..child.transforms.len());
+                let transform = &mut child.transforms[t];
+                let v = rng.random_range(0..transform.variations.len());
+                let old = transform.variations[v].id.clone();
+                let candidates: Vec<&String> =
+                    variation_pool.iter().filter(|id| **id != old).collect();
+                let Some(new) = candidates.choose(rng) else {
+                    return Err(EvolutionError::NotApplicable(*self));
+                };
+                transform.variations[v].id = new.to_string();
+                let prefix = format!("{}_", old);
+                transform.parameters.retain(|k, _| !k.starts_with(&prefix));
+            }
+            Self::RandomizeColors => {
+                for t in child.transforms.iter_mut() {
+                    match t.color_index {
+                        Some(_) => t.color_index = Some(rng.random_range(0.0..=1.0)),
+                        None => t.color = random_color(rng),
+                    }
+                }
+            }
+            Self::AddTransform => {
+                let id = variation_pool
+                    .choose(rng)
+                    .ok_or(EvolutionError::NotApplicable(*self))?;
+                let mut added =
+                    ContractiveRandom::default().generate(std::slice::from_ref(id), rng)?;
+                if child.palette.is_some() {
+                    for t in added.iter_mut() {
+                        t.color_index = Some(rng.random_range(0.0..=1.0));
+                    }
+                }
+                child.transforms.extend(added);
+            }
+            Self::RemoveTransform => {
+                let t = rng.random_range(0..child.transforms.len());
+                child.transforms.remove(t);
+            }
+        }
+        child.validate()?;
+        Ok(child)
+    }
+}
+
+/// Applies one mutation chosen uniformly from those applicable to `genome`.
+pub fn mutate(
+    genome: &FlameGenome,
+    variation_pool: &[String],
+    rng: &mut dyn RngCore,
+) -> Result<(Mutation, FlameGenome), EvolutionError> {
+    let applicable: Vec<Mutation> = Mutation::ALL
+        .into_iter()
+        .filter(|m| m.is_applicable(genome, variation_pool))
+        .collect();
+    let mutation = *applicable
+        .choose(rng)
+        .expect("perturbing affines is always applicable");
+    mutation
+        .apply(genome, variation_pool, rng)
+        .map(|child| (mutation, child))
+}
@@ -0,0 +1,207 @@
+/// Point-derived inputs available to every expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variable {
+    X,
+    Y,
+    R,
+    Theta,
+    Phi,
+}
+
+impl Variable {
+    pub const COUNT: usize = 5;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "x" => Some(Variable::X),
+            "y" => Some(Variable::Y),
+            "r" => Some(Variable::R),
+            "theta" | "θ" => Some(Variable::Theta),
+            "phi" | "φ" => Some(Variable::Phi),
+            _ => None,
+        }
+    }
+
+    pub fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constant {
+    Pi,
+    E,
+}
+
+impl Constant {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pi" | "π" => Some(Constant::Pi),
+            "e" => Some(Constant::E),
+            _ => None,
+        }
+    }
+
+    pub fn value(self) -> f64 {
+        match self {
+            Constant::Pi => std::f64::consts::PI,
+            Constant::E => std::f64::consts::E,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Tanh,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Abs,
+    Floor,
+    Ceil,
+    Sign,
+    Min,
+    Max,
+    Pow,
+    Mod,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let function = match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "asin" => Function::Asin,
+            "acos" => Function::Acos,
+            "atan" => Function::Atan,
+            "atan2" => Function::Atan2,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "tanh" => Function::Tanh,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log" | "log10" => Function::Log10,
+            "abs" => Function::Abs,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "sign" => Function::Sign,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "pow" => Function::Pow,
+            "mod" => Function::Mod,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Asin => "asin",
+            Function::Acos => "acos",
+            Function::Atan => "atan",
+            Function::Atan2 => "atan2",
+            Function::Sinh => "sinh",
+            Function::Cosh => "cosh",
+            Function::Tanh => "tanh",
+            Function::Sqrt => "sqrt",
+            Function::Exp => "exp",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Abs => "abs",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Sign => "sign",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Pow => "pow",
+            Function::Mod => "mod",
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Function::Atan2 | Function::Min | Function::Max | Function::Pow | Function::Mod => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(Variable),
+    Constant(Constant),
+    Parameter(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Call {
+        function: Function,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    /// Names of all parameters referenced by the expression, in order of first use.
+    pub fn parameters(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_parameters(&mut names);
+        names
+    }
+
+    fn collect_parameters<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Parameter(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Unary { operand, .. } => operand.collect_parameters(names),
+            Expr::Binary { left, right, .. } => {
+                left.collect_parameters(names);
+                right.collect_parameters(names);
+            }
+            Expr::Call { args, .. } => {
+                for arg in args {
+                    arg.collect_parameters(names);
+                }
+            }
+            Expr::Number(_) | Expr::Variable(_) | Expr::Constant(_) => {}
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use super::ExpressionError;
+use super::ast::{BinaryOp, Expr, Function, UnaryOp, Variable};
+
+/// Upper bound on the evaluation stack, so evaluation never allocates.
+pub const MAX_STACK_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Const(f64),
+    Load(usize),
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Call1(fn(f64) -> f64),
+    Call2(fn(f64, f64) -> f64),
+}
+
+/// Values of [`Variable`]s for one evaluation, indexed by [`Variable::slot`].
+pub type Inputs = [f64; Variable::COUNT];
+
+/// Stack-machine program produced from an [`Expr`] with parameters substituted and
+/// constant sub-expressions folded.
+#[derive(Clone, Debug)]
+pub struct CompiledExpression {
+    ops: Vec<Op>,
+    uses: [bool; Variable::COUNT],
+}
+
+impl CompiledExpression {
+    pub fn compile(
+        expr: &Expr,
+        parameters: &BTreeMap<String, f64>,
+    ) -> Result<Self, ExpressionError> {
+        let folded = fold(expr, parameters)?;
+
+        let mut program = Self {
+            ops: Vec::new(),
+            uses: [false; Variable::COUNT],
+        };
+        program.emit(&folded, 0)?;
+
+        Ok(program)
+    }
+
+    /// Whether evaluation reads `variable`; lets callers skip computing unused polar inputs.
+    pub fn uses(&self, variable: Variable) -> bool {
+        self.uses[variable.slot()]
+    }
+
+    pub fn evaluate(&self, inputs: &Inputs) -> f64 {
+        let mut stack = [0.0f64; MAX_STACK_DEPTH];
+        let mut top = 0usize;
+
+        for op in &self.ops {
+            match *op {
+                Op::Const(v) => {
+                    stack[top] = v;
+                    top += 1;
+                }
+                Op::Load(slot) => {
+                    stack[top] = inputs[slot];
+                    top += 1;
+                }
+                Op::Neg => stack[top - 1] = -stack[top - 1],
+                Op::Call1(f) => stack[top - 1] = f(stack[top - 1]),
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow | Op::Call2(_) => {
+                    top -= 1;
+                    let r = stack[top];
+                    let l = stack[top - 1];
+                    stack[top - 1] = match *op {
+                        Op::Add => l + r,
+                        Op::Sub => l - r,
+                        Op::Mul => l * r,
+                        Op::Div => l / r,
+                        Op::Pow => l.powf(r),
+                        Op::Call2(f) => f(l, r),
+                        _ => unreachable!(),
+                    };
+                }
+            }
+        }
+
+        stack[0]
+    }
+
+    /// Emits `expr` assuming `depth` values are already on the stack; returns the peak depth.
+    /// Left operands count one deeper too, so `depth` also bounds the recursion on chains.
+    fn emit(&mut self, expr: &Expr, depth: usize) -> Result<usize, ExpressionError> {
+        if depth + 1 > MAX_STACK_DEPTH {
+            return Err(ExpressionError::TooComplex {
+                max_depth: MAX_STACK_DEPTH,
+            });
+        }
+
+        match expr {
+            Expr::Number(v) => {
+                self.ops.push(Op::Const(*v));
+                Ok(depth + 1)
+            }
+            Expr::Constant(c) => {
+                self.ops.push(Op::Const(c.value()));
+                Ok(depth + 1)
+            }
+            Expr::Variable(v) => {
+                self.uses[v.slot()] = true;
+                self.ops.push(Op::Load(v.slot()));
+                Ok(depth + 1)
+            }
+            Expr::Parameter(name) => Err(ExpressionError::UnknownParameter { name: name.clone() }),
+            Expr::Unary { op, operand } => {
+                let peak = self.emit(operand, depth)?;
+                match op {
+                    UnaryOp::Neg => self.ops.push(Op::Neg),
+                }
+                Ok(peak)
+            }
+            Expr::Binary { op, left, right } => {
+                let left_peak = self.emit(left, depth + 1)?;
+                let right_peak = self.emit(right, depth + 1)?;
+                self.ops.push(match op {
+                    BinaryOp::Add => Op::Add,
+                    BinaryOp::Sub => Op::Sub,
+                    BinaryOp::Mul => Op::Mul,
+                    BinaryOp::Div => Op::Div,
+                    BinaryOp::Pow => Op::Pow,
+                });
+                Ok(left_peak.max(right_peak))
+            }
+            Expr::Call { function, args } => {
+                let mut peak = depth;
+                for (i, arg) in args.iter().enumerate() {
+                    peak = peak.max(self.emit(arg, depth + i)?);
+                }
+                self.ops.push(match args.len() {
+                    1 => Op::Call1(unary_fn(*function)),
+                    _ => Op::Call2(binary_fn(*function)),
+                });
+                Ok(peak)
+            }
+        }
+    }
+}
+
+/// Substitutes parameters and evaluates every sub-tree that does not depend on the point.
+fn fold(expr: &Expr, parameters: &BTreeMap<String, f64>) -> Result<Expr, ExpressionError> {
+    let folded = match expr {
+        Expr::Number(_) | Expr::Variable(_) => expr.clone(),
+        Expr::Constant(c) => Expr::Number(c.value()),
+        Expr::Parameter(name) => match parameters.get(name) {
+            Some(v) => Expr::Number(*v),
+            None => return Err(ExpressionError::UnknownParameter { name: name.clone() }),
+        },
+        Expr::Unary { op, operand } => match (op, fold(operand, parameters)?) {
+            (UnaryOp::Neg, Expr::Number(v)) => Expr::Number(-v),
+            (op, operand) => Expr::Unary {
+                op: *op,
+                operand: Box::new(operand),
+            },
+        },
+        Expr::Binary { op, left, right } => {
+            match (fold(left, parameters)?, fold(right, parameters)?) {
+                (Expr::Number(l), Expr::Number(r)) => Expr::Number(match op {
+                    BinaryOp::Add => l + r,
+                    BinaryOp::Sub => l - r,
+                    BinaryOp::Mul => l * r,
+                    BinaryOp::Div => l / r,
+                    BinaryOp::Pow => l.powf(r),
+                }),
+                (left, right) => Expr::Binary {
+                    op: *op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+            }
+        }
+        Expr::Call { function, args } => {
+            let args = args
+                .iter()
+                .map(|a| fold(a, parameters))
+                .collect::<Result<Vec<_>, _>>()?;
+            match args.as_slice() {
+                [Expr::Number(a)] => Expr::Number(unary_fn(*function)(*a)),
+                [Expr::Number(a), Expr::Number(b)] => Expr::Number(binary_fn(*function)(*a, *b)),
+                _ => Expr::Call {
+                    function: *function,
+                    args,
+                },
+            }
+        }
+    };
+    Ok(folded)
+}
+
+fn unary_fn(function: Function) -> fn(f64) -> f64 {
+    match function {
+        Function::Sin => f64::sin,
+        Function::Cos => f64::cos,
+        Function::Tan => f64::tan,
+        Function::Asin => f64::asin,
+        Function::Acos => f64::acos,
+        Function::Atan => f64::atan,
+        Function::Sinh => f64::sinh,
+        Function::Cosh => f64::cosh,
+        Function::Tanh => f64::tanh,
+        Function::Sqrt => f64::sqrt,
+        Function::Exp => f64::exp,
+        Function::Ln => f64::ln,
+        Function::Log10 => f64::log10,
+        Function::Abs => f64::abs,
+        Function::Floor => f64::floor,
+        Function::Ceil => f64::ceil,
+        Function::Sign => sign,
+        Function::Atan2 | Function::Min | Function::Max | Function::Pow | Function::Mod => {
+            unreachable!("binary function dispatched as unary")
+        }
+    }
+}
+
+fn binary_fn(function: Function) -> fn(f64, f64) -> f64 {
+    match function {
+        Function::Atan2 => f64::atan2,
+        Function::Min => f64::min,
+        Function::Max => f64::max,
+        Function::Pow => f64::powf,
+        Function::Mod => f64::rem_euclid,
+        _ => unreachable!("unary function dispatched as binary"),
+    }
+}
+
+fn sign(v: f64) -> f64 {
+    if v > 0.0 {
+        1.0
+    } else if v < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
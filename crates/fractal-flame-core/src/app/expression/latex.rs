@@ -0,0 +1,163 @@
+use super::ast::{BinaryOp, Constant, Expr, Function, UnaryOp, Variable};
+
+const PREC_ADD: u8 = 1;
+const PREC_MUL: u8 = 2;
+const PREC_NEG: u8 = 3;
+const PREC_POW: u8 = 4;
+const PREC_ATOM: u8 = 5;
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary { op, .. } => match op {
+            BinaryOp::Add | BinaryOp::Sub => PREC_ADD,
+            BinaryOp::Mul => PREC_MUL,
+            // \frac{}{} is self-delimiting.
+            BinaryOp::Div => PREC_ATOM,
+            BinaryOp::Pow => PREC_POW,
+        },
+        Expr::Unary { .. } => PREC_NEG,
+        Expr::Number(v) if *v < 0.0 => PREC_NEG,
+        _ => PREC_ATOM,
+    }
+}
+
+fn wrap(expr: &Expr, min_precedence: u8) -> String {
+    let inner = to_latex(expr);
+    if precedence(expr) < min_precedence {
+        format!(r"\left({}\right)", inner)
+    } else {
+        inner
+    }
+}
+
+/// Whether the rendered expression starts with a digit, in which case juxtaposition would be
+/// ambiguous (`2 \cdot 3`, not `23`).
+fn starts_with_number(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) => true,
+        Expr::Binary {
+            op: BinaryOp::Mul | BinaryOp::Pow,
+            left,
+            ..
+        } => starts_with_number(left),
+        _ => false,
+    }
+}
+
+fn number(v: f64) -> String {
+    if v.is_finite() {
+        format!("{}", v)
+    } else if v.is_nan() {
+        r"\mathrm{NaN}".to_string()
+    } else if v > 0.0 {
+        r"\infty".to_string()
+    } else {
+        r"-\infty".to_string()
+    }
+}
+
+fn parameter(name: &str) -> String {
+    const GREEK: &[&str] = &[
+        "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "iota", "kappa", "lambda",
+        "mu", "nu", "xi", "rho", "sigma", "tau", "upsilon", "chi", "psi", "omega",
+    ];
+    if name.chars().count() == 1 {
+        name.to_string()
+    } else if GREEK.contains(&name) {
+        format!(r"\{}", name)
+    } else {
+        format!(r"\mathrm{{{}}}", name.replace('_', r"\_"))
+    }
+}
+
+fn call(function: Function, args: &[Expr]) -> String {
+    let arg = |i: usize| to_latex(&args[i]);
+    match function {
+        Function::Sqrt => format!(r"\sqrt{{{}}}", arg(0)),
+        Function::Abs => format!(r"\left|{}\right|", arg(0)),
+        Function::Floor => format!(r"\lfloor {} \rfloor", arg(0)),
+        Function::Ceil => format!(r"\lceil {} \rceil", arg(0)),
+        Function::Exp => format!("e^{{{}}}", arg(0)),
+        Function::Pow => format!("{}^{{{}}}", wrap(&args[0], PREC_ATOM), arg(1)),
+        Function::Mod => format!(
+            r"{} \bmod {}",
+            wrap(&args[0], PREC_POW),
+            wrap(&args[1], PREC_POW)
+        ),
+        Function::Atan2 | Function::Min | Function::Max => {
+            let name = match function {
+                Function::Atan2 => r"\operatorname{atan2}",
+                Function::Min => r"\min",
+                _ => r"\max",
+            };
+            format!(r"{}({}, {})", name, arg(0), arg(1))
+        }
+        _ => {
+            let name = match function {
+                Function::Sin => r"\sin",
+                Function::Cos => r"\cos",
+                Function::Tan => r"\tan",
+                Function::Asin => r"\arcsin",
+                Function::Acos => r"\arccos",
+                Function::Atan => r"\arctan",
+                Function::Sinh => r"\sinh",
+                Function::Cosh => r"\cosh",
+                Function::Tanh => r"\tanh",
+                Function::Ln => r"\ln",
+                Function::Log10 => r"\log_{10}",
+                _ => r"\operatorname{sign}",
+            };
+            format!("{}({})", name, arg(0))
+        }
+    }
+}
+
+/// Renders an expression as KaTeX-compatible LaTeX with only the parentheses precedence needs.
+pub fn to_latex(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(v) => number(*v),
+        Expr::Variable(v) => match v {
+            Variable::X => "x",
+            Variable::Y => "y",
+            Variable::R => "r",
+            Variable::Theta => r"\theta",
+            Variable::Phi => r"\phi",
+        }
+        .to_string(),
+        Expr::Constant(c) => match c {
+            Constant::Pi => r"\pi",
+            Constant::E => "e",
+        }
+        .to_string(),
+        Expr::Parameter(name) => parameter(name),
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => format!("-{}", wrap(operand, PREC_MUL)),
+        Expr::Binary { op, left, right } => match op {
+            BinaryOp::Add => format!("{} + {}", to_latex(left), wrap(right, PREC_ADD)),
+            BinaryOp::Sub => format!("{} - {}", to_latex(left), wrap(right, PREC_MUL)),
+            BinaryOp::Mul => {
+                let separator = if starts_with_number(right) || precedence(right) == PREC_NEG {
+                    r" \cdot "
+                } else {
+                    ""
+                };
+                format!(
+                    "{}{}{}",
+                    wrap(left, PREC_MUL),
+                    separator,
+                    wrap(right, PREC_NEG)
+                )
+            }
+            BinaryOp::Div => format!(r"\frac{{{}}}{{{}}}", to_latex(left), to_latex(right)),
+            BinaryOp::Pow => format!("{}^{{{}}}", wrap(left, PREC_ATOM), to_latex(right)),
+        },
+        Expr::Call { function, args } => call(*function, args),
+    }
+}
+
+/// Formats a variation's pair of coordinate expressions the way built-in formulas are written.
+pub fn formula_latex(x: &Expr, y: &Expr) -> String {
+    format!(r"x' = {},\quad y' = {}", to_latex(x), to_latex(y))
+}
@@ -0,0 +1,131 @@
+pub mod ast;
+pub mod compiler;
+pub mod latex;
+pub mod parser;
+
+pub use ast::Expr;
+pub use compiler::CompiledExpression;
+pub use latex::{formula_latex, to_latex};
+pub use parser::parse;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ExpressionError {
+    #[error("Expression is empty")]
+    Empty,
+    #[error("Unexpected character '{found}' at position {position}")]
+    UnexpectedChar { position: usize, found: char },
+    #[error("Invalid number '{literal}' at position {position}")]
+    InvalidNumber { position: usize, literal: String },
+    #[error("Unexpected '{found}' at position {position}, expected {expected}")]
+    UnexpectedToken {
+        position: usize,
+        found: String,
+        expected: String,
+    },
+    #[error("Unexpected end of expression, expected {expected}")]
+    UnexpectedEnd { expected: String },
+    #[error("Unknown function '{name}' at position {position}")]
+    UnknownFunction { position: usize, name: String },
+    #[error(
+        "Function '{function}' at position {position} takes {expected} argument(s), got {found}"
+    )]
+    WrongArity {
+        position: usize,
+        function: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Unknown parameter '{name}'")]
+    UnknownParameter { name: String },
+    #[error("Expression is too deeply nested (max depth {max_depth})")]
+    TooComplex { max_depth: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn eval(source: &str, inputs: [f64; 5]) -> f64 {
+        let params = BTreeMap::from([("k".to_string(), 2.0)]);
+        CompiledExpression::compile(&parse(source).unwrap(), &params)
+            .unwrap()
+            .evaluate(&inputs)
+    }
+
+    #[test]
+    fn evaluates_with_precedence() {
+        assert_eq!(eval("1 + 2 * 3 ^ 2", [0.0; 5]), 19.0);
+        assert_eq!(eval("-2 ^ 2", [0.0; 5]), -4.0);
+        assert_eq!(eval("k * x - y / 4", [3.0, 2.0, 0.0, 0.0, 0.0]), 5.5);
+        assert_eq!(
+            eval("max(x, atan2(0, 1)) + 1e-1", [-1.0, 0.0, 0.0, 0.0, 0.0]),
+            0.1
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(matches!(
+            parse("x +"),
+            Err(ExpressionError::UnexpectedEnd { .. })
+        ));
+        assert!(matches!(
+            parse("foo(x)"),
+            Err(ExpressionError::UnknownFunction { .. })
+        ));
+        assert!(matches!(
+            parse("min(x)"),
+            Err(ExpressionError::WrongArity { .. })
+        ));
+        assert_eq!(
+            CompiledExpression::compile(&parse("x * q").unwrap(), &BTreeMap::new()).unwrap_err(),
+            ExpressionError::UnknownParameter {
+                name: "q".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let too_complex = Err(ExpressionError::TooComplex {
+            max_depth: parser::MAX_NESTING_DEPTH,
+        });
+        let parens = format!("{}x{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(parse(&parens), too_complex);
+        assert_eq!(parse(&format!("{}x", "-".repeat(100_000))), too_complex);
+        assert_eq!(
+            parse(&format!(
+                "{}x{}",
+                "sin(".repeat(100_000),
+                ")".repeat(100_000)
+            )),
+            too_complex
+        );
+        assert!(parse(&format!("{}x{}", "(".repeat(20), ")".repeat(20))).is_ok());
+    }
+
+    #[test]
+    fn rejects_long_operator_chains() {
+        let too_complex = Err(ExpressionError::TooComplex {
+            max_depth: parser::MAX_NESTING_DEPTH,
+        });
+        assert_eq!(parse(&vec!["x"; 100_000].join("+")), too_complex);
+        assert_eq!(parse(&vec!["x"; 100_000].join("*")), too_complex);
+        assert_eq!(eval(&vec!["x"; 20].join(" + "), [1.0; 5]), 20.0);
+    }
+
+    #[test]
+    fn renders_latex() {
+        let x = parse("x * sin(r^2) - y * cos(r^2)").unwrap();
+        let y = parse("sqrt(x) / (1 + theta)").unwrap();
+        assert_eq!(
+            formula_latex(&x, &y),
+            r"x' = x\sin(r^{2}) - y\cos(r^{2}),\quad y' = \frac{\sqrt{x}}{1 + \theta}"
+        );
+        assert_eq!(
+            to_latex(&parse("2 * -(x + y)").unwrap()),
+            r"2 \cdot -\left(x + y\right)"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use super::ExpressionError;
+use super::ast::{BinaryOp, Constant, Expr, Function, UnaryOp, Variable};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(n) => n.to_string(),
+            Token::Ident(name) => name.clone(),
+            Token::Plus => "+".to_string(),
+            Token::Minus => "-".to_string(),
+            Token::Star => "*".to_string(),
+            Token::Slash => "/".to_string(),
+            Token::Caret => "^".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::Comma => ",".to_string(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(pos, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if ch.is_ascii_digit() || ch == '.' {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else if (c == 'e' || c == 'E') && is_exponent(&source[i..]) {
+                    chars.next();
+                    if let Some(&(_, sign)) = chars.peek()
+                        && (sign == '+' || sign == '-')
+                    {
+                        chars.next();
+                    }
+                    end = i + 1;
+                    while let Some(&(j, d)) = chars.peek() {
+                        if !d.is_ascii_digit() {
+                            break;
+                        }
+                        end = j + 1;
+                        chars.next();
+                    }
+                    break;
+                } else {
+                    break;
+                }
+            }
+            let literal = &source[pos..end];
+            let value = literal
+                .parse::<f64>()
+                .map_err(|_| ExpressionError::InvalidNumber {
+                    position: pos,
+                    literal: literal.to_string(),
+                })?;
+            tokens.push((pos, Token::Number(value)));
+            continue;
+        }
+
+        if ch.is_alphabetic() || ch == '_' {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push((pos, Token::Ident(source[pos..end].to_string())));
+            continue;
+        }
+
+        let token = match ch {
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '^' => Token::Caret,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            other => {
+                return Err(ExpressionError::UnexpectedChar {
+                    position: pos,
+                    found: other,
+                });
+            }
+        };
+        chars.next();
+        tokens.push((pos, token));
+    }
+
+    Ok(tokens)
+}
+
+/// `1e-3` is a number, `2*e` is not: only treat `e` as an exponent when digits follow.
+fn is_exponent(rest: &str) -> bool {
+    let mut chars = rest.chars().skip(1);
+    match chars.next() {
+        Some('+') | Some('-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Deepest nesting of parentheses, calls, unary operators and chained binary operands
+/// accepted, so parsing and every later pass over the tree, which all recurse, stay well
+/// within the stack.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    source_len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(p, _)| *p)
+            .unwrap_or(self.source_len)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        let position = self.position();
+        match self.next() {
+            Some(ref t) if *t == expected => Ok(()),
+            Some(t) => Err(ExpressionError::UnexpectedToken {
+                position,
+                found: t.describe(),
+                expected: expected.describe(),
+            }),
+            None => Err(ExpressionError::UnexpectedEnd {
+                expected: expected.describe(),
+            }),
+        }
+    }
+
+    /// Counts one more level of nesting, failing once the tree would be too deep.
+    fn descend(&mut self) -> Result<(), ExpressionError> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(ExpressionError::TooComplex {
+                max_depth: MAX_NESTING_DEPTH,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Each operand of a chain like `a + b + c` nests the chain so far one level deeper.
+    fn parse_additive(&mut self) -> Result<Expr, ExpressionError> {
+        let depth = self.depth;
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Sub,
+                _ => {
+                    self.depth = depth;
+                    return Ok(left);
+                }
+            };
+            self.next();
+            self.descend()?;
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, ExpressionError> {
+        let depth = self.depth;
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Mul,
+                Some(Token::Slash) => BinaryOp::Div,
+                _ => {
+                    self.depth = depth;
+                    return Ok(left);
+                }
+            };
+            self.next();
+            self.descend()?;
+            let right = self.parse_unary()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+
+    /// Every nested sub-expression is parsed through here, so this is where nesting is
+    /// counted.
+    fn parse_unary(&mut self) -> Result<Expr, ExpressionError> {
+        self.descend()?;
+        let expr = self.parse_unary_nested();
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_unary_nested(&mut self) -> Result<Expr, ExpressionError> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.next();
+                let operand = self.parse_unary()?;
+                Ok(Expr::Unary {
+                    op: UnaryOp::Neg,
+                    operand: Box::new(operand),
+                })
+            }
+            Some(Token::Plus) => {
+                self.next();
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    /// `^` binds tighter than unary minus and is right-associative: `-x^2^3 = -(x^(2^3))`.
+    fn parse_power(&mut self) -> Result<Expr, ExpressionError> {
+        let base = self.parse_primary()?;
+        if let Some(Token::Caret) = self.peek() {
+            self.next();
+            let exponent = self.parse_unary()?;
+            return Ok(Expr::Binary {
+                op: BinaryOp::Pow,
+                left: Box::new(base),
+                right: Box::new(exponent),
+            });
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ExpressionError> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::LParen) => {
+                let inner = self.parse_additive()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    self.next();
+                    return self.parse_call(position, name);
+                }
+                if let Some(variable) = Variable::from_name(&name) {
+                    Ok(Expr::Variable(variable))
+                } else if let Some(constant) = Constant::from_name(&name) {
+                    Ok(Expr::Constant(constant))
+                } else {
+                    Ok(Expr::Parameter(name))
+                }
+            }
+            Some(t) => Err(ExpressionError::UnexpectedToken {
+                position,
+                found: t.describe(),
+                expected: "a number, name or '('".to_string(),
+            }),
+            None => Err(ExpressionError::UnexpectedEnd {
+                expected: "a number, name or '('".to_string(),
+            }),
+        }
+    }
+
+    fn parse_call(&mut self, position: usize, name: String) -> Result<Expr, ExpressionError> {
+        let function = Function::from_name(&name)
+            .ok_or(ExpressionError::UnknownFunction { position, name })?;
+
+        let mut args = Vec::new();
+        if let Some(Token::RParen) = self.peek() {
+            self.next();
+        } else {
+            loop {
+                args.push(self.parse_additive()?);
+                match self.peek() {
+                    Some(Token::Comma) => {
+                        self.next();
+                    }
+                    _ => {
+                        self.expect(Token::RParen)?;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if args.len() != function.arity() {
+            return Err(ExpressionError::WrongArity {
+                position,
+                function: function.name(),
+                expected: function.arity(),
+                found: args.len(),
+            });
+        }
+
+        Ok(Expr::Call { function, args })
+    }
+}
+
+/// Parses an infix expression such as `x * sin(r^2) - y * cos(r^2)`.
+///
+/// Names that are not a variable (`x`, `y`, `r`, `theta`/`θ`, `phi`/`φ`), a constant
+/// (`pi`/`π`, `e`) or a function call are treated as parameters and resolved at compile time.
+pub fn parse(source: &str) -> Result<Expr, ExpressionError> {
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err(ExpressionError::Empty);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        source_len: source.len(),
+        depth: 0,
+    };
+    let expr = parser.parse_additive()?;
+
+    if let Some((position, token)) = parser.tokens.get(parser.pos) {
+        return Err(ExpressionError::UnexpectedToken {
+            position: *position,
+            found: token.describe(),
+            expected: "an operator or end of input".to_string(),
+        });
+    }
+
+    Ok(expr)
+}
@@ -0,0 +1,175 @@
+use std::fmt::Write;
+
+use super::flam3_coefs_from_affine;
+use crate::domain::{Affine, Background, Color, FlameGenome, GenomeTransform, Palette};
+
+/// flam3 palettes always have this many entries.
+const FLAM3_PALETTE_SIZE: usize = 256;
+const FLAM3_DEFAULT_BRIGHTNESS: f64 = 4.0;
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Whether `name` can be written as an attribute name: an XML `Name` without colons, which
+/// would make it a namespace prefix.
+fn is_attribute_name(name: &str) -> bool {
+    fn is_start(c: char) -> bool {
+        matches!(c,
+            'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+            | '\u{F8}'..='\u{2FF}' | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}'
+            | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}'
+            | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{EFFFF}')
+    }
+    fn is_char(c: char) -> bool {
+        is_start(c)
+            || matches!(c, '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}'
+                | '\u{203F}'..='\u{2040}')
+    }
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_start) && chars.all(is_char)
+}
+
+fn coefs(affine: &Affine) -> String {
+    flam3_coefs_from_affine(affine)
+        .map(|c| c.to_string())
+        .join(" ")
+}
+
+/// The palette and per-transform color indices to export. flam3 only knows palette colors, so
+/// genomes that color transforms directly get a palette made of those colors.
+fn resolve_colors(genome: &FlameGenome) -> (Palette, Vec<f64>) {
+    let transforms: Vec<&GenomeTransform> = genome
+        .transforms
+        .iter()
+        .chain(genome.final_transform.iter())
+        .collect();
+
+    if let Some(ref palette) = genome.palette
+        && !palette.colors.is_empty()
+        && transforms.iter().all(|t| t.color_index.is_some())
+    {
+        let indices = transforms.iter().filter_map(|t| t.color_index).collect();
+        return (palette.clone(), indices);
+    }
+
+    let colors: Vec<Color> = transforms
+        .iter()
+        .map(|t| t.resolved_color(genome.palette.as_ref()))
+        .collect();
+    let last = colors.len().saturating_sub(1).max(1) as f64;
+    let indices = (0..colors.len()).map(|i| i as f64 / last).collect();
+    (Palette { colors }, indices)
+}
+
+fn write_xform(out: &mut String, tag: &str, transform: &GenomeTransform, color_index: f64) {
+    let _ = write!(out, "    <{}", tag);
+    if tag == "xform" {
+        let _ = write!(out, " weight=\"{}\"", transform.weight);
+    }
+    let _ = write!(
+        out,
+        " color=\"{}\" coefs=\"{}\"",
+        color_index,
+        coefs(&transform.affine)
+    );
+    if let Some(ref post) = transform.post {
+        let _ = write!(out, " post=\"{}\"", coefs(post));
+    }
+    // Names that are not valid attribute names, or repeat one already written, would make the
+    // document malformed, so those variations and parameters are left out.
+    let mut written = vec!["weight", "color", "coefs", "post"];
+    let attributes = transform
+        .variations
+        .iter()
+        .map(|variation| (variation.id.as_str(), variation.weight))
+        .chain(
+            transform
+                .parameters
+                .iter()
+                .map(|(name, &value)| (name.as_str(), value)),
+        );
+    for (name, value) in attributes {
+        if is_attribute_name(name) && !written.contains(&name) {
+            let _ = write!(out, " {}=\"{}\"", name, value);
+            written.push(name);
+        }
+    }
+    out.push_str("/>\n");
+}
+
+/// Serializes a genome as a flam3 `<flame>` document that Apophysis and JWildfire can open.
+///
+/// Custom variations are written under their ids; other programs only understand them if
+/// they have a variation of the same name.
+pub fn to_flame_xml(genome: &FlameGenome) -> String {
+    let camera = &genome.camera;
+    let (palette, color_indices) = resolve_colors(genome);
+    let quality = genome.quality.samples as f64 * genome.quality.iter_per_sample as f64
+        / (camera.width * camera.height) as f64;
+
+    let mut out = String::from("<flames>\n  <flame");
+    if let Some(ref name) = genome.name {
+        let _ = write!(out, " name=\"{}\"", escape(name));
+    }
+    let _ = write!(
+        out,
+        " version=\"fractal-flame\" size=\"{} {}\" center=\"{} {}\" scale=\"{}\" rotate=\"{}\" \
+         gamma=\"{}\" brightness=\"{}\" quality=\"{}\" oversample=\"1\" filter=\"0\"",
+        camera.width,
+        camera.height,
+        camera.center_x,
+        camera.center_y,
+        camera.zoom * camera.height as f64 / 2.0,
+        camera.rotate,
+        genome.tone_mapping.gamma,
+        genome.tone_mapping.brightness * FLAM3_DEFAULT_BRIGHTNESS,
+        quality,
+    );
+    // flam3 has no transparent or gradient backgrounds; those fall back to its black default.
+    if let Background::Solid {
+        color: Color { r, g, b },
+    } = genome.tone_mapping.background
+    {
+        let _ = write!(
+            out,
+            " background=\"{} {} {}\"",
+            r as f64 / 255.0,
+            g as f64 / 255.0,
+            b as f64 / 255.0
+        );
+    }
+    out.push_str(">\n");
+    if genome.symmetry > 1 {
+        let _ = writeln!(out, "    <symmetry kind=\"{}\"/>", genome.symmetry);
+    }
+
+    for (transform, &color_index) in genome.transforms.iter().zip(&color_indices) {
+        write_xform(&mut out, "xform", transform, color_index);
+    }
+    if let Some(ref transform) = genome.final_transform {
+        write_xform(
+            &mut out,
+            "finalxform",
+            transform,
+            color_indices[color_indices.len() - 1],
+        );
+    }
+
+    let last = (FLAM3_PALETTE_SIZE - 1) as f64;
+    for i in 0..FLAM3_PALETTE_SIZE {
+        let Color { r, g, b } = palette.color_at(i as f64 / last).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "    <color index=\"{}\" rgb=\"{} {} {}\"/>",
+            i, r, g, b
+        );
+    }
+    out.push_str("  </flame>\n</flames>\n");
+    out
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+
+use roxmltree::Node;
+
+use super::{FlameImportError, XFORM_ATTRIBUTES, affine_from_flam3_coefs};
+use crate::app::transformations::registry;
+use crate::domain::{
+    Affine, Background, Camera, Color, CustomVariationSpec, FlameGenome, GenomeTransform,
+    GenomeVariation, Palette, Quality, ToneMapping,
+};
+
+/// flam3 defaults for attributes that may be omitted.
+const FLAM3_DEFAULT_SCALE: f64 = 50.0;
+const FLAM3_DEFAULT_GAMMA: f64 = 4.0;
+const FLAM3_DEFAULT_BRIGHTNESS: f64 = 4.0;
+const FLAM3_DEFAULT_QUALITY: f64 = 1.0;
+
+fn attr<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute(name)
+}
+
+fn invalid(node: &Node, attribute: &str, value: &str) -> FlameImportError {
+    FlameImportError::InvalidAttribute {
+        element: node.tag_name().name().to_string(),
+        attribute: attribute.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_f64(node: &Node, name: &str, default: f64) -> Result<f64, FlameImportError> {
+    match attr(node, name) {
+        Some(v) => v.trim().parse().map_err(|_| invalid(node, name, v)),
+        None => Ok(default),
+    }
+}
+
+fn parse_numbers<const N: usize>(
+    node: &Node,
+    name: &str,
+) -> Result<Option<[f64; N]>, FlameImportError> {
+    let Some(value) = attr(node, name) else {
+        return Ok(None);
+    };
+    let numbers = value
+        .split_whitespace()
+        .map(str::parse::<f64>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid(node, name, value))?;
+    numbers
+        .try_into()
+        .map(Some)
+        .map_err(|_| invalid(node, name, value))
+}
+
+/// flam3 writes the background as channels in `[0, 1]`.
+fn unit_rgb(rgb: [f64; 3]) -> Color {
+    let [r, g, b] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color { r, g, b }
+}
+
+fn parse_palette(flame: &Node) -> Result<Option<Palette>, FlameImportError> {
+    let mut indexed: Vec<(usize, Color)> = Vec::new();
+    for color in flame.children().filter(|n| n.has_tag_name("color")) {
+        let index = parse_f64(&color, "index", -1.0)?;
+        let rgb = parse_numbers::<3>(&color, "rgb")?.ok_or(FlameImportError::MissingAttribute {
+            element: "color",
+            attribute: "rgb",
+        })?;
+        if index < 0.0 {
+            return Err(FlameImportError::MissingAttribute {
+                element: "color",
+                attribute: "index",
+            });
+        }
+        let [r, g, b] = rgb.map(|c| c.clamp(0.0, 255.0) as u8);
+        indexed.push((index as usize, Color { r, g, b }));
+    }
+    if !indexed.is_empty() {
+        indexed.sort_by_key(|(i, _)| *i);
+        return Ok(Some(Palette {
+            colors: indexed.into_iter().map(|(_, c)| c).collect(),
+        }));
+    }
+
+    let Some(palette) = flame.children().find(|n| n.has_tag_name("palette")) else {
+        return Ok(None);
+    };
+    let format = attr(&palette, "format").unwrap_or("RGB");
+    let bytes_per_color = match format.to_ascii_uppercase().as_str() {
+        "RGB" => 3,
+        "RGBA" => 4,
+        other => {
+            return Err(FlameImportError::Unsupported(format!(
+                "palette format '{}'",
+                other
+            )));
+        }
+    };
+    let hex: String = palette
+        .text()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let expected_len = attr(&palette, "count")
+        .and_then(|count| count.parse::<usize>().ok())
+        .map(|count| count.checked_mul(2 * bytes_per_color));
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit())
+        || !hex.len().is_multiple_of(2 * bytes_per_color)
+        || expected_len.is_some_and(|len| len != Some(hex.len()))
+    {
+        return Err(invalid(&palette, "data", &hex));
+    }
+    let data = (0..hex.len() / 2)
+        .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| invalid(&palette, "data", &hex))?;
+    Ok(Some(Palette {
+        colors: data
+            .chunks_exact(bytes_per_color)
+            .map(|c| Color {
+                r: c[0],
+                g: c[1],
+                b: c[2],
+            })
+            .collect(),
+    }))
+}
+
+fn is_supported_variation(name: &str, custom: &[CustomVariationSpec]) -> bool {
+    registry::is_builtin(name) || custom.iter().any(|c| c.id == name)
+}
+
+fn parse_xform(
+    node: &Node,
+    palette: Option<&Palette>,
+    custom: &[CustomVariationSpec],
+    unsupported: &mut Vec<String>,
+) -> Result<GenomeTransform, FlameImportError> {
+    let element = if node.has_tag_name("finalxform") {
+        "finalxform"
+    } else {
+        "xform"
+    };
+    let coefs = parse_numbers::<6>(node, "coefs")?.ok_or(FlameImportError::MissingAttribute {
+        element,
+        attribute: "coefs",
+    })?;
+    let post = parse_numbers::<6>(node, "post")?
+        .map(affine_from_flam3_coefs)
+        .filter(|p| *p != Affine::IDENTITY);
+    let color_index = parse_f64(node, "color", 0.0)?;
+
+    let numeric: Vec<(&str, &str, f64)> = node
+        .attributes()
+        .filter(|a| !XFORM_ATTRIBUTES.contains(&a.name()))
+        .map(|a| {
+            a.value()
+                .trim()
+                .parse::<f64>()
+                .map(|v| (a.name(), a.value(), v))
+                .map_err(|_| invalid(node, a.name(), a.value()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    // `julian_power` is a parameter of `julian` when `julian` is also present on the element.
+    let is_parameter = |name: &str| {
+        numeric.iter().any(|(other, _, _)| {
+            name.len() > other.len() && name.starts_with(&format!("{}_", other))
+        })
+    };
+
+    let mut variations = Vec::new();
+    let mut parameters = BTreeMap::new();
+    for &(name, _, value) in &numeric {
+        if is_parameter(name) {
+            parameters.insert(name.to_string(), value);
+        } else if value == 0.0 {
+            continue;
+        } else if is_supported_variation(name, custom) {
+            variations.push(GenomeVariation {
+                id: name.to_string(),
+                weight: value,
+            });
+        } else if !unsupported.iter().any(|u| u == name) {
+            unsupported.push(name.to_string());
+        }
+    }
+
+    let color = palette
+        .and_then(|p| p.color_at(color_index))
+        .unwrap_or_else(|| {
+            let v = (color_index.clamp(0.0, 1.0) * 255.0) as u8;
+            Color { r: v, g: v, b: v }
+        });
+
+    Ok(GenomeTransform {
+        affine: affine_from_flam3_coefs(coefs),
+        post,
+        weight: if element == "finalxform" {
+            1.0
+        } else {
+            parse_f64(node, "weight", 1.0)?
+        },
+        color,
+        color_index: Some(color_index),
+        variations,
+        parameters,
+    })
+}
+
+fn parse_flame(
+    flame: &Node,
+    custom: &[CustomVariationSpec],
+) -> Result<FlameGenome, FlameImportError> {
+    let [width, height] =
+        parse_numbers::<2>(flame, "size")?.ok_or(FlameImportError::MissingAttribute {
+            element: "flame",
+            attribute: "size",
+        })?;
+    if width < 1.0 || height < 1.0 {
+        return Err(invalid(
+            flame,
+            "size",
+            attr(flame, "size").unwrap_or_default(),
+        ));
+    }
+    let [center_x, center_y] = parse_numbers::<2>(flame, "center")?.unwrap_or([0.0, 0.0]);
+    let scale = parse_f64(flame, "scale", FLAM3_DEFAULT_SCALE)?;
+    let zoom = parse_f64(flame, "zoom", 0.0)?;
+    let rotate = parse_f64(flame, "rotate", 0.0)?;
+
+    let symmetry = match flame.children().find(|n| n.has_tag_name("symmetry")) {
+        Some(node) => {
+            let kind = parse_f64(&node, "kind", 1.0)?;
+            if !kind.is_finite() {
+                return Err(invalid(
+                    &node,
+                    "kind",
+                    attr(&node, "kind").unwrap_or_default(),
+                ));
+            }
+            if kind < 0.0 {
+                return Err(FlameImportError::Unsupported(format!(
+                    "dihedral symmetry (kind {})",
+                    kind
+                )));
+            }
+            (kind as usize).max(1)
+        }
+        None => 1,
+    };
+
+    let palette = parse_palette(flame)?;
+
+    let mut unsupported = Vec::new();
+    let transforms = flame
+        .children()
+        .filter(|n| n.has_tag_name("xform"))
+        .map(|n| parse_xform(&n, palette.as_ref(), custom, &mut unsupported))
+        .collect::<Result<Vec<_>, _>>()?;
+    let final_transform = flame
+        .children()
+        .find(|n| n.has_tag_name("finalxform"))
+        .map(|n| parse_xform(&n, palette.as_ref(), custom, &mut unsupported))
+        .transpose()?;
+    if !unsupported.is_empty() {
+        return Err(FlameImportError::UnsupportedVariations { names: unsupported });
+    }
+
+    let used = |id: &str| {
+        transforms
+            .iter()
+            .chain(final_transform.iter())
+            .any(|t| t.variations.iter().any(|v| v.id == id))
+    };
+    let custom_variations = custom.iter().filter(|c| used(&c.id)).cloned().collect();
+
+    let quality = Quality::default();
+    let density = parse_f64(flame, "quality", FLAM3_DEFAULT_QUALITY)?;
+    if !(density.is_finite() && density > 0.0) {
+        return Err(invalid(
+            flame,
+            "quality",
+            attr(flame, "quality").unwrap_or_default(),
+        ));
+    }
+    // Casts saturate, so a density or symmetry beyond any limit still fails `validate_within`.
+    let samples = (density * width * height / quality.iter_per_sample as f64).ceil() as usize;
+
+    let genome = FlameGenome {
+        name: attr(flame, "name").map(str::to_string),
+        transforms,
+        final_transform,
+        custom_variations,
+        symmetry,
+        camera: Camera {
+            width: width as usize,
+            height: height as usize,
+            center_x,
+            center_y,
+            // flam3 scale is pixels per unit; our zoom is relative to a 2-unit-tall window.
+            zoom: 2.0 * scale * 2f64.powf(zoom) / height,
+            rotate,
+        },
+        palette,
+        tone_mapping: ToneMapping {
+            gamma: parse_f64(flame, "gamma", FLAM3_DEFAULT_GAMMA)?,
+            brightness: parse_f64(flame, "brightness", FLAM3_DEFAULT_BRIGHTNESS)?
+                / FLAM3_DEFAULT_BRIGHTNESS,
+            background: match parse_numbers::<3>(flame, "background")? {
+                Some(rgb) => Background::Solid {
+                    color: unit_rgb(rgb),
+                },
+                None => Background::default(),
+            },
+        },
+        quality: Quality {
+            samples: samples.max(1),
+            ..quality
+        },
+        seed: None,
+    };
+    genome.validate()?;
+    Ok(genome)
+}
+
+/// Parses every `<flame>` in a flam3, Apophysis or JWildfire document, in document order.
+///
+/// Variations must be built-ins or one of `custom`; anything else is reported by name in
+/// [`FlameImportError::UnsupportedVariations`].
+pub fn parse_flames(
+    xml: &str,
+    custom: &[CustomVariationSpec],
+) -> Result<Vec<FlameGenome>, FlameImportError> {
+    let document = roxmltree::Document::parse(xml)?;
+    let flames = document
+        .descendants()
+        .filter(|n| n.has_tag_name("flame"))
+        .map(|n| parse_flame(&n, custom))
+        .collect::<Result<Vec<_>, _>>()?;
+    if flames.is_empty() {
+        return Err(FlameImportError::NoFlame);
+    }
+    Ok(flames)
+}
+
+/// Parses only the `index`-th `<flame>` of a document, so one unsupported flame in a
+/// collection does not prevent importing the others.
+pub fn parse_flame_at(
+    xml: &str,
+    index: usize,
+    custom: &[CustomVariationSpec],
+) -> Result<FlameGenome, FlameImportError> {
+    let document = roxmltree::Document::parse(xml)?;
+    let mut flames = document.descendants().filter(|n| n.has_tag_name("flame"));
+    let count = flames.clone().count();
+    match flames.nth(index) {
+        Some(flame) => parse_flame(&flame, custom),
+        None if count == 0 => Err(FlameImportError::NoFlame),
+        None => Err(FlameImportError::IndexOutOfRange { index, count }),
+    }
+}
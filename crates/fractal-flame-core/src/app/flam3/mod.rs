@@ -0,0 +1,220 @@
+pub mod export;
+pub mod import;
+
+pub use export::to_flame_xml;
+pub use import::{parse_flame_at, parse_flames};
+
+use crate::app::genome::GenomeError;
+use crate::domain::Affine;
+
+/// `<xform>` attributes that are neither variations nor variation parameters.
+pub(crate) const XFORM_ATTRIBUTES: &[&str] = &[
+    "weight",
+    "color",
+    "color_speed",
+    "symmetry",
+    "animate",
+    "coefs",
+    "post",
+    "opacity",
+    "name",
+    "var_color",
+    "chaos",
+    "plotmode",
+    "visible",
+    "enabled",
+    "motion_frequency",
+    "motion_function",
+    "motion_offset",
+];
+
+/// flam3 stores coefficients column-major: `x' = c0 x + c2 y + c4`, `y' = c1 x + c3 y + c5`.
+pub(crate) fn affine_from_flam3_coefs(c: [f64; 6]) -> Affine {
+    Affine::new(c[0], c[2], c[4], c[1], c[3], c[5])
+}
+
+pub(crate) fn flam3_coefs_from_affine(affine: &Affine) -> [f64; 6] {
+    [affine.a, affine.d, affine.b, affine.e, affine.c, affine.f]
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FlameImportError {
+    #[error("Invalid XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("No <flame> element found")]
+    NoFlame,
+    #[error("Flame index {index} is out of range, the document has {count} flame(s)")]
+    IndexOutOfRange { index: usize, count: usize },
+    #[error("<{element}> is missing attribute '{attribute}'")]
+    MissingAttribute {
+        element: &'static str,
+        attribute: &'static str,
+    },
+    #[error("<{element}> has invalid {attribute}=\"{value}\"")]
+    InvalidAttribute {
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    #[error("Unsupported variation(s): {}", .names.join(", "))]
+    UnsupportedVariations { names: Vec<String> },
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+    #[error(transparent)]
+    Genome(#[from] GenomeError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::genome::GenomeLimits;
+    use crate::domain::{Background, Color, GenomeVariation};
+
+    const FLAME: &str = r#"<flames>
+  <flame name="sample" size="200 100" center="0.5 0" scale="25" rotate="30" gamma="4" brightness="8" quality="5" background="0 0.5 1">
+    <symmetry kind="3"/>
+    <xform weight="0.5" color="0" coefs="0.5 0 0 0.5 0.1 0.2" linear="1"/>
+    <xform weight="1" color="1" coefs="1 0 0 1 0 0" post="1 0 0 1 0.3 0" swirl="0.5" polar="0.5" blur="0"/>
+    <finalxform color="0" coefs="1 0 0 1 0 0" spherical="1"/>
+    <color index="0" rgb="255 0 0"/>
+    <color index="1" rgb="0 0 255"/>
+  </flame>
+  <flame size="10 10"><xform coefs="1 0 0 1 0 0" bubble="1"/></flame>
+</flames>"#;
+
+    #[test]
+    fn imports_flame() {
+        let genome = parse_flame_at(FLAME, 0, &[]).unwrap();
+        assert_eq!(genome.name.as_deref(), Some("sample"));
+        assert_eq!(genome.transforms.len(), 2);
+        assert_eq!(
+            genome.transforms[0].affine,
+            Affine::new(0.5, 0.0, 0.1, 0.0, 0.5, 0.2)
+        );
+        assert_eq!(genome.transforms[1].variations.len(), 2);
+        assert_eq!(
+            genome.transforms[1].post,
+            Some(Affine::new(1.0, 0.0, 0.3, 0.0, 1.0, 0.0))
+        );
+        assert!(genome.final_transform.is_some());
+        assert_eq!(genome.symmetry, 3);
+        assert_eq!(genome.camera.zoom, 0.5);
+        assert_eq!(genome.tone_mapping.brightness, 2.0);
+        assert_eq!(
+            genome.tone_mapping.background,
+            Background::Solid {
+                color: Color {
+                    r: 0,
+                    g: 128,
+                    b: 255
+                }
+            }
+        );
+        assert_eq!(genome.quality.samples, 1000);
+        assert_eq!(genome.palette.unwrap().colors.len(), 2);
+
+        match parse_flames(FLAME, &[]) {
+            Err(FlameImportError::UnsupportedVariations { names }) => assert_eq!(names, ["bubble"]),
+            other => panic!("unexpected {:?}", other.map(|g| g.len())),
+        }
+        assert!(matches!(
+            parse_flame_at(FLAME, 2, &[]),
+            Err(FlameImportError::IndexOutOfRange { index: 2, count: 2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_palette_data() {
+        let flame = |data: &str| {
+            format!(
+                r#"<flame size="10 10"><xform coefs="1 0 0 1 0 0" linear="1"/><palette count="2" format="RGB">{}</palette></flame>"#,
+                data
+            )
+        };
+        let genome = parse_flame_at(&flame("ff0000 0000ff"), 0, &[]).unwrap();
+        assert_eq!(genome.palette.unwrap().colors.len(), 2);
+        for data in [
+            "ff0000 0000f",
+            "ff0000 0000ffé",
+            "ff0000 00é0ff",
+            "ff0000",
+            "ff0000 0000fg",
+        ] {
+            assert!(
+                matches!(
+                    parse_flame_at(&flame(data), 0, &[]),
+                    Err(FlameImportError::InvalidAttribute { .. })
+                ),
+                "accepted {:?}",
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unbounded_quality_and_symmetry() {
+        let flame = |quality: &str, kind: &str| {
+            format!(
+                r#"<flame size="10 10" quality="{}"><symmetry kind="{}"/><xform coefs="1 0 0 1 0 0" linear="1"/></flame>"#,
+                quality, kind
+            )
+        };
+        for (quality, kind) in [("inf", "1"), ("NaN", "1"), ("0", "1"), ("5", "inf")] {
+            assert!(
+                matches!(
+                    parse_flame_at(&flame(quality, kind), 0, &[]),
+                    Err(FlameImportError::InvalidAttribute { .. })
+                ),
+                "accepted quality {:?}, kind {:?}",
+                quality,
+                kind
+            );
+        }
+        let limits = GenomeLimits::default();
+        for (quality, kind) in [("1e300", "1"), ("5", "1e300")] {
+            let genome = parse_flame_at(&flame(quality, kind), 0, &[]).unwrap();
+            assert!(genome.validate_within(&limits).is_err());
+        }
+        let mut genome = parse_flame_at(&flame("5", "3"), 0, &[]).unwrap();
+        assert!(genome.validate_within(&limits).is_ok());
+        genome.quality.samples = usize::MAX;
+        genome.quality.iter_per_sample = usize::MAX;
+        assert!(to_flame_xml(&genome).contains("quality=\""));
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let genome = parse_flame_at(FLAME, 0, &[]).unwrap();
+        let exported = parse_flame_at(&to_flame_xml(&genome), 0, &[]).unwrap();
+        assert_eq!(exported.transforms, genome.transforms);
+        assert_eq!(exported.final_transform, genome.final_transform);
+        assert_eq!(exported.camera, genome.camera);
+        assert_eq!(exported.tone_mapping, genome.tone_mapping);
+        assert_eq!(exported.symmetry, genome.symmetry);
+    }
+
+    #[test]
+    fn export_skips_names_that_are_not_attribute_names() {
+        let mut genome = parse_flame_at(FLAME, 0, &[]).unwrap();
+        let transform = &mut genome.transforms[0];
+        for id in [r#"x="1"><inject a"#, "two words", "ns:name", "1st", "coefs"] {
+            transform.variations.push(GenomeVariation {
+                id: id.to_string(),
+                weight: 1.0,
+            });
+        }
+        transform.parameters.insert("linear".to_string(), 2.0);
+        transform.parameters.insert("swirl_é".to_string(), 0.5);
+
+        let xml = to_flame_xml(&genome);
+        assert!(!xml.contains("inject"));
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let xform = document
+            .descendants()
+            .find(|n| n.has_tag_name("xform"))
+            .unwrap();
+        let names: Vec<&str> = xform.attributes().map(|a| a.name()).collect();
+        assert_eq!(names, ["weight", "color", "coefs", "linear", "swirl_é"]);
+        assert_eq!(xform.attribute("linear"), Some("1"));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::{GenerationError, GenerationStrategy, ValueRange, random_color};
+use crate::domain::{Affine, GenomeTransform, GenomeVariation};
+
+/// Apophysis' "random flame": unconstrained coefficients, and each xform blends a random
+/// subset of the requested variations instead of using exactly one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApophysisRandom {
+    /// Range of all six coefficients.
+    pub coefficients: ValueRange,
+    pub weight: ValueRange,
+    /// Chance that each of the other requested variations is also blended into an xform.
+    pub mix_probability: f64,
+}
+
+impl Default for ApophysisRandom {
+    fn default() -> Self {
+        Self {
+            coefficients: ValueRange::new(-1.0, 1.0),
+            weight: ValueRange::new(0.1, 1.0),
+            mix_probability: 0.25,
+        }
+    }
+}
+
+impl GenerationStrategy for ApophysisRandom {
+    fn generate(
+        &self,
+        variation_ids: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<GenomeTransform>, GenerationError> {
+        self.validate()?;
+        variation_ids
+            .iter()
+            .map(|id| {
+                let mut c = [0.0; 6];
+                for value in &mut c {
+                    *value = self.coefficients.sample(rng)?;
+                }
+
+                let mut variations = vec![GenomeVariation {
+                    id: id.clone(),
+                    weight: rng.random_range(0.5..=1.0),
+                }];
+                for other in variation_ids {
+                    if other != id
+                        && !variations.iter().any(|v| &v.id == other)
+                        && rng.random::<f64>() < self.mix_probability
+                    {
+                        variations.push(GenomeVariation {
+                            id: other.clone(),
+                            weight: rng.random_range(0.0..=0.5),
+                        });
+                    }
+                }
+
+                Ok(GenomeTransform {
+                    affine: Affine::new(c[0], c[1], c[2], c[3], c[4], c[5]),
+                    post: None,
+                    weight: self.weight.sample(rng)?,
+                    color: random_color(rng),
+                    color_index: None,
+                    variations,
+                    parameters: BTreeMap::new(),
+                })
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), GenerationError> {
+        self.coefficients.validate("coefficients")?;
+        self.weight.validate("weight")
+    }
+}
@@ -0,0 +1,82 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::{GenerationError, GenerationStrategy, ValueRange, random_color, single_variation};
+use crate::domain::{Affine, GenomeTransform};
+
+const MAX_ATTEMPTS: usize = 100_000;
+
+/// Uniformly random affines, rejection-sampled until they pass a contractivity test.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContractiveRandom {
+    /// Range of the linear part `a, b, d, e`.
+    pub linear: ValueRange,
+    /// Range of the translation `c, f`.
+    pub translation: ValueRange,
+    pub weight: ValueRange,
+}
+
+impl Default for ContractiveRandom {
+    fn default() -> Self {
+        Self {
+            linear: ValueRange::new(-1.5, 1.5),
+            translation: ValueRange::new(-2.0, 2.0),
+            weight: ValueRange::new(0.1, 1.0),
+        }
+    }
+}
+
+/// Columns shorter than 1 and a combined norm bounded by the determinant.
+pub fn is_contractive(affine: &Affine) -> bool {
+    let Affine { a, b, d, e, .. } = *affine;
+    let det = a * e - b * d;
+    (a * a + d * d) < 1.0
+        && (b * b + e * e) < 1.0
+        && (a * a + b * b + d * d + e * e) < 1.0 + det * det
+}
+
+impl ContractiveRandom {
+    fn search_affine(&self, rng: &mut dyn RngCore) -> Result<Affine, GenerationError> {
+        for _ in 0..MAX_ATTEMPTS {
+            let a = self.linear.sample(rng)?;
+            let b = self.linear.sample(rng)?;
+            let c = self.translation.sample(rng)?;
+            let d = self.linear.sample(rng)?;
+            let e = self.linear.sample(rng)?;
+            let f = self.translation.sample(rng)?;
+            let affine = Affine::new(a, b, c, d, e, f);
+            if is_contractive(&affine) {
+                return Ok(affine);
+            }
+        }
+        Err(GenerationError::NoContractiveAffine {
+            attempts: MAX_ATTEMPTS,
+        })
+    }
+}
+
+impl GenerationStrategy for ContractiveRandom {
+    fn generate(
+        &self,
+        variation_ids: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<GenomeTransform>, GenerationError> {
+        self.validate()?;
+        variation_ids
+            .iter()
+            .map(|id| {
+                let affine = self.search_affine(rng)?;
+                let color = random_color(rng);
+                let weight = self.weight.sample(rng)?;
+                Ok(single_variation(affine, weight, color, id))
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), GenerationError> {
+        self.linear.validate("linear")?;
+        self.translation.validate("translation")?;
+        self.weight.validate("weight")
+    }
+}
@@ -0,0 +1,254 @@
+pub mod apophysis;
+pub mod contractive;
+pub mod similarity;
+pub mod template;
+
+pub use apophysis::ApophysisRandom;
+pub use contractive::ContractiveRandom;
+pub use similarity::Similarity;
+pub use template::TemplateJitter;
+
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{Affine, Color, GenomeTransform, GenomeVariation};
+use crate::infra::random::{self, RangeError};
+
+/// Worker stream reserved for genome generation, so it never shares a sequence with rendering.
+const GENERATION_STREAM: u64 = u64::MAX;
+
+/// Produces the transforms of a new genome.
+pub trait GenerationStrategy: Send + Sync {
+    /// Transforms for `variation_ids`, normally one per id in order.
+    fn generate(
+        &self,
+        variation_ids: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<GenomeTransform>, GenerationError>;
+
+    /// Checks the settings, so a request with unusable ones can be turned away before any
+    /// generation happens.
+    fn validate(&self) -> Result<(), GenerationError>;
+}
+
+/// Inclusive range a random value is drawn from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ValueRange {
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Result<f64, RangeError> {
+        random::generate_f64_with(rng, self.min, self.max, true)
+    }
+
+    /// Finite, ordered, and not so wide that its span overflows.
+    pub fn validate(&self, setting: &'static str) -> Result<(), GenerationError> {
+        if self.min.is_finite() && self.max.is_finite() && (self.max - self.min).is_finite() {
+            if self.min > self.max {
+                return Err(RangeError::InvalidFloatRange {
+                    min: self.min,
+                    max: self.max,
+                }
+                .into());
+            }
+            return Ok(());
+        }
+        Err(GenerationError::InvalidSetting {
+            setting,
+            message: format!("range {}..={} must be finite", self.min, self.max),
+        })
+    }
+}
+
+/// A non-negative amount values are nudged by in either direction; the span of twice it must
+/// be finite too.
+pub(crate) fn validate_amount(setting: &'static str, amount: f64) -> Result<(), GenerationError> {
+    if amount.is_finite() && amount >= 0.0 && (2.0 * amount).is_finite() {
+        return Ok(());
+    }
+    Err(GenerationError::InvalidSetting {
+        setting,
+        message: format!("{} must be finite and non-negative", amount),
+    })
+}
+
+/// A strategy and its settings, selectable by `kind` in JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    ContractiveRandom(ContractiveRandom),
+    Similarity(Similarity),
+    Apophysis(ApophysisRandom),
+    Template(TemplateJitter),
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self::ContractiveRandom(ContractiveRandom::default())
+    }
+}
+
+impl StrategyConfig {
+    pub fn strategy(&self) -> &dyn GenerationStrategy {
+        match self {
+            Self::ContractiveRandom(s) => s,
+            Self::Similarity(s) => s,
+            Self::Apophysis(s) => s,
+            Self::Template(s) => s,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GenerationError> {
+        self.strategy().validate()
+    }
+
+    /// Whether the strategy needs variation ids; a template brings its own.
+    pub fn uses_variation_ids(&self) -> bool {
+        !matches!(self, Self::Template(_))
+    }
+}
+
+/// The generator for a genome with the given seed.
+pub fn generation_rng(seed: u64) -> StdRng {
+    random::worker_rng(Some(seed), GENERATION_STREAM)
+}
+
+pub(crate) fn random_color(rng: &mut dyn RngCore) -> Color {
+    Color {
+        r: rng.random(),
+        g: rng.random(),
+        b: rng.random(),
+    }
+}
+
+/// A transform applying one full-strength variation after `affine`.
+pub(crate) fn single_variation(
+    affine: Affine,
+    weight: f64,
+    color: Color,
+    variation_id: &str,
+) -> GenomeTransform {
+    GenomeTransform {
+        affine,
+        post: None,
+        weight,
+        color,
+        color_index: None,
+        variations: vec![GenomeVariation {
+            id: variation_id.to_string(),
+            weight: 1.0,
+        }],
+        parameters: BTreeMap::new(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenerationError {
+    #[error("Invalid range: {0}")]
+    Range(#[from] RangeError),
+    #[error("No affine satisfying the contractivity test after {attempts} attempts")]
+    NoContractiveAffine { attempts: usize },
+    #[error("Template has no transforms")]
+    EmptyTemplate,
+    #[error("Invalid strategy setting '{setting}': {message}")]
+    InvalidSetting {
+        setting: &'static str,
+        message: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies_are_reproducible_from_seed() {
+        let ids = vec!["linear".to_string(), "swirl".to_string()];
+        for config in [
+            StrategyConfig::default(),
+            StrategyConfig::Similarity(Similarity::default()),
+            StrategyConfig::Apophysis(ApophysisRandom::default()),
+        ] {
+            let a = config
+                .strategy()
+                .generate(&ids, &mut generation_rng(7))
+                .unwrap();
+            let b = config
+                .strategy()
+                .generate(&ids, &mut generation_rng(7))
+                .unwrap();
+            assert_eq!(a, b);
+            assert_eq!(a.len(), 2);
+            assert_eq!(a[1].variations[0].id, "swirl");
+        }
+
+        let template = StrategyConfig::Template(TemplateJitter {
+            transforms: StrategyConfig::default()
+                .strategy()
+                .generate(&ids, &mut generation_rng(1))
+                .unwrap(),
+            ..TemplateJitter::default()
+        });
+        let jittered = template
+            .strategy()
+            .generate(&[], &mut generation_rng(2))
+            .unwrap();
+        assert_eq!(jittered.len(), 2);
+
+        let json = r#"{"kind": "similarity", "scale": {"min": 0.5, "max": 0.6}}"#;
+        let parsed: StrategyConfig = serde_json::from_str(json).unwrap();
+        assert!(matches!(parsed, StrategyConfig::Similarity(s) if s.scale.max == 0.6));
+    }
+
+    #[test]
+    fn rejects_unusable_settings_before_generating() {
+        let ids = vec!["linear".to_string()];
+        let template = |jitter, weight_jitter| {
+            StrategyConfig::Template(TemplateJitter {
+                transforms: StrategyConfig::default()
+                    .strategy()
+                    .generate(&ids, &mut generation_rng(1))
+                    .unwrap(),
+                jitter,
+                weight_jitter,
+                ..TemplateJitter::default()
+            })
+        };
+        for config in [
+            template(f64::NAN, 0.0),
+            template(f64::INFINITY, 0.0),
+            template(0.0, 1e308),
+            template(-0.1, 0.0),
+            StrategyConfig::ContractiveRandom(ContractiveRandom {
+                linear: ValueRange::new(-1e308, 1e308),
+                ..ContractiveRandom::default()
+            }),
+            StrategyConfig::Similarity(Similarity {
+                scale: ValueRange::new(0.5, f64::NAN),
+                ..Similarity::default()
+            }),
+            StrategyConfig::Apophysis(ApophysisRandom {
+                weight: ValueRange::new(1.0, 0.0),
+                ..ApophysisRandom::default()
+            }),
+        ] {
+            assert!(config.validate().is_err(), "accepted {:?}", config);
+            assert!(
+                config
+                    .strategy()
+                    .generate(&ids, &mut generation_rng(1))
+                    .is_err()
+            );
+        }
+        assert!(template(0.1, 0.2).validate().is_ok());
+    }
+}
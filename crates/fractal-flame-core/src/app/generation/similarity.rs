@@ -0,0 +1,73 @@
+use std::f64::consts::TAU;
+
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::{GenerationError, GenerationStrategy, ValueRange, random_color, single_variation};
+use crate::domain::{Affine, GenomeTransform};
+
+/// Rotation + uniform scale + translation, which keeps shapes undistorted (no shear).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Similarity {
+    /// Scale factor; values below 1 keep the map contractive.
+    pub scale: ValueRange,
+    /// Rotation in radians.
+    pub rotation: ValueRange,
+    pub translation: ValueRange,
+    pub weight: ValueRange,
+    /// Probability of mirroring the transform.
+    pub reflection_probability: f64,
+}
+
+impl Default for Similarity {
+    fn default() -> Self {
+        Self {
+            scale: ValueRange::new(0.3, 0.9),
+            rotation: ValueRange::new(0.0, TAU),
+            translation: ValueRange::new(-1.0, 1.0),
+            weight: ValueRange::new(0.1, 1.0),
+            reflection_probability: 0.0,
+        }
+    }
+}
+
+impl GenerationStrategy for Similarity {
+    fn generate(
+        &self,
+        variation_ids: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<GenomeTransform>, GenerationError> {
+        self.validate()?;
+        variation_ids
+            .iter()
+            .map(|id| {
+                let scale = self.scale.sample(rng)?;
+                let (sin, cos) = self.rotation.sample(rng)?.sin_cos();
+                let mirror = if rng.random::<f64>() < self.reflection_probability {
+                    -1.0
+                } else {
+                    1.0
+                };
+                let affine = Affine::new(
+                    scale * cos,
+                    -scale * sin * mirror,
+                    self.translation.sample(rng)?,
+                    scale * sin,
+                    scale * cos * mirror,
+                    self.translation.sample(rng)?,
+                );
+                let color = random_color(rng);
+                let weight = self.weight.sample(rng)?;
+                Ok(single_variation(affine, weight, color, id))
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), GenerationError> {
+        self.scale.validate("scale")?;
+        self.rotation.validate("rotation")?;
+        self.translation.validate("translation")?;
+        self.weight.validate("weight")
+    }
+}
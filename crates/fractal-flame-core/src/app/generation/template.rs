@@ -0,0 +1,88 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::{GenerationError, GenerationStrategy, validate_amount};
+use crate::domain::{Affine, Color, GenomeTransform};
+
+/// Copies of a template's transforms with every coefficient nudged by up to `jitter`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateJitter {
+    pub transforms: Vec<GenomeTransform>,
+    /// Maximum absolute change of each affine coefficient.
+    pub jitter: f64,
+    /// Maximum relative change of each transform weight, e.g. 0.2 for ±20%.
+    pub weight_jitter: f64,
+    /// Maximum change of each color channel.
+    pub color_jitter: u8,
+}
+
+pub(crate) fn jitter_affine(affine: &Affine, amount: f64, rng: &mut dyn RngCore) -> Affine {
+    let mut nudge = |v: f64| {
+        if amount > 0.0 {
+            v + rng.random_range(-amount..=amount)
+        } else {
+            v
+        }
+    };
+    Affine::new(
+        nudge(affine.a),
+        nudge(affine.b),
+        nudge(affine.c),
+        nudge(affine.d),
+        nudge(affine.e),
+        nudge(affine.f),
+    )
+}
+
+fn jitter_color(color: Color, amount: u8, rng: &mut dyn RngCore) -> Color {
+    if amount == 0 {
+        return color;
+    }
+    let amount = amount as i16;
+    let mut nudge = |c: u8| (c as i16 + rng.random_range(-amount..=amount)).clamp(0, 255) as u8;
+    Color {
+        r: nudge(color.r),
+        g: nudge(color.g),
+        b: nudge(color.b),
+    }
+}
+
+impl GenerationStrategy for TemplateJitter {
+    /// Ignores `variation_ids`: the template's own variations are kept.
+    fn generate(
+        &self,
+        _variation_ids: &[String],
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<GenomeTransform>, GenerationError> {
+        self.validate()?;
+        if self.transforms.is_empty() {
+            return Err(GenerationError::EmptyTemplate);
+        }
+        Ok(self
+            .transforms
+            .iter()
+            .map(|t| {
+                let weight = if self.weight_jitter > 0.0 {
+                    t.weight * (1.0 + rng.random_range(-self.weight_jitter..=self.weight_jitter))
+                } else {
+                    t.weight
+                };
+                GenomeTransform {
+                    affine: jitter_affine(&t.affine, self.jitter, rng),
+                    post: t.post.map(|p| jitter_affine(&p, self.jitter, rng)),
+                    weight: weight.max(0.0),
+                    color: jitter_color(t.color, self.color_jitter, rng),
+                    ..t.clone()
+                }
+            })
+            .collect())
+    }
+
+    /// An empty template is not an error here: the request may name a job to take the
+    /// transforms from.
+    fn validate(&self) -> Result<(), GenerationError> {
+        validate_amount("jitter", self.jitter)?;
+        validate_amount("weight_jitter", self.weight_jitter)
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::app::expression::ExpressionError;
+use crate::app::transformations::base_affine_transformation::BaseAffineTransformation;
+use crate::app::transformations::custom::{CustomVariation, CustomVariationDefinition};
+use crate::app::transformations::registry;
+use crate::app::transformations::xform::XForm;
+use crate::domain::transformation::Transformation;
+use crate::domain::{
+    Affine, Camera, Color, CustomVariationSpec, FlameGenome, GenomeTransform, Palette, Rect,
+};
+
+pub type CustomVariations = HashMap<String, Arc<CustomVariationDefinition>>;
+
+/// Most work one genome may ask for, so a single render cannot hold a worker indefinitely.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GenomeLimits {
+    pub max_samples: usize,
+    pub max_iter_per_sample: usize,
+    pub max_symmetry: usize,
+}
+
+impl Default for GenomeLimits {
+    fn default() -> Self {
+        Self {
+            max_samples: 1_000_000_000,
+            max_iter_per_sample: 10_000,
+            max_symmetry: 64,
+        }
+    }
+}
+
+impl FlameGenome {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// The region of the plane mapped onto the canvas.
+    pub fn world(&self) -> Rect {
+        let aspect = self.camera.width as f64 / self.camera.height as f64;
+        let height = 2.0 / self.camera.zoom;
+        let width = height * aspect;
+        Rect::new(
+            self.camera.center_x - width / 2.0,
+            self.camera.center_y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    /// Changes the output size, keeping the framing (the visible height) and the sample density
+    /// per pixel.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let old_area = (self.camera.width as f64 * self.camera.height as f64).max(1.0);
+        let new_area = width as f64 * height as f64;
+        self.quality.samples =
+            ((self.quality.samples as f64 * new_area / old_area).round() as usize).max(1);
+        self.camera.width = width;
+        self.camera.height = height;
+    }
+
+    pub fn validate(&self) -> Result<(), GenomeError> {
+        if self.transforms.is_empty() {
+            return Err(GenomeError::NoTransforms);
+        }
+        for (i, t) in self.transforms.iter().enumerate() {
+            if !t.weight.is_finite() || t.weight < 0.0 {
+                return Err(GenomeError::Invalid(format!(
+                    "transform {} has invalid weight {}",
+                    i, t.weight
+                )));
+            }
+            validate_transform(t, &format!("transform {}", i))?;
+        }
+        if let Some(ref t) = self.final_transform {
+            validate_transform(t, "final transform")?;
+        }
+        for spec in &self.custom_variations {
+            if spec.parameters.values().any(|v| !v.is_finite()) {
+                return Err(GenomeError::Invalid(format!(
+                    "custom variation '{}' has a parameter that is not finite",
+                    spec.id
+                )));
+            }
+        }
+        if self.transforms.iter().map(|t| t.weight).sum::<f64>() <= 0.0 {
+            return Err(GenomeError::Invalid(
+                "transform weights sum to zero".to_string(),
+            ));
+        }
+        let sides = 1..=Camera::MAX_DIMENSION;
+        if !sides.contains(&self.camera.width) || !sides.contains(&self.camera.height) {
+            return Err(GenomeError::Invalid(format!(
+                "camera size must be between 1 and {}",
+                Camera::MAX_DIMENSION
+            )));
+        }
+        if !(self.camera.zoom.is_finite() && self.camera.zoom > 0.0) {
+            return Err(GenomeError::Invalid(
+                "camera zoom must be positive".to_string(),
+            ));
+        }
+        if ![
+            self.camera.center_x,
+            self.camera.center_y,
+            self.camera.rotate,
+        ]
+        .iter()
+        .all(|v| v.is_finite())
+        {
+            return Err(GenomeError::Invalid(
+                "camera center and rotation must be finite".to_string(),
+            ));
+        }
+        if self.symmetry == 0 {
+            return Err(GenomeError::Invalid(
+                "symmetry must be at least 1".to_string(),
+            ));
+        }
+        if !(self.tone_mapping.gamma.is_finite() && self.tone_mapping.gamma > 0.0) {
+            return Err(GenomeError::Invalid("gamma must be positive".to_string()));
+        }
+        if !(self.tone_mapping.brightness.is_finite() && self.tone_mapping.brightness > 0.0) {
+            return Err(GenomeError::Invalid(
+                "brightness must be positive".to_string(),
+            ));
+        }
+        if self.quality.samples == 0 || self.quality.iter_per_sample == 0 {
+            return Err(GenomeError::Invalid(
+                "samples and iterations per sample must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// [`FlameGenome::validate`], and that the genome asks for no more work than `limits`.
+    pub fn validate_within(&self, limits: &GenomeLimits) -> Result<(), GenomeError> {
+        self.validate()?;
+        if self.quality.samples > limits.max_samples {
+            return Err(GenomeError::Invalid(format!(
+                "samples must be at most {}",
+                limits.max_samples
+            )));
+        }
+        if self.quality.iter_per_sample > limits.max_iter_per_sample {
+            return Err(GenomeError::Invalid(format!(
+                "iterations per sample must be at most {}",
+                limits.max_iter_per_sample
+            )));
+        }
+        if self.symmetry > limits.max_symmetry {
+            return Err(GenomeError::Invalid(format!(
+                "symmetry must be at most {}",
+                limits.max_symmetry
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compiles the genome's own custom variations.
+    pub fn custom_definitions(&self) -> Result<CustomVariations, GenomeError> {
+        self.custom_variations
+            .iter()
+            .map(|spec| {
+                spec.compile()
+                    .map(|d| (spec.id.clone(), Arc::new(d)))
+                    .map_err(|source| GenomeError::CustomVariation {
+                        id: spec.id.clone(),
+                        source,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn build_transformations(
+        &self,
+    ) -> Result<Vec<Box<dyn Transformation + Send + Sync>>, GenomeError> {
+        self.validate()?;
+        let custom = self.custom_definitions()?;
+        self.transforms
+            .iter()
+            .map(|t| build_transform(t, self.palette.as_ref(), &custom))
+            .collect()
+    }
+
+    pub fn build_final_transformation(
+        &self,
+    ) -> Result<Option<Box<dyn Transformation + Send + Sync>>, GenomeError> {
+        let Some(ref t) = self.final_transform else {
+            return Ok(None);
+        };
+        let custom = self.custom_definitions()?;
+        build_transform(t, self.palette.as_ref(), &custom).map(Some)
+    }
+}
+
+impl CustomVariationSpec {
+    pub fn compile(&self) -> Result<CustomVariationDefinition, ExpressionError> {
+        CustomVariationDefinition::new(
+            self.id.clone(),
+            self.name.clone(),
+            self.x.clone(),
+            self.y.clone(),
+            self.parameters.clone(),
+        )
+    }
+}
+
+impl GenomeTransform {
+    pub fn resolved_color(&self, palette: Option<&Palette>) -> Color {
+        match (self.color_index, palette) {
+            (Some(index), Some(palette)) => palette.color_at(index).unwrap_or(self.color),
+            _ => self.color,
+        }
+    }
+}
+
+fn base_affine(affine: &Affine, weight: f64, color: Color) -> BaseAffineTransformation {
+    BaseAffineTransformation::new(
+        weight, color, affine.a, affine.b, affine.c, affine.d, affine.e, affine.f,
+    )
+}
+
+/// Resolves a variation id against built-ins and then custom variations. Custom variation
+/// parameters can be overridden per transform with `{id}_{parameter}` keys.
+fn create_variation(
+    id: &str,
+    base: BaseAffineTransformation,
+    transform: &GenomeTransform,
+    custom: &CustomVariations,
+) -> Result<Box<dyn Transformation + Send + Sync>, GenomeError> {
+    if registry::is_builtin(id) {
+        return registry::create_variation(id, base)
+            .ok_or_else(|| GenomeError::UnknownVariation(id.to_string()));
+    }
+
+    let definition = custom
+        .get(id)
+        .ok_or_else(|| GenomeError::UnknownVariation(id.to_string()))?;
+
+    let prefix = format!("{}_", id);
+    let overrides: Vec<(&str, f64)> = transform
+        .parameters
+        .iter()
+        .filter_map(|(k, v)| k.strip_prefix(&prefix).map(|name| (name, *v)))
+        .collect();
+    if overrides.is_empty() {
+        return Ok(Box::new(CustomVariation::new(base, definition.clone())));
+    }
+
+    let mut parameters = definition.parameters.clone();
+    for (name, value) in overrides {
+        parameters.insert(name.to_string(), value);
+    }
+    let specialised = CustomVariationDefinition::new(
+        definition.id.clone(),
+        definition.name.clone(),
+        definition.x_source.clone(),
+        definition.y_source.clone(),
+        parameters,
+    )
+    .map_err(|source| GenomeError::CustomVariation {
+        id: id.to_string(),
+        source,
+    })?;
+    Ok(Box::new(CustomVariation::new(base, Arc::new(specialised))))
+}
+
+pub fn build_transform(
+    transform: &GenomeTransform,
+    palette: Option<&Palette>,
+    custom: &CustomVariations,
+) -> Result<Box<dyn Transformation + Send + Sync>, GenomeError> {
+    let color = transform.resolved_color(palette);
+    let base = base_affine(&transform.affine, transform.weight, color);
+
+    // A single full-strength variation is exactly the classic one-variation transform.
+    if let [only] = transform.variations.as_slice()
+        && only.weight == 1.0
+        && transform.post.is_none()
+    {
+        return create_variation(&only.id, base, transform, custom);
+    }
+
+    let identity = base_affine(&Affine::IDENTITY, transform.weight, color);
+    let variations = transform
+        .variations
+        .iter()
+        .map(|v| {
+            create_variation(&v.id, identity.clone(), transform, custom).map(|t| (v.weight, t))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let xform = XForm::new(base, variations);
+    Ok(Box::new(match transform.post {
+        Some(ref post) => xform.with_post(base_affine(post, transform.weight, color)),
+        None => xform,
+    }))
+}
+
+/// Checks what every transform needs, naming it `name` in errors.
+fn validate_transform(t: &GenomeTransform, name: &str) -> Result<(), GenomeError> {
+    if t.variations.is_empty() {
+        return Err(GenomeError::Invalid(format!("{} has no variations", name)));
+    }
+    let affines = std::iter::once(&t.affine).chain(t.post.iter());
+    let coefficients = affines.flat_map(|a| [a.a, a.b, a.c, a.d, a.e, a.f]);
+    let finite = coefficients
+        .chain(t.variations.iter().map(|v| v.weight))
+        .chain(t.parameters.values().copied())
+        .chain(t.color_index)
+        .all(f64::is_finite);
+    if !finite {
+        return Err(GenomeError::Invalid(format!(
+            "{} has a coefficient, weight or parameter that is not finite",
+            name
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenomeError {
+    #[error("Genome has no transforms")]
+    NoTransforms,
+    #[error("Unknown variation id: {0}")]
+    UnknownVariation(String),
+    #[error("Custom variation '{id}' is invalid: {source}")]
+    CustomVariation { id: String, source: ExpressionError },
+    #[error("Invalid genome: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::renderer::Renderer;
+    use crate::domain::{Camera, GenomeVariation, Quality, ToneMapping};
+    use std::collections::BTreeMap;
+
+    fn sample_genome() -> FlameGenome {
+        FlameGenome {
+            name: Some("test".to_string()),
+            transforms: vec![
+                GenomeTransform {
+                    affine: Affine::new(0.5, 0.0, 0.25, 0.0, 0.5, 0.0),
+                    post: None,
+                    weight: 1.0,
+                    color: Color { r: 255, g: 0, b: 0 },
+                    color_index: None,
+                    variations: vec![GenomeVariation {
+                        id: "swirl".to_string(),
+                        weight: 1.0,
+                    }],
+                    parameters: BTreeMap::new(),
+                },
+                GenomeTransform {
+                    affine: Affine::new(0.4, -0.2, 0.0, 0.2, 0.4, 0.1),
+                    post: None,
+                    weight: 0.5,
+                    color: Color { r: 0, g: 0, b: 255 },
+                    color_index: Some(1.0),
+                    variations: vec![
+                        GenomeVariation {
+                            id: "linear".to_string(),
+                            weight: 0.7,
+                        },
+                        GenomeVariation {
+                            id: "wave".to_string(),
+                            weight: 0.3,
+                        },
+                    ],
+                    parameters: BTreeMap::from([("wave_k".to_string(), 3.0)]),
+                },
+            ],
+            final_transform: None,
+            custom_variations: vec![CustomVariationSpec {
+                id: "wave".to_string(),
+                name: "Wave".to_string(),
+                x: "x + sin(k * y)".to_string(),
+                y: "y".to_string(),
+                parameters: BTreeMap::from([("k".to_string(), 1.0)]),
+            }],
+            symmetry: 2,
+            camera: Camera {
+                width: 32,
+                height: 16,
+                center_x: 0.0,
+                center_y: 0.0,
+                zoom: 1.0,
+                rotate: 0.0,
+            },
+            palette: Some(Palette {
+                colors: vec![Color::default(), Color { r: 0, g: 255, b: 0 }],
+            }),
+            tone_mapping: ToneMapping::default(),
+            quality: Quality {
+                samples: 200,
+                iter_per_sample: 10,
+            },
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let genome = sample_genome();
+        let json = genome.to_json().unwrap();
+        assert_eq!(FlameGenome::from_json(&json).unwrap(), genome);
+    }
+
+    #[test]
+    fn builds_a_renderer() {
+        let renderer = Renderer::from_genome(&sample_genome(), 2).unwrap();
+        assert_eq!(renderer.transformations.len(), 2);
+        assert_eq!(renderer.transformations[0].get_id(), "swirl");
+        assert_eq!(renderer.transformations[1].get_id(), "xform");
+        assert_eq!(
+            *renderer.transformations[1].color(),
+            Color { r: 0, g: 255, b: 0 }
+        );
+        assert_eq!(renderer.world.width, 4.0);
+        renderer.render().unwrap();
+
+        let mut unknown = sample_genome();
+        unknown.transforms[0].variations[0].id = "nope".to_string();
+        assert!(matches!(
+            Renderer::from_genome(&unknown, 1),
+            Err(GenomeError::UnknownVariation(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_degenerate_values() {
+        let cases: [fn(&mut FlameGenome); 7] = [
+            |g| g.quality.samples = 0,
+            |g| g.quality.iter_per_sample = 0,
+            |g| g.tone_mapping.brightness = f64::NAN,
+            |g| g.transforms[0].affine.c = f64::INFINITY,
+            |g| g.transforms[1].post = Some(Affine::new(1.0, 0.0, f64::NAN, 0.0, 1.0, 0.0)),
+            |g| {
+                g.transforms[1]
+                    .parameters
+                    .insert("wave_k".to_string(), f64::NAN);
+            },
+            |g| g.camera.rotate = f64::NEG_INFINITY,
+        ];
+        for break_genome in cases {
+            let mut genome = sample_genome();
+            break_genome(&mut genome);
+            assert!(matches!(genome.validate(), Err(GenomeError::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let limits = GenomeLimits {
+            max_samples: 1000,
+            max_iter_per_sample: 50,
+            max_symmetry: 4,
+        };
+        assert!(sample_genome().validate_within(&limits).is_ok());
+        let cases: [fn(&mut FlameGenome); 3] = [
+            |g| g.quality.samples = 1001,
+            |g| g.quality.iter_per_sample = usize::MAX,
+            |g| g.symmetry = 1_000_000_000_000_000_000,
+        ];
+        for break_genome in cases {
+            let mut genome = sample_genome();
+            break_genome(&mut genome);
+            assert!(genome.validate().is_ok());
+            assert!(matches!(
+                genome.validate_within(&limits),
+                Err(GenomeError::Invalid(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::domain::{Color, FractalImage};
+
+/// Bytes per pixel in a raw histogram: red, green, blue, then the hit count.
+pub const RECORD_BYTES: usize = 7;
+/// Bytes before the pixels: width, then height.
+const HEADER_BYTES: usize = 8;
+
+pub fn encode_record(color: Color, hit_count: i32, out: &mut Vec<u8>) {
+    out.extend([color.r, color.g, color.b]);
+    out.extend((hit_count.max(0) as u32).to_le_bytes());
+}
+
+pub fn decode_record(record: &[u8]) -> (Color, i32) {
+    let color = Color {
+        r: record[0],
+        g: record[1],
+        b: record[2],
+    };
+    let hit_count = u32::from_le_bytes([record[3], record[4], record[5], record[6]]);
+    (color, hit_count.min(i32::MAX as u32) as i32)
+}
+
+/// The canvas before tone mapping, so renders of the same genome made elsewhere can be summed
+/// with [`merge_histogram`].
+pub fn histogram_to_bytes(canvas: &FractalImage) -> Result<Vec<u8>, HistogramError> {
+    let mut out = Vec::with_capacity(HEADER_BYTES + canvas.data.len() * RECORD_BYTES);
+    out.extend((canvas.width as u32).to_le_bytes());
+    out.extend((canvas.height as u32).to_le_bytes());
+    for pixel in &canvas.data {
+        let data = pixel.read().map_err(|_| HistogramError::PixelAccess)?;
+        encode_record(data.color, data.hit_count, &mut out);
+    }
+    Ok(out)
+}
+
+/// Adds a histogram from [`histogram_to_bytes`] into `canvas`. Hit counts add up; colors are
+/// averaged weighted by how many hits each side contributed.
+pub fn merge_histogram(canvas: &FractalImage, bytes: &[u8]) -> Result<(), HistogramError> {
+    if bytes.len() < HEADER_BYTES {
+        return Err(HistogramError::Truncated);
+    }
+    let (header, records) = bytes.split_at(HEADER_BYTES);
+    let width = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if (width, height) != (canvas.width, canvas.height) {
+        return Err(HistogramError::SizeMismatch {
+            expected: (canvas.width, canvas.height),
+            actual: (width, height),
+        });
+    }
+    if records.len() != canvas.data.len() * RECORD_BYTES {
+        return Err(HistogramError::Truncated);
+    }
+
+    for (pixel, record) in canvas.data.iter().zip(records.chunks_exact(RECORD_BYTES)) {
+        let (color, hit_count) = decode_record(record);
+        if hit_count == 0 {
+            continue;
+        }
+        let mut data = pixel.write().map_err(|_| HistogramError::PixelAccess)?;
+        let total = data.hit_count as u64 + hit_count as u64;
+        let mix = |a: u8, b: u8| {
+            ((a as u64 * data.hit_count as u64 + b as u64 * hit_count as u64) / total) as u8
+        };
+        data.color = Color {
+            r: mix(data.color.r, color.r),
+            g: mix(data.color.g, color.g),
+            b: mix(data.color.b, color.b),
+        };
+        data.hit_count = total.min(i32::MAX as u64) as i32;
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistogramError {
+    #[error("Failed to access pixel data")]
+    PixelAccess,
+    #[error("Histogram is truncated")]
+    Truncated,
+    #[error("Histogram is {actual:?} pixels, expected {expected:?}")]
+    SizeMismatch {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_weighted_by_hits() {
+        let paint = |canvas: &FractalImage, index: usize, r: u8, hit_count: i32| {
+            let mut data = canvas.data[index].write().unwrap();
+            data.color = Color { r, g: 0, b: 0 };
+            data.hit_count = hit_count;
+        };
+        let canvas = FractalImage::new(2, 2);
+        paint(&canvas, 0, 100, 1);
+        let shard = FractalImage::new(2, 2);
+        paint(&shard, 0, 200, 3);
+        paint(&shard, 3, 50, 2);
+
+        merge_histogram(&canvas, &histogram_to_bytes(&shard).unwrap()).unwrap();
+
+        let pixel = |index: usize| {
+            let data = canvas.data[index].read().unwrap();
+            (data.color.r, data.hit_count)
+        };
+        assert_eq!(pixel(0), (175, 4));
+        assert_eq!(pixel(1), (0, 0));
+        assert_eq!(pixel(3), (50, 2));
+
+        let other_size = histogram_to_bytes(&FractalImage::new(3, 2)).unwrap();
+        assert!(matches!(
+            merge_histogram(&canvas, &other_size),
+            Err(HistogramError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            merge_histogram(&canvas, &other_size[..4]),
+            Err(HistogramError::Truncated)
+        ));
+    }
+}
@@ -1,12 +1,23 @@
-use crate::domain::FractalImage;
+pub mod animated;
+pub mod formats;
+pub mod rows;
+
+pub use animated::{AnimationFormat, AnimationSettings, encode_animation, rgba_from_png};
+pub use formats::{ExportFormat, export_image};
+pub use rows::{RowEncoder, stream_image, validate_streamable};
+
+use crate::domain::{Background, FlameGenome, FractalImage, ToneMapping};
 use image::{
     ColorType, ImageEncoder,
     codecs::png::{CompressionType, FilterType, PngEncoder},
 };
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
-/// Converts FractalImage to PNG bytes. Expects gamma correction already applied.
-pub fn fractal_image_to_png(canvas: &FractalImage) -> Result<Vec<u8>, ImageExportError> {
+/// iTXt keyword holding the genome JSON, which includes the seed and render settings.
+pub const PNG_GENOME_KEYWORD: &str = "fractal-flame:genome";
+const PNG_SOFTWARE: &str = "fractal-flame";
+
+fn rgba_bytes(canvas: &FractalImage) -> Result<Vec<u8>, ImageExportError> {
     let mut raw = Vec::with_capacity(canvas.width * canvas.height * 4);
 
     for y in 0..canvas.height {
@@ -22,6 +33,47 @@ pub fn fractal_image_to_png(canvas: &FractalImage) -> Result<Vec<u8>, ImageExpor
             }
         }
     }
+    Ok(raw)
+}
+
+/// Puts a flame pixel, premultiplied by `alpha`, over the background at pixel `(x, y)`.
+/// Returns premultiplied RGBA with channels in `0.0..=1.0`, though color exceeds one where
+/// brightness pushes it.
+fn over_background(
+    rgb: [f32; 3],
+    alpha: f32,
+    background: &Background,
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+) -> [f32; 4] {
+    let u = (x as f64 + 0.5) / width as f64;
+    let v = (y as f64 + 0.5) / height as f64;
+    match background.color_at(u, v) {
+        None => [rgb[0], rgb[1], rgb[2], alpha],
+        Some(color) => {
+            let rest = 1.0 - alpha;
+            [
+                rgb[0] + rest * color.r as f32 / 255.0,
+                rgb[1] + rest * color.g as f32 / 255.0,
+                rgb[2] + rest * color.b as f32 / 255.0,
+                1.0,
+            ]
+        }
+    }
+}
+
+/// Straight-alpha RGBA for formats that do not premultiply.
+fn unpremultiply([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    if a <= 0.0 {
+        [0.0; 4]
+    } else {
+        [r / a, g / a, b / a, a]
+    }
+}
+
+/// Converts FractalImage to PNG bytes. Expects gamma correction already applied.
+pub fn fractal_image_to_png(canvas: &FractalImage) -> Result<Vec<u8>, ImageExportError> {
+    let raw = rgba_bytes(canvas)?;
 
     let mut buf = Cursor::new(Vec::new());
     let encoder = PngEncoder::new(&mut buf);
@@ -37,11 +89,11 @@ pub fn fractal_image_to_png(canvas: &FractalImage) -> Result<Vec<u8>, ImageExpor
     Ok(buf.into_inner())
 }
 
-/// Snapshot of the canvas mid-render: reads pixels non-destructively, applies gamma on the fly.
+/// Snapshot of the canvas mid-render: reads pixels non-destructively, tone maps on the fly.
 /// Optimised for speed: single lock-acquisition pass + fast PNG compression.
 pub fn fractal_image_to_intermediate_png(
     canvas: &FractalImage,
-    gamma: f64,
+    tone_mapping: &ToneMapping,
 ) -> Result<Vec<u8>, ImageExportError> {
     let total = canvas.width * canvas.height;
 
@@ -66,21 +118,25 @@ pub fn fractal_image_to_intermediate_png(
         }
     }
 
-    let inv_gamma = 1.0 / gamma;
+    let inv_gamma = 1.0 / tone_mapping.gamma;
+    let size = (canvas.width, canvas.height);
     let mut raw = Vec::with_capacity(total * 4);
-    for &(r, g, b, hc) in &pixel_buf {
-        if hc > 0 && max_normal > 0.0 {
-            let normal = (hc as f64).log10() / max_normal;
-            let gf = normal.powf(inv_gamma);
-            raw.push(((r as f64) * gf) as u8);
-            raw.push(((g as f64) * gf) as u8);
-            raw.push(((b as f64) * gf) as u8);
+    for (i, &(r, g, b, hc)) in pixel_buf.iter().enumerate() {
+        let gf = if hc > 0 && max_normal > 0.0 {
+            (((hc as f64).log10() / max_normal).powf(inv_gamma) * tone_mapping.brightness) as f32
         } else {
-            raw.push(0);
-            raw.push(0);
-            raw.push(0);
-        }
-        raw.push(255);
+            0.0
+        };
+        let rgb = [r, g, b].map(|c| c as f32 / 255.0 * gf);
+        let position = (i % canvas.width, i / canvas.width);
+        let pixel = unpremultiply(over_background(
+            rgb,
+            gf.min(1.0),
+            &tone_mapping.background,
+            position,
+            size,
+        ));
+        raw.extend(pixel.map(|c| (c * 255.0).clamp(0.0, 255.0) as u8));
     }
 
     let mut buf = Cursor::new(Vec::new());
@@ -98,10 +154,353 @@ pub fn fractal_image_to_intermediate_png(
     Ok(buf.into_inner())
 }
 
+/// RGBA PNG encoder through the `png` crate, which can write text chunks.
+fn png_encoder<W: Write>(
+    out: W,
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    genome: Option<&FlameGenome>,
+) -> Result<png::Encoder<'static, W>, ImageExportError> {
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(depth);
+    encoder.add_text_chunk("Software".to_string(), PNG_SOFTWARE.to_string())?;
+    if let Some(genome) = genome {
+        let json = serde_json::to_string(genome).map_err(ImageExportError::Metadata)?;
+        if let Some(ref name) = genome.name {
+            encoder.add_itxt_chunk("Title".to_string(), name.clone())?;
+        }
+        encoder.add_itxt_chunk(PNG_GENOME_KEYWORD.to_string(), json)?;
+    }
+    Ok(encoder)
+}
+
+/// PNG with the genome in its text chunks; `raw` is RGBA at `depth`.
+fn encode_png(
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    raw: &[u8],
+    genome: Option<&FlameGenome>,
+) -> Result<Vec<u8>, ImageExportError> {
+    let mut buf = Vec::new();
+    let mut writer = png_encoder(&mut buf, width, height, depth, genome)?.write_header()?;
+    writer.write_image_data(raw)?;
+    writer.finish()?;
+
+    Ok(buf)
+}
+
+/// Like [`fractal_image_to_png`], with the genome embedded so the image carries its own recipe.
+pub fn fractal_image_to_png_with_genome(
+    canvas: &FractalImage,
+    genome: &FlameGenome,
+) -> Result<Vec<u8>, ImageExportError> {
+    let raw = rgba_bytes(canvas)?;
+    encode_png(
+        canvas.width as u32,
+        canvas.height as u32,
+        png::BitDepth::Eight,
+        &raw,
+        Some(genome),
+    )
+}
+
+/// Reads the genome embedded by [`fractal_image_to_png_with_genome`].
+pub fn genome_from_png(bytes: &[u8]) -> Result<FlameGenome, PngMetadataError> {
+    let reader = png::Decoder::new(Cursor::new(bytes)).read_info()?;
+    let info = reader.info();
+
+    let chunk = info
+        .utf8_text
+        .iter()
+        .find(|c| c.keyword == PNG_GENOME_KEYWORD)
+        .ok_or(PngMetadataError::NoGenome)?;
+    let json = chunk.get_text()?;
+    Ok(FlameGenome::from_json(&json)?)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ImageExportError {
     #[error("Failed to read pixel data")]
     PixelReadFailed,
     #[error("Failed to encode PNG: {0}")]
     EncodeFailed(#[from] image::ImageError),
+    #[error("Failed to encode PNG: {0}")]
+    PngEncodeFailed(#[from] png::EncodingError),
+    #[error("Failed to serialize PNG metadata: {0}")]
+    Metadata(serde_json::Error),
+    #[error("Failed to decode frame: {0}")]
+    FrameDecodeFailed(image::ImageError),
+    #[error("Failed to encode animation: {0}")]
+    Animation(String),
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+    #[error("Invalid export format: {0}")]
+    InvalidFormat(String),
+    #[error("Failed to write image: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PngMetadataError {
+    #[error("Not a readable PNG: {0}")]
+    Decode(#[from] png::DecodingError),
+    #[error("The PNG has no embedded flame genome")]
+    NoGenome,
+    #[error("Embedded genome is invalid: {0}")]
+    InvalidGenome(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_genome_in_png() {
+        let genome = FlameGenome::from_json(
+            r#"{
+                "transforms": [{"affine": {"a": 0.5, "b": 0, "c": 0, "d": 0, "e": 0.5, "f": 0},
+                                "color": {"r": 1, "g": 2, "b": 3},
+                                "variations": [{"id": "linear"}]}],
+                "camera": {"width": 4, "height": 3},
+                "seed": 42
+            }"#,
+        )
+        .unwrap();
+        let png = fractal_image_to_png_with_genome(&FractalImage::new(4, 3), &genome).unwrap();
+
+        assert_eq!(genome_from_png(&png).unwrap(), genome);
+        let plain = fractal_image_to_png(&FractalImage::new(4, 3)).unwrap();
+        assert!(matches!(
+            genome_from_png(&plain),
+            Err(PngMetadataError::NoGenome)
+        ));
+    }
+
+    #[test]
+    fn exports_every_format() {
+        let genome = FlameGenome::from_json(
+            r#"{
+                "transforms": [{"affine": {"a": 0.5, "b": 0, "c": 0, "d": 0, "e": 0.5, "f": 0},
+                                "color": {"r": 1, "g": 2, "b": 3},
+                                "variations": [{"id": "linear"}]}],
+                "camera": {"width": 4, "height": 3}
+            }"#,
+        )
+        .unwrap();
+        let canvas = FractalImage::new(4, 3);
+        for (i, pixel) in canvas.data.iter().enumerate() {
+            let mut data = pixel.write().unwrap();
+            data.color = crate::domain::Color {
+                r: 200,
+                g: 100,
+                b: 50,
+            };
+            data.hit_count = i as i32 * 10;
+        }
+        let tone_mapping = genome.tone_mapping;
+        let export = |format| export_image(&canvas, &tone_mapping, format, Some(&genome)).unwrap();
+
+        let png16 = export(ExportFormat::Png16);
+        assert_eq!(genome_from_png(&png16).unwrap(), genome);
+        let decoded = image::load_from_memory(&png16).unwrap();
+        assert_eq!(decoded.color(), ColorType::Rgba16);
+        let brightest = decoded.to_rgba16().get_pixel(3, 2).0;
+        assert_eq!(brightest[0], (200.0 / 255.0 * 65535.0f64).round() as u16);
+
+        for (format, expected) in [
+            (ExportFormat::Png, image::ImageFormat::Png),
+            (ExportFormat::Jpeg { quality: 90 }, image::ImageFormat::Jpeg),
+            (ExportFormat::WebpLossless, image::ImageFormat::WebP),
+            (
+                ExportFormat::Webp { quality: 75.0 },
+                image::ImageFormat::WebP,
+            ),
+            (ExportFormat::Tiff16, image::ImageFormat::Tiff),
+            (ExportFormat::Exr, image::ImageFormat::OpenExr),
+        ] {
+            let bytes = export(format);
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+            let decoded = image::load_from_memory_with_format(&bytes, expected).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (4, 3));
+        }
+
+        assert!(matches!(
+            export_image(
+                &canvas,
+                &tone_mapping,
+                ExportFormat::Jpeg { quality: 0 },
+                None
+            ),
+            Err(ImageExportError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn streams_like_whole_exports() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let canvas = FractalImage::new(5, 3);
+        for (i, pixel) in canvas.data.iter().enumerate() {
+            let mut data = pixel.write().unwrap();
+            data.color = crate::domain::Color {
+                r: 17 * i as u8,
+                g: 100,
+                b: 255 - 10 * i as u8,
+            };
+            data.hit_count = (i as i32 % 4) * 25;
+        }
+        let tone_mapping = crate::domain::ToneMapping::default();
+
+        for format in [
+            ExportFormat::Png,
+            ExportFormat::Png16,
+            ExportFormat::Tiff16,
+            ExportFormat::Jpeg { quality: 90 },
+        ] {
+            let mut file = tempfile::tempfile().unwrap();
+            stream_image(
+                &canvas,
+                &tone_mapping,
+                format,
+                None,
+                file.try_clone().unwrap(),
+            )
+            .unwrap();
+            let mut streamed = Vec::new();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_end(&mut streamed).unwrap();
+
+            let whole = export_image(&canvas, &tone_mapping, format, None).unwrap();
+            let decode = |bytes: &[u8]| image::load_from_memory(bytes).unwrap().to_rgba16();
+            assert_eq!(decode(&streamed), decode(&whole), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn composites_over_background() {
+        use crate::domain::{Background, Color, ToneMapping};
+
+        let canvas = FractalImage::new(2, 1);
+        {
+            let mut data = canvas.data[1].write().unwrap();
+            data.color = Color {
+                r: 200,
+                g: 100,
+                b: 50,
+            };
+            data.hit_count = 10;
+        }
+        let pixels = |background| {
+            let tone_mapping = ToneMapping {
+                background,
+                ..ToneMapping::default()
+            };
+            let png = export_image(&canvas, &tone_mapping, ExportFormat::Png, None).unwrap();
+            image::load_from_memory(&png).unwrap().to_rgba8()
+        };
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+
+        let transparent = pixels(Background::Transparent);
+        assert_eq!(transparent.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(transparent.get_pixel(1, 0).0, [200, 100, 50, 255]);
+
+        let solid = pixels(Background::Solid { color: white });
+        assert_eq!(solid.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(solid.get_pixel(1, 0).0, [200, 100, 50, 255]);
+
+        let gradient = pixels(Background::Gradient {
+            from: Color::default(),
+            to: white,
+            angle: 0.0,
+        });
+        assert_eq!(gradient.get_pixel(0, 0).0[3], 255);
+        assert!(gradient.get_pixel(0, 0).0[0] < 128);
+
+        let tone_mapping = ToneMapping {
+            background: Background::Transparent,
+            ..ToneMapping::default()
+        };
+        let intermediate = fractal_image_to_intermediate_png(&canvas, &tone_mapping).unwrap();
+        let intermediate = image::load_from_memory(&intermediate).unwrap().to_rgba8();
+        assert_eq!(intermediate.get_pixel(0, 0).0[3], 0);
+        assert_eq!(intermediate.get_pixel(1, 0).0[3], 255);
+    }
+
+    #[test]
+    fn encodes_animations() {
+        let frames: Vec<image::RgbaImage> = (0..3u8)
+            .map(|i| {
+                image::RgbaImage::from_fn(6, 4, |x, y| {
+                    image::Rgba([x as u8 * 40, y as u8 * 60, i * 100, 255])
+                })
+            })
+            .collect();
+        let encode = |format| {
+            encode_animation(
+                format,
+                6,
+                4,
+                frames.iter().cloned().map(Ok),
+                &AnimationSettings::default(),
+            )
+            .unwrap()
+        };
+
+        let gif = encode(AnimationFormat::Gif);
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(Cursor::new(gif))
+            .unwrap();
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 4);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+
+        let apng = encode(AnimationFormat::Apng);
+        let reader = png::Decoder::new(Cursor::new(apng)).read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 3);
+
+        let webp = encode(AnimationFormat::Webp);
+        let decoder = image_webp::WebPDecoder::new(Cursor::new(webp)).unwrap();
+        assert!(decoder.is_animated());
+        assert_eq!(decoder.num_frames(), 3);
+
+        let transparent = image::RgbaImage::from_fn(6, 4, |x, _| {
+            image::Rgba([255, 0, 0, if x < 3 { 0 } else { 255 }])
+        });
+        let webp = encode_animation(
+            AnimationFormat::Webp,
+            6,
+            4,
+            [Ok(transparent.clone()), Ok(transparent)].into_iter(),
+            &AnimationSettings {
+                frame_delay_ms: u32::MAX,
+                ..AnimationSettings::default()
+            },
+        )
+        .unwrap();
+        let mut decoder = image_webp::WebPDecoder::new(Cursor::new(webp)).unwrap();
+        assert!(decoder.has_alpha());
+        let mut frame = vec![0; decoder.output_buffer_size().unwrap()];
+        assert_eq!(decoder.read_frame(&mut frame).unwrap(), (1 << 24) - 1);
+        assert_eq!(frame[3], 0);
+        assert_eq!(frame[4 * 5 + 3], 255);
+
+        let wrong_size = encode_animation(
+            AnimationFormat::Gif,
+            5,
+            4,
+            frames.iter().cloned().map(Ok),
+            &AnimationSettings::default(),
+        );
+        assert!(matches!(wrong_size, Err(ImageExportError::Animation(_))));
+    }
 }
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use color_quant::NeuQuant;
+use image::RgbaImage;
+use image::imageops::{dither, index_colors};
+use serde::{Deserialize, Serialize};
+
+use super::ImageExportError;
+
+/// NeuQuant sampling factor: 1 is slowest and best, 30 fastest.
+const GIF_QUANTIZER_SPEED: i32 = 10;
+/// Largest canvas side an animated WebP can describe.
+const WEBP_MAX_DIMENSION: u32 = 1 << 24;
+/// Longest frame an animated WebP can hold, in milliseconds.
+const WEBP_MAX_FRAME_DURATION_MS: u32 = (1 << 24) - 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    Webp,
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Gif => "image/gif",
+            Self::Apng => "image/apng",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// Time each frame is shown. GIF rounds this to hundredths of a second.
+    pub frame_delay_ms: u32,
+    /// Times the animation plays; 0 loops forever.
+    pub loop_count: u16,
+    /// Floyd-Steinberg dithering when reducing GIF frames to 256 colors.
+    pub dither: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            frame_delay_ms: 40,
+            loop_count: 0,
+            dither: true,
+        }
+    }
+}
+
+/// Decodes a stored frame.
+pub fn rgba_from_png(bytes: &[u8]) -> Result<RgbaImage, ImageExportError> {
+    image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map(|image| image.to_rgba8())
+        .map_err(ImageExportError::FrameDecodeFailed)
+}
+
+/// Encodes `frames`, all `width` x `height`, as one animated image. Frames are pulled one at a
+/// time so callers can decode them lazily.
+pub fn encode_animation<I>(
+    format: AnimationFormat,
+    width: u32,
+    height: u32,
+    frames: I,
+    settings: &AnimationSettings,
+) -> Result<Vec<u8>, ImageExportError>
+where
+    I: ExactSizeIterator<Item = Result<RgbaImage, ImageExportError>>,
+{
+    if frames.len() == 0 {
+        return Err(ImageExportError::Animation("no frames".to_string()));
+    }
+    let frames = frames.map(|frame| {
+        let frame = frame?;
+        if frame.dimensions() != (width, height) {
+            return Err(ImageExportError::Animation(format!(
+                "frame is {}x{}, expected {}x{}",
+                frame.width(),
+                frame.height(),
+                width,
+                height
+            )));
+        }
+        Ok(frame)
+    });
+    match format {
+        AnimationFormat::Gif => encode_gif(width, height, frames, settings),
+        AnimationFormat::Apng => encode_apng(width, height, frames, settings),
+        AnimationFormat::Webp => encode_webp(width, height, frames, settings),
+    }
+}
+
+fn animation_error(e: impl std::fmt::Display) -> ImageExportError {
+    ImageExportError::Animation(e.to_string())
+}
+
+/// Each frame gets its own 256-color palette, so colors that only appear late are not lost.
+fn encode_gif<I>(
+    width: u32,
+    height: u32,
+    frames: I,
+    settings: &AnimationSettings,
+) -> Result<Vec<u8>, ImageExportError>
+where
+    I: Iterator<Item = Result<RgbaImage, ImageExportError>>,
+{
+    let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(ImageExportError::Animation(format!(
+            "GIF frames are at most {} pixels wide and high",
+            u16::MAX
+        )));
+    };
+    let mut buf = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut buf, w, h, &[]).map_err(animation_error)?;
+    encoder
+        .set_repeat(match settings.loop_count {
+            0 => gif::Repeat::Infinite,
+            n => gif::Repeat::Finite(n),
+        })
+        .map_err(animation_error)?;
+    let delay = (settings.frame_delay_ms.div_ceil(10)).min(u16::MAX as u32) as u16;
+
+    for frame in frames {
+        let mut frame = frame?;
+        let quantizer = NeuQuant::new(GIF_QUANTIZER_SPEED, 256, frame.as_raw());
+        if settings.dither {
+            dither(&mut frame, &quantizer);
+        }
+        let indices = index_colors(&frame, &quantizer);
+        encoder
+            .write_frame(&gif::Frame {
+                delay,
+                width: w,
+                height: h,
+                palette: Some(quantizer.color_map_rgb()),
+                buffer: Cow::Owned(indices.into_raw()),
+                ..gif::Frame::default()
+            })
+            .map_err(animation_error)?;
+    }
+    drop(encoder);
+    Ok(buf)
+}
+
+fn encode_apng<I>(
+    width: u32,
+    height: u32,
+    frames: I,
+    settings: &AnimationSettings,
+) -> Result<Vec<u8>, ImageExportError>
+where
+    I: ExactSizeIterator<Item = Result<RgbaImage, ImageExportError>>,
+{
+    let mut buf = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buf, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let frame_count = u32::try_from(frames.len())
+        .map_err(|_| ImageExportError::Animation("too many frames for APNG".to_string()))?;
+    encoder.set_animated(frame_count, settings.loop_count as u32)?;
+    encoder.set_frame_delay(settings.frame_delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame?.as_raw())?;
+    }
+    writer.finish()?;
+    Ok(buf)
+}
+
+/// A RIFF size field for `len` bytes, which must leave room for the chunk's padding byte.
+fn riff_size(len: usize) -> Result<u32, ImageExportError> {
+    u32::try_from(len)
+        .ok()
+        .filter(|&size| size < u32::MAX)
+        .ok_or_else(|| ImageExportError::Animation("WebP files are at most 4 GiB".to_string()))
+}
+
+fn write_chunk(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) -> Result<(), ImageExportError> {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&riff_size(data.len())?.to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    Ok(())
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+/// Lossless frames in the extended WebP container: `VP8X`, `ANIM`, then one `ANMF` per frame.
+fn encode_webp<I>(
+    width: u32,
+    height: u32,
+    frames: I,
+    settings: &AnimationSettings,
+) -> Result<Vec<u8>, ImageExportError>
+where
+    I: Iterator<Item = Result<RgbaImage, ImageExportError>>,
+{
+    if width > WEBP_MAX_DIMENSION || height > WEBP_MAX_DIMENSION {
+        return Err(ImageExportError::Animation(format!(
+            "WebP frames are at most {} pixels wide and high",
+            WEBP_MAX_DIMENSION
+        )));
+    }
+    const ANIMATION_FLAG: u8 = 1 << 1;
+    const ALPHA_FLAG: u8 = 1 << 4;
+    const NO_BLEND: u8 = 1 << 1;
+
+    let mut chunks = Vec::new();
+    let mut vp8x = vec![ANIMATION_FLAG | ALPHA_FLAG, 0, 0, 0];
+    vp8x.extend_from_slice(&u24(width - 1));
+    vp8x.extend_from_slice(&u24(height - 1));
+    write_chunk(&mut chunks, b"VP8X", &vp8x)?;
+
+    // Transparent background, so frames with a transparent background stay see-through,
+    // then the loop count.
+    let mut anim = vec![0, 0, 0, 0];
+    anim.extend_from_slice(&settings.loop_count.to_le_bytes());
+    write_chunk(&mut chunks, b"ANIM", &anim)?;
+
+    let duration = u24(settings.frame_delay_ms.min(WEBP_MAX_FRAME_DURATION_MS));
+    for frame in frames {
+        let frame = frame?;
+        let mut still = Vec::new();
+        image_webp::WebPEncoder::new(&mut still)
+            .encode(frame.as_raw(), width, height, image_webp::ColorType::Rgba8)
+            .map_err(animation_error)?;
+
+        // A simple-format file is `RIFF`, size, `WEBP`, then the `VP8L` chunk we embed.
+        let mut anmf = vec![0; 6];
+        anmf.extend_from_slice(&u24(width - 1));
+        anmf.extend_from_slice(&u24(height - 1));
+        anmf.extend_from_slice(&duration);
+        anmf.push(NO_BLEND);
+        anmf.extend_from_slice(&still[12..]);
+        write_chunk(&mut chunks, b"ANMF", &anmf)?;
+    }
+
+    let size = riff_size(chunks.len() + 4)?;
+    let mut out = Vec::with_capacity(chunks.len() + 12);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(&chunks);
+    Ok(out)
+}
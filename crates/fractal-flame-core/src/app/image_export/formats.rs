@@ -0,0 +1,216 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::openexr::OpenExrEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+
+use super::{ImageExportError, encode_png, over_background, unpremultiply};
+use crate::domain::{Color, FlameGenome, FractalImage, ToneMapping};
+
+/// How a finished render is encoded. Only PNG carries the genome as metadata.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    /// 16 bits per channel, keeping the smooth falloff of dim regions.
+    Png16,
+    Jpeg {
+        /// 1 to 100.
+        quality: u8,
+    },
+    WebpLossless,
+    Webp {
+        /// 0 to 100.
+        quality: f32,
+    },
+    /// 16-bit TIFF for print.
+    Tiff16,
+    /// Unclamped 32-bit float OpenEXR for HDR compositing.
+    Exr,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png | Self::Png16 => "png",
+            Self::Jpeg { .. } => "jpg",
+            Self::WebpLossless | Self::Webp { .. } => "webp",
+            Self::Tiff16 => "tiff",
+            Self::Exr => "exr",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png | Self::Png16 => "image/png",
+            Self::Jpeg { .. } => "image/jpeg",
+            Self::WebpLossless | Self::Webp { .. } => "image/webp",
+            Self::Tiff16 => "image/tiff",
+            Self::Exr => "image/x-exr",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ImageExportError> {
+        match *self {
+            Self::Jpeg { quality } if !(1..=100).contains(&quality) => Err(
+                ImageExportError::InvalidFormat("JPEG quality must be between 1 and 100".into()),
+            ),
+            Self::Webp { quality } if !(0.0..=100.0).contains(&quality) => Err(
+                ImageExportError::InvalidFormat("WebP quality must be between 0 and 100".into()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Premultiplied RGBA over the background, in `0.0..=1.0` except where brightness pushes color
+/// past one. Tone maps like
+/// [`Renderer::apply_gamma_correction`](crate::app::renderer::Renderer::apply_gamma_correction)
+/// without rounding to 8 bits; alpha is the tone-mapped density. `max_normal` is the log10 of the
+/// image's highest hit count.
+pub(super) fn tone_map_pixel(
+    color: Color,
+    hit_count: i32,
+    max_normal: f64,
+    tone_mapping: &ToneMapping,
+    position: (usize, usize),
+    size: (usize, usize),
+) -> [f32; 4] {
+    let factor = if hit_count <= 0 || max_normal <= 0.0 {
+        0.0
+    } else {
+        let normal = (hit_count as f64).log10() / max_normal;
+        normal.powf(1.0 / tone_mapping.gamma) * tone_mapping.brightness
+    };
+    let rgb = [color.r, color.g, color.b].map(|c| (c as f64 / 255.0 * factor) as f32);
+    over_background(
+        rgb,
+        factor.min(1.0) as f32,
+        &tone_mapping.background,
+        position,
+        size,
+    )
+}
+
+/// [`tone_map_pixel`] for the whole canvas, leaving the canvas untouched.
+fn tone_mapped(
+    canvas: &FractalImage,
+    tone_mapping: &ToneMapping,
+) -> Result<Vec<[f32; 4]>, ImageExportError> {
+    let mut hits = Vec::with_capacity(canvas.width * canvas.height);
+    let mut max_normal = 0.0f64;
+    for pixel in &canvas.data {
+        let data = pixel
+            .read()
+            .map_err(|_| ImageExportError::PixelReadFailed)?;
+        if data.hit_count > 0 {
+            max_normal = max_normal.max((data.hit_count as f64).log10());
+        }
+        hits.push((data.color, data.hit_count));
+    }
+
+    let size = (canvas.width, canvas.height);
+    Ok(hits
+        .into_iter()
+        .enumerate()
+        .map(|(i, (color, hit_count))| {
+            let position = (i % canvas.width, i / canvas.width);
+            tone_map_pixel(color, hit_count, max_normal, tone_mapping, position, size)
+        })
+        .collect())
+}
+
+pub(super) fn to_u8(value: f32) -> u8 {
+    (value * 255.0).clamp(0.0, 255.0) as u8
+}
+
+pub(super) fn to_u16(value: f32) -> u16 {
+    (value * 65535.0).clamp(0.0, 65535.0).round() as u16
+}
+
+/// Encodes a rendered canvas, before gamma correction, as `format`. `genome` is embedded when
+/// the format supports it.
+pub fn export_image(
+    canvas: &FractalImage,
+    tone_mapping: &ToneMapping,
+    format: ExportFormat,
+    genome: Option<&FlameGenome>,
+) -> Result<Vec<u8>, ImageExportError> {
+    format.validate()?;
+    let (width, height) = (canvas.width as u32, canvas.height as u32);
+    let pixels = tone_mapped(canvas, tone_mapping)?;
+
+    let rgba8 = || -> Vec<u8> {
+        pixels
+            .iter()
+            .flat_map(|p| unpremultiply(*p).map(to_u8))
+            .collect()
+    };
+    let rgba16 = || -> Vec<u16> {
+        pixels
+            .iter()
+            .flat_map(|p| unpremultiply(*p).map(to_u16))
+            .collect()
+    };
+
+    let mut buf = Cursor::new(Vec::new());
+    match format {
+        ExportFormat::Png => {
+            return encode_png(width, height, png::BitDepth::Eight, &rgba8(), genome);
+        }
+        ExportFormat::Png16 => {
+            let raw: Vec<u8> = rgba16().iter().flat_map(|v| v.to_be_bytes()).collect();
+            return encode_png(width, height, png::BitDepth::Sixteen, &raw, genome);
+        }
+        ExportFormat::Jpeg { quality } => {
+            // No alpha channel, so a transparent background flattens onto black.
+            let rgb: Vec<u8> = pixels
+                .iter()
+                .flat_map(|[r, g, b, _]| [to_u8(*r), to_u8(*g), to_u8(*b)])
+                .collect();
+            JpegEncoder::new_with_quality(&mut buf, quality).write_image(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+        ExportFormat::WebpLossless => {
+            image_webp::WebPEncoder::new(&mut buf)
+                .encode(&rgba8(), width, height, image_webp::ColorType::Rgba8)
+                .map_err(|e| ImageExportError::Encode(e.to_string()))?;
+        }
+        ExportFormat::Webp { quality } => {
+            let raw = rgba8();
+            let encoded = webp::Encoder::from_rgba(&raw, width, height).encode(quality);
+            return Ok(encoded.to_vec());
+        }
+        ExportFormat::Tiff16 => {
+            let raw: Vec<u8> = rgba16().iter().flat_map(|v| v.to_ne_bytes()).collect();
+            TiffEncoder::new(&mut buf).write_image(
+                &raw,
+                width,
+                height,
+                ExtendedColorType::Rgba16,
+            )?;
+        }
+        ExportFormat::Exr => {
+            // OpenEXR stores premultiplied alpha.
+            let raw: Vec<u8> = pixels
+                .iter()
+                .flatten()
+                .flat_map(|c| c.to_ne_bytes())
+                .collect();
+            OpenExrEncoder::new(&mut buf).write_image(
+                &raw,
+                width,
+                height,
+                ExtendedColorType::Rgba32F,
+            )?;
+        }
+    }
+    Ok(buf.into_inner())
+}
@@ -0,0 +1,248 @@
+use std::io::Write;
+
+use super::formats::{to_u8, to_u16, tone_map_pixel};
+use super::{ExportFormat, ImageExportError, export_image, png_encoder, unpremultiply};
+use crate::domain::{Color, FlameGenome, FractalImage, ToneMapping};
+
+const TIFF_SHORT: u16 = 3;
+const TIFF_LONG: u16 = 4;
+const TIFF_IFD_ENTRIES: usize = 11;
+
+/// Tone maps and encodes an image one row of histogram pixels at a time, for images too large
+/// to hold whole. Only PNG and TIFF formats can be written this way. The encoder owns its
+/// output, so write to a file or another handle that outlives it.
+pub struct RowEncoder<W: Write + 'static> {
+    sink: Sink<W>,
+    format: ExportFormat,
+    tone_mapping: ToneMapping,
+    max_normal: f64,
+    width: usize,
+    height: usize,
+    row: usize,
+    buf: Vec<u8>,
+}
+
+enum Sink<W: Write + 'static> {
+    Png(Box<png::StreamWriter<'static, W>>),
+    /// The header is already written, so rows go straight to the output.
+    Tiff(W),
+}
+
+/// Whether [`RowEncoder`] can write `format`.
+pub fn validate_streamable(format: ExportFormat) -> Result<(), ImageExportError> {
+    match format {
+        ExportFormat::Png | ExportFormat::Png16 | ExportFormat::Tiff16 => Ok(()),
+        other => Err(ImageExportError::InvalidFormat(format!(
+            "{} cannot be written row by row",
+            other.extension()
+        ))),
+    }
+}
+
+/// Header of an uncompressed 16-bit RGBA TIFF with one strip per row. Every strip's size is
+/// known up front, so the header can point at rows that have not been written yet and the file
+/// is written front to back.
+fn write_tiff_header<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+) -> Result<(), ImageExportError> {
+    let row_bytes = width * 8;
+    let bits_offset = 8 + 2 + TIFF_IFD_ENTRIES * 12 + 4;
+    let offsets_offset = bits_offset + 8;
+    let counts_offset = offsets_offset + 4 * height;
+    let data_offset = counts_offset + 4 * height;
+    height
+        .checked_mul(row_bytes)
+        .and_then(|data| data.checked_add(data_offset))
+        .and_then(|total| u32::try_from(total).ok())
+        .ok_or_else(|| ImageExportError::InvalidFormat("TIFF files are limited to 4 GiB".into()))?;
+
+    let mut header = Vec::with_capacity(data_offset);
+    header.extend(b"II");
+    header.extend(42u16.to_le_bytes());
+    header.extend(8u32.to_le_bytes());
+    header.extend((TIFF_IFD_ENTRIES as u16).to_le_bytes());
+    // A single strip's offset and size fit in the entry itself.
+    let (offsets, counts) = if height == 1 {
+        (data_offset, row_bytes)
+    } else {
+        (offsets_offset, counts_offset)
+    };
+    for (tag, kind, count, value) in [
+        (256u16, TIFF_LONG, 1, width),
+        (257, TIFF_LONG, 1, height),
+        (258, TIFF_SHORT, 4, bits_offset),
+        // No compression.
+        (259, TIFF_SHORT, 1, 1),
+        // RGB.
+        (262, TIFF_SHORT, 1, 2),
+        (273, TIFF_LONG, height, offsets),
+        (277, TIFF_SHORT, 1, 4),
+        (278, TIFF_LONG, 1, 1),
+        (279, TIFF_LONG, height, counts),
+        // Chunky, RGBARGBA.
+        (284, TIFF_SHORT, 1, 1),
+        // Unassociated alpha.
+        (338, TIFF_SHORT, 1, 2),
+    ] {
+        header.extend(tag.to_le_bytes());
+        header.extend(kind.to_le_bytes());
+        header.extend((count as u32).to_le_bytes());
+        header.extend((value as u32).to_le_bytes());
+    }
+    header.extend(0u32.to_le_bytes());
+    header.extend([16u16; 4].iter().flat_map(|bits| bits.to_le_bytes()));
+    for row in 0..height {
+        header.extend(((data_offset + row * row_bytes) as u32).to_le_bytes());
+    }
+    for _ in 0..height {
+        header.extend((row_bytes as u32).to_le_bytes());
+    }
+    out.write_all(&header)?;
+    Ok(())
+}
+
+impl<W: Write + 'static> RowEncoder<W> {
+    /// `max_hit_count` is the highest hit count anywhere in the image, which every row is
+    /// tone mapped against.
+    pub fn new(
+        mut out: W,
+        width: usize,
+        height: usize,
+        format: ExportFormat,
+        tone_mapping: &ToneMapping,
+        max_hit_count: i32,
+        genome: Option<&FlameGenome>,
+    ) -> Result<Self, ImageExportError> {
+        validate_streamable(format)?;
+        let png_stream = |out, depth| -> Result<_, ImageExportError> {
+            Ok(
+                png_encoder(out, width as u32, height as u32, depth, genome)?
+                    .write_header()?
+                    .into_stream_writer()?,
+            )
+        };
+        let sink = match format {
+            ExportFormat::Tiff16 => {
+                write_tiff_header(&mut out, width, height)?;
+                Sink::Tiff(out)
+            }
+            ExportFormat::Png16 => Sink::Png(Box::new(png_stream(out, png::BitDepth::Sixteen)?)),
+            _ => Sink::Png(Box::new(png_stream(out, png::BitDepth::Eight)?)),
+        };
+        Ok(Self {
+            sink,
+            format,
+            tone_mapping: *tone_mapping,
+            max_normal: if max_hit_count > 0 {
+                (max_hit_count as f64).log10()
+            } else {
+                0.0
+            },
+            width,
+            height,
+            row: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Writes the next row, given as averaged color and hit count per pixel.
+    pub fn write_row(&mut self, pixels: &[(Color, i32)]) -> Result<(), ImageExportError> {
+        if pixels.len() != self.width || self.row >= self.height {
+            return Err(ImageExportError::Encode(format!(
+                "row {} does not fit a {}x{} image",
+                self.row, self.width, self.height
+            )));
+        }
+        let size = (self.width, self.height);
+        self.buf.clear();
+        for (x, &(color, hit_count)) in pixels.iter().enumerate() {
+            let pixel = unpremultiply(tone_map_pixel(
+                color,
+                hit_count,
+                self.max_normal,
+                &self.tone_mapping,
+                (x, self.row),
+                size,
+            ));
+            match self.format {
+                ExportFormat::Png16 => self
+                    .buf
+                    .extend(pixel.map(to_u16).iter().flat_map(|v| v.to_be_bytes())),
+                ExportFormat::Tiff16 => self
+                    .buf
+                    .extend(pixel.map(to_u16).iter().flat_map(|v| v.to_le_bytes())),
+                _ => self.buf.extend(pixel.map(to_u8)),
+            }
+        }
+        match self.sink {
+            Sink::Png(ref mut writer) => writer.write_all(&self.buf)?,
+            Sink::Tiff(ref mut out) => out.write_all(&self.buf)?,
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    /// Flushes the encoder once every row is written.
+    pub fn finish(self) -> Result<(), ImageExportError> {
+        if self.row != self.height {
+            return Err(ImageExportError::Encode(format!(
+                "only {} of {} rows were written",
+                self.row, self.height
+            )));
+        }
+        match self.sink {
+            Sink::Png(writer) => writer.finish()?,
+            Sink::Tiff(mut out) => out.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// Like [`export_image`], but writes to `out` as it encodes, without a tone-mapped copy of the
+/// canvas or the whole encoded image in memory. Formats that cannot be written row by row are
+/// encoded whole first.
+pub fn stream_image<W: Write + 'static>(
+    canvas: &FractalImage,
+    tone_mapping: &ToneMapping,
+    format: ExportFormat,
+    genome: Option<&FlameGenome>,
+    mut out: W,
+) -> Result<(), ImageExportError> {
+    if validate_streamable(format).is_err() {
+        out.write_all(&export_image(canvas, tone_mapping, format, genome)?)?;
+        return Ok(());
+    }
+
+    let mut max_hit_count = 0;
+    for pixel in &canvas.data {
+        let data = pixel
+            .read()
+            .map_err(|_| ImageExportError::PixelReadFailed)?;
+        max_hit_count = max_hit_count.max(data.hit_count);
+    }
+
+    let (width, height) = (canvas.width, canvas.height);
+    let mut encoder = RowEncoder::new(
+        out,
+        width,
+        height,
+        format,
+        tone_mapping,
+        max_hit_count,
+        genome,
+    )?;
+    let mut row = Vec::with_capacity(width);
+    for pixels in canvas.data.chunks_exact(width.max(1)).take(height) {
+        row.clear();
+        for pixel in pixels {
+            let data = pixel
+                .read()
+                .map_err(|_| ImageExportError::PixelReadFailed)?;
+            row.push((data.color, data.hit_count));
+        }
+        encoder.write_row(&row)?;
+    }
+    encoder.finish()
+}
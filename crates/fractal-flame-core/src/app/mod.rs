@@ -1,5 +1,13 @@
+pub mod animation;
+pub mod evolution;
+pub mod expression;
+pub mod flam3;
+pub mod generation;
+pub mod genome;
+pub mod histogram;
 pub mod image_export;
 pub mod renderer;
+pub mod tiled;
 pub mod transformations;
 
 pub use image_export::fractal_image_to_png;
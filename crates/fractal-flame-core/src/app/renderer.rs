@@ -1,20 +1,110 @@
+use crate::app::genome::GenomeError;
 use crate::domain::transformation::Transformation;
-use crate::domain::{Color, FractalImage, Pixel, Point, Rect};
+use crate::domain::{Color, FlameGenome, FractalImage, Pixel, Point, Rect, TemporalSample, Tile};
 use crate::infra::random;
+use rand::rngs::StdRng;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often a paused render checks whether it may go on.
+const PAUSE_POLL: Duration = Duration::from_millis(20);
+
+/// What a render does once it has been paused for as long as it may be.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseExpiry {
+    #[default]
+    Resume,
+    Cancel,
+}
+
+/// Lets another thread pause, resume or cancel a render. Workers check it before each sample.
+#[derive(Debug, Default)]
+pub struct RenderControl {
+    paused: AtomicBool,
+    cancelled: AtomicBool,
+    /// How long a pause may last, if it is bounded, and what happens after.
+    max_pause: Option<(Duration, PauseExpiry)>,
+}
+
+impl RenderControl {
+    /// A control whose pauses end by themselves after `max`, as `expiry` says.
+    pub fn with_max_pause(max: Duration, expiry: PauseExpiry) -> Self {
+        Self {
+            max_pause: Some((max, expiry)),
+            ..Self::default()
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Stops the render at the next sample; it then fails with [`RenderCancelled`].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Blocks while the render is paused, and fails once it is cancelled.
+    fn checkpoint(&self) -> Result<(), RenderCancelled> {
+        let mut paused_at = None;
+        loop {
+            if self.is_cancelled() {
+                return Err(RenderCancelled);
+            }
+            if !self.is_paused() {
+                return Ok(());
+            }
+            let paused_at = *paused_at.get_or_insert_with(Instant::now);
+            if let Some((max, expiry)) = self.max_pause
+                && paused_at.elapsed() >= max
+            {
+                match expiry {
+                    PauseExpiry::Resume => self.set_paused(false),
+                    PauseExpiry::Cancel => self.cancel(),
+                }
+                continue;
+            }
+            std::thread::sleep(PAUSE_POLL);
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Render cancelled")]
+pub struct RenderCancelled;
 
 pub struct Renderer {
     pub canvas: Arc<FractalImage>,
     pub world: Arc<Rect>,
+    /// The part of `world` plotted onto the canvas, when not all of it. Orbits still start
+    /// anywhere in `world`.
+    pub view: Option<Arc<Rect>>,
     pub transformations: Arc<Vec<Box<dyn Transformation + Send + Sync>>>,
+    pub final_transformation: Option<Box<dyn Transformation + Send + Sync>>,
     pub samples: usize,
     pub iter_per_sample: usize,
     pub symmetry: usize,
     pub gamma: f64,
+    pub brightness: f64,
+    /// Camera rotation around the center of `world`, in radians.
+    pub rotation: f64,
     pub max_threads: usize,
+    /// Seeds each worker thread's generator; `None` draws fresh entropy.
+    pub seed: Option<u64>,
     pub progress: Option<Arc<AtomicUsize>>,
+    pub control: Option<Arc<RenderControl>>,
 }
 
 impl Renderer {
@@ -31,16 +121,67 @@ impl Renderer {
         Self {
             canvas: Arc::new(canvas),
             world: Arc::new(world),
+            view: None,
             transformations: Arc::new(transformations),
+            final_transformation: None,
             samples,
             iter_per_sample,
             symmetry,
             gamma,
+            brightness: 1.0,
+            rotation: 0.0,
             max_threads,
+            seed: None,
             progress: None,
+            control: None,
         }
     }
 
+    pub fn from_genome(genome: &FlameGenome, max_threads: usize) -> Result<Self, GenomeError> {
+        let canvas = FractalImage::new(genome.camera.width, genome.camera.height);
+        Self::from_genome_with_canvas(genome, canvas, max_threads)
+    }
+
+    /// Renders only `tile` of the genome's frame, on a canvas the size of the tile. Tiles
+    /// rendered with the same seed trace the same orbits, so they line up at the seams.
+    pub fn from_genome_tile(
+        genome: &FlameGenome,
+        tile: &Tile,
+        max_threads: usize,
+    ) -> Result<Self, GenomeError> {
+        let canvas = FractalImage::new(tile.width, tile.height);
+        let mut renderer = Self::from_genome_with_canvas(genome, canvas, max_threads)?;
+        renderer.view = Some(Arc::new(tile.view(
+            &renderer.world,
+            genome.camera.width,
+            genome.camera.height,
+        )));
+        Ok(renderer)
+    }
+
+    fn from_genome_with_canvas(
+        genome: &FlameGenome,
+        canvas: FractalImage,
+        max_threads: usize,
+    ) -> Result<Self, GenomeError> {
+        let transformations = genome.build_transformations()?;
+        let mut renderer = Self::new(
+            canvas,
+            genome.world(),
+            transformations,
+            genome.quality.samples,
+            genome.quality.iter_per_sample,
+            genome.symmetry,
+            genome.tone_mapping.gamma,
+            max_threads,
+        );
+        renderer.final_transformation = genome.build_final_transformation()?;
+        renderer.brightness = genome.tone_mapping.brightness;
+        renderer.rotation = genome.camera.rotate.to_radians();
+        renderer.seed = genome.seed;
+        Ok(renderer)
+    }
+
     fn render_sample(
         &self,
         thread_id: usize,
@@ -56,30 +197,41 @@ impl Renderer {
         let end_sample =
             start_sample + samples_per_thread + if thread_id < remainder { 1 } else { 0 };
 
+        let mut rng = random::worker_rng(self.seed, thread_id as u64);
+        let view = self.view.as_deref().unwrap_or(&self.world);
+
         for _ in start_sample..end_sample {
-            let start_point = get_random_point_from_world(&self.world)?;
+            if let Some(ref control) = self.control {
+                control.checkpoint()?;
+            }
+            let start_point = get_random_point_from_world(&mut rng, &self.world)?;
             let mut current_point = start_point;
 
             for iter in -20i32..self.iter_per_sample as i32 {
-                let transformation = get_random_transformation(&self.transformations)?;
+                let transformation = get_random_transformation(&mut rng, &self.transformations)?;
                 current_point = transformation.apply(&current_point);
 
                 if iter < 0 {
                     continue;
                 }
 
+                let plotted_point = match self.final_transformation {
+                    Some(ref final_transformation) => final_transformation.apply(&current_point),
+                    None => current_point,
+                };
+
                 for symmetry_step in 0..self.symmetry {
                     let theta = (symmetry_step as f64)
                         * (2.0 * std::f64::consts::PI / self.symmetry as f64);
                     let symmetry_transform =
                         crate::app::transformations::symmetry::Symmetry::new(theta);
-                    let symmetric_point = symmetry_transform.apply(&current_point);
+                    let symmetric_point = self.to_camera(symmetry_transform.apply(&plotted_point));
 
-                    if !self.world.contains_point(&symmetric_point) {
+                    if !view.contains_point(&symmetric_point) {
                         continue;
                     }
 
-                    if let Some(pixel) = self.map_to_pixel(&symmetric_point) {
+                    if let Some(pixel) = self.map_to_pixel(view, &symmetric_point) {
                         let color = transformation.color();
                         calculate_color(pixel, color);
                     }
@@ -108,6 +260,43 @@ impl Renderer {
         Ok(())
     }
 
+    /// Renders every sub-frame into the canvas, each with its weight's share of `samples`, so
+    /// motion during the shutter interval smears instead of strobing. Sub-frames must match the
+    /// canvas size; tone mapping stays the renderer's own.
+    pub fn render_temporal(
+        &mut self,
+        subframes: &[TemporalSample],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let total_samples = self.samples;
+        let total_weight: f64 = subframes.iter().map(|s| s.weight).sum();
+        let base_seed = self.seed;
+        let mut assigned = 0;
+
+        for (index, subframe) in subframes.iter().enumerate() {
+            let samples = if index + 1 == subframes.len() {
+                total_samples - assigned
+            } else {
+                let share = total_samples as f64 * subframe.weight / total_weight;
+                (share.round() as usize).min(total_samples - assigned)
+            };
+            assigned += samples;
+
+            let genome = &subframe.genome;
+            self.transformations = Arc::new(genome.build_transformations()?);
+            self.final_transformation = genome.build_final_transformation()?;
+            self.world = Arc::new(genome.world());
+            self.rotation = genome.camera.rotate.to_radians();
+            // Distinct streams per sub-frame, so they do not retrace the same orbits.
+            self.seed = base_seed.map(|seed| seed.wrapping_add(index as u64));
+            self.samples = samples;
+            self.render()?;
+        }
+
+        self.samples = total_samples;
+        self.seed = base_seed;
+        Ok(())
+    }
+
     pub fn apply_gamma_correction(&self) {
         let mut max_normal = 0.0f64;
 
@@ -134,7 +323,8 @@ impl Renderer {
                             if data.hit_count > 0 {
                                 data.normal /= max_normal;
 
-                                let gamma_factor = data.normal.powf(1.0 / self.gamma);
+                                let gamma_factor =
+                                    data.normal.powf(1.0 / self.gamma) * self.brightness;
                                 data.color.r = ((data.color.r as f64) * gamma_factor) as u8;
                                 data.color.g = ((data.color.g as f64) * gamma_factor) as u8;
                                 data.color.b = ((data.color.b as f64) * gamma_factor) as u8;
@@ -146,33 +336,46 @@ impl Renderer {
         }
     }
 
-    fn map_to_pixel(&self, point: &Point) -> Option<&Pixel> {
-        let x = ((self.canvas.width as f64) * (point.x - self.world.x) / self.world.width) as usize;
-        let y =
-            ((self.canvas.height as f64) * (point.y - self.world.y) / self.world.height) as usize;
+    /// Rotates `point` around the center of the world so the rotated camera sees it unrotated.
+    fn to_camera(&self, point: Point) -> Point {
+        if self.rotation == 0.0 {
+            return point;
+        }
+        let cx = self.world.x + self.world.width / 2.0;
+        let cy = self.world.y + self.world.height / 2.0;
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let (dx, dy) = (point.x - cx, point.y - cy);
+        Point::new(cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+    }
+
+    fn map_to_pixel(&self, view: &Rect, point: &Point) -> Option<&Pixel> {
+        let x = ((self.canvas.width as f64) * (point.x - view.x) / view.width) as usize;
+        let y = ((self.canvas.height as f64) * (point.y - view.y) / view.height) as usize;
 
         self.canvas.pixel_at(x, y)
     }
 }
 
 fn get_random_point_from_world(
+    rng: &mut StdRng,
     world: &Rect,
 ) -> Result<Point, Box<dyn std::error::Error + Send + Sync>> {
-    let x = random::generate_f64(world.x, world.x + world.width, false)?;
-    let y = random::generate_f64(world.y, world.y + world.height, false)?;
+    let x = random::generate_f64_with(rng, world.x, world.x + world.width, false)?;
+    let y = random::generate_f64_with(rng, world.y, world.y + world.height, false)?;
     Ok(Point::new(x, y))
 }
 
-fn get_random_transformation(
-    transformations: &[Box<dyn Transformation + Send + Sync>],
-) -> Result<&Box<dyn Transformation + Send + Sync>, Box<dyn std::error::Error + Send + Sync>> {
+fn get_random_transformation<'a>(
+    rng: &mut StdRng,
+    transformations: &'a [Box<dyn Transformation + Send + Sync>],
+) -> Result<&'a Box<dyn Transformation + Send + Sync>, Box<dyn std::error::Error + Send + Sync>> {
     if transformations.is_empty() {
         return Err("No transformations available".into());
     }
 
     let total_weight: f64 = transformations.iter().map(|t| t.weight()).sum();
 
-    let random_value = random::generate_f64(0.0, total_weight, true)?;
+    let random_value = random::generate_f64_with(rng, 0.0, total_weight, true)?;
 
     let mut current_weight = 0.0;
     for transformation in transformations {
@@ -208,3 +411,72 @@ fn calculate_color(pixel: &Pixel, color: &Color) {
         data.hit_count += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renderer() -> Renderer {
+        let genome = FlameGenome::from_json(
+            r#"{
+                "transforms": [
+                    {"affine": {"a": 0.5, "b": 0, "c": 0, "d": 0, "e": 0.5, "f": 0},
+                     "color": {"r": 255, "g": 0, "b": 0}, "variations": [{"id": "linear"}]},
+                    {"affine": {"a": 0.5, "b": 0, "c": 0.5, "d": 0, "e": 0.5, "f": 0.5},
+                     "color": {"r": 0, "g": 0, "b": 255}, "variations": [{"id": "linear"}]}
+                ],
+                "camera": {"width": 16, "height": 16},
+                "quality": {"samples": 500, "iter_per_sample": 20},
+                "seed": 3
+            }"#,
+        )
+        .unwrap();
+        let mut renderer = Renderer::from_genome(&genome, 2).unwrap();
+        renderer.progress = Some(Arc::default());
+        renderer.control = Some(Arc::default());
+        renderer
+    }
+
+    #[test]
+    fn pauses_resumes_and_cancels() {
+        let renderer = Arc::new(renderer());
+        let control = renderer.control.clone().unwrap();
+        let progress = renderer.progress.clone().unwrap();
+
+        control.set_paused(true);
+        let running = std::thread::spawn({
+            let renderer = renderer.clone();
+            move || renderer.render().is_ok()
+        });
+        std::thread::sleep(PAUSE_POLL * 5);
+        assert_eq!(progress.load(Ordering::Relaxed), 0);
+        control.set_paused(false);
+        assert!(running.join().unwrap());
+        assert_eq!(progress.load(Ordering::Relaxed), 500);
+
+        control.cancel();
+        let error = renderer.render().unwrap_err();
+        assert!(error.downcast_ref::<RenderCancelled>().is_some());
+        assert_eq!(progress.load(Ordering::Relaxed), 500);
+    }
+
+    #[test]
+    fn ends_pauses_that_last_too_long() {
+        let max = PAUSE_POLL * 3;
+        let mut renderer = renderer();
+        let control = Arc::new(RenderControl::with_max_pause(max, PauseExpiry::Resume));
+        renderer.control = Some(control.clone());
+        control.set_paused(true);
+        let started = Instant::now();
+        renderer.render().unwrap();
+        assert!(started.elapsed() >= max);
+        assert!(!control.is_paused());
+
+        let control = Arc::new(RenderControl::with_max_pause(max, PauseExpiry::Cancel));
+        renderer.control = Some(control.clone());
+        control.set_paused(true);
+        let error = renderer.render().unwrap_err();
+        assert!(error.downcast_ref::<RenderCancelled>().is_some());
+        assert!(control.is_cancelled());
+    }
+}
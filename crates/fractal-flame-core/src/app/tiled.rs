@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
+use crate::app::genome::GenomeError;
+use crate::app::histogram::{RECORD_BYTES, decode_record, encode_record};
+use crate::app::image_export::{ExportFormat, ImageExportError, RowEncoder, validate_streamable};
+use crate::app::renderer::{RenderControl, Renderer};
+use crate::domain::{FlameGenome, Tile};
+
+/// Renders a genome one tile at a time, so peak memory depends on the tile size rather than
+/// the output size. Every tile runs the full sample budget, plotting only the points that land
+/// inside it, and parks its histogram in an anonymous temporary file until the whole image
+/// can be tone mapped and encoded row by row.
+pub struct TiledRenderer {
+    pub genome: FlameGenome,
+    /// Largest tile side, in pixels.
+    pub tile_size: usize,
+    pub max_threads: usize,
+    /// Counts samples across all tiles, up to [`TiledRenderer::total_samples`].
+    pub progress: Option<Arc<AtomicUsize>>,
+    /// Pauses or cancels whichever tile is rendering.
+    pub control: Option<Arc<RenderControl>>,
+}
+
+impl TiledRenderer {
+    pub fn new(genome: FlameGenome, tile_size: usize, max_threads: usize) -> Self {
+        Self {
+            genome,
+            tile_size,
+            max_threads,
+            progress: None,
+            control: None,
+        }
+    }
+
+    pub fn tiles(&self) -> Vec<Tile> {
+        Tile::grid(
+            self.genome.camera.width,
+            self.genome.camera.height,
+            self.tile_size,
+        )
+    }
+
+    pub fn total_samples(&self) -> usize {
+        self.genome.quality.samples * self.tiles().len()
+    }
+
+    /// Renders every tile and writes the image to `out` as `format`, which must be one
+    /// [`RowEncoder`] can write.
+    pub fn render<W: Write + 'static>(
+        &self,
+        format: ExportFormat,
+        out: W,
+    ) -> Result<(), TiledRenderError> {
+        // Fail before rendering anything if the format cannot be streamed.
+        validate_streamable(format)?;
+        let histogram = self.render_tiles()?;
+        self.encode(histogram, format, out)
+    }
+
+    /// Renders every tile into a histogram of the whole image, parked on disk.
+    pub fn render_tiles(&self) -> Result<SpilledHistogram, TiledRenderError> {
+        let mut file = tempfile::tempfile()?;
+        let mut max_hit_count = 0;
+        for tile in self.tiles() {
+            max_hit_count = max_hit_count.max(self.render_tile(&tile, &mut file)?);
+        }
+        Ok(SpilledHistogram {
+            file,
+            max_hit_count,
+        })
+    }
+
+    /// Tone maps `histogram` and writes it to `out` as `format`, one row at a time.
+    pub fn encode<W: Write + 'static>(
+        &self,
+        histogram: SpilledHistogram,
+        format: ExportFormat,
+        out: W,
+    ) -> Result<(), TiledRenderError> {
+        let width = self.genome.camera.width;
+        let height = self.genome.camera.height;
+        let mut encoder = RowEncoder::new(
+            out,
+            width,
+            height,
+            format,
+            &self.genome.tone_mapping,
+            histogram.max_hit_count,
+            Some(&self.genome),
+        )?;
+        let mut spill = histogram.file;
+        spill.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(spill);
+        let mut record_row = vec![0; width * RECORD_BYTES];
+        let mut row = Vec::with_capacity(width);
+        for _ in 0..height {
+            reader.read_exact(&mut record_row)?;
+            row.clear();
+            row.extend(record_row.chunks_exact(RECORD_BYTES).map(decode_record));
+            encoder.write_row(&row)?;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Renders `tile` into its place in the row-major `spill` file and returns its highest hit
+    /// count.
+    fn render_tile(&self, tile: &Tile, spill: &mut File) -> Result<i32, TiledRenderError> {
+        let mut renderer = Renderer::from_genome_tile(&self.genome, tile, self.max_threads)?;
+        renderer.progress = self.progress.clone();
+        renderer.control = self.control.clone();
+        renderer
+            .render()
+            .map_err(|e| TiledRenderError::Render(e.to_string()))?;
+
+        let width = self.genome.camera.width;
+        let mut max_hit_count = 0;
+        let mut record_row = Vec::with_capacity(tile.width * RECORD_BYTES);
+        let mut writer = BufWriter::new(spill);
+        for y in 0..tile.height {
+            record_row.clear();
+            for x in 0..tile.width {
+                let Some(pixel) = renderer.canvas.pixel_at(x, y) else {
+                    continue;
+                };
+                let data = pixel
+                    .read()
+                    .map_err(|_| ImageExportError::PixelReadFailed)?;
+                max_hit_count = max_hit_count.max(data.hit_count);
+                encode_record(data.color, data.hit_count, &mut record_row);
+            }
+            let offset = ((tile.y + y) * width + tile.x) * RECORD_BYTES;
+            writer.seek(SeekFrom::Start(offset as u64))?;
+            writer.write_all(&record_row)?;
+        }
+        writer.flush()?;
+        Ok(max_hit_count)
+    }
+}
+
+/// The histogram of a [`TiledRenderer`]'s whole image, in row-major records in a temporary
+/// file.
+pub struct SpilledHistogram {
+    file: File,
+    max_hit_count: i32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TiledRenderError {
+    #[error(transparent)]
+    Genome(#[from] GenomeError),
+    #[error("Failed to render tile: {0}")]
+    Render(String),
+    #[error("Failed to spill tile histogram: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Export(#[from] ImageExportError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::image_export::export_image;
+
+    #[test]
+    fn tiles_match_a_whole_render() {
+        let genome = FlameGenome::from_json(
+            r#"{
+                "transforms": [
+                    {"affine": {"a": 0.5, "b": 0, "c": 0, "d": 0, "e": 0.5, "f": 0},
+                     "color": {"r": 255, "g": 0, "b": 0}, "variations": [{"id": "linear"}]},
+                    {"affine": {"a": 0.5, "b": 0, "c": 0.5, "d": 0, "e": 0.5, "f": 0.5},
+                     "color": {"r": 0, "g": 0, "b": 255}, "variations": [{"id": "linear"}]}
+                ],
+                "camera": {"width": 24, "height": 18},
+                "quality": {"samples": 200, "iter_per_sample": 50},
+                "seed": 7
+            }"#,
+        )
+        .unwrap();
+
+        let whole = Renderer::from_genome(&genome, 1).unwrap();
+        whole.render().unwrap();
+        let expected = export_image(
+            whole.canvas.as_ref(),
+            &genome.tone_mapping,
+            ExportFormat::Png,
+            Some(&genome),
+        )
+        .unwrap();
+
+        let tiled = TiledRenderer::new(genome.clone(), 10, 1);
+        assert_eq!(tiled.tiles().len(), 6);
+        let mut out = tempfile::tempfile().unwrap();
+        tiled
+            .render(ExportFormat::Png, out.try_clone().unwrap())
+            .unwrap();
+        let mut png = Vec::new();
+        out.seek(SeekFrom::Start(0)).unwrap();
+        out.read_to_end(&mut png).unwrap();
+
+        let expected = image::load_from_memory(&expected).unwrap().to_rgba8();
+        let actual = image::load_from_memory(&png).unwrap().to_rgba8();
+        let differing = expected
+            .pixels()
+            .zip(actual.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+        // Points right on a tile seam may round into the neighbouring pixel.
+        assert!(differing <= 24, "{differing} pixels differ");
+
+        assert!(matches!(
+            tiled.render(ExportFormat::Jpeg { quality: 90 }, Vec::new()),
+            Err(TiledRenderError::Export(ImageExportError::InvalidFormat(_)))
+        ));
+    }
+}
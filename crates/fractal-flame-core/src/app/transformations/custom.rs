@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::base_affine_transformation::BaseAffineTransformation;
+use crate::app::expression::ast::Variable;
+use crate::app::expression::compiler::Inputs;
+use crate::app::expression::{CompiledExpression, ExpressionError, formula_latex, parse};
+use crate::domain::transformation::Transformation;
+use crate::domain::{Color, Point};
+
+/// A variation defined at runtime by a pair of expressions for `x'` and `y'`.
+#[derive(Clone, Debug)]
+pub struct CustomVariationDefinition {
+    pub id: String,
+    pub name: String,
+    pub x_source: String,
+    pub y_source: String,
+    pub parameters: BTreeMap<String, f64>,
+    formula: String,
+    x: CompiledExpression,
+    y: CompiledExpression,
+}
+
+impl CustomVariationDefinition {
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        x_source: impl Into<String>,
+        y_source: impl Into<String>,
+        parameters: BTreeMap<String, f64>,
+    ) -> Result<Self, ExpressionError> {
+        let x_source = x_source.into();
+        let y_source = y_source.into();
+
+        let x_expr = parse(&x_source)?;
+        let y_expr = parse(&y_source)?;
+        let x = CompiledExpression::compile(&x_expr, &parameters)?;
+        let y = CompiledExpression::compile(&y_expr, &parameters)?;
+
+        Ok(Self {
+            id: id.into(),
+            name: name.into(),
+            x_source,
+            y_source,
+            parameters,
+            formula: formula_latex(&x_expr, &y_expr),
+            x,
+            y,
+        })
+    }
+
+    pub fn formula(&self) -> &str {
+        &self.formula
+    }
+
+    pub fn evaluate(&self, p: &Point) -> Point {
+        let uses = |v: Variable| self.x.uses(v) || self.y.uses(v);
+
+        let mut inputs: Inputs = [0.0; Variable::COUNT];
+        inputs[Variable::X.slot()] = p.x;
+        inputs[Variable::Y.slot()] = p.y;
+        if uses(Variable::R) {
+            inputs[Variable::R.slot()] = p.r();
+        }
+        if uses(Variable::Theta) {
+            inputs[Variable::Theta.slot()] = p.theta();
+        }
+        if uses(Variable::Phi) {
+            inputs[Variable::Phi.slot()] = p.phi();
+        }
+
+        Point::new(self.x.evaluate(&inputs), self.y.evaluate(&inputs))
+    }
+}
+
+#[derive(Clone)]
+pub struct CustomVariation {
+    pub base: BaseAffineTransformation,
+    pub definition: Arc<CustomVariationDefinition>,
+}
+
+impl CustomVariation {
+    pub fn new(base: BaseAffineTransformation, definition: Arc<CustomVariationDefinition>) -> Self {
+        Self { base, definition }
+    }
+}
+
+impl Transformation for CustomVariation {
+    fn apply(&self, point: &Point) -> Point {
+        let p = self.base.apply(point);
+        self.definition.evaluate(&p)
+    }
+
+    fn weight(&self) -> f64 {
+        self.base.weight()
+    }
+
+    fn color(&self) -> &Color {
+        self.base.color()
+    }
+
+    fn get_name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    fn get_formula(&self) -> &str {
+        self.definition.formula()
+    }
+}
@@ -1,4 +1,5 @@
 pub mod base_affine_transformation;
+pub mod custom;
 pub mod diamond;
 pub mod disc;
 pub mod ex;
@@ -8,8 +9,10 @@ pub mod horseshoe;
 pub mod hyperbolic;
 pub mod linear;
 pub mod polar;
+pub mod registry;
 pub mod sinusoidal;
 pub mod spherical;
 pub mod spiral;
 pub mod swirl;
 pub mod symmetry;
+pub mod xform;
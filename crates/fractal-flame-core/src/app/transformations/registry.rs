@@ -0,0 +1,52 @@
+use super::base_affine_transformation::BaseAffineTransformation;
+use super::{
+    diamond::Diamond, disc::Disc, ex::Ex, handkerchief::Handkerchief, heart::Heart,
+    horseshoe::Horseshoe, hyperbolic::Hyperbolic, linear::Linear, polar::Polar,
+    sinusoidal::Sinusoidal, spherical::Spherical, spiral::Spiral, swirl::Swirl,
+};
+use crate::domain::transformation::Transformation;
+
+/// Ids of all built-in variations, in display order.
+pub const VARIATION_IDS: &[&str] = &[
+    "diamond",
+    "disc",
+    "ex",
+    "heart",
+    "horseshoe",
+    "spherical",
+    "swirl",
+    "linear",
+    "polar",
+    "spiral",
+    "handkerchief",
+    "hyperbolic",
+    "sinusoidal",
+];
+
+pub fn is_builtin(id: &str) -> bool {
+    VARIATION_IDS.contains(&id)
+}
+
+/// Creates the built-in variation `id` on top of `base`, or `None` if the id is unknown.
+pub fn create_variation(
+    id: &str,
+    base: BaseAffineTransformation,
+) -> Option<Box<dyn Transformation + Send + Sync>> {
+    let t: Box<dyn Transformation + Send + Sync> = match id {
+        "diamond" => Box::new(Diamond { base }),
+        "disc" => Box::new(Disc::new(base)),
+        "ex" => Box::new(Ex::new(base)),
+        "heart" => Box::new(Heart::new(base)),
+        "horseshoe" => Box::new(Horseshoe::new(base)),
+        "spherical" => Box::new(Spherical::new(base)),
+        "swirl" => Box::new(Swirl::new(base)),
+        "linear" => Box::new(Linear::new(base)),
+        "polar" => Box::new(Polar::new(base)),
+        "spiral" => Box::new(Spiral::new(base)),
+        "handkerchief" => Box::new(Handkerchief::new(base)),
+        "hyperbolic" => Box::new(Hyperbolic::new(base)),
+        "sinusoidal" => Box::new(Sinusoidal::new(base)),
+        _ => return None,
+    };
+    Some(t)
+}
@@ -0,0 +1,70 @@
+use super::base_affine_transformation::BaseAffineTransformation;
+use crate::domain::transformation::Transformation;
+use crate::domain::{Color, Point};
+
+/// flam3-style transform: the affine is applied once and the variations' outputs are summed
+/// with their blend weights, then the optional post affine is applied. Each variation is
+/// expected to carry an identity affine.
+pub struct XForm {
+    pub base: BaseAffineTransformation,
+    pub variations: Vec<(f64, Box<dyn Transformation + Send + Sync>)>,
+    pub post: Option<BaseAffineTransformation>,
+}
+
+impl XForm {
+    pub fn new(
+        base: BaseAffineTransformation,
+        variations: Vec<(f64, Box<dyn Transformation + Send + Sync>)>,
+    ) -> Self {
+        Self {
+            base,
+            variations,
+            post: None,
+        }
+    }
+
+    pub fn with_post(mut self, post: BaseAffineTransformation) -> Self {
+        self.post = Some(post);
+        self
+    }
+}
+
+impl Transformation for XForm {
+    fn apply(&self, point: &Point) -> Point {
+        let p = self.base.apply(point);
+
+        let mut x = 0.0;
+        let mut y = 0.0;
+        for (weight, variation) in &self.variations {
+            let v = variation.apply(&p);
+            x += weight * v.x;
+            y += weight * v.y;
+        }
+
+        let p = Point::new(x, y);
+        match self.post {
+            Some(ref post) => post.apply(&p),
+            None => p,
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        self.base.weight()
+    }
+
+    fn color(&self) -> &Color {
+        self.base.color()
+    }
+
+    fn get_name(&self) -> &str {
+        "XForm"
+    }
+
+    fn get_id(&self) -> &str {
+        "xform"
+    }
+
+    fn get_formula(&self) -> &str {
+        r"(x', y') = \sum_i w_i V_i(ax + by + c,\ dx + ey + f)"
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use super::genome::FlameGenome;
+
+/// A genome pinned to a point in time; frames between keyframes are interpolated.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f64,
+    pub genome: FlameGenome,
+}
+
+/// How sub-frames are weighted across the shutter interval, as flam3's `temporal_filter_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TemporalFilter {
+    Box,
+    Gaussian,
+    /// Weights grow towards the end of the shutter for a positive exponent and towards the
+    /// start for a negative one, leaving a trail behind moving parts.
+    Exponential {
+        exponent: f64,
+    },
+}
+
+/// Accumulates several sub-frame genomes spread over a shutter interval into each frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionBlur {
+    /// Sub-frames per frame, as flam3's `temporal_samples`.
+    pub temporal_samples: usize,
+    /// Fraction of the time between two frames the shutter stays open, centered on the frame.
+    pub shutter: f64,
+    pub filter: TemporalFilter,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        Self {
+            temporal_samples: 8,
+            shutter: 1.0,
+            filter: TemporalFilter::Box,
+        }
+    }
+}
+
+/// One sub-frame of a motion-blurred frame and its share of the frame's samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemporalSample {
+    pub genome: FlameGenome,
+    pub weight: f64,
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::color::Color;
+
+fn one() -> f64 {
+    1.0
+}
+fn default_symmetry() -> usize {
+    1
+}
+fn default_gamma() -> f64 {
+    2.2
+}
+fn default_samples() -> usize {
+    100_000
+}
+fn default_iter_per_sample() -> usize {
+    100
+}
+
+/// Affine coefficients: `x' = ax + by + c`, `y' = dx + ey + f`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Affine {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 0.0,
+        e: 1.0,
+        f: 0.0,
+    };
+
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+}
+
+/// One variation inside a transform, blended with the others by `weight`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenomeVariation {
+    pub id: String,
+    #[serde(default = "one")]
+    pub weight: f64,
+}
+
+/// An xform: a pre-affine followed by a weighted sum of variations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenomeTransform {
+    pub affine: Affine,
+    /// Optional affine applied after the variations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<Affine>,
+    /// Selection probability relative to the other transforms.
+    #[serde(default = "one")]
+    pub weight: f64,
+    pub color: Color,
+    /// Position in the genome palette; when set together with a palette it overrides `color`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_index: Option<f64>,
+    pub variations: Vec<GenomeVariation>,
+    /// Variation parameters keyed as `{variation_id}_{parameter}`, as in flam3.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+/// Source of a user-defined variation referenced by id from [`GenomeVariation`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomVariationSpec {
+    pub id: String,
+    pub name: String,
+    pub x: String,
+    pub y: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+/// Output size and the visible window of the plane: `2 / zoom` units tall around the center,
+/// rotated by `rotate` degrees.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Camera {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub center_x: f64,
+    #[serde(default)]
+    pub center_y: f64,
+    #[serde(default = "one")]
+    pub zoom: f64,
+    #[serde(default)]
+    pub rotate: f64,
+}
+
+impl Camera {
+    /// Largest accepted canvas side, in pixels.
+    pub const MAX_DIMENSION: usize = 65_536;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub colors: Vec<Color>,
+}
+
+impl Palette {
+    /// Color at `index` in `[0, 1]`, clamped to the ends of the palette.
+    pub fn color_at(&self, index: f64) -> Option<Color> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let last = self.colors.len() - 1;
+        let i = (index.clamp(0.0, 1.0) * last as f64).round() as usize;
+        Some(self.colors[i.min(last)])
+    }
+}
+
+/// What shows through where the flame is faint or empty.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Background {
+    /// Alpha follows tone-mapped density, for compositing over other artwork.
+    Transparent,
+    Solid {
+        color: Color,
+    },
+    /// Blends from `from` to `to` across the image; `angle` is in degrees, 0 running left to
+    /// right and 90 top to bottom.
+    Gradient {
+        from: Color,
+        to: Color,
+        #[serde(default)]
+        angle: f64,
+    },
+}
+
+impl Background {
+    /// Color at `(x, y)`, both in `[0, 1]` from the top left; `None` when transparent.
+    pub fn color_at(&self, x: f64, y: f64) -> Option<Color> {
+        match *self {
+            Self::Transparent => None,
+            Self::Solid { color } => Some(color),
+            Self::Gradient { from, to, angle } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // Project onto the gradient direction, scaled so opposite corners reach 0 and 1.
+                let t = 0.5 + ((x - 0.5) * cos + (y - 0.5) * sin) / (cos.abs() + sin.abs());
+                let t = t.clamp(0.0, 1.0);
+                let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+                Some(Color {
+                    r: mix(from.r, to.r),
+                    g: mix(from.g, to.g),
+                    b: mix(from.b, to.b),
+                })
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Solid {
+            color: Color::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToneMapping {
+    #[serde(default = "default_gamma")]
+    pub gamma: f64,
+    #[serde(default = "one")]
+    pub brightness: f64,
+    #[serde(default)]
+    pub background: Background,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            gamma: default_gamma(),
+            brightness: one(),
+            background: Background::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quality {
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+    #[serde(default = "default_iter_per_sample")]
+    pub iter_per_sample: usize,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self {
+            samples: default_samples(),
+            iter_per_sample: default_iter_per_sample(),
+        }
+    }
+}
+
+/// Everything needed to reproduce a render.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlameGenome {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub transforms: Vec<GenomeTransform>,
+    /// Applied to every point before plotting without feeding back into the iteration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_transform: Option<GenomeTransform>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_variations: Vec<CustomVariationSpec>,
+    #[serde(default = "default_symmetry")]
+    pub symmetry: usize,
+    pub camera: Camera,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+    #[serde(default)]
+    pub tone_mapping: ToneMapping,
+    #[serde(default)]
+    pub quality: Quality,
+    /// Seeds the chaos game; renders with the same seed and thread count plot the same points.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
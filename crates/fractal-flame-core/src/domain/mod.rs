@@ -1,13 +1,19 @@
+pub mod animation;
 pub mod color;
 pub mod fractal_image;
+pub mod genome;
 pub mod pixel;
 pub mod point;
 pub mod rect;
+pub mod tile;
 pub mod transformation;
 
+pub use animation::*;
 pub use color::*;
 pub use fractal_image::*;
+pub use genome::*;
 pub use pixel::*;
 pub use point::*;
 pub use rect::*;
+pub use tile::*;
 pub use transformation::*;
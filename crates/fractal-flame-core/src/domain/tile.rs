@@ -0,0 +1,41 @@
+use super::rect::Rect;
+
+/// A rectangle of output pixels, rendered on its own canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    /// Covers a `width` by `height` image with tiles at most `size` on a side, row by row.
+    pub fn grid(width: usize, height: usize, size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        (0..height)
+            .step_by(size)
+            .flat_map(|y| {
+                (0..width).step_by(size).map(move |x| Tile {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    /// The part of `world` this tile shows, when the whole `width` by `height` image shows all
+    /// of it.
+    pub fn view(&self, world: &Rect, width: usize, height: usize) -> Rect {
+        let pixel_width = world.width / width as f64;
+        let pixel_height = world.height / height as f64;
+        Rect::new(
+            world.x + self.x as f64 * pixel_width,
+            world.y + self.y as f64 * pixel_height,
+            self.width as f64 * pixel_width,
+            self.height as f64 * pixel_height,
+        )
+    }
+}
@@ -6,7 +6,7 @@ pub trait Transformation: Any {
     fn weight(&self) -> f64;
     fn color(&self) -> &Color;
 
-    fn get_name(&self) -> &'static str;
-    fn get_id(&self) -> &'static str;
-    fn get_formula(&self) -> &'static str;
+    fn get_name(&self) -> &str;
+    fn get_id(&self) -> &str;
+    fn get_formula(&self) -> &str;
 }
@@ -1,4 +1,5 @@
-use rand::{Rng, rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng, rng};
 
 #[derive(Debug, Clone)]
 pub enum RangeError {
@@ -22,6 +23,16 @@ impl std::fmt::Display for RangeError {
 impl std::error::Error for RangeError {}
 
 pub fn generate_f64(min: f64, max: f64, include_max: bool) -> Result<f64, RangeError> {
+    generate_f64_with(&mut rng(), min, max, include_max)
+}
+
+/// Like [`generate_f64`], drawing from `rng` instead of the thread-local generator.
+pub fn generate_f64_with<R: Rng + ?Sized>(
+    rng: &mut R,
+    min: f64,
+    max: f64,
+    include_max: bool,
+) -> Result<f64, RangeError> {
     if min > max {
         return Err(RangeError::InvalidFloatRange { min, max });
     }
@@ -31,7 +42,6 @@ pub fn generate_f64(min: f64, max: f64, include_max: bool) -> Result<f64, RangeE
     let include = if include_max { 1u64 } else { 0u64 };
     let upper = 1_000_000_000u64 + include;
 
-    let mut rng = rng();
     let n: u64 = rng.random_range(0..upper);
 
     let mut value = min + diff * (n as f64 / 1_000_000_000.0);
@@ -54,3 +64,21 @@ pub fn generate_i32(min: i32, max: i32) -> Result<i32, RangeError> {
     let mut rng = rng();
     Ok(rng.random_range(min..max))
 }
+
+pub fn generate_seed() -> u64 {
+    rng().random()
+}
+
+/// An independent seed for stream `stream` of work seeded with `seed`, such as one shard of a
+/// render split across machines.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    worker_rng(Some(seed), stream).random()
+}
+
+/// A generator for one worker: reproducible from `seed` and `stream` when a seed is given.
+pub fn worker_rng(seed: Option<u64>, stream: u64) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        None => StdRng::from_rng(&mut rng()),
+    }
+}
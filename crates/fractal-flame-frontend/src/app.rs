@@ -3,7 +3,9 @@ use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::JsCast;
 use wasm_bindgen::closure::Closure;
-use web_sys::{EventSource, HtmlInputElement, KeyboardEvent, MessageEvent, MouseEvent};
+use web_sys::{
+    EventSource, HtmlInputElement, HtmlSelectElement, KeyboardEvent, MessageEvent, MouseEvent,
+};
 use yew::prelude::*;
 
 fn api_base() -> &'static str {
@@ -22,9 +24,22 @@ struct VariationsResponse {
     variations: Vec<VariationDto>,
 }
 
+/// Genome generation strategies offered by the backend, as `(kind, label)`.
+const STRATEGIES: &[(&str, &str)] = &[
+    ("contractive_random", "Contractive random"),
+    ("similarity", "Similarity"),
+    ("apophysis", "Apophysis-style"),
+];
+
+#[derive(Clone, Debug, Serialize)]
+struct StrategyRequest {
+    kind: String,
+}
+
 #[derive(Clone, Debug, Serialize)]
 struct StartRenderRequest {
     variation_ids: Vec<String>,
+    strategy: StrategyRequest,
     symmetry: usize,
     gamma: f64,
     width: usize,
@@ -55,6 +70,7 @@ pub struct AppState {
     gamma: f64,
     width: usize,
     height: usize,
+    strategy: String,
     last_job_id: Option<String>,
     last_render_image: Option<String>,
     render_progress: Option<usize>,
@@ -73,6 +89,7 @@ impl Default for AppState {
             gamma: 2.2,
             width: 1920,
             height: 1080,
+            strategy: STRATEGIES[0].0.to_string(),
             last_job_id: None,
             last_render_image: None,
             render_progress: None,
@@ -333,6 +350,17 @@ pub fn app() -> Html {
         })
     };
 
+    let on_strategy_change = {
+        let state = state.clone();
+        Callback::from(move |v: String| {
+            let current = (*state).clone();
+            state.set(AppState {
+                strategy: v,
+                ..current
+            });
+        })
+    };
+
     let on_start_render = {
         let state = state.clone();
         Callback::from(move |_| {
@@ -341,6 +369,9 @@ pub fn app() -> Html {
             wasm_bindgen_futures::spawn_local(async move {
                 let body = StartRenderRequest {
                     variation_ids: current.selected.clone(),
+                    strategy: StrategyRequest {
+                        kind: current.strategy.clone(),
+                    },
                     symmetry: current.symmetry,
                     gamma: current.gamma,
                     width: current.width,
@@ -389,27 +420,44 @@ pub fn app() -> Html {
     };
 
     app_view(
-        &*state,
-        on_toggle,
-        on_select_all,
-        on_symmetry_change,
-        on_gamma_change,
-        on_width_change,
-        on_height_change,
-        on_start_render,
+        &state,
+        AppViewProps {
+            on_toggle,
+            on_select_all,
+            on_symmetry_change,
+            on_gamma_change,
+            on_width_change,
+            on_height_change,
+            on_strategy_change,
+            on_start_render,
+        },
     )
 }
 
-fn app_view(
-    state: &AppState,
+/// Callbacks the main view wires into its controls.
+#[derive(Clone, PartialEq)]
+struct AppViewProps {
     on_toggle: Callback<String>,
     on_select_all: Callback<()>,
     on_symmetry_change: Callback<usize>,
     on_gamma_change: Callback<f64>,
     on_width_change: Callback<usize>,
     on_height_change: Callback<usize>,
+    on_strategy_change: Callback<String>,
     on_start_render: Callback<()>,
-) -> Html {
+}
+
+fn app_view(state: &AppState, props: AppViewProps) -> Html {
+    let AppViewProps {
+        on_toggle,
+        on_select_all,
+        on_symmetry_change,
+        on_gamma_change,
+        on_width_change,
+        on_height_change,
+        on_strategy_change,
+        on_start_render,
+    } = props;
     html! {
         <div class="app">
             <header class="header">
@@ -485,6 +533,20 @@ fn app_view(
                             }}
                         />
                     </label>
+                    <label>
+                        {"Generator: "}
+                        <select
+                            onchange={move |e: Event| {
+                                if let Some(select) = e.target_dyn_into::<HtmlSelectElement>() {
+                                    on_strategy_change.emit(select.value());
+                                }
+                            }}
+                        >
+                            {STRATEGIES.iter().map(|(kind, label)| html! {
+                                <option value={*kind} selected={state.strategy == *kind}>{*label}</option>
+                            }).collect::<Html>()}
+                        </select>
+                    </label>
                 </div>
                 <FetchByPictureId />
             </header>
@@ -728,6 +790,15 @@ fn job_id_display(props: &JobIdDisplayProps) -> Html {
                     <span class="download-btn-icon">{"↓"}</span>
                     {"Download"}
                 </a>
+                <a
+                    href={format!("{}/api/render/{}/flame", api_base(), props.job_id)}
+                    download={format!("{}.flame", props.job_id)}
+                    class="download-btn"
+                    title="Download parameters as a flam3 .flame file"
+                >
+                    <span class="download-btn-icon">{"↓"}</span>
+                    {".flame"}
+                </a>
             </div>
             if *fullscreen_open {
                 <div
@@ -775,6 +846,9 @@ fn job_id_display(props: &JobIdDisplayProps) -> Html {
         {preloader_html}
         {image_html}
         {actions_html}
+        if is_completed {
+            <GenomeDisplay job_id={props.job_id.clone()} />
+        }
     </> };
 
     html! {
@@ -906,6 +980,104 @@ fn fetch_by_picture_id() -> Html {
     }
 }
 
+#[derive(Clone, Properties, PartialEq)]
+struct GenomeDisplayProps {
+    job_id: String,
+}
+
+/// Collapsible view of the genome a job was rendered from, with a copy button.
+#[function_component(GenomeDisplay)]
+fn genome_display(props: &GenomeDisplayProps) -> Html {
+    let open = use_state(|| false);
+    let genome = use_state(|| Option::<String>::None);
+    let error = use_state(|| Option::<String>::None);
+    let copied = use_state(|| false);
+
+    {
+        let genome = genome.clone();
+        let error = error.clone();
+        use_effect_with(props.job_id.clone(), move |_| {
+            genome.set(None);
+            error.set(None);
+        });
+    }
+
+    let on_toggle = {
+        let open = open.clone();
+        let genome = genome.clone();
+        let error = error.clone();
+        let job_id = props.job_id.clone();
+        Callback::from(move |_| {
+            let now_open = !*open;
+            open.set(now_open);
+            if !now_open || genome.is_some() {
+                return;
+            }
+            let genome = genome.clone();
+            let error = error.clone();
+            let url = format!("{}/api/render/{}/genome", api_base(), job_id);
+            wasm_bindgen_futures::spawn_local(async move {
+                match Request::get(&url).send().await {
+                    Ok(resp) if resp.ok() => match resp.text().await {
+                        Ok(text) => {
+                            let pretty = serde_json::from_str::<serde_json::Value>(&text)
+                                .and_then(|v| serde_json::to_string_pretty(&v))
+                                .unwrap_or(text);
+                            genome.set(Some(pretty));
+                        }
+                        Err(e) => error.set(Some(format!("Failed to read genome: {}", e))),
+                    },
+                    Ok(resp) => error.set(Some(format!("Genome unavailable ({})", resp.status()))),
+                    Err(e) => error.set(Some(format!("Request failed: {}", e))),
+                }
+            });
+        })
+    };
+
+    let on_copy = {
+        let genome = genome.clone();
+        let copied = copied.clone();
+        Callback::from(move |_| {
+            let Some(text) = (*genome).clone() else {
+                return;
+            };
+            let copied = copied.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(window) = web_sys::window() {
+                    let promise = window.navigator().clipboard().write_text(&text);
+                    if wasm_bindgen_futures::JsFuture::from(promise).await.is_ok() {
+                        copied.set(true);
+                        gloo_timers::future::TimeoutFuture::new(1500).await;
+                        copied.set(false);
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="genome-display">
+            <button class="fullscreen-btn" onclick={on_toggle}>
+                {if *open { "Hide genome" } else { "Show genome" }}
+            </button>
+            if *open {
+                if let Some(ref err) = *error {
+                    <span class="fetch-by-id-error">{err}</span>
+                } else if let Some(ref text) = *genome {
+                    <div class="genome-json-container">
+                        <button class="genome-copy-btn" onclick={on_copy} title="Copy genome JSON">
+                            {if *copied { "✓ Copied" } else { "⧉ Copy" }}
+                        </button>
+                        <pre class="genome-json">{text}</pre>
+                    </div>
+                } else {
+                    <p class="render-status">{"Loading genome..."}</p>
+                }
+            }
+        </div>
+    }
+}
+
 #[derive(Clone, Properties, PartialEq)]
 struct FormulaDisplayProps {
     formula: String,